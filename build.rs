@@ -0,0 +1,18 @@
+// Regenerates the committed C header include/comp_perf.h for the API in src/ffi.rs when
+// COMP_PERF_GENERATE_HEADER is set, e.g. COMP_PERF_GENERATE_HEADER=1 cargo build --lib.
+// Ordinary builds leave the source directory untouched.
+fn main() {
+    println!("cargo:rerun-if-env-changed=COMP_PERF_GENERATE_HEADER");
+    if std::env::var_os("COMP_PERF_GENERATE_HEADER").is_none() {
+        return;
+    }
+    println!("cargo:rerun-if-changed=src/ffi.rs");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+    let crate_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+    match cbindgen::generate(&crate_dir) {
+        Ok(bindings) => {
+            bindings.write_to_file(format!("{}/include/comp_perf.h", crate_dir));
+        },
+        Err(err) => println!("cargo:warning=Unable to generate include/comp_perf.h: {}", err),
+    }
+}
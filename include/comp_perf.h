@@ -0,0 +1,130 @@
+#ifndef COMP_PERF_H
+#define COMP_PERF_H
+
+/* Generated with cbindgen by COMP_PERF_GENERATE_HEADER=1 cargo build - do not edit */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+#define COMP_PERF_NUM_COMPONENTS 21
+
+#define COMP_PERF_OK 0
+
+#define COMP_PERF_ERROR_NULL_POINTER 1
+
+#define COMP_PERF_ERROR_COMPOSITION 2
+
+#define COMP_PERF_ERROR_NO_SOLUTION 3
+
+#define COMP_PERF_ERROR_INVALID_ARGUMENT 4
+
+#define COMP_PERF_PRESSURE_KPA 0
+
+#define COMP_PERF_PRESSURE_PSI 1
+
+#define COMP_PERF_PRESSURE_BAR 2
+
+#define COMP_PERF_PRESSURE_MPA 3
+
+#define COMP_PERF_PRESSURE_ATM 4
+
+#define COMP_PERF_PRESSURE_MBAR 5
+
+#define COMP_PERF_PRESSURE_KGF_CM2 6
+
+#define COMP_PERF_PRESSURE_INH2O 7
+
+#define COMP_PERF_PRESSURE_MMHG 8
+
+#define COMP_PERF_TEMPERATURE_K 0
+
+#define COMP_PERF_TEMPERATURE_C 1
+
+#define COMP_PERF_TEMPERATURE_F 2
+
+#define COMP_PERF_TEMPERATURE_R 3
+
+#define R_DETAIL 8.31451
+
+/**
+ * Gas properties from AGA8 DETAIL.  Pressure in kPa, temperature in K, density in
+ * mol/l, molar mass in g/mol, energies in J/mol, entropy and heat capacities in
+ * J/(mol-K), speed of sound in m/s and Joule-Thomson coefficient in K/kPa.
+ */
+typedef struct CompPerfState {
+  double p;
+  double t;
+  double d;
+  double z;
+  double mm;
+  double u;
+  double h;
+  double s;
+  double cp;
+  double cv;
+  double w;
+  double kappa;
+  double g;
+  double jt;
+} CompPerfState;
+
+/**
+ * Properties at pressure p (kPa) and temperature t (K).  Returns COMP_PERF_OK or an
+ * error code.
+ *
+ * # Safety
+ * `x` must point to COMP_PERF_NUM_COMPONENTS doubles and `out` to a CompPerfState.
+ */
+int comp_perf_state(const double *x, double p, double t, struct CompPerfState *out);
+
+/**
+ * Compression from p1 (kPa) and t1 (K) to a higher p2 (kPa) with an isentropic
+ * efficiency between 0 and 1.  Writes the discharge state; the specific work is the
+ * enthalpy rise.  COMP_PERF_ERROR_INVALID_ARGUMENT if p2 is not above p1.
+ *
+ * # Safety
+ * `x` must point to COMP_PERF_NUM_COMPONENTS doubles and `out` to a CompPerfState.
+ */
+int comp_perf_compress(const double *x,
+                       double p1,
+                       double t1,
+                       double p2,
+                       double efficiency,
+                       struct CompPerfState *out);
+
+/**
+ * Isenthalpic throttling from p1 (kPa) and t1 (K) to a lower p2 (kPa).  Writes the
+ * downstream state.  COMP_PERF_ERROR_INVALID_ARGUMENT if p2 is not below p1.
+ *
+ * # Safety
+ * `x` must point to COMP_PERF_NUM_COMPONENTS doubles and `out` to a CompPerfState.
+ */
+int comp_perf_throttle(const double *x, double p1, double t1, double p2, struct CompPerfState *out);
+
+/**
+ * Converts a pressure in the given COMP_PERF_PRESSURE_* unit to kPa.  NaN for an
+ * unknown unit.
+ */
+double comp_perf_pressure_to_kpa(double value, int unit);
+
+/**
+ * Converts a pressure in kPa to the given COMP_PERF_PRESSURE_* unit.  NaN for an
+ * unknown unit.
+ */
+double comp_perf_pressure_from_kpa(double value, int unit);
+
+/**
+ * Converts a temperature in the given COMP_PERF_TEMPERATURE_* unit to K.  NaN for an
+ * unknown unit.
+ */
+double comp_perf_temperature_to_kelvin(double value, int unit);
+
+/**
+ * Converts a temperature in K to the given COMP_PERF_TEMPERATURE_* unit.  NaN for an
+ * unknown unit.
+ */
+double comp_perf_temperature_from_kelvin(double value, int unit);
+
+#endif  /* COMP_PERF_H */
@@ -0,0 +1,67 @@
+"""Gas properties from AGA8 DETAIL through the comp_perf native module.
+
+Build and install the package with maturin from this directory, e.g.
+``maturin develop --release`` or ``pip install .``.
+
+    >>> import gas_thermodynamics as gt
+    >>> gas = gt.Composition(methane=0.9, ethane=0.06, propane=0.02, nitrogen=0.01, carbon_dioxide=0.01)
+    >>> state = gt.state(gas, p=5000.0, t=300.0)
+    >>> discharge = gt.compress(gas, p1=5000.0, t1=300.0, p2=9000.0, efficiency=0.8)
+
+Pressures are in kPa and temperatures in K; see ``State`` for the other units.  The unit
+conversions take the labels in PRESSURE_UNITS and TEMPERATURE_UNITS, in any case.
+"""
+
+from . import _native
+from ._native import (
+    COMPONENTS,
+    PRESSURE_UNITS,
+    TEMPERATURE_UNITS,
+    CalculationError,
+    State,
+    pressure_from_kpa,
+    pressure_to_kpa,
+    temperature_from_kelvin,
+    temperature_to_kelvin,
+)
+
+
+class Composition:
+    """Mole fractions by component name; missing components are zero."""
+
+    def __init__(self, **fractions):
+        unknown = set(fractions) - set(COMPONENTS)
+        if unknown:
+            raise ValueError(f"unknown components: {', '.join(sorted(unknown))}")
+        self.fractions = {name: float(fractions.get(name, 0.0)) for name in COMPONENTS}
+
+    @classmethod
+    def from_mole_percent(cls, **percents):
+        return cls(**{name: value / 100.0 for name, value in percents.items()})
+
+    def normalized(self):
+        total = sum(self.fractions.values())
+        return Composition(**{name: value / total for name, value in self.fractions.items()})
+
+    def _array(self):
+        return [self.fractions[name] for name in COMPONENTS]
+
+    def __repr__(self):
+        present = ", ".join(f"{name}={value:g}" for name, value in self.fractions.items() if value)
+        return f"Composition({present})"
+
+
+def state(composition, p, t):
+    """Properties at p (kPa) and t (K)."""
+    return _native.state(composition._array(), p, t)
+
+
+def compress(composition, p1, t1, p2, efficiency):
+    """Discharge state of a compression from p1, t1 to p2 with an isentropic efficiency
+    between 0 and 1.  The specific work in J/mol is the enthalpy rise."""
+    return _native.compress(composition._array(), p1, t1, p2, efficiency)
+
+
+def throttle(composition, p1, t1, p2):
+    """Downstream state of an isenthalpic expansion from p1, t1 to a lower p2."""
+    return _native.throttle(composition._array(), p1, t1, p2)
@@ -0,0 +1,142 @@
+use aga8::composition::Composition;
+use aga8::detail::Detail;
+use colored::Colorize;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+use comp_perf::thermo::solve_state;
+
+use crate::components::array_to_comp;
+use crate::csv::{component_columns, component_fractions, field_f64, CsvTable};
+use crate::quality::{gross_hv_volumetric, relative_density};
+use crate::validation::{check_state, Range};
+use crate::{initial_program_state, Pressure, Temperature};
+
+use std::fs;
+
+const OUTPUT_HEADERS: [&str; 13] = [
+    "sample", "pressure_kpa", "temperature_k", "sum_mol_pct", "molar_mass", "z", "density_kg_m3", "z_base",
+    "hv_mj_m3", "relative_density", "wobbe_mj_m3", "range", "status",
+];
+
+struct GcRecord {
+    sample: String,
+    // Composition as entered, before normalization
+    sum: f64,
+    gas_comp: Composition,
+    // kPa and K
+    p: f64,
+    t: f64,
+}
+
+// One GC analysis per row: optional sample column, pressure (kPa), temperature (K) and
+// component columns named as in COMPONENT_NAMES in mol%.  Missing components are zero.
+fn read_gc_records(table: &CsvTable) -> Result<Vec<Result<GcRecord, String>>, String> {
+    let sample_col = table.column("sample");
+    let p_col = table.require_column("pressure")?;
+    let t_col = table.require_column("temperature")?;
+    let component_cols = component_columns(table)?;
+
+    let records = table.rows.iter().enumerate().map(|(line, row)| {
+        let row_error = |err: String| format!("Row {}: {}", line + 2, err);
+        let p = field_f64(row, Some(p_col)).map_err(row_error)?.ok_or(row_error("missing pressure".to_string()))?;
+        let t = field_f64(row, Some(t_col)).map_err(row_error)?.ok_or(row_error("missing temperature".to_string()))?;
+        let gas_comp = array_to_comp(&component_fractions(row, &component_cols).map_err(row_error)?);
+        Ok(GcRecord {
+            sample: sample_col.and_then(|i| row.get(i)).cloned().unwrap_or_else(|| (line + 1).to_string()),
+            sum: gas_comp.sum(),
+            gas_comp,
+            p,
+            t,
+        })
+    }).collect();
+    Ok(records)
+}
+
+// Output line with the leading fields given and the remaining results left empty
+fn failed_line(fields: &[String], status: &str) -> String {
+    let mut line = fields.to_vec();
+    line.resize(OUTPUT_HEADERS.len() - 1, String::new());
+    line.push(status.replace(',', " "));
+    line.join(",")
+}
+
+fn record_line(state: &mut Detail, mut record: GcRecord, tolerance: f64, p_base: Pressure, t_base: Temperature) -> String {
+    let prefix = vec![record.sample.replace(',', " "), record.p.to_string(), record.t.to_string(), format!("{:.4}", record.sum * 100.0)];
+    if record.sum <= 0.0 || (record.sum - 1.0).abs() > tolerance {
+        return failed_line(&prefix, "composition sum outside the normalization tolerance");
+    }
+    record.gas_comp.normalize().unwrap();
+    let gas_comp = record.gas_comp;
+    // The worker's instance is solved at flowing conditions and then at base conditions
+    let solved = state.set_composition(&gas_comp).ok()
+        .and_then(|_| solve_state(state, record.p, record.t))
+        .map(|_| (state.mm, state.z, state.d))
+        .and_then(|flowing| solve_state(state, p_base.kpa(), t_base.kelvin()).map(|_| (flowing, state.z)));
+    let Some(((mm, z, d), z_base)) = solved else {
+        return failed_line(&prefix, "density calculation failed");
+    };
+    let hv = gross_hv_volumetric(&gas_comp, p_base, t_base);
+    let rd = relative_density(&gas_comp, p_base, t_base);
+    let violations = check_state(&gas_comp, record.p, record.t);
+    let range = if violations.iter().any(|violation| violation.range == Range::Extended) {
+        "outside"
+    } else if violations.is_empty() {
+        "normal"
+    } else {
+        "extended"
+    };
+    format!("{},{:.4},{:.6},{:.4},{:.6},{:.4},{:.4},{:.4},{},ok",
+        prefix.join(","), mm, z, d * mm, z_base, hv, rd, hv / rd.sqrt(), range)
+}
+
+// Quality statement for a CSV of GC analyses.  Writes to the output path, or stdout when
+// no path is given.  Returns the process exit code.
+pub fn run_batch(args: &[String]) -> i32 {
+    let Some(input) = args.first() else {
+        eprintln!("Usage: comp_perf batch <input.csv> [output.csv]");
+        return 2;
+    };
+    let settings = initial_program_state();
+    let records = match CsvTable::read(input).and_then(|table| read_gc_records(&table)) {
+        Ok(records) => records,
+        Err(err) => {
+            eprintln!("{}", format!("** {} **", err).red().bold());
+            return 1;
+        }
+    };
+
+    let evaluate = |state: &mut Detail, record: Result<GcRecord, String>| match record {
+        Ok(record) => record_line(state, record, settings.normalize_tolerance, settings.base_pressure, settings.base_temperature),
+        Err(err) => failed_line(&[], &err),
+    };
+    // Records are evaluated in parallel with one DETAIL instance per worker; collect keeps
+    // the input order
+    #[cfg(feature = "parallel")]
+    let lines: Vec<String> = records.into_par_iter().map_init(Detail::new, evaluate).collect();
+    #[cfg(not(feature = "parallel"))]
+    let lines: Vec<String> = {
+        let mut state = Detail::new();
+        records.into_iter().map(|record| evaluate(&mut state, record)).collect()
+    };
+    let count = lines.len();
+    let failures = lines.iter().filter(|line| !line.ends_with(",ok")).count();
+    let mut text = OUTPUT_HEADERS.join(",");
+    text.push('\n');
+    for line in lines {
+        text.push_str(&line);
+        text.push('\n');
+    }
+
+    match args.get(1) {
+        Some(output) => {
+            if let Err(err) = fs::write(output, &text) {
+                eprintln!("{}", format!("** Unable to write {}: {} **", output, err).red().bold());
+                return 1;
+            }
+            outln!("{} records written to {} ({} failed)", count, output, failures);
+        },
+        None => out!("{}", text),
+    }
+    0
+}
@@ -0,0 +1,58 @@
+use std::hint::black_box;
+use std::time::Instant;
+
+use aga8::composition::Composition;
+
+use crate::comparison::try_gerg_state;
+use crate::process::{isenthalpic_state, isentropic_state};
+use crate::{get_gas_comp, try_gas_state, GasComp};
+
+const SINGLE_POINTS: usize = 2000;
+const GRID_SIZE: usize = 40;
+const FLASH_POINTS: usize = 200;
+
+// Runs f for count evaluations and prints the throughput.
+fn time_points<F: FnMut(usize)>(label: &str, count: usize, mut f: F) {
+    let start = Instant::now();
+    for i in 0..count {
+        f(i);
+    }
+    let elapsed = start.elapsed().as_secs_f64();
+    outln!("{:<40} {:>10} {:>12.4} {:>14.0}", label, count, elapsed, count as f64 / elapsed);
+}
+
+fn grid_point(i: usize) -> (f64, f64) {
+    let p = 100.0 + 10_000.0 * (i / GRID_SIZE) as f64 / GRID_SIZE as f64;
+    let t = 250.0 + 150.0 * (i % GRID_SIZE) as f64 / GRID_SIZE as f64;
+    (p, t)
+}
+
+fn bench_backend(name: &str, gas_comp: &Composition, evaluate: fn(&Composition, f64, f64) -> Option<f64>) {
+    time_points(&format!("{} single point", name), SINGLE_POINTS, |_| {
+        black_box(evaluate(gas_comp, 5000.0, 300.0));
+    });
+    time_points(&format!("{} {}x{} P-T table", name, GRID_SIZE, GRID_SIZE), GRID_SIZE * GRID_SIZE, |i| {
+        let (p, t) = grid_point(i);
+        black_box(evaluate(gas_comp, p, t));
+    });
+}
+
+pub fn run_bench() {
+    let gas_comp = get_gas_comp(GasComp::NaturalGas);
+    outln!("Benchmark: natural gas, {} repetitions per single-point test", SINGLE_POINTS);
+    outln!();
+    outln!("{:<40} {:>10} {:>12} {:>14}", "Test", "Points", "Seconds", "Points/s");
+
+    bench_backend("DETAIL", &gas_comp, |gas_comp, p, t| try_gas_state(gas_comp, p, t).map(|state| state.z));
+    bench_backend("GERG-2008", &gas_comp, |gas_comp, p, t| try_gerg_state(gas_comp, p, t).map(|state| state.z));
+
+    let inlet = try_gas_state(&gas_comp, 7000.0, 300.0).unwrap();
+    time_points("DETAIL isentropic flash (P, s)", FLASH_POINTS, |i| {
+        let p = 1000.0 + 5000.0 * i as f64 / FLASH_POINTS as f64;
+        black_box(isentropic_state(&gas_comp, p, inlet.s, inlet.t));
+    });
+    time_points("DETAIL isenthalpic flash (P, h)", FLASH_POINTS, |i| {
+        let p = 1000.0 + 5000.0 * i as f64 / FLASH_POINTS as f64;
+        black_box(isenthalpic_state(&gas_comp, p, inlet.h, inlet.t));
+    });
+}
@@ -0,0 +1,197 @@
+use aga8::composition::Composition;
+use colored::Colorize;
+
+use crate::components::{array_to_comp, comp_to_array, NUM_COMPONENTS};
+use crate::custom_composition::{enter_custom_composition, print_composition};
+use crate::process::isenthalpic_state;
+use crate::{
+    change_conditions, change_gas_comp, get_gas_comp, new_gas_state, print_gas_state, print_state_errors, read_f64,
+    read_input, read_pressure, read_temperature, try_gas_state, update_gas_state, GasComp, Pressure, ProgramState, Temperature,
+};
+
+pub struct BlendStream {
+    pub name: String,
+    pub gas_comp: Composition,
+    // Share of the blend in parts on the selected basis
+    pub ratio: f64,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum BlendBasis {
+    Molar,
+    // Volumes at the base conditions, converted to moles with the real-gas Z of each stream
+    Volumetric,
+}
+
+pub fn blend_compositions(streams: &[BlendStream], basis: BlendBasis, p_base: Pressure, t_base: Temperature) -> Composition {
+    let mut x = [0.0; NUM_COMPONENTS];
+    let mut total = 0.0;
+    for stream in streams {
+        let moles = match basis {
+            BlendBasis::Molar => stream.ratio,
+            BlendBasis::Volumetric => stream.ratio / new_gas_state(&stream.gas_comp, p_base.kpa(), t_base.kelvin()).z,
+        };
+        for (xi, stream_xi) in x.iter_mut().zip(comp_to_array(&stream.gas_comp)) {
+            *xi += moles * stream_xi;
+        }
+        total += moles;
+    }
+    for xi in x.iter_mut() {
+        *xi /= total;
+    }
+    array_to_comp(&x)
+}
+
+pub fn select_stream_gas(program_state: &ProgramState) -> Option<(String, Composition)> {
+    outln!("Select stream gas:");
+    outln!("1 - Air");
+    outln!("2 - Argon");
+    outln!("3 - Nitrogen");
+    outln!("4 - Oxygen");
+    outln!("5 - Natural Gas (typical pipeline)");
+    outln!("6 - Methane");
+    outln!("7 - Hydrogen");
+    outln!("c - Current gas ({})", program_state.gas);
+    outln!("m - Custom composition");
+    let (name, preset) = match read_input().as_str() {
+        "1" => ("Air", GasComp::Air),
+        "2" => ("Argon", GasComp::Argon),
+        "3" => ("Nitrogen", GasComp::Nitrogen),
+        "4" => ("Oxygen", GasComp::Oxygen),
+        "5" => ("Natural Gas", GasComp::NaturalGas),
+        "6" => ("Methane", GasComp::Methane),
+        "7" => ("Hydrogen", GasComp::Hydrogen),
+        "c" => return Some((program_state.gas.clone(), array_to_comp(&comp_to_array(&program_state.gas_comp)))),
+        "m" => return enter_custom_composition(program_state.normalize_tolerance).map(|gas_comp| ("Custom".to_string(), gas_comp)),
+        _ => return None,
+    };
+    Some((name.to_string(), get_gas_comp(preset)))
+}
+
+// Prompts for the blend streams and returns the blend name and composition.
+pub fn blend_gases(program_state: &ProgramState) -> Option<(String, Composition)> {
+    outln!();
+    outln!("{}", "Gas Blending".green().bold());
+    outln!("Blend basis: 1 - Molar, 2 - Volumetric (at base conditions)");
+    let basis = match read_input().as_str() {
+        "2" => BlendBasis::Volumetric,
+        _ => BlendBasis::Molar,
+    };
+    let count = read_f64("Enter number of streams (2 or more):").max(2.0) as usize;
+
+    let mut streams = Vec::new();
+    for i in 0..count {
+        outln!("Stream {}:", i + 1);
+        let (name, gas_comp) = match select_stream_gas(program_state) {
+            Some(stream) => stream,
+            None => {
+                outln!("{}", "**Invalid selection!**".bold().red());
+                return None;
+            }
+        };
+        let ratio = read_f64("Enter share of the blend (any parts, e.g. 80):");
+        if ratio < 0.0 {
+            outln!("{}", "**Share cannot be negative!**".bold().red());
+            return None;
+        }
+        streams.push(BlendStream { name, gas_comp, ratio });
+    }
+
+    let total: f64 = streams.iter().map(|stream| stream.ratio).sum();
+    if total <= 0.0 {
+        outln!("{}", "**Blend shares sum to zero!**".bold().red());
+        return None;
+    }
+    let blend = blend_compositions(&streams, basis, program_state.base_pressure, program_state.base_temperature);
+    let basis_label = if basis == BlendBasis::Molar { "mol" } else { "vol" };
+    let parts: Vec<String> = streams.iter()
+        .map(|stream| format!("{} {:.1}%", stream.name, stream.ratio / total * 100.0))
+        .collect();
+    Some((format!("Blend {} ({})", basis_label, parts.join(", ")), blend))
+}
+
+pub struct MixStream {
+    pub gas_comp: Composition,
+    // Mass flow in kg/s
+    pub mass_flow: f64,
+    pub p: Pressure,
+    pub t: Temperature,
+}
+
+// Adiabatic mixing: the mixed stream at p_mix has the flow-weighted molar enthalpy of the
+// feeds.  Returns the mixed composition and temperature.
+pub fn mix_streams(streams: &[MixStream], p_mix: Pressure) -> Option<(Composition, Temperature)> {
+    let mut total_moles = 0.0;
+    let mut total_enthalpy = 0.0;
+    let mut t_guess = 0.0;
+    let mut x = [0.0; NUM_COMPONENTS];
+    for stream in streams {
+        let state = try_gas_state(&stream.gas_comp, stream.p.kpa(), stream.t.kelvin())?;
+        // kg/s divided by g/mol gives kmol/s
+        let moles = stream.mass_flow / state.mm;
+        total_moles += moles;
+        total_enthalpy += moles * state.h;
+        t_guess += moles * stream.t.kelvin();
+        for (xi, stream_xi) in x.iter_mut().zip(comp_to_array(&stream.gas_comp)) {
+            *xi += moles * stream_xi;
+        }
+    }
+    for xi in x.iter_mut() {
+        *xi /= total_moles;
+    }
+    let mixed_comp = array_to_comp(&x);
+    let mixed = isenthalpic_state(&mixed_comp, p_mix.kpa(), total_enthalpy / total_moles, t_guess / total_moles)?;
+    Some((mixed_comp, Temperature::from_kelvin(mixed.t)))
+}
+
+pub fn stream_mixing(program_state: &mut ProgramState) {
+    outln!();
+    outln!("{}", "Adiabatic Stream Mixing".green().bold());
+    let count = read_f64("Enter number of streams (2 or more):").max(2.0) as usize;
+    let mut streams = Vec::new();
+    let mut names = Vec::new();
+    for i in 0..count {
+        outln!("Stream {}:", i + 1);
+        let (name, gas_comp) = match select_stream_gas(program_state) {
+            Some(stream) => stream,
+            None => {
+                outln!("{}", "**Invalid selection!**".bold().red());
+                print_gas_state(program_state);
+                return;
+            }
+        };
+        let mass_flow = read_f64("Enter mass flow (kg/s):");
+        let p = read_pressure(program_state, "stream pressure");
+        let t = read_temperature(program_state, "stream temperature");
+        names.push(name);
+        streams.push(MixStream { gas_comp, mass_flow, p, t });
+    }
+    let p_mix = read_pressure(program_state, "mixing pressure");
+
+    let (mixed_comp, t_mix) = match mix_streams(&streams, p_mix) {
+        Some(mixed) => mixed,
+        None => {
+            outln!("{}", "** Unable to solve the mixed stream temperature **".red().bold());
+            print_gas_state(program_state);
+            return;
+        }
+    };
+    let total_flow: f64 = streams.iter().map(|stream| stream.mass_flow).sum();
+
+    outln!();
+    print_composition(&mixed_comp);
+    outln!("{:<30} {:10.4} {:10}", "Mixed Mass Flow: ", total_flow, "kg/s");
+    outln!("{:<30} {:10.4} {:10}", "Mixed Pressure: ", p_mix.value(program_state.units.pressure), program_state.units.pressure.label());
+    outln!("{:<30} {:10.4} {:10}", "Mixed Temperature: ", t_mix.value(program_state.units.temp), program_state.units.temp.label());
+
+    outln!("Load the mixed stream as the current state? (y/n)");
+    if read_input() == "y" {
+        program_state.gas = format!("Mixed ({})", names.join(" + "));
+        change_gas_comp(program_state, mixed_comp);
+        change_conditions(program_state, p_mix, t_mix);
+        program_state.show_inlet_state = false;
+        program_state.show_discharge_state = false;
+        print_state_errors(&update_gas_state(program_state));
+    }
+    print_gas_state(program_state);
+}
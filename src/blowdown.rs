@@ -0,0 +1,423 @@
+use aga8::composition::Composition;
+use aga8::detail::Detail;
+use colored::Colorize;
+use comp_perf::thermo::solve_isentropic_state;
+
+use crate::csv::write_csv;
+use crate::process::{isochoric_state, solve_isochoric_state};
+use crate::release::orifice_exit;
+use crate::{
+    get_pressure, get_temperature, print_gas_state, read_f64, read_input, read_pressure, read_temperature, try_gas_state, Pressure, ProgramState,
+    Temperature,
+};
+
+use std::f64::consts::PI;
+
+// Largest share of the inventory released in one internal time step
+const MAX_STEP_FRACTION: f64 = 0.02;
+// Stop once the vessel is within this factor of the back pressure
+const BACK_PRESSURE_MARGIN: f64 = 1.01;
+const MAX_STEPS: usize = 100_000;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum VesselModel {
+    // Adiabatic, reversible expansion of the gas remaining in the vessel
+    Isentropic,
+    // Heat transfer from the walls keeps the gas at the initial temperature
+    Isothermal,
+}
+
+// Solves state, which has the composition set, for the vessel state with density
+// d_target (mol/l) at entropy s (J/(mol-K)) by Newton iteration on pressure from p (kPa)
+// and t (K), using (dp/drho) at constant entropy = w^2.
+fn solve_isentropic_density_state(state: &mut Detail, d_target: f64, s: f64, mut p: f64, mut t: f64) -> Option<()> {
+    for _ in 0..50 {
+        solve_isentropic_state(state, p, s, t)?;
+        let dp = state.w * state.w * state.mm * (d_target - state.d) / 1000.0;
+        p = (p + dp).max(0.5 * p);
+        t = state.t;
+        if dp.abs() < 1.0e-9 * p {
+            return solve_isentropic_state(state, p, s, t);
+        }
+    }
+    None
+}
+
+// Vessel state of the first step and a second instance for the next one.  The steps
+// swap the two instead of building new states, which keeps the large DETAIL instances
+// off the stack.
+fn vessel_states(gas_comp: &Composition, initial: &Detail) -> Option<(Box<Detail>, Box<Detail>)> {
+    let state = Box::new(isochoric_state(gas_comp, initial.d, initial.t, initial.p)?);
+    let mut next = Box::new(Detail::new());
+    next.set_composition(gas_comp).ok()?;
+    Some((state, next))
+}
+
+pub struct BlowdownInputs {
+    // Vessel volume in m3, orifice area in m2 and discharge coefficient
+    pub volume: f64,
+    pub area: f64,
+    pub cd: f64,
+    pub p_back: Pressure,
+    pub p_end: Pressure,
+    pub model: VesselModel,
+    // Report interval and end time in s
+    pub report_interval: f64,
+    pub t_max: f64,
+}
+
+// Also used for the filling record
+pub struct BlowdownPoint {
+    // Time in s, pressure in kPa, temperature in K and mass flow in kg/s
+    pub time: f64,
+    pub p: f64,
+    pub t: f64,
+    pub mass_flow: f64,
+}
+
+pub struct BlowdownResult {
+    pub points: Vec<BlowdownPoint>,
+    // False when a vessel or orifice state could not be solved before the end, which
+    // usually means the expansion reached the two-phase region.
+    pub solved: bool,
+}
+
+// Time steps the vessel from the initial state until the end pressure, the back
+// pressure, or the end time is reached.  Points are recorded every report interval.
+pub fn blowdown(gas_comp: &Composition, initial: &Detail, inputs: &BlowdownInputs) -> BlowdownResult {
+    let mut points = Vec::new();
+    let solved = blowdown_steps(gas_comp, initial, inputs, &mut points).is_some();
+    BlowdownResult { points, solved }
+}
+
+fn blowdown_steps(gas_comp: &Composition, initial: &Detail, inputs: &BlowdownInputs, points: &mut Vec<BlowdownPoint>) -> Option<()> {
+    let s0 = initial.s;
+    let t0 = initial.t;
+    let mm = initial.mm;
+    // Inventory in mol
+    let mut moles = initial.d * 1000.0 * inputs.volume;
+    let (mut state, mut next) = vessel_states(gas_comp, initial)?;
+    let mut time = 0.0;
+    let mut next_report = 0.0;
+    let mut last_mass_flow = 0.0;
+
+    for _ in 0..MAX_STEPS {
+        let Some(exit) = orifice_exit(gas_comp, state.p, state.t, inputs.p_back.kpa()) else {
+            // Keep the last solved vessel state with the previous flow in the record
+            if time > 0.0 && points.last().is_none_or(|last| last.time < time) {
+                points.push(BlowdownPoint { time, p: state.p, t: state.t, mass_flow: last_mass_flow });
+            }
+            return None;
+        };
+        let mass_flow = inputs.cd * inputs.area * exit.density * exit.velocity;
+        let point = BlowdownPoint { time, p: state.p, t: state.t, mass_flow };
+        if time >= next_report {
+            points.push(point);
+            next_report += inputs.report_interval;
+        } else if state.p <= inputs.p_end.kpa() || state.p <= inputs.p_back.kpa() * BACK_PRESSURE_MARGIN || time >= inputs.t_max {
+            points.push(point);
+        }
+        if state.p <= inputs.p_end.kpa() || state.p <= inputs.p_back.kpa() * BACK_PRESSURE_MARGIN || time >= inputs.t_max {
+            return Some(());
+        }
+
+        last_mass_flow = mass_flow;
+        // kg/s to mol/s
+        let molar_flow = mass_flow * 1000.0 / mm;
+        let dt = (MAX_STEP_FRACTION * moles / molar_flow).min(next_report - time).max(1.0e-6);
+        moles -= molar_flow * dt;
+        let d = moles / inputs.volume / 1000.0;
+        let solved = match inputs.model {
+            VesselModel::Isentropic => solve_isentropic_density_state(&mut next, d, s0, state.p, state.t),
+            VesselModel::Isothermal => solve_isochoric_state(&mut next, d, t0, state.p),
+        };
+        if solved.is_none() {
+            // Keep the last solved state in the record
+            if points.last().is_none_or(|last| last.time < time) {
+                points.push(BlowdownPoint { time, p: state.p, t: state.t, mass_flow });
+            }
+            return None;
+        }
+        std::mem::swap(&mut state, &mut next);
+        time += dt;
+    }
+    Some(())
+}
+
+// Solves state, which has the composition set, for the vessel state with density
+// d_target (mol/l) and internal energy u (J/mol) by Newton iteration on temperature from
+// p (kPa) and t (K), using (du/dT) at constant density = cv.
+fn solve_internal_energy_density_state(state: &mut Detail, d_target: f64, u: f64, mut p: f64, mut t: f64) -> Option<()> {
+    for _ in 0..50 {
+        solve_isochoric_state(state, d_target, t, p)?;
+        let dt = (u - state.u) / state.cv;
+        t = (t + dt).max(0.5 * t);
+        p = state.p;
+        if dt.abs() < 1.0e-9 * t {
+            return solve_isochoric_state(state, d_target, t, p);
+        }
+    }
+    None
+}
+
+pub struct FillingInputs {
+    // Vessel volume in m3, restriction area in m2 and discharge coefficient
+    pub volume: f64,
+    pub area: f64,
+    pub cd: f64,
+    // Supply stagnation pressure and temperature, held constant
+    pub p_supply: Pressure,
+    pub t_supply: Temperature,
+    pub p_target: Pressure,
+    // Isentropic here means adiabatic: no heat exchange with the walls
+    pub model: VesselModel,
+    // Report interval and end time in s
+    pub report_interval: f64,
+    pub t_max: f64,
+}
+
+// Time steps the filling of a vessel of the same gas from its initial state until the
+// target pressure, the supply pressure, or the end time is reached.  The adiabatic
+// energy balance adds the supply enthalpy to the vessel internal energy, which is the
+// source of the temperature rise in a fast fill.
+pub fn filling(gas_comp: &Composition, initial: &Detail, inputs: &FillingInputs) -> BlowdownResult {
+    let mut points = Vec::new();
+    let solved = filling_steps(gas_comp, initial, inputs, &mut points).is_some();
+    BlowdownResult { points, solved }
+}
+
+fn filling_steps(gas_comp: &Composition, initial: &Detail, inputs: &FillingInputs, points: &mut Vec<BlowdownPoint>) -> Option<()> {
+    let (p_supply, t_supply) = (inputs.p_supply.kpa(), inputs.t_supply.kelvin());
+    let supply = try_gas_state(gas_comp, p_supply, t_supply)?;
+    let t0 = initial.t;
+    let mm = initial.mm;
+    // Inventory in mol and internal energy in J
+    let mut moles = initial.d * 1000.0 * inputs.volume;
+    let mut energy = moles * initial.u;
+    let (mut state, mut next) = vessel_states(gas_comp, initial)?;
+    let mut time = 0.0;
+    let mut next_report = 0.0;
+
+    for _ in 0..MAX_STEPS {
+        let done = state.p >= inputs.p_target.kpa() || state.p * BACK_PRESSURE_MARGIN >= p_supply || time >= inputs.t_max;
+        let mass_flow = if done {
+            0.0
+        } else {
+            let exit = orifice_exit(gas_comp, p_supply, t_supply, state.p)?;
+            inputs.cd * inputs.area * exit.density * exit.velocity
+        };
+        if time >= next_report || done {
+            points.push(BlowdownPoint { time, p: state.p, t: state.t, mass_flow });
+            next_report += inputs.report_interval;
+        }
+        if done {
+            return Some(());
+        }
+
+        // kg/s to mol/s
+        let molar_flow = mass_flow * 1000.0 / mm;
+        let dt = (MAX_STEP_FRACTION * moles / molar_flow).min(next_report - time).max(1.0e-6);
+        moles += molar_flow * dt;
+        energy += molar_flow * dt * supply.h;
+        let d = moles / inputs.volume / 1000.0;
+        let solved = match inputs.model {
+            VesselModel::Isentropic => solve_internal_energy_density_state(&mut next, d, energy / moles, state.p, state.t),
+            VesselModel::Isothermal => solve_isochoric_state(&mut next, d, t0, state.p),
+        };
+        if solved.is_none() {
+            if points.last().is_none_or(|last| last.time < time) {
+                points.push(BlowdownPoint { time, p: state.p, t: state.t, mass_flow });
+            }
+            return None;
+        }
+        std::mem::swap(&mut state, &mut next);
+        time += dt;
+    }
+    Some(())
+}
+
+pub fn vessel_blowdown(program_state: &mut ProgramState) {
+    outln!();
+    outln!("{}", "Vessel Blowdown".yellow().bold());
+    outln!("Initial vessel state is the current state.");
+    let volume = read_f64("Enter vessel volume (m3):");
+    let diameter = read_f64("Enter orifice diameter (mm):") / 1000.0;
+    let cd = read_f64("Enter discharge coefficient (e.g. 0.62 sharp edged, 0.85 restriction orifice):");
+    let p_back = read_pressure(program_state, "back pressure");
+    let p_end = read_pressure(program_state, "end pressure");
+    outln!("Vessel model: 1 - Isentropic (adiabatic, conservative for low temperature), 2 - Isothermal");
+    let model = match read_input().as_str() {
+        "2" => VesselModel::Isothermal,
+        _ => VesselModel::Isentropic,
+    };
+    let report_interval = read_f64("Enter report interval (s):").max(1.0e-3);
+    let t_max = read_f64("Enter maximum simulated time (s):");
+
+    let area = PI * diameter * diameter / 4.0;
+    let inputs = BlowdownInputs { volume, area, cd, p_back, p_end, model, report_interval, t_max };
+    let result = blowdown(&program_state.gas_comp, &program_state.gas_state, &inputs);
+    let points = result.points;
+
+    let p_label = format!("P ({})", program_state.units.pressure.label());
+    let t_label = format!("T ({})", program_state.units.temp.label());
+    let headers = ["Time (s)", p_label.as_str(), t_label.as_str(), "Mass Flow (kg/s)"];
+    let mut rows = Vec::new();
+    outln!();
+    outln!("{:>10} {:>14} {:>12} {:>18}", headers[0], headers[1], headers[2], headers[3]);
+    for point in &points {
+        let p_display = get_pressure(point.p, program_state.units.pressure);
+        let t_display = get_temperature(point.t, program_state.units.temp);
+        outln!("{:10.1} {:14.4} {:12.4} {:18.4}", point.time, p_display, t_display, point.mass_flow);
+        rows.push(vec![point.time, p_display, t_display, point.mass_flow]);
+    }
+
+    if let (Some(last), Some(coldest)) = (points.last(), points.iter().min_by(|a, b| a.t.total_cmp(&b.t))) {
+        outln!();
+        outln!("{:<30} {:10.4} {:10}", "Minimum Gas Temperature: ", get_temperature(coldest.t, program_state.units.temp), program_state.units.temp.label());
+        outln!("{:<30} {:10.1} {:10}", "Elapsed Time: ", last.time, "s");
+        if !result.solved {
+            outln!("{}", "** Stopped early: a vessel or orifice state could not be solved (possible two-phase region) **".red().bold());
+        } else if last.p > p_end.kpa() {
+            outln!("{}", "* End pressure was not reached within the simulated time or above the back pressure".yellow());
+        }
+        if model == VesselModel::Isentropic {
+            outln!("Isentropic gas temperatures bound the metal temperature from below; wall heat transfer raises them.");
+        }
+    }
+
+    outln!();
+    outln!("Enter CSV file path to export the blowdown (leave blank to skip):");
+    let path = read_input();
+    if !path.is_empty() {
+        match write_csv(&path, &headers, &rows) {
+            Ok(()) => outln!("Blowdown written to {}", path),
+            Err(err) => outln!("{}", format!("** {} **", err).red().bold()),
+        }
+    }
+
+    print_gas_state(program_state);
+}
+
+pub fn vessel_filling(program_state: &mut ProgramState) {
+    outln!();
+    outln!("{}", "Vessel Filling (Fast Fill)".yellow().bold());
+    outln!("Supply is the current state, held constant.");
+    let volume = read_f64("Enter vessel volume (m3):");
+    let diameter = read_f64("Enter restriction diameter (mm):") / 1000.0;
+    let cd = read_f64("Enter discharge coefficient (e.g. 0.62 sharp edged, 0.85 restriction orifice):");
+    let p_initial = read_pressure(program_state, "initial vessel pressure");
+    let t_initial = read_temperature(program_state, "initial vessel temperature");
+    let p_target = read_pressure(program_state, "target vessel pressure");
+    outln!("Vessel model: 1 - Adiabatic (conservative for high temperature), 2 - Isothermal");
+    let model = match read_input().as_str() {
+        "2" => VesselModel::Isothermal,
+        _ => VesselModel::Isentropic,
+    };
+    let report_interval = read_f64("Enter report interval (s):").max(1.0e-3);
+    let t_max = read_f64("Enter maximum simulated time (s):");
+
+    let Some(initial) = try_gas_state(&program_state.gas_comp, p_initial.kpa(), t_initial.kelvin()) else {
+        outln!("{}", "** Unable to solve the initial vessel state **".red().bold());
+        print_gas_state(program_state);
+        return;
+    };
+    let area = PI * diameter * diameter / 4.0;
+    let inputs = FillingInputs {
+        volume,
+        area,
+        cd,
+        p_supply: Pressure::from_kpa(program_state.gas_state.p),
+        t_supply: Temperature::from_kelvin(program_state.gas_state.t),
+        p_target,
+        model,
+        report_interval,
+        t_max,
+    };
+    let result = filling(&program_state.gas_comp, &initial, &inputs);
+    let points = result.points;
+
+    let p_label = format!("P ({})", program_state.units.pressure.label());
+    let t_label = format!("T ({})", program_state.units.temp.label());
+    let headers = ["Time (s)", p_label.as_str(), t_label.as_str(), "Mass Flow (kg/s)"];
+    let mut rows = Vec::new();
+    outln!();
+    outln!("{:>10} {:>14} {:>12} {:>18}", headers[0], headers[1], headers[2], headers[3]);
+    for point in &points {
+        let p_display = get_pressure(point.p, program_state.units.pressure);
+        let t_display = get_temperature(point.t, program_state.units.temp);
+        outln!("{:10.1} {:14.4} {:12.4} {:18.4}", point.time, p_display, t_display, point.mass_flow);
+        rows.push(vec![point.time, p_display, t_display, point.mass_flow]);
+    }
+
+    if let (Some(last), Some(hottest)) = (points.last(), points.iter().max_by(|a, b| a.t.total_cmp(&b.t))) {
+        let filled_mass = (try_gas_state(&program_state.gas_comp, last.p, last.t).map_or(initial.d, |state| state.d) - initial.d) * volume * initial.mm;
+        outln!();
+        outln!("{:<30} {:10.4} {:10}", "Maximum Gas Temperature: ", get_temperature(hottest.t, program_state.units.temp), program_state.units.temp.label());
+        outln!("{:<30} {:10.4} {:10}", "Mass Added: ", filled_mass, "kg");
+        outln!("{:<30} {:10.1} {:10}", "Time to Target: ", last.time, "s");
+        if !result.solved {
+            outln!("{}", "** Stopped early: a vessel or restriction state could not be solved **".red().bold());
+        } else if last.p < p_target.kpa() {
+            outln!("{}", "* Target pressure was not reached within the simulated time or below the supply pressure".yellow());
+        }
+        if model == VesselModel::Isentropic {
+            // Settled pressure once the gas cools back to the initial temperature at the final density
+            if let Some(settled) = try_gas_state(&program_state.gas_comp, last.p, last.t)
+                .and_then(|state| isochoric_state(&program_state.gas_comp, state.d, t_initial.kelvin(), last.p))
+            {
+                outln!("{:<30} {:10.4} {:10}", "Settled Pressure: ", get_pressure(settled.p, program_state.units.pressure), program_state.units.pressure.label());
+            }
+            outln!("Adiabatic gas temperatures bound the fill temperature from above; wall heat transfer lowers them.");
+        }
+    }
+
+    outln!();
+    outln!("Enter CSV file path to export the filling (leave blank to skip):");
+    let path = read_input();
+    if !path.is_empty() {
+        match write_csv(&path, &headers, &rows) {
+            Ok(()) => outln!("Filling written to {}", path),
+            Err(err) => outln!("{}", format!("** {} **", err).red().bold()),
+        }
+    }
+
+    print_gas_state(program_state);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::quality::R;
+
+    // Choked isentropic discharge of an ideal gas: p/p0 = (1 + (k - 1)/2 t/tau)^(-2k/(k - 1))
+    // with tau = V / (Cd A a0 (2/(k + 1))^((k + 1)/(2(k - 1)))).  Nitrogen from 1000 kPa and
+    // 300 K is close to ideal with k 1.4: half the pressure in 25.5 s from 1 m3 through
+    // 1 cm2, at T/T0 = 0.5^(2/7).
+    #[test]
+    fn ideal_gas_choked_blowdown() {
+        let gas_comp = Composition { nitrogen: 1.0, ..Default::default() };
+        let initial = try_gas_state(&gas_comp, 1000.0, 300.0).unwrap();
+        let inputs = BlowdownInputs {
+            volume: 1.0,
+            area: 1.0e-4,
+            cd: 1.0,
+            p_back: Pressure::from_kpa(101.325),
+            p_end: Pressure::from_kpa(500.0),
+            model: VesselModel::Isentropic,
+            report_interval: 10.0,
+            t_max: 100.0,
+        };
+        let result = blowdown(&gas_comp, &initial, &inputs);
+        assert!(result.solved);
+        let end = result.points.last().unwrap();
+
+        let k: f64 = 1.4;
+        let a0 = (k * R * 300.0 / (initial.mm / 1000.0)).sqrt();
+        let tau = inputs.volume / (inputs.area * a0 * (2.0 / (k + 1.0)).powf((k + 1.0) / (2.0 * (k - 1.0))));
+        let time = |ratio: f64| (ratio.powf(-(k - 1.0) / (2.0 * k)) - 1.0) * 2.0 / (k - 1.0) * tau;
+        assert!((time(0.5) - 25.5).abs() < 0.1);
+        // Explicit steps of 2% of the inventory
+        assert!((end.time / time(end.p / 1000.0) - 1.0).abs() < 0.03);
+        assert!((end.t / (300.0 * (end.p / 1000.0).powf((k - 1.0) / k)) - 1.0).abs() < 0.005);
+    }
+}
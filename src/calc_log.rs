@@ -0,0 +1,43 @@
+use colored::Colorize;
+
+use crate::template::report_context;
+use crate::{output, print_gas_state, read_input, ProgramState};
+
+use std::fs::OpenOptions;
+use std::io::Write;
+
+// Appends the state as one JSON line: timestamp, gas, units, composition, the current
+// state and any inlet and discharge.  A failed write turns the log off.
+pub fn append(program_state: &mut ProgramState) {
+    let Some(path) = &program_state.log_path else {
+        return;
+    };
+    let result = serde_json::to_string(&report_context(program_state))
+        .map_err(|err| err.to_string())
+        .and_then(|line| {
+            let mut file = OpenOptions::new().create(true).append(true).open(path).map_err(|err| err.to_string())?;
+            writeln!(file, "{}", line).map_err(|err| err.to_string())
+        });
+    if let Err(err) = result {
+        outln!("{}", format!("** Unable to write calculation log {}: {} - logging turned off **", path, err).red().bold());
+        program_state.log_path = None;
+    }
+}
+
+pub fn log_settings(program_state: &mut ProgramState) {
+    if !output::quiet() {
+        match &program_state.log_path {
+            Some(path) => outln!("Calculation log: {}", path),
+            None => outln!("Calculation log: off"),
+        }
+        outln!("Enter log file path (blank to turn logging off):");
+    }
+    let path = read_input();
+    if path.is_empty() {
+        program_state.log_path = None;
+    } else {
+        program_state.log_path = Some(path);
+        append(program_state);
+    }
+    print_gas_state(program_state);
+}
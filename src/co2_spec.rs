@@ -0,0 +1,182 @@
+use aga8::composition::Composition;
+use colored::Colorize;
+
+use crate::comparison::try_gerg_state;
+use crate::tariff::{format_limit, ComplianceCheck};
+use crate::{get_pressure, print_gas_state, read_input, read_pressure, read_temperature, ProgramState};
+
+// CO2 critical point (Span and Wagner, 1996): kPa and K
+const CO2_CRITICAL_PRESSURE: f64 = 7377.3;
+const CO2_CRITICAL_TEMPERATURE: f64 = 304.1282;
+
+// Impurity limits in ppm (mol) unless noted.  Indicative values from published
+// recommendations; confirm against the current operator specification.
+pub struct Co2Spec {
+    pub name: &'static str,
+    // Minimum CO2 in mol%
+    pub co2_min: Option<f64>,
+    pub water: Option<f64>,
+    pub oxygen: Option<f64>,
+    pub nitrogen: Option<f64>,
+    pub argon: Option<f64>,
+    pub methane: Option<f64>,
+    pub hydrogen: Option<f64>,
+    pub carbon_monoxide: Option<f64>,
+    pub hydrogen_sulfide: Option<f64>,
+    // Sum of N2, O2, Ar, CH4, H2, CO and He
+    pub non_condensables: Option<f64>,
+}
+
+pub const CO2_SPECS: [Co2Spec; 3] = [
+    Co2Spec {
+        name: "DYNAMIS pipeline, aquifer storage",
+        co2_min: Some(95.5),
+        water: Some(500.0),
+        oxygen: Some(40_000.0),
+        nitrogen: Some(40_000.0),
+        argon: Some(40_000.0),
+        methane: Some(40_000.0),
+        hydrogen: Some(40_000.0),
+        carbon_monoxide: Some(2000.0),
+        hydrogen_sulfide: Some(200.0),
+        non_condensables: Some(40_000.0),
+    },
+    Co2Spec {
+        name: "DYNAMIS pipeline, EOR",
+        co2_min: Some(95.5),
+        water: Some(500.0),
+        oxygen: Some(100.0),
+        nitrogen: Some(40_000.0),
+        argon: Some(40_000.0),
+        methane: Some(20_000.0),
+        hydrogen: Some(40_000.0),
+        carbon_monoxide: Some(2000.0),
+        hydrogen_sulfide: Some(200.0),
+        non_condensables: Some(40_000.0),
+    },
+    Co2Spec {
+        name: "Northern Lights ship transport",
+        co2_min: None,
+        water: Some(30.0),
+        oxygen: Some(10.0),
+        nitrogen: None,
+        argon: None,
+        methane: None,
+        hydrogen: Some(50.0),
+        carbon_monoxide: Some(100.0),
+        hydrogen_sulfide: Some(9.0),
+        non_condensables: None,
+    },
+];
+
+fn non_condensables(gas_comp: &Composition) -> f64 {
+    gas_comp.nitrogen + gas_comp.oxygen + gas_comp.argon + gas_comp.methane + gas_comp.hydrogen
+        + gas_comp.carbon_monoxide + gas_comp.helium
+}
+
+pub fn check_co2_spec(spec: &Co2Spec, gas_comp: &Composition) -> Vec<ComplianceCheck> {
+    let ppm = 1.0e6;
+    let candidates = [
+        ("Carbon Dioxide", "mol%", gas_comp.carbon_dioxide * 100.0, spec.co2_min, None),
+        ("Water", "ppm", gas_comp.water * ppm, None, spec.water),
+        ("Oxygen", "ppm", gas_comp.oxygen * ppm, None, spec.oxygen),
+        ("Nitrogen", "ppm", gas_comp.nitrogen * ppm, None, spec.nitrogen),
+        ("Argon", "ppm", gas_comp.argon * ppm, None, spec.argon),
+        ("Methane", "ppm", gas_comp.methane * ppm, None, spec.methane),
+        ("Hydrogen", "ppm", gas_comp.hydrogen * ppm, None, spec.hydrogen),
+        ("Carbon Monoxide", "ppm", gas_comp.carbon_monoxide * ppm, None, spec.carbon_monoxide),
+        ("Hydrogen Sulfide", "ppm", gas_comp.hydrogen_sulfide * ppm, None, spec.hydrogen_sulfide),
+        ("Non-condensables", "ppm", non_condensables(gas_comp) * ppm, None, spec.non_condensables),
+    ];
+    candidates.into_iter()
+        .filter(|(_, _, _, min, max)| min.is_some() || max.is_some())
+        .map(|(parameter, unit, value, min, max)| ComplianceCheck { parameter, unit, value, min, max })
+        .collect()
+}
+
+// Saturation pressure of pure CO2 in kPa below the critical temperature (Span and
+// Wagner ancillary equation).
+pub fn co2_vapor_pressure(t: f64) -> f64 {
+    let tau = 1.0 - t / CO2_CRITICAL_TEMPERATURE;
+    let sum = -7.0602087 * tau + 1.9391218 * tau.powf(1.5) - 1.6463597 * tau.powi(2) - 3.2995634 * tau.powi(4);
+    CO2_CRITICAL_PRESSURE * (CO2_CRITICAL_TEMPERATURE / t * sum).exp()
+}
+
+// Phase of pure CO2 at p (kPa) and t (K) and the pressure margin (kPa) above the dense
+// phase boundary: the saturation pressure below the critical temperature, the critical
+// pressure above it.
+pub fn dense_phase_margin(p: f64, t: f64) -> (&'static str, f64) {
+    if t < CO2_CRITICAL_TEMPERATURE {
+        let margin = p - co2_vapor_pressure(t);
+        (if margin > 0.0 { "Dense liquid" } else { "Gas" }, margin)
+    } else {
+        let margin = p - CO2_CRITICAL_PRESSURE;
+        (if margin > 0.0 { "Dense (supercritical)" } else { "Gas" }, margin)
+    }
+}
+
+pub fn co2_spec_report(program_state: &mut ProgramState) {
+    outln!();
+    outln!("{}", "CO2 Transport / Storage Specification Report".cyan().bold());
+    outln!("DETAIL is outside its range for CO2-rich streams, so the transport condition is entered here and evaluated with GERG-2008.");
+    outln!("Select specification:");
+    for (i, spec) in CO2_SPECS.iter().enumerate() {
+        outln!("{} - {}", i + 1, spec.name);
+    }
+    let Some(spec) = read_input().parse::<usize>().ok().and_then(|i| CO2_SPECS.get(i.wrapping_sub(1))) else {
+        outln!("{}", "**Invalid selection!**".bold().red());
+        print_gas_state(program_state);
+        return;
+    };
+    let p = read_pressure(program_state, "transport pressure");
+    let t = read_temperature(program_state, "transport temperature");
+    let checks = check_co2_spec(spec, &program_state.gas_comp);
+
+    outln!();
+    outln!("{} - {}", spec.name, program_state.gas);
+    outln!("{:<22} {:>12} {:>10} {:>10} {:<8} {:>6}", "Parameter", "Value", "Min", "Max", "Unit", "Result");
+    for check in &checks {
+        let result = if check.passes() { "PASS".green().bold() } else { "FAIL".red().bold() };
+        outln!("{:<22} {:12.4} {} {} {:<8} {:>6}", check.parameter, check.value, format_limit(check.min), format_limit(check.max), check.unit, result);
+    }
+    let failures = checks.iter().filter(|check| !check.passes()).count();
+    if failures > 0 {
+        outln!("{}", format!("** Stream fails {} of {} specification limits **", failures, checks.len()).red().bold());
+    }
+
+    let gas_comp = &program_state.gas_comp;
+    let (phase, margin) = dense_phase_margin(p.kpa(), t.kelvin());
+    let p_unit = program_state.units.pressure.label();
+    outln!();
+    outln!("{}", "Dense Phase".bold());
+    outln!("{:<30} {:10.4} {:10}", "Pressure: ", p.value(program_state.units.pressure), p_unit);
+    outln!("{:<30} {:10.4} {:10}", "Temperature: ", t.value(program_state.units.temp), program_state.units.temp.label());
+    if t.kelvin() < CO2_CRITICAL_TEMPERATURE {
+        outln!("{:<30} {:10.4} {:10}", "CO2 Saturation Pressure: ", get_pressure(co2_vapor_pressure(t.kelvin()), program_state.units.pressure), p_unit);
+    }
+    outln!("{:<30} {:10.4} {:10}", "Margin Above Boundary: ", get_pressure(margin, program_state.units.pressure), p_unit);
+    outln!("{:<30} {:>10}", "Phase (pure CO2): ", phase);
+    match try_gerg_state(gas_comp, p.kpa(), t.kelvin()) {
+        Some(state) => outln!("{:<30} {:10.4} {:10}", "Density (GERG-2008): ", state.d * state.mm, "kg/m3"),
+        None => outln!("{}", "* GERG-2008 density did not converge".yellow()),
+    }
+    if non_condensables(gas_comp) > 0.0 {
+        outln!("{}", "* Non-condensables widen the two-phase envelope above the pure CO2 boundary; keep a pressure margin".yellow());
+    }
+
+    print_gas_state(program_state);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Span and Wagner (1996) saturation table: 3485.1 kPa at 0 C and 5729.1 kPa at 20 C
+    #[test]
+    fn co2_saturation_pressures() {
+        assert!((co2_vapor_pressure(273.15) - 3485.1).abs() < 1.0);
+        assert!((co2_vapor_pressure(293.15) - 5729.1).abs() < 1.0);
+        assert_eq!(dense_phase_margin(6000.0, 293.15).0, "Dense liquid");
+        assert_eq!(dense_phase_margin(5000.0, 293.15).0, "Gas");
+    }
+}
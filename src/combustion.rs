@@ -0,0 +1,178 @@
+use aga8::composition::Composition;
+use aga8::detail::Detail;
+use colored::Colorize;
+
+use crate::components::{array_to_comp, comp_to_array, COMPONENT_LABELS, NUM_COMPONENTS};
+use crate::{get_gas_comp, print_gas_state, read_f64, GasComp, ProgramState};
+
+const SULFUR_DIOXIDE_MOLAR_MASS: f64 = 64.066;
+
+// Carbon, hydrogen, oxygen and sulfur atoms per molecule, same order as the fields of
+// aga8::composition::Composition
+const ATOMS: [[f64; 4]; NUM_COMPONENTS] = [
+    [1.0, 4.0, 0.0, 0.0], // Methane
+    [0.0, 0.0, 0.0, 0.0], // Nitrogen
+    [1.0, 0.0, 2.0, 0.0], // Carbon Dioxide
+    [2.0, 6.0, 0.0, 0.0], // Ethane
+    [3.0, 8.0, 0.0, 0.0], // Propane
+    [4.0, 10.0, 0.0, 0.0], // Isobutane
+    [4.0, 10.0, 0.0, 0.0], // n-Butane
+    [5.0, 12.0, 0.0, 0.0], // Isopentane
+    [5.0, 12.0, 0.0, 0.0], // n-Pentane
+    [6.0, 14.0, 0.0, 0.0], // Hexane
+    [7.0, 16.0, 0.0, 0.0], // Heptane
+    [8.0, 18.0, 0.0, 0.0], // Octane
+    [9.0, 20.0, 0.0, 0.0], // Nonane
+    [10.0, 22.0, 0.0, 0.0], // Decane
+    [0.0, 2.0, 0.0, 0.0], // Hydrogen
+    [0.0, 0.0, 2.0, 0.0], // Oxygen
+    [1.0, 0.0, 1.0, 0.0], // Carbon Monoxide
+    [0.0, 2.0, 1.0, 0.0], // Water
+    [0.0, 2.0, 0.0, 1.0], // Hydrogen Sulfide
+    [0.0, 0.0, 0.0, 0.0], // Helium
+    [0.0, 0.0, 0.0, 0.0], // Argon
+];
+
+// Complete combustion of one mol of fuel.  Carbon burns to CO2, hydrogen to water and
+// sulfur to SO2; nitrogen and the noble gases pass through.
+pub struct Combustion {
+    // O2 demand in mol per mol of fuel, net of the oxygen in the fuel
+    pub oxygen_demand: f64,
+    // Stoichiometric and actual air in mol per mol of fuel
+    pub stoichiometric_air: f64,
+    pub air: f64,
+    // Flue gas in mol per mol of fuel, by component of Composition, and its SO2
+    pub flue: [f64; NUM_COMPONENTS],
+    pub sulfur_dioxide: f64,
+}
+
+impl Combustion {
+    pub fn flue_total(&self) -> f64 {
+        self.flue.iter().sum::<f64>() + self.sulfur_dioxide
+    }
+
+    pub fn flue_dry_total(&self) -> f64 {
+        self.flue_total() - self.flue[17]
+    }
+}
+
+// Combustion of the fuel with air of the given composition and excess air (fraction over
+// stoichiometric)
+pub fn combustion(fuel: &Composition, air: &Composition, excess_air: f64) -> Option<Combustion> {
+    let x = comp_to_array(fuel);
+    let (mut carbon, mut hydrogen, mut oxygen, mut sulfur) = (0.0, 0.0, 0.0, 0.0);
+    for (xi, [c, h, o, s]) in x.iter().zip(ATOMS) {
+        carbon += xi * c;
+        hydrogen += xi * h;
+        oxygen += xi * o;
+        sulfur += xi * s;
+    }
+    let oxygen_demand = carbon + hydrogen / 4.0 + sulfur - oxygen / 2.0;
+    if oxygen_demand <= 0.0 || air.oxygen <= 0.0 {
+        return None;
+    }
+    let stoichiometric_air = oxygen_demand / air.oxygen;
+    let air_moles = stoichiometric_air * (1.0 + excess_air);
+
+    // Inerts of the fuel and all of the air, then the combustion products and excess O2
+    let mut flue = [0.0; NUM_COMPONENTS];
+    for (j, [c, h, o, s]) in ATOMS.iter().enumerate() {
+        if c + h + o + s == 0.0 {
+            flue[j] = x[j];
+        }
+    }
+    for (flue_j, air_j) in flue.iter_mut().zip(comp_to_array(air)) {
+        *flue_j += air_moles * air_j;
+    }
+    flue[2] += carbon;
+    flue[17] = hydrogen / 2.0 + air_moles * air.water;
+    flue[15] = air_moles * air.oxygen - oxygen_demand;
+    Some(Combustion { oxygen_demand, stoichiometric_air, air: air_moles, flue, sulfur_dioxide: sulfur })
+}
+
+fn molar_mass(gas_comp: &Composition) -> f64 {
+    let mut state = Detail::new();
+    state.set_composition(gas_comp).ok();
+    state.molar_mass()
+}
+
+// Flue gas molar mass in g/mol
+pub fn flue_molar_mass(result: &Combustion) -> f64 {
+    let moles: f64 = result.flue.iter().sum();
+    let mass = molar_mass(&array_to_comp(&result.flue.map(|n| n / moles))) * moles
+        + result.sulfur_dioxide * SULFUR_DIOXIDE_MOLAR_MASS;
+    mass / result.flue_total()
+}
+
+// Stoichiometric air, excess air flue gas composition and molar mass of the working gas
+// burned with dry air
+pub fn combustion_report(program_state: &mut ProgramState) {
+    let air = get_gas_comp(GasComp::Air);
+    let excess_air = read_f64("Enter excess air (% over stoichiometric):") / 100.0;
+    // Substoichiometric combustion would leave CO and unburned fuel
+    if excess_air < 0.0 {
+        outln!("{}", "** Excess air cannot be negative **".red().bold());
+        print_gas_state(program_state);
+        return;
+    }
+    let Some(result) = combustion(&program_state.gas_comp, &air, excess_air) else {
+        outln!("{}", "** The gas has no combustibles **".red().bold());
+        print_gas_state(program_state);
+        return;
+    };
+    let mm_fuel = molar_mass(&program_state.gas_comp);
+    let mm_air = molar_mass(&air);
+
+    outln!();
+    outln!("{}", format!("Combustion of {} with dry air", program_state.gas).bold());
+    outln!("{:<30} {:10.4} {:10}", "Oxygen Demand: ", result.oxygen_demand, "mol/mol");
+    outln!("{:<30} {:10.4} {:10}", "Stoichiometric Air: ", result.stoichiometric_air, "mol/mol");
+    outln!("{:<30} {:10.4} {:10}", "Stoichiometric Air: ", result.stoichiometric_air * mm_air / mm_fuel, "kg/kg");
+    outln!("{:<30} {:10.4} {:10}", "Excess Air: ", excess_air * 100.0, "%");
+    outln!("{:<30} {:10.4} {:10}", "Combustion Air: ", result.air, "mol/mol");
+    outln!("{:<30} {:10.4} {:10}", "Combustion Air: ", result.air * mm_air / mm_fuel, "kg/kg");
+    outln!("{:<30} {:10.4} {:10}", "Flue Gas: ", result.flue_total(), "mol/mol");
+    outln!("{:<30} {:10.4} {:10}", "Flue Gas Molar Mass: ", flue_molar_mass(&result), "g/mol");
+
+    outln!();
+    outln!("{:<20} {:>12} {:>12}", "Flue Gas", "Wet mol%", "Dry mol%");
+    let (wet, dry) = (result.flue_total(), result.flue_dry_total());
+    for (j, n) in result.flue.iter().enumerate() {
+        if *n > 0.0 {
+            let dry_percent = if j == 17 { String::from("-") } else { format!("{:.4}", n / dry * 100.0) };
+            outln!("{:<20} {:12.4} {:>12}", COMPONENT_LABELS[j], n / wet * 100.0, dry_percent);
+        }
+    }
+    if result.sulfur_dioxide > 0.0 {
+        outln!("{:<20} {:12.4} {:12.4}", "Sulfur Dioxide", result.sulfur_dioxide / wet * 100.0, result.sulfur_dioxide / dry * 100.0);
+    }
+    print_gas_state(program_state);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Methane in 21/79 air: 2 mol O2 and 9.524 mol air per mol, and 11.73 mol% CO2 in the
+    // dry flue gas (the ultimate CO2 of natural gas firing)
+    #[test]
+    fn methane_stoichiometric_combustion() {
+        let fuel = Composition { methane: 1.0, ..Default::default() };
+        let air = Composition { nitrogen: 0.79, oxygen: 0.21, ..Default::default() };
+        let result = combustion(&fuel, &air, 0.0).unwrap();
+        assert!((result.oxygen_demand - 2.0).abs() < 1.0e-12);
+        assert!((result.stoichiometric_air - 9.5238).abs() < 1.0e-4);
+        assert!((result.flue[17] - 2.0).abs() < 1.0e-12);
+        assert!(result.flue[15].abs() < 1.0e-12);
+        assert!((result.flue[2] / result.flue_dry_total() * 100.0 - 11.73).abs() < 0.01);
+    }
+
+    // Carbon dioxide in the air passes through to the flue gas
+    #[test]
+    fn air_carbon_dioxide_passes_through() {
+        let fuel = Composition { methane: 1.0, ..Default::default() };
+        let air = Composition { nitrogen: 0.7896, oxygen: 0.21, carbon_dioxide: 0.0004, ..Default::default() };
+        let result = combustion(&fuel, &air, 0.1).unwrap();
+        assert!((result.flue[2] - (1.0 + result.air * 0.0004)).abs() < 1.0e-12);
+    }
+}
@@ -0,0 +1,120 @@
+use aga8::composition::Composition;
+use aga8::gerg2008::Gerg2008;
+use colored::Colorize;
+
+use crate::gross::{gross_composition, GrossInputs};
+use crate::peng_robinson::{PengRobinson, PrState};
+use crate::quality::{gross_hv_volumetric, relative_density};
+use crate::{print_gas_state, try_gas_state, ProgramState};
+
+// Property set shared by the equations of state being compared
+struct EosProperties {
+    d: f64,
+    z: f64,
+    h: f64,
+    s: f64,
+    cp: f64,
+    cv: f64,
+    w: f64,
+    kappa: f64,
+    jt: f64,
+}
+
+type PropertyRow = (&'static str, fn(&EosProperties) -> f64);
+
+fn detail_properties(gas_comp: &Composition, p: f64, t: f64) -> Option<EosProperties> {
+    let state = try_gas_state(gas_comp, p, t)?;
+    Some(EosProperties { d: state.d, z: state.z, h: state.h, s: state.s, cp: state.cp, cv: state.cv, w: state.w, kappa: state.kappa, jt: state.jt })
+}
+
+pub fn try_gerg_state(gas_comp: &Composition, p: f64, t: f64) -> Option<Gerg2008> {
+    let mut state = Gerg2008::new();
+    state.set_composition(gas_comp).ok()?;
+    state.p = p;
+    state.t = t;
+    state.density(0).ok()?;
+    state.properties();
+    Some(state)
+}
+
+fn gerg_properties(gas_comp: &Composition, p: f64, t: f64) -> Option<EosProperties> {
+    let state = try_gerg_state(gas_comp, p, t)?;
+    Some(EosProperties { d: state.d, z: state.z, h: state.h, s: state.s, cp: state.cp, cv: state.cv, w: state.w, kappa: state.kappa, jt: state.jt })
+}
+
+// Single phase Peng-Robinson state; None in the two-phase region
+fn peng_robinson_properties(gas_comp: &Composition, p: f64, t: f64) -> Option<EosProperties> {
+    let PrState::SinglePhase(state) = PengRobinson::new(gas_comp).flash(p, t)? else {
+        return None;
+    };
+    Some(EosProperties { d: state.d, z: state.z, h: state.h, s: state.s, cp: state.cp, cv: state.cv, w: state.w, kappa: state.kappa, jt: state.jt })
+}
+
+// Gross characterization built from the current composition's heating value, relative
+// density, CO2 and H2, then evaluated with DETAIL.
+fn gross_properties(program_state: &ProgramState) -> Option<EosProperties> {
+    let gas_comp = &program_state.gas_comp;
+    let inputs = GrossInputs {
+        heating_value: gross_hv_volumetric(gas_comp, program_state.base_pressure, program_state.base_temperature),
+        relative_density: relative_density(gas_comp, program_state.base_pressure, program_state.base_temperature),
+        carbon_dioxide: gas_comp.carbon_dioxide,
+        hydrogen: gas_comp.hydrogen,
+    };
+    let equivalent = gross_composition(&inputs, program_state.base_pressure, program_state.base_temperature).ok()?;
+    detail_properties(&equivalent, program_state.gas_state.p, program_state.gas_state.t)
+}
+
+fn format_value(value: Option<f64>) -> String {
+    match value {
+        Some(value) => format!("{:12.4}", value),
+        None => format!("{:>12}", "n/a"),
+    }
+}
+
+fn format_deviation(value: Option<f64>, reference: Option<f64>) -> String {
+    match (value, reference) {
+        (Some(value), Some(reference)) if reference != 0.0 => format!("{:9.4}", (value - reference) / reference.abs() * 100.0),
+        _ => format!("{:>9}", "-"),
+    }
+}
+
+pub fn eos_comparison(program_state: &mut ProgramState) {
+    let p = program_state.gas_state.p;
+    let t = program_state.gas_state.t;
+    let detail = detail_properties(&program_state.gas_comp, p, t);
+    let gerg = gerg_properties(&program_state.gas_comp, p, t);
+    let gross = gross_properties(program_state);
+    let peng_robinson = peng_robinson_properties(&program_state.gas_comp, p, t);
+
+    outln!();
+    outln!("{}", "Equation of State Comparison".cyan().bold());
+    outln!("{:<22} {:>12} {:>12} {:>9} {:>12} {:>9} {:>12} {:>9}", "Property", "DETAIL", "GERG-2008", "Dev %", "Gross", "Dev %",
+        "Peng-Rob.", "Dev %");
+    let rows: [PropertyRow; 9] = [
+        ("Density mol/l", |props| props.d),
+        ("Compressibility Z", |props| props.z),
+        ("Enthalpy J/mol", |props| props.h),
+        ("Entropy J/(mol-K)", |props| props.s),
+        ("Cp J/(mol-K)", |props| props.cp),
+        ("Cv J/(mol-K)", |props| props.cv),
+        ("Speed of Sound m/s", |props| props.w),
+        ("Isentropic Exp. k", |props| props.kappa),
+        ("JT Coefficient K/kPa", |props| props.jt),
+    ];
+    for (label, property) in rows {
+        let reference = detail.as_ref().map(property);
+        let gerg_value = gerg.as_ref().map(property);
+        let gross_value = gross.as_ref().map(property);
+        let peng_robinson_value = peng_robinson.as_ref().map(property);
+        outln!("{:<22} {} {} {} {} {} {} {}", label, format_value(reference),
+            format_value(gerg_value), format_deviation(gerg_value, reference),
+            format_value(gross_value), format_deviation(gross_value, reference),
+            format_value(peng_robinson_value), format_deviation(peng_robinson_value, reference));
+    }
+    outln!();
+    outln!("Deviations are relative to DETAIL.  Gross uses the equivalent gas built from the current");
+    outln!("heating value, relative density, CO2 and H2 at the base conditions.  Peng-Robinson is the");
+    outln!("cubic equation with the DETAIL ideal gas part, n/a inside the phase envelope.");
+
+    print_gas_state(program_state);
+}
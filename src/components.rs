@@ -0,0 +1,258 @@
+use aga8::composition::Composition;
+use aga8::detail::Detail;
+
+pub const NUM_COMPONENTS: usize = 21;
+
+// Field names of aga8::composition::Composition, in order
+pub const COMPONENT_NAMES: [&str; NUM_COMPONENTS] = [
+    "methane",
+    "nitrogen",
+    "carbon_dioxide",
+    "ethane",
+    "propane",
+    "isobutane",
+    "n_butane",
+    "isopentane",
+    "n_pentane",
+    "hexane",
+    "heptane",
+    "octane",
+    "nonane",
+    "decane",
+    "hydrogen",
+    "oxygen",
+    "carbon_monoxide",
+    "water",
+    "hydrogen_sulfide",
+    "helium",
+    "argon",
+];
+
+// Display names in the same order
+pub const COMPONENT_LABELS: [&str; NUM_COMPONENTS] = [
+    "Methane",
+    "Nitrogen",
+    "Carbon Dioxide",
+    "Ethane",
+    "Propane",
+    "Isobutane",
+    "n-Butane",
+    "Isopentane",
+    "n-Pentane",
+    "Hexane",
+    "Heptane",
+    "Octane",
+    "Nonane",
+    "Decane",
+    "Hydrogen",
+    "Oxygen",
+    "Carbon Monoxide",
+    "Water",
+    "Hydrogen Sulfide",
+    "Helium",
+    "Argon",
+];
+
+// Ideal gross (superior) heating values at 25 C in kJ/mol (ISO 6976), same order as the
+// fields of aga8::composition::Composition
+pub const GROSS_HV: [f64; NUM_COMPONENTS] = [
+    891.56, // Methane
+    0.0, // Nitrogen
+    0.0, // Carbon Dioxide
+    1562.14, // Ethane
+    2221.10, // Propane
+    2870.58, // Isobutane
+    2879.76, // n-Butane
+    3531.68, // Isopentane
+    3538.60, // n-Pentane
+    4198.24, // Hexane
+    4857.18, // Heptane
+    5516.01, // Octane
+    6175.82, // Nonane
+    6834.90, // Decane
+    285.83, // Hydrogen
+    0.0, // Oxygen
+    282.98, // Carbon Monoxide
+    0.0, // Water
+    562.01, // Hydrogen Sulfide
+    0.0, // Helium
+    0.0, // Argon
+];
+
+// Critical temperatures in K, critical pressures in kPa and acentric factors for the
+// cubic equations of state, same order as the fields of aga8::composition::Composition
+pub const CRITICAL_TEMPERATURE: [f64; NUM_COMPONENTS] = [
+    190.564, // Methane
+    126.192, // Nitrogen
+    304.128, // Carbon Dioxide
+    305.322, // Ethane
+    369.89, // Propane
+    407.81, // Isobutane
+    425.125, // n-Butane
+    460.35, // Isopentane
+    469.7, // n-Pentane
+    507.82, // Hexane
+    540.13, // Heptane
+    569.32, // Octane
+    594.55, // Nonane
+    617.7, // Decane
+    33.145, // Hydrogen
+    154.581, // Oxygen
+    132.86, // Carbon Monoxide
+    647.096, // Water
+    373.1, // Hydrogen Sulfide
+    5.1953, // Helium
+    150.687, // Argon
+];
+
+pub const CRITICAL_PRESSURE: [f64; NUM_COMPONENTS] = [
+    4599.2, // Methane
+    3395.8, // Nitrogen
+    7377.3, // Carbon Dioxide
+    4872.2, // Ethane
+    4251.2, // Propane
+    3629.0, // Isobutane
+    3796.0, // n-Butane
+    3378.0, // Isopentane
+    3367.5, // n-Pentane
+    3034.0, // Hexane
+    2736.0, // Heptane
+    2497.0, // Octane
+    2281.0, // Nonane
+    2103.0, // Decane
+    1296.4, // Hydrogen
+    5043.0, // Oxygen
+    3494.0, // Carbon Monoxide
+    22064.0, // Water
+    9000.0, // Hydrogen Sulfide
+    227.6, // Helium
+    4863.0, // Argon
+];
+
+pub const ACENTRIC_FACTOR: [f64; NUM_COMPONENTS] = [
+    0.0114, // Methane
+    0.0372, // Nitrogen
+    0.2239, // Carbon Dioxide
+    0.0995, // Ethane
+    0.1521, // Propane
+    0.184, // Isobutane
+    0.201, // n-Butane
+    0.2274, // Isopentane
+    0.251, // n-Pentane
+    0.299, // Hexane
+    0.349, // Heptane
+    0.393, // Octane
+    0.443, // Nonane
+    0.488, // Decane
+    -0.219, // Hydrogen
+    0.0222, // Oxygen
+    0.0497, // Carbon Monoxide
+    0.3443, // Water
+    0.1005, // Hydrogen Sulfide
+    -0.3836, // Helium
+    -0.0022, // Argon
+];
+
+pub fn comp_to_array(gas_comp: &Composition) -> [f64; NUM_COMPONENTS] {
+    [
+        gas_comp.methane,
+        gas_comp.nitrogen,
+        gas_comp.carbon_dioxide,
+        gas_comp.ethane,
+        gas_comp.propane,
+        gas_comp.isobutane,
+        gas_comp.n_butane,
+        gas_comp.isopentane,
+        gas_comp.n_pentane,
+        gas_comp.hexane,
+        gas_comp.heptane,
+        gas_comp.octane,
+        gas_comp.nonane,
+        gas_comp.decane,
+        gas_comp.hydrogen,
+        gas_comp.oxygen,
+        gas_comp.carbon_monoxide,
+        gas_comp.water,
+        gas_comp.hydrogen_sulfide,
+        gas_comp.helium,
+        gas_comp.argon,
+    ]
+}
+
+pub fn array_to_comp(x: &[f64; NUM_COMPONENTS]) -> Composition {
+    Composition {
+        methane: x[0],
+        nitrogen: x[1],
+        carbon_dioxide: x[2],
+        ethane: x[3],
+        propane: x[4],
+        isobutane: x[5],
+        n_butane: x[6],
+        isopentane: x[7],
+        n_pentane: x[8],
+        hexane: x[9],
+        heptane: x[10],
+        octane: x[11],
+        nonane: x[12],
+        decane: x[13],
+        hydrogen: x[14],
+        oxygen: x[15],
+        carbon_monoxide: x[16],
+        water: x[17],
+        hydrogen_sulfide: x[18],
+        helium: x[19],
+        argon: x[20],
+    }
+}
+
+// Molar mass (g/mol) of the pure component at index i
+pub fn component_molar_mass(i: usize) -> f64 {
+    let mut x = [0.0; NUM_COMPONENTS];
+    x[i] = 1.0;
+    let mut state = Detail::new();
+    state.set_composition(&array_to_comp(&x)).unwrap();
+    state.molar_mass()
+}
+
+// Composition from mole fractions by component name, e.g. ("methane", 0.9).  The
+// fractions must sum to 1.
+pub fn composition_from_fractions<'a>(fractions: impl IntoIterator<Item = (&'a str, f64)>) -> Result<Composition, String> {
+    let mut x = [0.0; NUM_COMPONENTS];
+    for (name, value) in fractions {
+        let index = COMPONENT_NAMES.iter().position(|component| *component == name).ok_or(format!("Unknown component '{}'", name))?;
+        x[index] = value;
+    }
+    let sum: f64 = x.iter().sum();
+    if (sum - 1.0).abs() > 1.0e-4 {
+        return Err(format!("Mole fractions sum to {}", sum));
+    }
+    Ok(array_to_comp(&x))
+}
+
+// Serde adapter for aga8 compositions as a map of mole fractions by component name,
+// for fields marked #[serde(with = "composition_serde")].  Zero fractions are omitted.
+#[cfg(feature = "serde")]
+pub mod composition_serde {
+    use aga8::composition::Composition;
+    use serde::de::Error;
+    use serde::ser::SerializeMap;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    use super::{comp_to_array, composition_from_fractions, COMPONENT_NAMES};
+
+    use std::collections::HashMap;
+
+    pub fn serialize<S: Serializer>(gas_comp: &Composition, serializer: S) -> Result<S::Ok, S::Error> {
+        let x = comp_to_array(gas_comp);
+        let mut map = serializer.serialize_map(None)?;
+        for (name, fraction) in COMPONENT_NAMES.iter().zip(x).filter(|(_, fraction)| *fraction != 0.0) {
+            map.serialize_entry(name, &fraction)?;
+        }
+        map.end()
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Composition, D::Error> {
+        let fractions = HashMap::<String, f64>::deserialize(deserializer)?;
+        composition_from_fractions(fractions.iter().map(|(name, value)| (name.as_str(), *value))).map_err(D::Error::custom)
+    }
+}
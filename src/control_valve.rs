@@ -0,0 +1,273 @@
+use colored::Colorize;
+
+use crate::process::isenthalpic_state;
+use crate::{print_gas_state, read_f64, read_pressure, Pressure, ProgramState};
+
+use std::f64::consts::PI;
+
+// Speed of sound in the steel pipe wall (m/s) and in air at standard conditions (m/s)
+const STEEL_SOUND_SPEED: f64 = 5000.0;
+const AIR_SOUND_SPEED: f64 = 343.0;
+// Jet diameter constant for Cv (IEC 60534-8-3 N14; 4.9e-3 is the Kv value)
+const N14: f64 = 4.6e-3;
+// Mass flow sizing constant for Cv with kg/h, kPa and kg/m3 (IEC 60534-2-1 N6; 3.16 for Kv)
+const N6: f64 = 2.73;
+// Kv = 0.865 Cv
+const KV_PER_CV: f64 = 0.865;
+
+pub struct NoiseInputs {
+    // Upstream pressure, density in kg/m3 and isentropic exponent
+    pub p1: Pressure,
+    pub rho1: f64,
+    pub kappa: f64,
+    // Downstream pressure, density in kg/m3 and speed of sound in m/s
+    pub p2: Pressure,
+    pub rho2: f64,
+    pub c2: f64,
+    // Mass flow in kg/s
+    pub mass_flow: f64,
+    pub cv: f64,
+    // Liquid pressure recovery factor, valve style modifier and acoustic power ratio
+    pub fl: f64,
+    pub fd: f64,
+    pub rw: f64,
+    // Downstream pipe internal diameter and wall thickness in m
+    pub pipe_diameter: f64,
+    pub wall_thickness: f64,
+}
+
+pub struct NoiseResult {
+    pub regime: &'static str,
+    pub jet_mach: f64,
+    pub outlet_mach: f64,
+    pub acoustic_efficiency: f64,
+    pub peak_frequency: f64,
+    pub internal_spl: f64,
+    pub transmission_loss: f64,
+    // A-weighted sound pressure level 1 m from the pipe wall in dB(A)
+    pub external_spl: f64,
+}
+
+// Aerodynamic noise screening estimate following the structure of IEC 60534-8-3:
+// flow regime from the vena contracta pressures, acoustic efficiency from the jet Mach
+// number, internal sound pressure level and pipe wall transmission loss.
+pub fn aerodynamic_noise(inputs: &NoiseInputs) -> NoiseResult {
+    let k = inputs.kappa;
+    let fl2 = inputs.fl * inputs.fl;
+    let p1 = inputs.p1.kpa() * 1000.0;
+    let p2 = inputs.p2.kpa() * 1000.0;
+
+    let pvcc = p1 * (2.0 / (k + 1.0)).powf(k / (k - 1.0));
+    let p2c = p1 - fl2 * (p1 - pvcc);
+    let alpha = pvcc / p2c;
+    let p2b = p1 / alpha * (1.0 / k).powf(k / (k - 1.0));
+    let p2ce = p1 / (22.0 * alpha);
+
+    let jet_mach = |ratio: f64| (2.0 / (k - 1.0) * (ratio.powf((k - 1.0) / k) - 1.0)).sqrt();
+    let cvcc = (2.0 * k / (k + 1.0) * p1 / inputs.rho1).sqrt();
+    let (regime, mach, stream_power, efficiency) = if p2 >= p2c {
+        let pvc = p1 - (p1 - p2) / fl2;
+        let uvc = (2.0 * k / (k - 1.0) * (1.0 - (pvc / p1).powf((k - 1.0) / k)) * p1 / inputs.rho1).sqrt();
+        let mvc = uvc / cvcc;
+        ("I", mvc, inputs.mass_flow * uvc * uvc / 2.0, 1.0e-4 * mvc.powf(3.6))
+    } else {
+        let stream_power = inputs.mass_flow * cvcc * cvcc / 2.0;
+        if p2 >= p2b {
+            let mj = jet_mach(p1 / (alpha * p2));
+            let regime = if p2 >= pvcc { "II" } else { "III" };
+            (regime, mj, stream_power, 1.0e-4 * mj.powf(6.6 * fl2))
+        } else if p2 >= p2ce {
+            let mj = jet_mach(p1 / (alpha * p2));
+            ("IV", mj, stream_power, 1.0e-4 * mj * mj / 2.0 * 2.0_f64.sqrt().powf(6.6 * fl2))
+        } else {
+            let mj = jet_mach(22.0);
+            ("V", mj, stream_power, 1.0e-4 * mj * mj / 2.0 * 2.0_f64.sqrt().powf(6.6 * fl2))
+        }
+    };
+    let acoustic_power = efficiency * inputs.rw * stream_power;
+
+    let jet_diameter = N14 * inputs.fd * (inputs.cv * inputs.fl).sqrt();
+    let peak_frequency = 0.2 * mach * cvcc / jet_diameter;
+
+    let di = inputs.pipe_diameter;
+    let ts = inputs.wall_thickness;
+    let outlet_velocity = inputs.mass_flow / (inputs.rho2 * PI * di * di / 4.0);
+    let outlet_mach = outlet_velocity / inputs.c2;
+    let lg = 16.0 * (1.0 / (1.0 - outlet_mach.min(0.99))).log10();
+    let internal_spl = 10.0 * (3.2e9 * acoustic_power * inputs.rho2 * inputs.c2 / (di * di)).log10() + lg;
+
+    // Pipe ring, first coincidence and cut-off frequencies
+    let fr = STEEL_SOUND_SPEED / (PI * di);
+    let fo = fr / 4.0 * (inputs.c2 / AIR_SOUND_SPEED);
+    let fg = 3.0_f64.sqrt() * AIR_SOUND_SPEED * AIR_SOUND_SPEED / (PI * ts * STEEL_SOUND_SPEED);
+    let gx = if peak_frequency < fo {
+        (fo / fr).powf(2.0 / 3.0) * (peak_frequency / fo).powi(4)
+    } else if peak_frequency < fr {
+        (peak_frequency / fr).powf(2.0 / 3.0)
+    } else {
+        1.0
+    };
+    let gy = if peak_frequency < fo {
+        if fo < fg { fo / fg } else { 1.0 }
+    } else if peak_frequency < fg {
+        peak_frequency / fg
+    } else {
+        1.0
+    };
+    let rho_air = 1.293;
+    let transmission_loss = 10.0 * (7.6e-7 * (inputs.c2 / (ts * peak_frequency)).powi(2) * gx
+        / (inputs.rho2 * inputs.c2 / (415.0 * gy) + 1.0) * (101_325.0 / p2)).log10();
+    let distance_correction = 10.0 * ((di + 2.0 * ts + 2.0) / (di + 2.0 * ts)).log10();
+    let external_spl = 5.0 + internal_spl + transmission_loss - distance_correction + 10.0 * (rho_air * AIR_SOUND_SPEED / 415.0).log10();
+
+    NoiseResult {
+        regime,
+        jet_mach: mach,
+        outlet_mach,
+        acoustic_efficiency: efficiency,
+        peak_frequency,
+        internal_spl,
+        transmission_loss,
+        external_spl,
+    }
+}
+
+pub struct SizingResult {
+    pub choked: bool,
+    // Pressure differential ratio used for sizing and its choked limit
+    pub x: f64,
+    pub x_choked: f64,
+    pub expansion_factor: f64,
+    pub cv: f64,
+}
+
+// Required Cv for a compressible fluid per IEC 60534-2-1 with the piping geometry
+// factor taken as 1 (valve the same size as the line).  rho1 in kg/m3, mass flow in
+// kg/s and xt the pressure differential ratio factor.
+pub fn required_cv(p1: Pressure, p2: Pressure, rho1: f64, kappa: f64, mass_flow: f64, xt: f64) -> SizingResult {
+    let (p1, p2) = (p1.kpa(), p2.kpa());
+    let f_gamma = kappa / 1.4;
+    let x_choked = f_gamma * xt;
+    let x_actual = (p1 - p2) / p1;
+    let choked = x_actual >= x_choked;
+    let x = x_actual.min(x_choked);
+    let expansion_factor = 1.0 - x / (3.0 * x_choked);
+    let cv = mass_flow * 3600.0 / (N6 * expansion_factor * (x * p1 * rho1).sqrt());
+    SizingResult { choked, x: x_actual, x_choked, expansion_factor, cv }
+}
+
+pub fn valve_sizing(program_state: &mut ProgramState) {
+    outln!();
+    outln!("{}", "Control Valve Sizing (IEC 60534-2-1, gas)".yellow().bold());
+    outln!("Upstream state is the current state.");
+    let inlet = &program_state.gas_state;
+    let p2 = read_pressure(program_state, "downstream pressure");
+    let mass_flow = read_f64("Enter required mass flow (kg/s):");
+    let xt = read_f64("Enter pressure differential ratio factor xT (e.g. 0.72 globe, 0.55 ball):");
+    let rated_cv = read_f64("Enter rated Cv of the selected valve (0 if not selected):");
+    if p2.kpa() >= inlet.p || p2.kpa() <= 0.0 {
+        outln!("{}", "** Downstream pressure must be between zero and the upstream pressure **".red().bold());
+        print_gas_state(program_state);
+        return;
+    }
+
+    let result = required_cv(Pressure::from_kpa(inlet.p), p2, inlet.d * inlet.mm, inlet.kappa, mass_flow, xt);
+
+    outln!();
+    outln!("{:<30} {:10.4} {:10}", "Upstream Density: ", inlet.d * inlet.mm, "kg/m3");
+    outln!("{:<30} {:10.4} {:10}", "Isentropic Exponent k: ", inlet.kappa, "[]");
+    outln!("{:<30} {:10.4} {:10}", "Pressure Drop Ratio x: ", result.x, "[]");
+    outln!("{:<30} {:10.4} {:10}", "Choked Limit Fk xT: ", result.x_choked, "[]");
+    outln!("{:<30} {:10.4} {:10}", "Expansion Factor Y: ", result.expansion_factor, "[]");
+    outln!("{:<30} {:10.2} {:10}", "Required Cv: ", result.cv, "US gpm/psi^0.5");
+    outln!("{:<30} {:10.2} {:10}", "Required Kv: ", result.cv * KV_PER_CV, "m3/h/bar^0.5");
+    if result.choked {
+        outln!("{}", "** Flow is choked - increasing the pressure drop will not increase the flow **".yellow().bold());
+    }
+    if rated_cv > 0.0 {
+        let opening = result.cv / rated_cv * 100.0;
+        outln!("{:<30} {:10.1} {:10}", "Required / Rated Cv: ", opening, "%");
+        if opening > 100.0 {
+            outln!("{}", "** Selected valve is too small for the required flow **".red().bold());
+        } else if !(10.0..=80.0).contains(&opening) {
+            outln!("{}", "* Required Cv is outside 10 - 80 % of the rated Cv".yellow());
+        }
+    }
+
+    print_gas_state(program_state);
+}
+
+pub fn valve_noise(program_state: &mut ProgramState) {
+    outln!();
+    outln!("{}", "Control Valve Aerodynamic Noise (IEC 60534-8-3 screening)".yellow().bold());
+    let inlet = &program_state.gas_state;
+    outln!("Upstream state is the current state.");
+    let p2 = read_pressure(program_state, "downstream pressure");
+    let mass_flow = read_f64("Enter mass flow (kg/s):");
+    let cv = read_f64("Enter valve Cv at the operating point:");
+    let fl = read_f64("Enter liquid pressure recovery factor FL (e.g. 0.9):");
+    let fd = read_f64("Enter valve style modifier Fd (e.g. 0.42 globe, 0.1 multi-hole trim):");
+    let rw = read_f64("Enter acoustic power ratio rw (e.g. 0.25 globe):");
+    let pipe_diameter = read_f64("Enter downstream pipe internal diameter (mm):") / 1000.0;
+    let wall_thickness = read_f64("Enter downstream pipe wall thickness (mm):") / 1000.0;
+
+    let outlet = match isenthalpic_state(&program_state.gas_comp, p2.kpa(), inlet.h, inlet.t) {
+        Some(outlet) => outlet,
+        None => {
+            outln!("{}", "** Unable to calculate the downstream state **".red().bold());
+            print_gas_state(program_state);
+            return;
+        }
+    };
+
+    let result = aerodynamic_noise(&NoiseInputs {
+        p1: Pressure::from_kpa(inlet.p),
+        rho1: inlet.d * inlet.mm,
+        kappa: inlet.kappa,
+        p2,
+        rho2: outlet.d * outlet.mm,
+        c2: outlet.w,
+        mass_flow,
+        cv,
+        fl,
+        fd,
+        rw,
+        pipe_diameter,
+        wall_thickness,
+    });
+
+    outln!();
+    outln!("{:<30} {:>10}", "Flow Regime: ", result.regime);
+    outln!("{:<30} {:10.4} {:10}", "Jet Mach Number: ", result.jet_mach, "[]");
+    outln!("{:<30} {:10.4} {:10}", "Outlet Mach Number: ", result.outlet_mach, "[]");
+    outln!("{:<30} {:10.3e} {:10}", "Acoustic Efficiency: ", result.acoustic_efficiency, "[]");
+    outln!("{:<30} {:10.1} {:10}", "Peak Frequency: ", result.peak_frequency, "Hz");
+    outln!("{:<30} {:10.1} {:10}", "Internal SPL: ", result.internal_spl, "dB");
+    outln!("{:<30} {:10.1} {:10}", "Transmission Loss: ", result.transmission_loss, "dB");
+    outln!("{:<30} {:10.1} {:10}", "External SPL at 1 m: ", result.external_spl, "dB(A)");
+    if result.outlet_mach > 0.3 {
+        outln!("{}", "** Outlet Mach number above 0.3 - consider a larger outlet pipe **".yellow().bold());
+    }
+
+    print_gas_state(program_state);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // IEC 60534-2-1 Annex D compressible flow example: carbon dioxide at 680 kPa and 433 K
+    // (Z 0.988, k 1.30) to 310 kPa, 3800 standard m3/h, xT 0.60.  Published Y 0.675 and
+    // Kv 62.7.
+    #[test]
+    fn iec_compressible_example() {
+        let rho1 = 680.0 * 44.01 / (0.988 * 8.314 * 433.0);
+        let rho_standard = 101.325 * 44.01 / (8.314 * 273.15);
+        let mass_flow = 3800.0 * rho_standard / 3600.0;
+        let result = required_cv(Pressure::from_kpa(680.0), Pressure::from_kpa(310.0), rho1, 1.30, mass_flow, 0.60);
+        assert!(!result.choked);
+        assert!((result.x - 0.544).abs() < 1.0e-3);
+        assert!((result.expansion_factor - 0.675).abs() < 1.0e-3);
+        assert!((result.cv * KV_PER_CV - 62.7).abs() < 0.3);
+    }
+}
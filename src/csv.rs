@@ -0,0 +1,71 @@
+use crate::components::{COMPONENT_NAMES, NUM_COMPONENTS};
+
+use std::fs;
+
+// Minimal comma separated file reader: the first line holds the column names.
+pub struct CsvTable {
+    pub headers: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+}
+
+impl CsvTable {
+    pub fn read(path: &str) -> Result<CsvTable, String> {
+        let text = fs::read_to_string(path).map_err(|err| format!("Unable to read {}: {}", path, err))?;
+        let mut lines = text.lines().filter(|line| !line.trim().is_empty());
+        let headers = match lines.next() {
+            Some(line) => split_line(line),
+            None => return Err(format!("{} is empty", path)),
+        };
+        let rows = lines.map(split_line).collect();
+        Ok(CsvTable { headers, rows })
+    }
+
+    pub fn column(&self, name: &str) -> Option<usize> {
+        self.headers.iter().position(|header| header.eq_ignore_ascii_case(name))
+    }
+
+    pub fn require_column(&self, name: &str) -> Result<usize, String> {
+        self.column(name).ok_or(format!("Missing column '{}'", name))
+    }
+}
+
+pub fn split_line(line: &str) -> Vec<String> {
+    line.split(',').map(|field| field.trim().to_string()).collect()
+}
+
+// Parses an optional numeric field; empty or missing fields are None.
+pub fn field_f64(row: &[String], column: Option<usize>) -> Result<Option<f64>, String> {
+    match column.and_then(|i| row.get(i)) {
+        Some(field) if !field.is_empty() => field.parse::<f64>().map(Some).map_err(|_| format!("Invalid number '{}'", field)),
+        _ => Ok(None),
+    }
+}
+
+// Column index of every component named as in COMPONENT_NAMES, None where absent
+pub fn component_columns(table: &CsvTable) -> Result<Vec<Option<usize>>, String> {
+    let columns: Vec<Option<usize>> = COMPONENT_NAMES.iter().map(|name| table.column(name)).collect();
+    if columns.iter().all(Option::is_none) {
+        return Err("No component columns found".to_string());
+    }
+    Ok(columns)
+}
+
+// Mole fractions from component fields in mol%; missing components are zero.
+pub fn component_fractions(row: &[String], columns: &[Option<usize>]) -> Result<[f64; NUM_COMPONENTS], String> {
+    let mut x = [0.0; NUM_COMPONENTS];
+    for (xi, column) in x.iter_mut().zip(columns) {
+        *xi = field_f64(row, *column)?.unwrap_or(0.0) / 100.0;
+    }
+    Ok(x)
+}
+
+pub fn write_csv(path: &str, headers: &[&str], rows: &[Vec<f64>]) -> Result<(), String> {
+    let mut text = headers.join(",");
+    text.push('\n');
+    for row in rows {
+        let fields: Vec<String> = row.iter().map(|value| value.to_string()).collect();
+        text.push_str(&fields.join(","));
+        text.push('\n');
+    }
+    fs::write(path, text).map_err(|err| format!("Unable to write {}: {}", path, err))
+}
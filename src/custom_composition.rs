@@ -0,0 +1,89 @@
+use aga8::composition::Composition;
+use colored::Colorize;
+
+use crate::components::{array_to_comp, comp_to_array, COMPONENT_LABELS, NUM_COMPONENTS};
+use crate::read_input;
+
+pub enum Normalization {
+    // The sum was already 1.0
+    Unchanged,
+    // Scaled automatically because the sum was within the tolerance
+    Automatic { sum: f64 },
+    // Scaled after the user accepted it
+    Accepted { sum: f64 },
+    Rejected { sum: f64 },
+}
+
+// Normalizes the composition when its sum deviates from 1.0.  Deviations within the
+// tolerance are corrected automatically, larger ones only if the user accepts.
+pub fn normalize_composition(gas_comp: &mut Composition, tolerance: f64) -> Normalization {
+    let sum = gas_comp.sum();
+    if (sum - 1.0).abs() < 1.0e-12 {
+        return Normalization::Unchanged;
+    }
+    if sum <= 0.0 {
+        return Normalization::Rejected { sum };
+    }
+    if (sum - 1.0).abs() <= tolerance {
+        gas_comp.normalize().unwrap();
+        return Normalization::Automatic { sum };
+    }
+    outln!("{}", format!("* Composition sums to {:.6} ({:+.4} mol%). Normalize to 1.0? (y/n)", sum, (sum - 1.0) * 100.0).yellow());
+    if read_input() == "y" {
+        gas_comp.normalize().unwrap();
+        Normalization::Accepted { sum }
+    } else {
+        Normalization::Rejected { sum }
+    }
+}
+
+pub fn print_normalization(normalization: &Normalization) {
+    match normalization {
+        Normalization::Unchanged => (),
+        Normalization::Automatic { sum } | Normalization::Accepted { sum } => {
+            outln!("Normalized: sum was {:.6}, every component scaled by {:.6} ({:+.4} mol% total adjustment)",
+                sum, 1.0 / sum, (1.0 - sum) * 100.0);
+        },
+        Normalization::Rejected { sum } => {
+            outln!("{}", format!("** Composition sum {:.6} was not normalized **", sum).red().bold());
+        },
+    }
+}
+
+// Prompts for every component in mol%; blank entries are zero.
+pub fn enter_custom_composition(tolerance: f64) -> Option<Composition> {
+    outln!("Enter each component in mol% (leave blank for 0):");
+    let mut x = [0.0; NUM_COMPONENTS];
+    for (i, label) in COMPONENT_LABELS.iter().enumerate() {
+        loop {
+            outln!("{}:", label);
+            let input = read_input();
+            if input.is_empty() {
+                break;
+            }
+            match input.parse::<f64>() {
+                Ok(value) if value >= 0.0 => {
+                    x[i] = value / 100.0;
+                    break;
+                },
+                _ => outln!("{}", "**Invalid number!**".bold().red()),
+            }
+        }
+    }
+
+    let mut gas_comp = array_to_comp(&x);
+    let normalization = normalize_composition(&mut gas_comp, tolerance);
+    print_normalization(&normalization);
+    match normalization {
+        Normalization::Rejected { .. } => None,
+        _ => Some(gas_comp),
+    }
+}
+
+pub fn print_composition(gas_comp: &Composition) {
+    for (label, fraction) in COMPONENT_LABELS.iter().zip(comp_to_array(gas_comp)) {
+        if fraction > 0.0 {
+            outln!("{:<30} {:10.4} {:10}", format!("{}: ", label), fraction * 100.0, "mol%");
+        }
+    }
+}
@@ -0,0 +1,133 @@
+use colored::Colorize;
+
+use crate::quality::gross_hv_volumetric;
+use crate::{print_gas_state, read_f64, read_input, Pressure, ProgramState, Temperature};
+
+// m3 per standard cubic foot
+const M3_PER_SCF: f64 = 0.0283168466;
+// Standard cubic feet are at 60 F and 14.696 psia
+const SCF_PRESSURE: f64 = 101.325;
+const SCF_TEMPERATURE: f64 = 288.705556;
+
+#[allow(non_camel_case_types, clippy::upper_case_acronyms)]
+#[derive(Clone, Copy, PartialEq)]
+pub enum FlowUnit {
+    Sm3_h,
+    Sm3_d,
+    MMscfd,
+    Mscf_d,
+}
+
+pub const FLOW_UNITS: [FlowUnit; 4] = [FlowUnit::Sm3_h, FlowUnit::Sm3_d, FlowUnit::MMscfd, FlowUnit::Mscf_d];
+
+impl FlowUnit {
+    pub fn label(self) -> &'static str {
+        match self {
+            FlowUnit::Sm3_h => "Sm3/h",
+            FlowUnit::Sm3_d => "Sm3/d",
+            FlowUnit::MMscfd => "MMscfd",
+            FlowUnit::Mscf_d => "Mscf/d",
+        }
+    }
+
+    // m3 per day at the unit's reference conditions
+    fn m3_per_day(self, flow: f64) -> f64 {
+        match self {
+            FlowUnit::Sm3_h => flow * 24.0,
+            FlowUnit::Sm3_d => flow,
+            FlowUnit::MMscfd => flow * 1.0e6 * M3_PER_SCF,
+            FlowUnit::Mscf_d => flow * 1.0e3 * M3_PER_SCF,
+        }
+    }
+
+    fn is_scf(self) -> bool {
+        matches!(self, FlowUnit::MMscfd | FlowUnit::Mscf_d)
+    }
+}
+
+#[allow(non_camel_case_types, clippy::upper_case_acronyms)]
+#[derive(Clone, Copy, PartialEq)]
+pub enum EnergyFlowUnit {
+    MMBtu_d,
+    GJ_d,
+    MWh_d,
+}
+
+pub const ENERGY_FLOW_UNITS: [EnergyFlowUnit; 3] = [EnergyFlowUnit::MMBtu_d, EnergyFlowUnit::GJ_d, EnergyFlowUnit::MWh_d];
+
+impl EnergyFlowUnit {
+    pub fn label(self) -> &'static str {
+        match self {
+            EnergyFlowUnit::MMBtu_d => "MMBtu/d",
+            EnergyFlowUnit::GJ_d => "GJ/d",
+            EnergyFlowUnit::MWh_d => "MWh/d",
+        }
+    }
+}
+
+// Converts an energy flow in MJ/d; the Btu is the International Table Btu
+pub fn get_energy_flow(energy: f64, unit: EnergyFlowUnit) -> f64 {
+    match unit {
+        EnergyFlowUnit::MMBtu_d => energy / 1055.05585,
+        EnergyFlowUnit::GJ_d => energy / 1000.0,
+        EnergyFlowUnit::MWh_d => energy / 3600.0,
+    }
+}
+
+// Pressure and temperature of the standard volume: the base conditions for Sm3 and 60 F
+// and 14.696 psia for scf
+fn reference_conditions(program_state: &ProgramState, unit: FlowUnit) -> (Pressure, Temperature) {
+    if unit.is_scf() {
+        (Pressure::from_kpa(SCF_PRESSURE), Temperature::from_kelvin(SCF_TEMPERATURE))
+    } else {
+        (program_state.base_pressure, program_state.base_temperature)
+    }
+}
+
+// Energy flow in MJ/d and the heating value in MJ/m3 at the reference conditions of the
+// flow unit
+pub fn energy_flow(program_state: &ProgramState, flow: f64, unit: FlowUnit) -> (f64, f64) {
+    let (p_ref, t_ref) = reference_conditions(program_state, unit);
+    let hv = gross_hv_volumetric(&program_state.gas_comp, p_ref, t_ref);
+    (hv * unit.m3_per_day(flow), hv)
+}
+
+// Energy flow of the working gas from a standard volumetric flow and its gross heating value
+pub fn energy_flow_report(program_state: &mut ProgramState) {
+    outln!();
+    outln!("{}", "Energy Flow Rate".magenta().bold());
+    let flow_unit = loop {
+        outln!("Select standard flow unit:");
+        for (i, unit) in FLOW_UNITS.iter().enumerate() {
+            outln!("{} - {}", i + 1, unit.label());
+        }
+        let choice = read_input();
+        if let Some(unit) = choice.parse::<usize>().ok().and_then(|i| FLOW_UNITS.get(i.wrapping_sub(1))) {
+            break *unit;
+        }
+    };
+    let flow = read_f64(&format!("Enter standard flow ({}):", flow_unit.label()));
+    outln!("Select energy flow unit:");
+    for (i, unit) in ENERGY_FLOW_UNITS.iter().enumerate() {
+        outln!("{} - {}", i + 1, unit.label());
+    }
+    outln!("a - All");
+    let choice = read_input();
+    let energy_units = match choice.parse::<usize>().ok().and_then(|i| ENERGY_FLOW_UNITS.get(i.wrapping_sub(1))) {
+        Some(unit) => vec![*unit],
+        None => ENERGY_FLOW_UNITS.to_vec(),
+    };
+
+    let (energy, hv) = energy_flow(program_state, flow, flow_unit);
+    let (p_ref, t_ref) = reference_conditions(program_state, flow_unit);
+    outln!();
+    outln!("Reference conditions {:.4} {}, {:.2} {}",
+        p_ref.value(program_state.units.pressure), program_state.units.pressure.label(),
+        t_ref.value(program_state.units.temp), program_state.units.temp.label());
+    outln!("{:<30} {:10.4} {:10}", "Gross Heating Value: ", hv, "MJ/m3");
+    outln!("{:<30} {:10.4} {:10}", "Standard Flow: ", flow, flow_unit.label());
+    for unit in energy_units {
+        outln!("{:<30} {:10.4} {:10}", "Energy Flow: ", get_energy_flow(energy, unit), unit.label());
+    }
+    print_gas_state(program_state);
+}
@@ -0,0 +1,141 @@
+use colored::Colorize;
+
+use crate::csv::{field_f64, CsvTable};
+use crate::{
+    print_gas_state, read_f64, read_input, read_pressure, read_temperature, Pressure, ProgramState,
+    Temperature, TemperatureDifference,
+};
+
+// Vertex i of the envelope in kPa and K
+fn vertex(envelope: &[(Pressure, Temperature)], i: usize) -> (f64, f64) {
+    let (p, t) = envelope[i % envelope.len()];
+    (p.kpa(), t.kelvin())
+}
+
+// Ray casting test of a point against the envelope polygon
+pub fn inside_envelope(envelope: &[(Pressure, Temperature)], p: Pressure, t: Temperature) -> bool {
+    let (p, t) = (p.kpa(), t.kelvin());
+    let mut inside = false;
+    for i in 0..envelope.len() {
+        let (p1, t1) = vertex(envelope, i);
+        let (p2, t2) = vertex(envelope, i + 1);
+        if (t1 > t) != (t2 > t) && p < p1 + (t - t1) / (t2 - t1) * (p2 - p1) {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+// Distances from the point to the envelope boundary along the pressure axis at constant
+// temperature and along the temperature axis at constant pressure.  None when the line
+// through the point does not cross the envelope.
+pub fn boundary_margins(envelope: &[(Pressure, Temperature)], p: Pressure, t: Temperature) -> (Option<Pressure>, Option<TemperatureDifference>) {
+    let (p, t) = (p.kpa(), t.kelvin());
+    let mut p_margin: Option<f64> = None;
+    let mut t_margin: Option<f64> = None;
+    for i in 0..envelope.len() {
+        let (p1, t1) = vertex(envelope, i);
+        let (p2, t2) = vertex(envelope, i + 1);
+        if (t1 - t) * (t2 - t) <= 0.0 && t1 != t2 {
+            let p_edge = p1 + (t - t1) / (t2 - t1) * (p2 - p1);
+            p_margin = Some(p_margin.map_or((p - p_edge).abs(), |margin| margin.min((p - p_edge).abs())));
+        }
+        if (p1 - p) * (p2 - p) <= 0.0 && p1 != p2 {
+            let t_edge = t1 + (p - p1) / (p2 - p1) * (t2 - t1);
+            t_margin = Some(t_margin.map_or((t - t_edge).abs(), |margin| margin.min((t - t_edge).abs())));
+        }
+    }
+    (p_margin.map(Pressure::from_kpa), t_margin.map(TemperatureDifference::from_kelvin))
+}
+
+fn enter_envelope(program_state: &ProgramState) -> Vec<(Pressure, Temperature)> {
+    let count = read_f64("Enter number of envelope vertices (3 or more):").max(3.0) as usize;
+    let mut envelope = Vec::new();
+    for i in 0..count {
+        outln!("Vertex {}:", i + 1);
+        let p = read_pressure(program_state, "pressure");
+        let t = read_temperature(program_state, "temperature");
+        envelope.push((p, t));
+    }
+    envelope
+}
+
+// Reads a CSV with the columns pressure and temperature in the current display units,
+// one vertex per row in order around the envelope.
+fn read_envelope(program_state: &ProgramState, path: &str) -> Result<Vec<(Pressure, Temperature)>, String> {
+    let table = CsvTable::read(path)?;
+    let p_col = table.require_column("pressure")?;
+    let t_col = table.require_column("temperature")?;
+    let mut envelope = Vec::new();
+    for (line, row) in table.rows.iter().enumerate() {
+        let row_error = |err: String| format!("Row {}: {}", line + 2, err);
+        let p = field_f64(row, Some(p_col)).map_err(row_error)?.ok_or(row_error("missing pressure".to_string()))?;
+        let t = field_f64(row, Some(t_col)).map_err(row_error)?.ok_or(row_error("missing temperature".to_string()))?;
+        envelope.push((Pressure::new(p, program_state.units.pressure), Temperature::new(t, program_state.units.temp)));
+    }
+    if envelope.len() < 3 {
+        return Err(format!("{} needs at least 3 vertices", path));
+    }
+    Ok(envelope)
+}
+
+pub fn operating_envelope(program_state: &mut ProgramState) {
+    outln!();
+    outln!("{}", "Pressure-Temperature Operating Envelope".cyan().bold());
+    if program_state.envelope.is_empty() {
+        outln!("No envelope defined.");
+    } else {
+        outln!("Current envelope has {} vertices.", program_state.envelope.len());
+    }
+    outln!("1 - Check states against the current envelope");
+    outln!("2 - Enter a new envelope");
+    outln!("3 - Load a new envelope from CSV (columns: pressure,temperature)");
+    match read_input().as_str() {
+        "2" => program_state.envelope = enter_envelope(program_state),
+        "3" => {
+            outln!("Enter CSV file path:");
+            match read_envelope(program_state, &read_input()) {
+                Ok(envelope) => program_state.envelope = envelope,
+                Err(err) => outln!("{}", format!("** {} **", err).red().bold()),
+            }
+        },
+        _ => (),
+    }
+    if program_state.envelope.is_empty() {
+        print_gas_state(program_state);
+        return;
+    }
+
+    let mut states = vec![("Current", &program_state.gas_state)];
+    if program_state.show_inlet_state {
+        states.push(("Inlet", &program_state.inlet_state));
+    }
+    if program_state.show_discharge_state {
+        states.push(("Discharge", &program_state.discharge_state));
+    }
+
+    let p_unit = program_state.units.pressure.label();
+    let t_unit = program_state.units.temp.label();
+    outln!();
+    outln!("{:<10} {:>12} {:>12} {:>8} {:>14} {:>14}", "State", format!("P {}", p_unit), format!("T {}", t_unit),
+        "Inside", format!("P margin {}", p_unit), format!("T margin {}", t_unit));
+    for (name, state) in states {
+        let (p, t) = (Pressure::from_kpa(state.p), Temperature::from_kelvin(state.t));
+        let inside = inside_envelope(&program_state.envelope, p, t);
+        let (p_margin, t_margin) = boundary_margins(&program_state.envelope, p, t);
+        let p_margin = p_margin.map_or(format!("{:>14}", "-"), |margin| format!("{:14.4}", margin.value(program_state.units.pressure)));
+        let t_margin = t_margin.map_or(format!("{:>14}", "-"), |margin| format!("{:14.4}", margin.value(program_state.units.temp)));
+        let line = format!("{:<10} {:12.4} {:12.4} {:>8} {} {}", name,
+            p.value(program_state.units.pressure), t.value(program_state.units.temp),
+            if inside { "Yes" } else { "No" }, p_margin, t_margin);
+        if inside {
+            outln!("{}", line);
+        } else {
+            outln!("{}", line.red().bold());
+        }
+    }
+    outln!();
+    outln!("Margins are the distance to the nearest envelope edge at constant temperature (P) and constant pressure (T).");
+
+    print_gas_state(program_state);
+}
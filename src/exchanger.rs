@@ -0,0 +1,69 @@
+use aga8::detail::Detail;
+use colored::Colorize;
+
+use crate::{
+    energy_in_units, get_power, get_pressure, get_temperature, pressure_to_kpa, print_gas_state, read_f64, read_input, read_temperature, try_gas_state,
+    ProgramState,
+};
+
+// kW to MMBtu/h
+const MMBTU_H_PER_KW: f64 = 3.412142e-3;
+
+// Pressure (kPa), temperature (K), molar enthalpy (J/mol) and molar mass (g/mol)
+struct EndState {
+    p: f64,
+    t: f64,
+    h: f64,
+    mm: f64,
+}
+
+impl EndState {
+    fn from_detail(state: &Detail) -> EndState {
+        EndState { p: state.p, t: state.t, h: state.h, mm: state.mm }
+    }
+}
+
+pub fn exchanger_duty(program_state: &mut ProgramState) {
+    outln!();
+    outln!("{}", "Heat Exchanger Duty".yellow().bold());
+    outln!("1 - Current state to an outlet temperature");
+    if program_state.show_inlet_state && program_state.show_discharge_state {
+        outln!("2 - Inlet condition to discharge condition");
+    }
+    let (inlet, outlet) = match read_input().as_str() {
+        "2" if program_state.show_inlet_state && program_state.show_discharge_state => {
+            (EndState::from_detail(&program_state.inlet_state), EndState::from_detail(&program_state.discharge_state))
+        },
+        _ => {
+            let inlet = &program_state.gas_state;
+            let t_out = read_temperature(program_state, "outlet temperature");
+            let dp = read_f64(&format!("Enter pressure drop ({}, 0 for none):", program_state.units.pressure.label()));
+            let p_out = inlet.p - pressure_to_kpa(dp, program_state.units.pressure);
+            match try_gas_state(&program_state.gas_comp, p_out, t_out.kelvin()) {
+                Some(outlet) => (EndState::from_detail(inlet), EndState::from_detail(&outlet)),
+                None => {
+                    outln!("{}", "** Unable to calculate the outlet state **".red().bold());
+                    print_gas_state(program_state);
+                    return;
+                }
+            }
+        },
+    };
+    let mass_flow = read_f64("Enter mass flow (kg/s):");
+
+    // J/mol divided by g/mol is kJ/kg, times kg/s gives kW
+    let duty = (outlet.h - inlet.h) / inlet.mm * mass_flow;
+    let p_unit = program_state.units.pressure.label();
+    let t_unit = program_state.units.temp.label();
+    outln!();
+    outln!("{:<30} {:10.4} {:10}", "Inlet Pressure: ", get_pressure(inlet.p, program_state.units.pressure), p_unit);
+    outln!("{:<30} {:10.4} {:10}", "Inlet Temperature: ", get_temperature(inlet.t, program_state.units.temp), t_unit);
+    outln!("{:<30} {:10.4} {:10}", "Outlet Pressure: ", get_pressure(outlet.p, program_state.units.pressure), p_unit);
+    outln!("{:<30} {:10.4} {:10}", "Outlet Temperature: ", get_temperature(outlet.t, program_state.units.temp), t_unit);
+    outln!("{:<30} {:10.4} {:10}", "Specific Duty: ", energy_in_units(outlet.h - inlet.h, inlet.mm, program_state.units.internal_energy), program_state.units.internal_energy.label());
+    outln!("{:<30} {:10.4} {:10}", "Duty: ", get_power(duty, program_state.units.power), program_state.units.power.label());
+    outln!("{:<30} {:10.4} {:10}", "Duty: ", duty * MMBTU_H_PER_KW, "MMBtu/h");
+    outln!("{:<30} {:>10}", "Service: ", if duty >= 0.0 { "Heating" } else { "Cooling" });
+
+    print_gas_state(program_state);
+}
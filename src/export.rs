@@ -0,0 +1,61 @@
+use colored::Colorize;
+
+use crate::template::{render_template, report_context};
+use crate::{print_gas_state, read_input, ProgramState};
+
+use std::fs;
+
+const MARKDOWN_REPORT: &str = include_str!("../templates/report.md");
+const HTML_REPORT: &str = include_str!("../templates/report.html");
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum ReportFormat {
+    Markdown,
+    Html,
+}
+
+fn format_from_path(path: &str) -> Option<ReportFormat> {
+    let extension = path.rsplit_once('.')?.1.to_lowercase();
+    match extension.as_str() {
+        "md" | "markdown" => Some(ReportFormat::Markdown),
+        "html" | "htm" => Some(ReportFormat::Html),
+        _ => None,
+    }
+}
+
+pub fn export_report_text(program_state: &ProgramState, format: ReportFormat) -> Result<String, String> {
+    let context = report_context(program_state);
+    match format {
+        ReportFormat::Markdown => render_template(MARKDOWN_REPORT, &context, false),
+        ReportFormat::Html => render_template(HTML_REPORT, &context, true),
+    }
+}
+
+// Writes the current state, with the inlet and discharge columns when set, as a
+// Markdown or HTML report.  The format follows the file extension.
+pub fn export_report(program_state: &mut ProgramState, path: &str) {
+    let path = if path.is_empty() {
+        outln!("Enter report file path (.md or .html):");
+        read_input()
+    } else {
+        path.to_string()
+    };
+    let format = match format_from_path(&path) {
+        Some(format) => format,
+        None => {
+            outln!("Report format: 1 - Markdown, 2 - HTML");
+            match read_input().as_str() {
+                "2" => ReportFormat::Html,
+                _ => ReportFormat::Markdown,
+            }
+        },
+    };
+    let result = export_report_text(program_state, format).and_then(|text| {
+        fs::write(&path, text).map_err(|err| format!("Unable to write {}: {}", path, err))
+    });
+    match result {
+        Ok(()) => outln!("Report written to {}", path),
+        Err(err) => outln!("{}", format!("** {} **", err).red().bold()),
+    }
+    print_gas_state(program_state);
+}
@@ -0,0 +1,286 @@
+// C API over the core calculations.  Compositions are arrays of COMP_PERF_NUM_COMPONENTS
+// mole fractions in the AGA8 order (methane, nitrogen, carbon dioxide, ethane, ...),
+// pressures are in kPa and temperatures in K.  The header include/comp_perf.h is
+// generated by build.rs.
+
+use aga8::composition::Composition;
+use aga8::detail::Detail;
+
+use crate::components::{array_to_comp, NUM_COMPONENTS};
+use crate::thermo::{compressed_state, throttled_state, try_gas_state};
+use crate::units::{get_pressure, get_temperature, pressure_to_kpa, temperature_to_kelvin, UnitPressure, UnitTemp};
+
+use std::cmp::Ordering;
+use std::os::raw::c_int;
+
+pub const COMP_PERF_NUM_COMPONENTS: usize = 21;
+const _: () = assert!(COMP_PERF_NUM_COMPONENTS == NUM_COMPONENTS);
+
+pub const COMP_PERF_OK: c_int = 0;
+pub const COMP_PERF_ERROR_NULL_POINTER: c_int = 1;
+pub const COMP_PERF_ERROR_COMPOSITION: c_int = 2;
+pub const COMP_PERF_ERROR_NO_SOLUTION: c_int = 3;
+pub const COMP_PERF_ERROR_INVALID_ARGUMENT: c_int = 4;
+
+pub const COMP_PERF_PRESSURE_KPA: c_int = 0;
+pub const COMP_PERF_PRESSURE_PSI: c_int = 1;
+pub const COMP_PERF_PRESSURE_BAR: c_int = 2;
+pub const COMP_PERF_PRESSURE_MPA: c_int = 3;
+pub const COMP_PERF_PRESSURE_ATM: c_int = 4;
+pub const COMP_PERF_PRESSURE_MBAR: c_int = 5;
+pub const COMP_PERF_PRESSURE_KGF_CM2: c_int = 6;
+pub const COMP_PERF_PRESSURE_INH2O: c_int = 7;
+pub const COMP_PERF_PRESSURE_MMHG: c_int = 8;
+
+pub const COMP_PERF_TEMPERATURE_K: c_int = 0;
+pub const COMP_PERF_TEMPERATURE_C: c_int = 1;
+pub const COMP_PERF_TEMPERATURE_F: c_int = 2;
+pub const COMP_PERF_TEMPERATURE_R: c_int = 3;
+
+/// Gas properties from AGA8 DETAIL.  Pressure in kPa, temperature in K, density in
+/// mol/l, molar mass in g/mol, energies in J/mol, entropy and heat capacities in
+/// J/(mol-K), speed of sound in m/s and Joule-Thomson coefficient in K/kPa.
+#[repr(C)]
+#[derive(Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "python", pyo3::pyclass(name = "State", frozen, get_all, skip_from_py_object))]
+#[cfg_attr(feature = "wasm", wasm_bindgen::prelude::wasm_bindgen(js_name = GasState))]
+pub struct CompPerfState {
+    pub p: f64,
+    pub t: f64,
+    pub d: f64,
+    pub z: f64,
+    pub mm: f64,
+    pub u: f64,
+    pub h: f64,
+    pub s: f64,
+    pub cp: f64,
+    pub cv: f64,
+    pub w: f64,
+    pub kappa: f64,
+    pub g: f64,
+    pub jt: f64,
+}
+
+impl CompPerfState {
+    pub fn of(state: &Detail) -> CompPerfState {
+        CompPerfState {
+            p: state.p,
+            t: state.t,
+            d: state.d,
+            z: state.z,
+            mm: state.mm,
+            u: state.u,
+            h: state.h,
+            s: state.s,
+            cp: state.cp,
+            cv: state.cv,
+            w: state.w,
+            kappa: state.kappa,
+            g: state.g,
+            jt: state.jt,
+        }
+    }
+}
+
+fn pressure_unit(unit: c_int) -> Option<UnitPressure> {
+    match unit {
+        COMP_PERF_PRESSURE_KPA => Some(UnitPressure::kPa),
+        COMP_PERF_PRESSURE_PSI => Some(UnitPressure::PSI),
+        COMP_PERF_PRESSURE_BAR => Some(UnitPressure::Bar),
+        COMP_PERF_PRESSURE_MPA => Some(UnitPressure::MPa),
+        COMP_PERF_PRESSURE_ATM => Some(UnitPressure::atm),
+        COMP_PERF_PRESSURE_MBAR => Some(UnitPressure::mbar),
+        COMP_PERF_PRESSURE_KGF_CM2 => Some(UnitPressure::kgf_cm2),
+        COMP_PERF_PRESSURE_INH2O => Some(UnitPressure::inH2O),
+        COMP_PERF_PRESSURE_MMHG => Some(UnitPressure::mmHg),
+        _ => None,
+    }
+}
+
+fn temperature_unit(unit: c_int) -> Option<UnitTemp> {
+    match unit {
+        COMP_PERF_TEMPERATURE_K => Some(UnitTemp::K),
+        COMP_PERF_TEMPERATURE_C => Some(UnitTemp::C),
+        COMP_PERF_TEMPERATURE_F => Some(UnitTemp::F),
+        COMP_PERF_TEMPERATURE_R => Some(UnitTemp::R),
+        _ => None,
+    }
+}
+
+// Reads and checks the composition array.  The AGA8 check of the sum lets negative and
+// NaN fractions through.
+unsafe fn read_composition(x: *const f64) -> Result<Composition, c_int> {
+    if x.is_null() {
+        return Err(COMP_PERF_ERROR_NULL_POINTER);
+    }
+    // SAFETY: the caller passes an array of NUM_COMPONENTS doubles
+    let x = unsafe { &*(x as *const [f64; NUM_COMPONENTS]) };
+    if !x.iter().all(|xi| (0.0..=1.0).contains(xi)) {
+        return Err(COMP_PERF_ERROR_COMPOSITION);
+    }
+    let gas_comp = array_to_comp(x);
+    Detail::new().set_composition(&gas_comp).map_err(|_| COMP_PERF_ERROR_COMPOSITION)?;
+    Ok(gas_comp)
+}
+
+// Runs a calculation and writes its state to out
+unsafe fn solve(x: *const f64, out: *mut CompPerfState, calculate: impl FnOnce(&Composition) -> Option<Detail>) -> c_int {
+    if out.is_null() {
+        return COMP_PERF_ERROR_NULL_POINTER;
+    }
+    let gas_comp = match unsafe { read_composition(x) } {
+        Ok(gas_comp) => gas_comp,
+        Err(code) => return code,
+    };
+    match calculate(&gas_comp) {
+        Some(state) => {
+            // SAFETY: out was checked for null and points to a CompPerfState
+            unsafe { out.write(CompPerfState::of(&state)) };
+            COMP_PERF_OK
+        },
+        None => COMP_PERF_ERROR_NO_SOLUTION,
+    }
+}
+
+/// Properties at pressure p (kPa) and temperature t (K).  Returns COMP_PERF_OK or an
+/// error code.
+///
+/// # Safety
+/// `x` must point to COMP_PERF_NUM_COMPONENTS doubles and `out` to a CompPerfState.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn comp_perf_state(x: *const f64, p: f64, t: f64, out: *mut CompPerfState) -> c_int {
+    unsafe { solve(x, out, |gas_comp| try_gas_state(gas_comp, p, t)) }
+}
+
+/// Compression from p1 (kPa) and t1 (K) to a higher p2 (kPa) with an isentropic
+/// efficiency between 0 and 1.  Writes the discharge state; the specific work is the
+/// enthalpy rise.  COMP_PERF_ERROR_INVALID_ARGUMENT if p2 is not above p1.
+///
+/// # Safety
+/// `x` must point to COMP_PERF_NUM_COMPONENTS doubles and `out` to a CompPerfState.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn comp_perf_compress(x: *const f64, p1: f64, t1: f64, p2: f64, efficiency: f64, out: *mut CompPerfState) -> c_int {
+    if !(efficiency > 0.0 && efficiency <= 1.0 && p2 > p1) {
+        return COMP_PERF_ERROR_INVALID_ARGUMENT;
+    }
+    unsafe { solve(x, out, |gas_comp| compressed_state(gas_comp, p1, t1, p2, efficiency)) }
+}
+
+/// Isenthalpic throttling from p1 (kPa) and t1 (K) to a lower p2 (kPa).  Writes the
+/// downstream state.  COMP_PERF_ERROR_INVALID_ARGUMENT if p2 is not below p1.
+///
+/// # Safety
+/// `x` must point to COMP_PERF_NUM_COMPONENTS doubles and `out` to a CompPerfState.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn comp_perf_throttle(x: *const f64, p1: f64, t1: f64, p2: f64, out: *mut CompPerfState) -> c_int {
+    if p2.partial_cmp(&p1) != Some(Ordering::Less) {
+        return COMP_PERF_ERROR_INVALID_ARGUMENT;
+    }
+    unsafe { solve(x, out, |gas_comp| throttled_state(gas_comp, p1, t1, p2)) }
+}
+
+/// Converts a pressure in the given COMP_PERF_PRESSURE_* unit to kPa.  NaN for an
+/// unknown unit.
+#[unsafe(no_mangle)]
+pub extern "C" fn comp_perf_pressure_to_kpa(value: f64, unit: c_int) -> f64 {
+    pressure_unit(unit).map_or(f64::NAN, |unit| pressure_to_kpa(value, unit))
+}
+
+/// Converts a pressure in kPa to the given COMP_PERF_PRESSURE_* unit.  NaN for an
+/// unknown unit.
+#[unsafe(no_mangle)]
+pub extern "C" fn comp_perf_pressure_from_kpa(value: f64, unit: c_int) -> f64 {
+    pressure_unit(unit).map_or(f64::NAN, |unit| get_pressure(value, unit))
+}
+
+/// Converts a temperature in the given COMP_PERF_TEMPERATURE_* unit to K.  NaN for an
+/// unknown unit.
+#[unsafe(no_mangle)]
+pub extern "C" fn comp_perf_temperature_to_kelvin(value: f64, unit: c_int) -> f64 {
+    temperature_unit(unit).map_or(f64::NAN, |unit| temperature_to_kelvin(value, unit))
+}
+
+/// Converts a temperature in K to the given COMP_PERF_TEMPERATURE_* unit.  NaN for an
+/// unknown unit.
+#[unsafe(no_mangle)]
+pub extern "C" fn comp_perf_temperature_from_kelvin(value: f64, unit: c_int) -> f64 {
+    temperature_unit(unit).map_or(f64::NAN, |unit| get_temperature(value, unit))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::ptr;
+
+    // Methane, with the other components zero
+    const METHANE: [f64; NUM_COMPONENTS] = {
+        let mut x = [0.0; NUM_COMPONENTS];
+        x[0] = 1.0;
+        x
+    };
+
+    fn empty_state() -> CompPerfState {
+        CompPerfState { p: 0.0, t: 0.0, d: 0.0, z: 0.0, mm: 0.0, u: 0.0, h: 0.0, s: 0.0, cp: 0.0, cv: 0.0, w: 0.0, kappa: 0.0, g: 0.0, jt: 0.0 }
+    }
+
+    #[test]
+    fn state_and_null_pointers() {
+        let mut out = empty_state();
+        assert_eq!(unsafe { comp_perf_state(METHANE.as_ptr(), 1000.0, 300.0, &mut out) }, COMP_PERF_OK);
+        assert!((out.p - 1000.0).abs() < 1.0e-9 && out.t == 300.0);
+        assert!((out.mm - 16.043).abs() < 1.0e-3);
+        assert_eq!(unsafe { comp_perf_state(ptr::null(), 1000.0, 300.0, &mut out) }, COMP_PERF_ERROR_NULL_POINTER);
+        assert_eq!(unsafe { comp_perf_state(METHANE.as_ptr(), 1000.0, 300.0, ptr::null_mut()) }, COMP_PERF_ERROR_NULL_POINTER);
+        assert_eq!(unsafe { comp_perf_throttle(ptr::null(), 1000.0, 300.0, 100.0, ptr::null_mut()) }, COMP_PERF_ERROR_NULL_POINTER);
+    }
+
+    // Errors leave out as it was
+    #[test]
+    fn composition_and_solution_errors() {
+        let mut out = empty_state();
+        let mut x = METHANE;
+        x[0] = 0.5;
+        assert_eq!(unsafe { comp_perf_state(x.as_ptr(), 1000.0, 300.0, &mut out) }, COMP_PERF_ERROR_COMPOSITION);
+        // Fractions that sum to 1 but are not all between 0 and 1
+        x[1] = -0.5;
+        x[0] = 1.5;
+        assert_eq!(unsafe { comp_perf_state(x.as_ptr(), 1000.0, 300.0, &mut out) }, COMP_PERF_ERROR_COMPOSITION);
+        x = METHANE;
+        x[20] = f64::NAN;
+        assert_eq!(unsafe { comp_perf_state(x.as_ptr(), 1000.0, 300.0, &mut out) }, COMP_PERF_ERROR_COMPOSITION);
+        assert_eq!(unsafe { comp_perf_state(METHANE.as_ptr(), 1000.0, f64::NAN, &mut out) }, COMP_PERF_ERROR_NO_SOLUTION);
+        assert_eq!(out.p, 0.0);
+    }
+
+    #[test]
+    fn invalid_process_arguments() {
+        let mut out = empty_state();
+        let compress = |p2: f64, efficiency: f64, out: &mut CompPerfState| unsafe { comp_perf_compress(METHANE.as_ptr(), 1000.0, 300.0, p2, efficiency, out) };
+        assert_eq!(compress(3000.0, 0.8, &mut out), COMP_PERF_OK);
+        assert!(out.t > 300.0);
+        for (p2, efficiency) in [(3000.0, 0.0), (3000.0, 1.5), (3000.0, f64::NAN), (1000.0, 0.8), (500.0, 0.8), (f64::NAN, 0.8)] {
+            assert_eq!(compress(p2, efficiency, &mut out), COMP_PERF_ERROR_INVALID_ARGUMENT, "{} {}", p2, efficiency);
+        }
+
+        let throttle = |p2: f64, out: &mut CompPerfState| unsafe { comp_perf_throttle(METHANE.as_ptr(), 3000.0, 300.0, p2, out) };
+        assert_eq!(throttle(1000.0, &mut out), COMP_PERF_OK);
+        assert!(out.t < 300.0);
+        for p2 in [3000.0, 5000.0, f64::NAN] {
+            assert_eq!(throttle(p2, &mut out), COMP_PERF_ERROR_INVALID_ARGUMENT, "{}", p2);
+        }
+    }
+
+    #[test]
+    fn unit_conversions() {
+        assert!((comp_perf_pressure_to_kpa(1.0, COMP_PERF_PRESSURE_PSI) - 6.894757).abs() < 1.0e-6);
+        assert!((comp_perf_pressure_from_kpa(101.325, COMP_PERF_PRESSURE_ATM) - 1.0).abs() < 1.0e-12);
+        assert_eq!(comp_perf_temperature_to_kelvin(0.0, COMP_PERF_TEMPERATURE_C), 273.15);
+        assert!((comp_perf_temperature_from_kelvin(273.15, COMP_PERF_TEMPERATURE_F) - 32.0).abs() < 1.0e-12);
+        assert!(comp_perf_pressure_to_kpa(1.0, 9).is_nan());
+        assert!(comp_perf_pressure_from_kpa(1.0, -1).is_nan());
+        assert!(comp_perf_temperature_to_kelvin(1.0, 4).is_nan());
+        assert!(comp_perf_temperature_from_kelvin(1.0, -1).is_nan());
+    }
+}
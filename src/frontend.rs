@@ -0,0 +1,37 @@
+use std::io::{self, Write};
+
+// Line input and output of the interactive program, so the calculations can be driven
+// from something other than a terminal.
+pub trait Frontend {
+    // None at the end of input
+    fn read_line(&mut self) -> Option<String>;
+    // Text without a line break, such as a prompt
+    fn write(&mut self, text: &str);
+    fn write_line(&mut self, text: &str);
+}
+
+// Standard input and output
+pub struct Console;
+
+impl Frontend for Console {
+    fn read_line(&mut self) -> Option<String> {
+        let mut input = String::new();
+        match io::stdin().read_line(&mut input) {
+            Ok(0) => None,
+            Ok(_) => Some(input.trim().to_string()),
+            Err(err) => panic!("Unable to read input: {}", err),
+        }
+    }
+
+    fn write(&mut self, text: &str) {
+        let mut stdout = io::stdout();
+        write!(stdout, "{}", text).ok();
+        stdout.flush().ok();
+    }
+
+    fn write_line(&mut self, text: &str) {
+        let mut stdout = io::stdout();
+        writeln!(stdout, "{}", text).ok();
+        stdout.flush().ok();
+    }
+}
@@ -0,0 +1,136 @@
+use aga8::composition::Composition;
+use colored::Colorize;
+
+use crate::components::{array_to_comp, COMPONENT_LABELS, NUM_COMPONENTS};
+use crate::csv::{component_columns, component_fractions, CsvTable};
+use crate::custom_composition::{normalize_composition, print_normalization, Normalization};
+use crate::read_input;
+
+// Repeatability in mol% by concentration range in mol% (GPA 2261)
+const REPEATABILITY: [(f64, f64); 5] = [(0.1, 0.01), (1.0, 0.04), (5.0, 0.07), (10.0, 0.08), (100.0, 0.16)];
+// Repeatability limit is 2.77 standard deviations
+const REPEATABILITY_FACTOR: f64 = 2.77;
+
+fn repeatability(x: f64) -> f64 {
+    REPEATABILITY.iter().find(|(upper, _)| x <= *upper).map_or(REPEATABILITY[4].1, |(_, r)| *r)
+}
+
+struct GcAnalysis {
+    sample: String,
+    // Components in mol%
+    x: [f64; NUM_COMPONENTS],
+    // Components outside the repeatability of the median
+    outliers: Vec<usize>,
+}
+
+fn median(values: &mut [f64]) -> f64 {
+    values.sort_by(|a, b| a.total_cmp(b));
+    let mid = values.len() / 2;
+    if values.len().is_multiple_of(2) { 0.5 * (values[mid - 1] + values[mid]) } else { values[mid] }
+}
+
+// One analysis per row with an optional sample column and component columns in mol%.
+fn read_analyses(path: &str) -> Result<Vec<GcAnalysis>, String> {
+    let table = CsvTable::read(path)?;
+    let sample_col = table.column("sample");
+    let columns = component_columns(&table)?;
+    let mut analyses = Vec::new();
+    for (line, row) in table.rows.iter().enumerate() {
+        let fractions = component_fractions(row, &columns).map_err(|err| format!("Row {}: {}", line + 2, err))?;
+        analyses.push(GcAnalysis {
+            sample: sample_col.and_then(|i| row.get(i)).cloned().unwrap_or_else(|| (line + 1).to_string()),
+            x: fractions.map(|x| x * 100.0),
+            outliers: Vec::new(),
+        });
+    }
+    if analyses.len() < 2 {
+        return Err(format!("{} needs at least 2 analyses", path));
+    }
+    Ok(analyses)
+}
+
+// Flags components that differ from the median of all analyses by more than the
+// repeatability, then returns the uncertainty-weighted mean and its expanded (k=2)
+// uncertainty in mol% over the analyses without outliers.
+fn weighted_mean(analyses: &mut [GcAnalysis]) -> Option<([f64; NUM_COMPONENTS], [f64; NUM_COMPONENTS])> {
+    for j in 0..NUM_COMPONENTS {
+        let mut values: Vec<f64> = analyses.iter().map(|analysis| analysis.x[j]).collect();
+        let center = median(&mut values);
+        for analysis in analyses.iter_mut() {
+            if (analysis.x[j] - center).abs() > repeatability(center) {
+                analysis.outliers.push(j);
+            }
+        }
+    }
+
+    let accepted: Vec<&GcAnalysis> = analyses.iter().filter(|analysis| analysis.outliers.is_empty()).collect();
+    if accepted.is_empty() {
+        return None;
+    }
+    let mut mean = [0.0; NUM_COMPONENTS];
+    let mut uncertainty = [0.0; NUM_COMPONENTS];
+    for j in 0..NUM_COMPONENTS {
+        let mut sum_weights = 0.0;
+        let mut sum = 0.0;
+        for analysis in &accepted {
+            let u = repeatability(analysis.x[j]) / REPEATABILITY_FACTOR;
+            let weight = 1.0 / (u * u);
+            sum_weights += weight;
+            sum += weight * analysis.x[j];
+        }
+        mean[j] = sum / sum_weights;
+        uncertainty[j] = 2.0 / sum_weights.sqrt();
+    }
+    Some((mean, uncertainty))
+}
+
+// Prompts for a CSV of duplicate analyses and returns the name and normalized mean
+// composition, or None when no composition should be loaded.
+pub fn average_gc_analyses(tolerance: f64) -> Option<(String, Composition)> {
+    outln!("Enter CSV file path of duplicate GC analyses (columns: [sample,]methane,ethane,... in mol%):");
+    let mut analyses = match read_analyses(&read_input()) {
+        Ok(analyses) => analyses,
+        Err(err) => {
+            outln!("{}", format!("** {} **", err).red().bold());
+            return None;
+        }
+    };
+    let result = weighted_mean(&mut analyses);
+
+    outln!();
+    outln!("{:<12} {:>10} {:<10}", "Sample", "Sum mol%", "Outliers");
+    for analysis in &analyses {
+        let sum: f64 = analysis.x.iter().sum();
+        if analysis.outliers.is_empty() {
+            outln!("{:<12} {:10.4} {:<10}", analysis.sample, sum, "-");
+        } else {
+            let names: Vec<&str> = analysis.outliers.iter().map(|j| COMPONENT_LABELS[*j]).collect();
+            outln!("{}", format!("{:<12} {:10.4} {} (excluded)", analysis.sample, sum, names.join(", ")).yellow());
+        }
+    }
+
+    let Some((mean, uncertainty)) = result else {
+        outln!("{}", "** Every analysis has an outlier - no mean composition **".red().bold());
+        return None;
+    };
+    let accepted = analyses.iter().filter(|analysis| analysis.outliers.is_empty()).count();
+    outln!();
+    outln!("{:<20} {:>12} {:>12}", "Component", "Mean mol%", "U(k=2) mol%");
+    for j in 0..NUM_COMPONENTS {
+        if mean[j] > 0.0 {
+            outln!("{:<20} {:12.4} {:12.4}", COMPONENT_LABELS[j], mean[j], uncertainty[j]);
+        }
+    }
+
+    let mut gas_comp = array_to_comp(&mean.map(|x| x / 100.0));
+    let normalization = normalize_composition(&mut gas_comp, tolerance);
+    print_normalization(&normalization);
+    if let Normalization::Rejected { .. } = normalization {
+        return None;
+    }
+    outln!("Load the mean composition as the working gas? (y/n)");
+    if read_input() != "y" {
+        return None;
+    }
+    Some((format!("GC mean ({} of {} analyses)", accepted, analyses.len()), gas_comp))
+}
@@ -0,0 +1,143 @@
+use aga8::composition::Composition;
+use colored::Colorize;
+
+use crate::components::{array_to_comp, comp_to_array, COMPONENT_LABELS, NUM_COMPONENTS};
+use crate::csv::{component_columns, component_fractions, CsvTable};
+use crate::custom_composition::{normalize_composition, print_normalization, Normalization};
+use crate::quality::{gross_hv_volumetric, relative_density, R};
+use crate::{new_gas_state, print_gas_state, read_f64, read_input, Pressure, ProgramState, Temperature};
+
+// Quantities that feed the volume-to-energy conversion on an invoice
+pub struct BillingQuantities {
+    // MJ/m3 at base conditions
+    pub heating_value: f64,
+    // GJ/h at the reference standard volume flow
+    pub energy_flow: f64,
+    pub relative_density: f64,
+    pub wobbe_index: f64,
+    // kg/m3 at base and at flowing conditions
+    pub base_density: f64,
+    pub flowing_density: f64,
+    // Supercompressibility factor sqrt(Zb/Zf)
+    pub fpv: f64,
+}
+
+// Flowing conditions p and t, base conditions p_base and t_base and the reference flow in
+// m3/h at base conditions
+pub fn billing_quantities(gas_comp: &Composition, p: Pressure, t: Temperature, p_base: Pressure, t_base: Temperature, flow: f64) -> BillingQuantities {
+    let base = new_gas_state(gas_comp, p_base.kpa(), t_base.kelvin());
+    let flowing = new_gas_state(gas_comp, p.kpa(), t.kelvin());
+    let heating_value = gross_hv_volumetric(gas_comp, p_base, t_base);
+    let relative_density = relative_density(gas_comp, p_base, t_base);
+    BillingQuantities {
+        heating_value,
+        energy_flow: heating_value * flow / 1000.0,
+        relative_density,
+        wobbe_index: heating_value / relative_density.sqrt(),
+        base_density: p_base.kpa() * base.mm / (base.z * R * t_base.kelvin()),
+        flowing_density: flowing.d * flowing.mm,
+        fpv: (base.z / flowing.z).sqrt(),
+    }
+}
+
+// The last two analyses in the file, oldest first, with their sample names
+fn read_last_two(path: &str) -> Result<[(String, [f64; NUM_COMPONENTS]); 2], String> {
+    let table = CsvTable::read(path)?;
+    let sample_col = table.column("sample");
+    let columns = component_columns(&table)?;
+    let count = table.rows.len();
+    if count < 2 {
+        return Err(format!("{} needs at least 2 analyses", path));
+    }
+    let analysis = |line: usize| -> Result<(String, [f64; NUM_COMPONENTS]), String> {
+        let row = &table.rows[line];
+        let x = component_fractions(row, &columns).map_err(|err| format!("Row {}: {}", line + 2, err))?;
+        let sample = sample_col.and_then(|i| row.get(i)).cloned().unwrap_or_else(|| (line + 1).to_string());
+        Ok((sample, x))
+    };
+    Ok([analysis(count - 2)?, analysis(count - 1)?])
+}
+
+fn normalized(sample: &str, x: &[f64; NUM_COMPONENTS], tolerance: f64) -> Option<Composition> {
+    let mut gas_comp = array_to_comp(x);
+    let normalization = normalize_composition(&mut gas_comp, tolerance);
+    if !matches!(normalization, Normalization::Unchanged) {
+        outln!("Sample {}:", sample);
+        print_normalization(&normalization);
+    }
+    match normalization {
+        Normalization::Rejected { .. } => None,
+        _ => Some(gas_comp),
+    }
+}
+
+// Compares the last two GC analyses of a stream (e.g. yesterday and today) at the
+// current pressure and temperature and the program base conditions, and flags billing
+// quantities that moved more than the exception limit.
+pub fn composition_delta_report(program_state: &mut ProgramState) {
+    outln!();
+    outln!("{}", "GC Delta-Composition Impact".cyan().bold());
+    outln!("Enter CSV file path of GC analyses, oldest first (columns: [sample,]methane,ethane,... in mol%):");
+    let [(previous_name, previous_x), (current_name, current_x)] = match read_last_two(&read_input()) {
+        Ok(analyses) => analyses,
+        Err(err) => {
+            outln!("{}", format!("** {} **", err).red().bold());
+            print_gas_state(program_state);
+            return;
+        }
+    };
+    let tolerance = program_state.normalize_tolerance;
+    let (Some(previous_comp), Some(current_comp)) = (normalized(&previous_name, &previous_x, tolerance), normalized(&current_name, &current_x, tolerance)) else {
+        print_gas_state(program_state);
+        return;
+    };
+    let flow = read_f64("Enter reference flow at base conditions (m3/h):");
+    let limit = read_f64("Enter exception limit (% change):").abs();
+
+    let (p, t) = (Pressure::from_kpa(program_state.gas_state.p), Temperature::from_kelvin(program_state.gas_state.t));
+    let (p_base, t_base) = (program_state.base_pressure, program_state.base_temperature);
+    let previous = billing_quantities(&previous_comp, p, t, p_base, t_base, flow);
+    let current = billing_quantities(&current_comp, p, t, p_base, t_base, flow);
+
+    outln!();
+    outln!("{:<30} {:10.4} {:10}", "Flowing Pressure: ", p.value(program_state.units.pressure), program_state.units.pressure.label());
+    outln!("{:<30} {:10.4} {:10}", "Flowing Temperature: ", t.value(program_state.units.temp), program_state.units.temp.label());
+    outln!("{:<30} {:10.4} {:10}", "Reference Flow: ", flow, "m3/h");
+    outln!();
+    outln!("{:<22} {:>12} {:>12} {:>12} {:>9} {:<8}", "Quantity", previous_name, current_name, "Change", "Change %", "Unit");
+    let rows = [
+        ("Gross Heating Value", previous.heating_value, current.heating_value, "MJ/m3"),
+        ("Energy Flow", previous.energy_flow, current.energy_flow, "GJ/h"),
+        ("Relative Density", previous.relative_density, current.relative_density, "[]"),
+        ("Wobbe Index", previous.wobbe_index, current.wobbe_index, "MJ/m3"),
+        ("Base Density", previous.base_density, current.base_density, "kg/m3"),
+        ("Flowing Density", previous.flowing_density, current.flowing_density, "kg/m3"),
+        ("Fpv", previous.fpv, current.fpv, "[]"),
+    ];
+    let mut exceptions = 0;
+    for (name, before, after, unit) in rows {
+        let percent = (after - before) / before * 100.0;
+        let line = format!("{:<22} {:12.4} {:12.4} {:12.4} {:9.4} {:<8}", name, before, after, after - before, percent, unit);
+        if percent.abs() > limit {
+            exceptions += 1;
+            outln!("{}", line.red().bold());
+        } else {
+            outln!("{}", line);
+        }
+    }
+
+    outln!();
+    outln!("{:<22} {:>12} {:>12} {:>12}", "Component (mol%)", previous_name, current_name, "Change");
+    let (before, after) = (comp_to_array(&previous_comp), comp_to_array(&current_comp));
+    for j in 0..NUM_COMPONENTS {
+        if before[j] > 0.0 || after[j] > 0.0 {
+            outln!("{:<22} {:12.4} {:12.4} {:12.4}", COMPONENT_LABELS[j], before[j] * 100.0, after[j] * 100.0, (after[j] - before[j]) * 100.0);
+        }
+    }
+    if exceptions > 0 {
+        outln!("{}", format!("** {} quantities changed by more than {}% - report as an analyzer exception **", exceptions, limit).red().bold());
+    } else {
+        outln!("{}", format!("All quantities within {}%", limit).green());
+    }
+    print_gas_state(program_state);
+}
@@ -0,0 +1,269 @@
+use aga8::composition::Composition;
+use colored::Colorize;
+
+use crate::components::{array_to_comp, COMPONENT_LABELS, NUM_COMPONENTS};
+use crate::csv::split_line;
+use crate::custom_composition::{normalize_composition, print_normalization, Normalization};
+use crate::lumping::{name_key, LumpingRules};
+use crate::read_input;
+
+use std::fs;
+use std::path::Path;
+
+// CAS registry numbers in the order of COMPONENT_NAMES
+const CAS_NUMBERS: [&str; NUM_COMPONENTS] = [
+    "74-82-8",
+    "7727-37-9",
+    "124-38-9",
+    "74-84-0",
+    "74-98-6",
+    "75-28-5",
+    "106-97-8",
+    "78-78-4",
+    "109-66-0",
+    "110-54-3",
+    "142-82-5",
+    "111-65-9",
+    "111-84-2",
+    "124-18-5",
+    "1333-74-0",
+    "7782-44-7",
+    "630-08-0",
+    "7732-18-5",
+    "7783-06-4",
+    "7440-59-7",
+    "7440-37-1",
+];
+
+// Component names as printed by common GC reports, lower case without spaces, dashes or
+// underscores, and the index of the component they are
+const NAME_ALIASES: [(&str, usize); 63] = [
+    ("methane", 0), ("c1", 0), ("ch4", 0),
+    ("nitrogen", 1), ("n2", 1),
+    ("carbondioxide", 2), ("co2", 2),
+    ("ethane", 3), ("c2", 3), ("c2h6", 3),
+    ("propane", 4), ("c3", 4), ("c3h8", 4),
+    ("isobutane", 5), ("ibutane", 5), ("ic4", 5), ("2methylpropane", 5),
+    ("nbutane", 6), ("butane", 6), ("nc4", 6),
+    ("isopentane", 7), ("ipentane", 7), ("ic5", 7), ("2methylbutane", 7),
+    ("npentane", 8), ("pentane", 8), ("nc5", 8),
+    ("hexane", 9), ("nhexane", 9), ("nc6", 9), ("c6", 9),
+    ("heptane", 10), ("nheptane", 10), ("nc7", 10), ("c7", 10),
+    ("octane", 11), ("noctane", 11), ("nc8", 11), ("c8", 11),
+    ("nonane", 12), ("nnonane", 12), ("nc9", 12), ("c9", 12),
+    ("decane", 13), ("ndecane", 13), ("nc10", 13), ("c10", 13),
+    ("hydrogen", 14), ("h2", 14),
+    ("oxygen", 15), ("o2", 15),
+    ("carbonmonoxide", 16), ("co", 16),
+    ("water", 17), ("h2o", 17), ("watervapor", 17),
+    ("hydrogensulfide", 18), ("hydrogensulphide", 18), ("h2s", 18),
+    ("helium", 19), ("he", 19),
+    ("argon", 20), ("ar", 20),
+];
+
+// Summary rows of a report, not components
+const TOTAL_ROWS: [&str; 4] = ["total", "sum", "totals", "unnormalizedtotal"];
+
+// Index of the component with this CAS number or name, None for components AGA8 does not
+// model
+pub fn component_index(name: &str, cas: Option<&str>) -> Option<usize> {
+    cas.and_then(|cas| CAS_NUMBERS.iter().position(|number| *number == cas.trim()))
+        .or_else(|| NAME_ALIASES.iter().find(|(alias, _)| *alias == name_key(name)).map(|(_, index)| *index))
+}
+
+// Header columns of the component name, optional CAS number and mol%.  Reports with raw
+// and normalized amounts use the normalized column, or any other before the unnormalized
+// one.
+fn report_columns(headers: &[String]) -> Option<(usize, Option<usize>, usize)> {
+    let keys: Vec<String> = headers.iter().map(|header| name_key(header)).collect();
+    let name = keys.iter().position(|key| matches!(key.as_str(), "component" | "componentname" | "name" | "compound" | "analyte" | "peakname"))?;
+    let cas = keys.iter().position(|key| key.starts_with("cas"));
+    let is_mol = |key: &String| key.contains("mol") && (key.contains('%') || key.contains("percent"));
+    let mol = keys.iter().position(|key| is_mol(key) && key.contains("norm") && !key.contains("unnorm"))
+        .or_else(|| keys.iter().position(|key| is_mol(key) && !key.contains("unnorm")))
+        .or_else(|| keys.iter().position(is_mol))?;
+    Some((name, cas, mol))
+}
+
+pub struct GcReport {
+    // Mole fractions of the supported components
+    pub x: [f64; NUM_COMPONENTS],
+    // Report name, component index and mol% of every mapped row
+    pub mapped: Vec<(String, usize, f64)>,
+    // Report name, component lumped into and mol% of unsupported components with a
+    // lumping rule
+    pub lumped: Vec<(String, usize, f64)>,
+    // Report name and mol% of unsupported components without a lumping rule
+    pub unmapped: Vec<(String, f64)>,
+    // Report names of components below the detection limit
+    pub undetected: Vec<String>,
+}
+
+impl GcReport {
+    // Supported components the report does not list, taken as zero
+    pub fn unreported(&self) -> Vec<usize> {
+        (0..NUM_COMPONENTS).filter(|j| self.mapped.iter().all(|(_, index, _)| index != j)).collect()
+    }
+}
+
+// Reads the component table of a report.  Title and sample information lines above the
+// header row are skipped, as are total rows.  Amounts reported as blank, "ND" or below a
+// detection limit ("<0.001") are zero.  Unsupported components are added to a supported
+// one by the lumping rules.
+pub fn read_gc_report(path: &str, rules: &LumpingRules) -> Result<GcReport, String> {
+    let text = fs::read_to_string(path).map_err(|err| format!("Unable to read {}: {}", path, err))?;
+    let mut lines = text.lines().map(split_line);
+    let (name_col, cas_col, mol_col) = lines.by_ref().find_map(|headers| report_columns(&headers))
+        .ok_or(format!("No component and mol% columns found in {}", path))?;
+
+    let mut report = GcReport { x: [0.0; NUM_COMPONENTS], mapped: Vec::new(), lumped: Vec::new(), unmapped: Vec::new(), undetected: Vec::new() };
+    for row in lines {
+        let Some(name) = row.get(name_col).filter(|name| !name.is_empty()) else {
+            continue;
+        };
+        if TOTAL_ROWS.contains(&name_key(name).as_str()) {
+            continue;
+        }
+        let amount = row.get(mol_col).map_or("", String::as_str);
+        let value = if amount.is_empty() || amount.starts_with('<') || matches!(name_key(amount).as_str(), "nd" | "n.d." | "bdl") {
+            report.undetected.push(name.clone());
+            0.0
+        } else {
+            amount.parse::<f64>().map_err(|_| format!("Invalid mol% '{}' for {}", amount, name))?
+        };
+        let cas = cas_col.and_then(|i| row.get(i)).map(String::as_str);
+        if let Some(index) = component_index(name, cas) {
+            report.x[index] += value / 100.0;
+            report.mapped.push((name.clone(), index, value));
+        } else if let Some(index) = rules.component(name, cas) {
+            report.x[index] += value / 100.0;
+            report.lumped.push((name.clone(), index, value));
+        } else {
+            report.unmapped.push((name.clone(), value));
+        }
+    }
+    if report.mapped.is_empty() {
+        return Err(format!("No supported components in {}", path));
+    }
+    Ok(report)
+}
+
+pub fn print_gc_report(report: &GcReport) {
+    outln!();
+    outln!("{:<24} {:<20} {:>10}", "Report Name", "Component", "mol%");
+    for (name, index, value) in &report.mapped {
+        outln!("{:<24} {:<20} {:10.4}", name, COMPONENT_LABELS[*index], value);
+    }
+    for (name, index, value) in &report.lumped {
+        outln!("{:<24} {:<20} {:10.4}", name, format!("{} (lumped)", COMPONENT_LABELS[*index]), value);
+    }
+    for (name, value) in &report.unmapped {
+        outln!("{}", format!("{:<24} {:<20} {:10.4}", name, "(not supported)", value).yellow());
+    }
+    if !report.unmapped.is_empty() {
+        let total: f64 = report.unmapped.iter().map(|(_, value)| value).sum();
+        outln!("{}", format!("* {:.4} mol% of unsupported components excluded (no lumping rule)", total).yellow());
+    }
+    if !report.lumped.is_empty() {
+        let total: f64 = report.lumped.iter().map(|(_, _, value)| value).sum();
+        outln!("{}", format!("* {:.4} mol% of unsupported components lumped into supported ones", total).yellow());
+    }
+    if !report.undetected.is_empty() {
+        outln!("Below detection limit, taken as zero: {}", report.undetected.join(", "));
+    }
+    let unreported: Vec<&str> = report.unreported().into_iter().map(|j| COMPONENT_LABELS[j]).collect();
+    if !unreported.is_empty() {
+        outln!("Not reported, taken as zero: {}", unreported.join(", "));
+    }
+}
+
+// Prompts for a GC report CSV and returns the name and normalized composition, or None
+// when no composition should be loaded.
+pub fn import_gc_report(tolerance: f64, rules: &LumpingRules) -> Option<(String, Composition)> {
+    outln!("Enter CSV file path of the GC report (columns: component name, optional CAS number, mol%):");
+    let path = read_input();
+    let report = match read_gc_report(&path, rules) {
+        Ok(report) => report,
+        Err(err) => {
+            outln!("{}", format!("** {} **", err).red().bold());
+            return None;
+        }
+    };
+    print_gc_report(&report);
+
+    let mut gas_comp = array_to_comp(&report.x);
+    let normalization = normalize_composition(&mut gas_comp, tolerance);
+    print_normalization(&normalization);
+    if let Normalization::Rejected { .. } = normalization {
+        return None;
+    }
+    let file = Path::new(&path).file_name().map_or(path.clone(), |name| name.to_string_lossy().into_owned());
+    Some((format!("GC report {}", file), gas_comp))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Reads a report written to a temporary file
+    fn read(name: &str, text: &str) -> Result<GcReport, String> {
+        let path = std::env::temp_dir().join(format!("comp_perf_{}_{}.csv", name, std::process::id()));
+        fs::write(&path, text).unwrap();
+        let report = read_gc_report(path.to_str().unwrap(), &LumpingRules::default());
+        fs::remove_file(&path).ok();
+        report
+    }
+
+    // The CAS number decides over the name; names match without case, spaces or dashes
+    #[test]
+    fn components_by_cas_number_and_name() {
+        assert_eq!(component_index("Peak 1", Some(" 74-82-8 ")), Some(0));
+        assert_eq!(component_index("Methane", Some("124-38-9")), Some(2));
+        assert_eq!(component_index("n-Butane", Some("0-00-0")), Some(6));
+        assert_eq!(component_index("I-C4", None), Some(5));
+        assert_eq!(component_index("Carbon Dioxide", Some("")), Some(2));
+        assert_eq!(component_index("Hydrogen_Sulphide", None), Some(18));
+        assert_eq!(component_index("Ethylene", Some("74-85-1")), None);
+    }
+
+    // The normalized amount is read when the report also has the raw amount
+    #[test]
+    fn normalized_column() {
+        let headers = |line: &str| report_columns(&split_line(line));
+        assert_eq!(headers("Component,CAS No.,Mol% (raw),Mol% (normalized)"), Some((0, Some(1), 3)));
+        assert_eq!(headers("Peak Name,Unnormalized mol %,Mole Percent"), Some((0, None, 2)));
+        assert_eq!(headers("Component,Mass%"), None);
+    }
+
+    #[test]
+    fn report_rows() {
+        let text = "Sample: Station 4\n\
+                    Component,CAS,Mol% (raw),Mol% (normalized)\n\
+                    Methane,74-82-8,90.1,90.0\n\
+                    Ethane,,5.0,5.0\n\
+                    n-Butane,,1.0,1.0\n\
+                    Neopentane,,0.5,0.5\n\
+                    Peak 7,71-43-2,0.5,0.5\n\
+                    Mystery peak,,0.5,0.5\n\
+                    Nitrogen,,ND,ND\n\
+                    Helium,,<0.01,<0.01\n\
+                    Total,,100.1,97.5\n";
+        let report = read("gc_report_rows", text).unwrap();
+        assert_eq!(report.x[0], 0.9);
+        assert_eq!(report.x[6], 0.01);
+        // Neopentane and benzene are lumped into n-pentane and n-hexane
+        assert_eq!((report.x[8], report.x[9]), (0.005, 0.005));
+        assert_eq!(report.lumped.len(), 2);
+        assert_eq!(report.unmapped, [(String::from("Mystery peak"), 0.5)]);
+        assert_eq!(report.undetected, ["Nitrogen", "Helium"]);
+        assert!(!report.unreported().contains(&1));
+        assert!(report.unreported().contains(&4));
+    }
+
+    #[test]
+    fn report_errors() {
+        assert!(read("gc_report_headers", "Name,Amount\nMethane,100\n").err().unwrap().starts_with("No component and mol% columns"));
+        assert_eq!(read("gc_report_amount", "Component,Mol%\nMethane,ninety\n").err().unwrap(), "Invalid mol% 'ninety' for Methane");
+        assert!(read("gc_report_supported", "Component,Mol%\nMystery peak,100\n").err().unwrap().starts_with("No supported components"));
+    }
+}
@@ -0,0 +1,139 @@
+use aga8::composition::Composition;
+
+use crate::components::GROSS_HV;
+use crate::quality::R;
+use crate::{get_gas_comp, try_gas_state, GasComp, Pressure, Temperature};
+
+// Molar masses (g/mol) of the equivalent-composition building blocks
+const MM_METHANE: f64 = 16.0425;
+const MM_ETHANE: f64 = 30.069;
+const MM_NITROGEN: f64 = 28.0134;
+const MM_CARBON_DIOXIDE: f64 = 44.0095;
+const MM_HYDROGEN: f64 = 2.0159;
+
+// Gross characterization inputs in the style of SGERG-88 / AGA8 Gross method 2
+pub struct GrossInputs {
+    // Real-gas gross heating value in MJ/m3 at the base conditions
+    pub heating_value: f64,
+    // Real-gas relative density (air = 1) at the base conditions
+    pub relative_density: f64,
+    pub carbon_dioxide: f64,
+    pub hydrogen: f64,
+}
+
+fn solve_3x3(a: [[f64; 3]; 3], b: [f64; 3]) -> Option<[f64; 3]> {
+    let det = |m: [[f64; 3]; 3]| {
+        m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+            - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+            + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+    };
+    let d = det(a);
+    if d.abs() < 1.0e-12 {
+        return None;
+    }
+    let mut x = [0.0; 3];
+    for (col, value) in x.iter_mut().enumerate() {
+        let mut m = a;
+        for row in 0..3 {
+            m[row][col] = b[row];
+        }
+        *value = det(m) / d;
+    }
+    Some(x)
+}
+
+// Characterizes the gas as an equivalent methane/ethane/nitrogen mixture (plus the given
+// CO2 and H2) that reproduces the heating value and relative density at base conditions.
+// The real-gas Z used to convert between molar and volumetric quantities is iterated.
+pub fn gross_composition(inputs: &GrossInputs, p_base: Pressure, t_base: Temperature) -> Result<Composition, String> {
+    let (p_base, t_base) = (p_base.kpa(), t_base.kelvin());
+    let air = get_gas_comp(GasComp::Air);
+    let air_state = try_gas_state(&air, p_base, t_base).ok_or("Unable to evaluate air at base conditions")?;
+    let z_air = air_state.z;
+    let mm_air = air_state.mm;
+
+    let hv_methane = GROSS_HV[0];
+    let hv_ethane = GROSS_HV[3];
+    let hv_hydrogen = GROSS_HV[14];
+
+    let mut z = 1.0;
+    let mut gas_comp = Composition::default();
+    for _ in 0..20 {
+        // Molar heating value (kJ/mol) and molar mass (g/mol) implied by the inputs
+        let hv_molar = inputs.heating_value * z * R * t_base / p_base;
+        let mm = inputs.relative_density * mm_air * z / z_air;
+
+        let remainder = 1.0 - inputs.carbon_dioxide - inputs.hydrogen;
+        let x = solve_3x3(
+            [
+                [1.0, 1.0, 1.0],
+                [hv_methane, hv_ethane, 0.0],
+                [MM_METHANE, MM_ETHANE, MM_NITROGEN],
+            ],
+            [
+                remainder,
+                hv_molar - hv_hydrogen * inputs.hydrogen,
+                mm - MM_CARBON_DIOXIDE * inputs.carbon_dioxide - MM_HYDROGEN * inputs.hydrogen,
+            ],
+        ).ok_or("Unable to solve for the equivalent composition")?;
+
+        if x.iter().any(|fraction| *fraction < -1.0e-9) {
+            return Err(format!(
+                "Inputs cannot be represented by an equivalent gas (CH4 {:.4}, C2H6 {:.4}, N2 {:.4})",
+                x[0], x[1], x[2]
+            ));
+        }
+
+        gas_comp = Composition {
+            methane: x[0].max(0.0),
+            ethane: x[1].max(0.0),
+            nitrogen: x[2].max(0.0),
+            carbon_dioxide: inputs.carbon_dioxide,
+            hydrogen: inputs.hydrogen,
+            ..Default::default()
+        };
+        let z_new = try_gas_state(&gas_comp, p_base, t_base).ok_or("Unable to evaluate the equivalent gas at base conditions")?.z;
+        if (z_new - z).abs() < 1.0e-10 {
+            break;
+        }
+        z = z_new;
+    }
+    Ok(gas_comp)
+}
+
+pub fn print_equivalent_composition(gas_comp: &Composition) {
+    outln!("Equivalent composition (mole fraction):");
+    for (name, fraction) in [
+        ("Methane", gas_comp.methane),
+        ("Ethane", gas_comp.ethane),
+        ("Nitrogen", gas_comp.nitrogen),
+        ("Carbon Dioxide", gas_comp.carbon_dioxide),
+        ("Hydrogen", gas_comp.hydrogen),
+    ] {
+        outln!("{:<30} {:10.6}", format!("{}: ", name), fraction);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::quality::{gross_hv_volumetric, relative_density};
+
+    // A methane/ethane/nitrogen gas with CO2 and H2 characterizes back to itself from its
+    // heating value and relative density
+    #[test]
+    fn equivalent_gas_round_trip() {
+        let gas = Composition { methane: 0.86, ethane: 0.06, nitrogen: 0.04, carbon_dioxide: 0.02, hydrogen: 0.02, ..Default::default() };
+        let (p_base, t_base) = (Pressure::from_kpa(101.325), Temperature::from_kelvin(288.15));
+        let inputs = GrossInputs {
+            heating_value: gross_hv_volumetric(&gas, p_base, t_base),
+            relative_density: relative_density(&gas, p_base, t_base),
+            carbon_dioxide: gas.carbon_dioxide,
+            hydrogen: gas.hydrogen,
+        };
+        let gas_comp = gross_composition(&inputs, p_base, t_base).unwrap();
+        assert!((gas_comp.methane - 0.86).abs() < 1.0e-3);
+        assert!((gas_comp.ethane - 0.06).abs() < 1.0e-3);
+        assert!((gas_comp.nitrogen - 0.04).abs() < 1.0e-3);
+    }
+}
@@ -0,0 +1,100 @@
+use aga8::composition::Composition;
+use colored::Colorize;
+
+use crate::process::isenthalpic_state;
+use crate::{
+    get_power, get_temperature, new_gas_state, power_to_kw, print_gas_state, read_f64, read_pressure, read_temperature, Pressure,
+    ProgramState, Temperature, TemperatureDifference,
+};
+
+struct HeaterCase {
+    p_supply: Pressure,
+    t_supply: Temperature,
+    // Gas temperature leaving the heater in K
+    t_heated: f64,
+    // Gas temperature downstream of the pressure regulator without heating in K
+    t_unheated: f64,
+    // Heater duty in kW
+    duty: f64,
+}
+
+// Heats the supply gas so that after the isenthalpic letdown to p_delivery its temperature
+// is t_target.  Returns None when the heated state cannot be solved.
+fn heater_case(gas_comp: &Composition, p_supply: Pressure, t_supply: Temperature, p_delivery: Pressure, t_target: Temperature, mass_flow: f64) -> Option<HeaterCase> {
+    let supply = new_gas_state(gas_comp, p_supply.kpa(), t_supply.kelvin());
+    let unheated = isenthalpic_state(gas_comp, p_delivery.kpa(), supply.h, t_supply.kelvin())?;
+    let target = new_gas_state(gas_comp, p_delivery.kpa(), t_target.kelvin());
+    let (t_heated, dh) = if target.h > supply.h {
+        let heated = isenthalpic_state(gas_comp, p_supply.kpa(), target.h, t_target.kelvin())?;
+        (heated.t, target.h - supply.h)
+    } else {
+        (t_supply.kelvin(), 0.0)
+    };
+    Some(HeaterCase {
+        p_supply,
+        t_supply,
+        t_heated,
+        t_unheated: unheated.t,
+        // J/mol to kJ/kg is 1/mm, times kg/s gives kW
+        duty: dh / supply.mm * mass_flow,
+    })
+}
+
+pub fn fuel_gas_heater(program_state: &mut ProgramState) {
+    outln!();
+    outln!("{}", "Fuel Gas Heater Sizing".yellow().bold());
+    let p_supply_min = read_pressure(program_state, "minimum supply pressure");
+    let p_supply_max = read_pressure(program_state, "maximum supply pressure");
+    let t_supply_min = read_temperature(program_state, "minimum supply (ambient) temperature");
+    let t_supply_max = read_temperature(program_state, "maximum supply (ambient) temperature");
+    let p_delivery = read_pressure(program_state, "delivery pressure downstream of the regulator");
+    let t_dew = read_temperature(program_state, "dew point at delivery pressure");
+    let superheat = TemperatureDifference::from_kelvin(read_f64("Enter required superheat above the dew point (K):"));
+    let mass_flow = read_f64("Enter fuel gas mass flow (kg/s):");
+    let efficiency = read_f64("Enter heater efficiency (e.g. 0.98 electric, 0.8 bath):");
+    let power_unit = program_state.units.power.label();
+    let rated_duty = read_f64(&format!("Enter installed heater rating ({}, 0 if not selected):", power_unit));
+    let rated_duty = power_to_kw(rated_duty, program_state.units.power);
+    let t_target = t_dew + superheat;
+
+    let mut cases = Vec::new();
+    for p_supply in [p_supply_min, p_supply_max] {
+        for t_supply in [t_supply_min, t_supply_max] {
+            match heater_case(&program_state.gas_comp, p_supply, t_supply, p_delivery, t_target, mass_flow) {
+                Some(case) => cases.push(case),
+                None => outln!("{}", "** Unable to solve a heater case **".red().bold()),
+            }
+        }
+    }
+
+    let p_unit = program_state.units.pressure.label();
+    let t_unit = program_state.units.temp.label();
+    outln!();
+    outln!("{:>14} {:>14} {:>16} {:>14} {:>12}",
+        format!("P supply {}", p_unit), format!("T supply {}", t_unit), format!("T unheated {}", t_unit), format!("T heated {}", t_unit), format!("Duty {}", power_unit));
+    for case in &cases {
+        outln!("{:14.2} {:14.2} {:16.2} {:14.2} {:12.2}",
+            case.p_supply.value(program_state.units.pressure),
+            case.t_supply.value(program_state.units.temp),
+            get_temperature(case.t_unheated, program_state.units.temp),
+            get_temperature(case.t_heated, program_state.units.temp),
+            get_power(case.duty, program_state.units.power));
+    }
+
+    if let Some(worst) = cases.iter().max_by(|a, b| a.duty.total_cmp(&b.duty)) {
+        let heater_input = worst.duty / efficiency;
+        outln!();
+        outln!("{:<30} {:10.2} {:10}", "Target Delivery Temperature: ", t_target.value(program_state.units.temp), t_unit);
+        outln!("{:<30} {:10.2} {:10}", "Worst Case Gas Duty: ", get_power(worst.duty, program_state.units.power), power_unit);
+        outln!("{:<30} {:10.2} {:10}", "Required Heater Input: ", get_power(heater_input, program_state.units.power), power_unit);
+        if rated_duty > 0.0 {
+            let margin = (rated_duty - heater_input) / heater_input * 100.0;
+            outln!("{:<30} {:10.2} {:10}", "Duty Margin: ", margin, "%");
+            if margin < 0.0 {
+                outln!("{}", "** Installed heater is undersized for the worst case **".red().bold());
+            }
+        }
+    }
+
+    print_gas_state(program_state);
+}
@@ -0,0 +1,233 @@
+use aga8::detail::Detail;
+
+use crate::components::{array_to_comp, comp_to_array, NUM_COMPONENTS};
+use crate::lumping::LumpingRules;
+use crate::precision::Precision;
+use crate::tags::StreamTags;
+use crate::{set_gas_state, solve_gas_state, DischargeLink, Pressure, ProgramState, Temperature, TemperatureDifference, Units};
+
+// Oldest snapshots are dropped beyond this many undo steps
+const MAX_UNDO: usize = 50;
+
+#[derive(Clone, PartialEq)]
+struct PointSnapshot {
+    x: [f64; NUM_COMPONENTS],
+    p: f64,
+    t: f64,
+}
+
+impl PointSnapshot {
+    fn of(state: &Detail) -> PointSnapshot {
+        PointSnapshot { x: state.x, p: state.p, t: state.t }
+    }
+
+    // Errors were shown when the state was first calculated
+    fn restore(&self) -> Detail {
+        solve_gas_state(&array_to_comp(&self.x), self.p, self.t).0
+    }
+}
+
+// Everything the user can change from the menus, without the calculated properties
+#[derive(Clone, PartialEq)]
+pub struct Snapshot {
+    gas: String,
+    gas_comp: [f64; NUM_COMPONENTS],
+    p: f64,
+    t: f64,
+    units: Units,
+    inlet: Option<PointSnapshot>,
+    discharge: Option<PointSnapshot>,
+    base_pressure: Pressure,
+    base_temperature: Temperature,
+    normalize_tolerance: f64,
+    lumping: LumpingRules,
+    hydrate_margin: TemperatureDifference,
+    wichert_aziz: bool,
+    dead_state_pressure: Pressure,
+    dead_state_temperature: Temperature,
+    envelope: Vec<(Pressure, Temperature)>,
+    discharge_link: Option<DischargeLink>,
+    tags: StreamTags,
+    precision: Precision,
+}
+
+impl Snapshot {
+    fn take(program_state: &ProgramState) -> Snapshot {
+        Snapshot {
+            gas: program_state.gas.clone(),
+            gas_comp: comp_to_array(&program_state.gas_comp),
+            p: program_state.gas_state.p,
+            t: program_state.gas_state.t,
+            units: program_state.units,
+            inlet: program_state.show_inlet_state.then(|| PointSnapshot::of(&program_state.inlet_state)),
+            discharge: program_state.show_discharge_state.then(|| PointSnapshot::of(&program_state.discharge_state)),
+            base_pressure: program_state.base_pressure,
+            base_temperature: program_state.base_temperature,
+            normalize_tolerance: program_state.normalize_tolerance,
+            lumping: program_state.lumping.clone(),
+            hydrate_margin: program_state.hydrate_margin,
+            wichert_aziz: program_state.wichert_aziz,
+            dead_state_pressure: program_state.dead_state_pressure,
+            dead_state_temperature: program_state.dead_state_temperature,
+            envelope: program_state.envelope.clone(),
+            discharge_link: program_state.discharge_link,
+            tags: program_state.tags.clone(),
+            precision: program_state.precision.clone(),
+        }
+    }
+
+    fn restore(&self, program_state: &mut ProgramState) {
+        program_state.gas = self.gas.clone();
+        program_state.gas_comp = array_to_comp(&self.gas_comp);
+        set_gas_state(program_state, solve_gas_state(&program_state.gas_comp, self.p, self.t).0);
+        program_state.units = self.units;
+        program_state.show_inlet_state = self.inlet.is_some();
+        program_state.inlet_state = self.inlet.as_ref().map_or_else(Detail::new, PointSnapshot::restore);
+        program_state.show_discharge_state = self.discharge.is_some();
+        program_state.discharge_state = self.discharge.as_ref().map_or_else(Detail::new, PointSnapshot::restore);
+        program_state.base_pressure = self.base_pressure;
+        program_state.base_temperature = self.base_temperature;
+        program_state.normalize_tolerance = self.normalize_tolerance;
+        program_state.lumping = self.lumping.clone();
+        program_state.hydrate_margin = self.hydrate_margin;
+        program_state.wichert_aziz = self.wichert_aziz;
+        program_state.dead_state_pressure = self.dead_state_pressure;
+        program_state.dead_state_temperature = self.dead_state_temperature;
+        program_state.envelope = self.envelope.clone();
+        program_state.discharge_link = self.discharge_link;
+        program_state.tags = self.tags.clone();
+        program_state.precision = self.precision.clone();
+    }
+}
+
+#[derive(Default)]
+pub struct History {
+    // State as last shown to the user
+    current: Option<Snapshot>,
+    undo: Vec<Snapshot>,
+    redo: Vec<Snapshot>,
+}
+
+// Records the state when it differs from the last recorded one.  Called each time the
+// state is shown, so every menu action that changes it becomes one undo step.  Returns
+// true for a new state.
+pub fn record(program_state: &mut ProgramState) -> bool {
+    let snapshot = Snapshot::take(program_state);
+    let history = &mut program_state.history;
+    let changed = match history.current.take() {
+        Some(previous) if previous != snapshot => {
+            history.undo.push(previous);
+            if history.undo.len() > MAX_UNDO {
+                history.undo.remove(0);
+            }
+            history.redo.clear();
+            true
+        },
+        Some(_) => false,
+        None => true,
+    };
+    history.current = Some(snapshot);
+    changed
+}
+
+fn apply(snapshot: Snapshot, program_state: &mut ProgramState) {
+    snapshot.restore(program_state);
+    // Recalculated states can differ in the last digits from the stored snapshot
+    program_state.history.current = Some(Snapshot::take(program_state));
+}
+
+// Returns false when there is nothing to undo.
+pub fn undo(program_state: &mut ProgramState) -> bool {
+    let Some(previous) = program_state.history.undo.pop() else {
+        return false;
+    };
+    if let Some(current) = program_state.history.current.take() {
+        program_state.history.redo.push(current);
+    }
+    apply(previous, program_state);
+    true
+}
+
+// Returns false when there is nothing to redo.
+pub fn redo(program_state: &mut ProgramState) -> bool {
+    let Some(next) = program_state.history.redo.pop() else {
+        return false;
+    };
+    if let Some(current) = program_state.history.current.take() {
+        program_state.history.undo.push(current);
+    }
+    apply(next, program_state);
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{change_conditions, initial_program_state, update_gas_state};
+
+    fn set_pressure(program_state: &mut ProgramState, p: f64) {
+        change_conditions(program_state, Pressure::from_kpa(p), Temperature::from_kelvin(300.0));
+        update_gas_state(program_state);
+    }
+
+    // The solved pressure can differ from the set one in the last digits
+    fn pressure(program_state: &ProgramState) -> f64 {
+        (program_state.gas_state.p * 1.0e6).round() / 1.0e6
+    }
+
+    #[test]
+    fn undo_and_redo_steps() {
+        let mut program_state = initial_program_state();
+        assert!(!undo(&mut program_state));
+        assert!(record(&mut program_state));
+        assert!(!record(&mut program_state));
+        for p in [1000.0, 2000.0, 3000.0] {
+            set_pressure(&mut program_state, p);
+            assert!(record(&mut program_state));
+        }
+
+        assert!(undo(&mut program_state));
+        assert!(undo(&mut program_state));
+        assert_eq!(pressure(&program_state), 1000.0);
+        assert!(redo(&mut program_state));
+        assert_eq!(pressure(&program_state), 2000.0);
+        // Showing the restored state is not a new step
+        assert!(!record(&mut program_state));
+
+        // A new change drops the steps that were undone
+        set_pressure(&mut program_state, 5000.0);
+        assert!(record(&mut program_state));
+        assert!(!redo(&mut program_state));
+        assert!(undo(&mut program_state));
+        assert_eq!(pressure(&program_state), 2000.0);
+    }
+
+    #[test]
+    fn undo_restores_the_calculated_state() {
+        let mut program_state = initial_program_state();
+        record(&mut program_state);
+        let (p, t, d) = (pressure(&program_state), program_state.gas_state.t, program_state.gas_state.d);
+        set_pressure(&mut program_state, 4000.0);
+        record(&mut program_state);
+        undo(&mut program_state);
+        assert_eq!((pressure(&program_state), program_state.gas_state.t), (p, t));
+        assert!((program_state.gas_state.d - d).abs() < 1.0e-12);
+    }
+
+    // Only the last MAX_UNDO steps are kept
+    #[test]
+    fn undo_depth_is_limited() {
+        let mut program_state = initial_program_state();
+        record(&mut program_state);
+        for step in 1..=MAX_UNDO + 10 {
+            set_pressure(&mut program_state, 100.0 * step as f64);
+            record(&mut program_state);
+        }
+        let mut steps = 0;
+        while undo(&mut program_state) {
+            steps += 1;
+        }
+        assert_eq!(steps, MAX_UNDO);
+        assert_eq!(pressure(&program_state), 1000.0);
+    }
+}
@@ -0,0 +1,81 @@
+use colored::Colorize;
+
+use crate::{get_pressure, get_temperature, get_temperature_difference, print_gas_state, ProgramState};
+
+// Molar mass of dry air in g/mol, the reference of the gas gravity
+const AIR_MOLAR_MASS: f64 = 28.9647;
+// Gas gravities covered by the Katz chart
+const GRAVITY_RANGE: (f64, f64) = (0.555, 1.0);
+const PSI_PER_KPA: f64 = 0.145038;
+
+pub fn gas_gravity(mm: f64) -> f64 {
+    mm / AIR_MOLAR_MASS
+}
+
+// Hydrate formation temperature in K at p (kPa) of a sweet natural gas with the given
+// gravity, by the Towler and Mokhatab (2005) fit of the Katz gas gravity chart:
+// T (F) = 13.47 ln P + 34.27 ln G - 1.675 ln P ln G - 20.35 with P in psia.  None
+// outside the gravity range of the chart.
+pub fn hydrate_temperature(gravity: f64, p: f64) -> Option<f64> {
+    if !(GRAVITY_RANGE.0..=GRAVITY_RANGE.1).contains(&gravity) || p <= 0.0 {
+        return None;
+    }
+    let ln_p = (p * PSI_PER_KPA).ln();
+    let ln_g = gravity.ln();
+    let t_fahrenheit = 13.47 * ln_p + 34.27 * ln_g - 1.675 * ln_p * ln_g - 20.35;
+    Some((t_fahrenheit + 459.67) / 1.8)
+}
+
+// Warning when the current temperature is less than the hydrate margin above the
+// estimated hydrate formation temperature.  Gases without methane are not checked.
+pub fn hydrate_warning(program_state: &ProgramState) -> Option<String> {
+    let state = &program_state.gas_state;
+    if program_state.gas_comp.methane <= 0.0 {
+        return None;
+    }
+    let t_hydrate = hydrate_temperature(gas_gravity(state.mm), state.p)?;
+    let margin = state.t - t_hydrate;
+    if margin >= program_state.hydrate_margin.kelvin() {
+        return None;
+    }
+    let unit = program_state.units.temp;
+    Some(format!("Hydrate risk if free water is present: {:.2} {} is {:.2} {} {} the estimated hydrate temperature of {:.2} {}",
+        get_temperature(state.t, unit), unit.label(), get_temperature_difference(margin.abs(), unit), unit.label(),
+        if margin >= 0.0 { "above" } else { "below" }, get_temperature(t_hydrate, unit), unit.label()))
+}
+
+pub fn hydrate_report(program_state: &mut ProgramState) {
+    let state = &program_state.gas_state;
+    let units = program_state.units;
+    let gravity = gas_gravity(state.mm);
+    outln!();
+    outln!("{}", "Hydrate Formation Temperature (Katz gas gravity method)".cyan().bold());
+    outln!("{:<30} {:10.4} {:10}", "Pressure: ", get_pressure(state.p, units.pressure), units.pressure.label());
+    outln!("{:<30} {:10.4} {:10}", "Gas Gravity: ", gravity, "[]");
+    match hydrate_temperature(gravity, state.p) {
+        Some(t_hydrate) => {
+            outln!("{:<30} {:10.4} {:10}", "Hydrate Temperature: ", get_temperature(t_hydrate, units.temp), units.temp.label());
+            outln!("{:<30} {:10.4} {:10}", "Margin: ", get_temperature_difference(state.t - t_hydrate, units.temp), units.temp.label());
+            outln!("{:<30} {:10.4} {:10}", "Warning Margin: ", program_state.hydrate_margin.value(units.temp), units.temp.label());
+            if program_state.gas_comp.carbon_dioxide + program_state.gas_comp.hydrogen_sulfide > 0.05 {
+                outln!("{}", "* Gravity method is for sweet gas; CO2 and H2S raise the hydrate temperature".yellow());
+            }
+        },
+        None => outln!("{}", format!("** Gas gravity outside the Katz chart range ({} to {}) **", GRAVITY_RANGE.0, GRAVITY_RANGE.1).red().bold()),
+    }
+    print_gas_state(program_state);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Towler and Mokhatab (2005): a 0.6 gravity gas at 1000 psia forms hydrates at 61.1 F,
+    // on the Katz chart curve.  Gravities off the chart are not estimated.
+    #[test]
+    fn towler_mokhatab_katz_chart() {
+        let t = hydrate_temperature(0.6, 1000.0 / PSI_PER_KPA).unwrap();
+        assert!((t * 1.8 - 459.67 - 61.1).abs() < 0.05);
+        assert!(hydrate_temperature(0.5, 1000.0 / PSI_PER_KPA).is_none());
+    }
+}
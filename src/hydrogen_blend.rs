@@ -0,0 +1,72 @@
+use aga8::composition::Composition;
+use colored::Colorize;
+
+use crate::components::{array_to_comp, comp_to_array};
+use crate::quality::{gross_hv_volumetric, relative_density};
+use crate::validation::{check_state, Range};
+use crate::{get_density, print_gas_state, read_f64, try_gas_state, ProgramState};
+
+// Hydrogen injected into the gas as fraction h of the blend
+pub fn hydrogen_blend(gas_comp: &Composition, h: f64) -> Composition {
+    let mut x = comp_to_array(gas_comp).map(|xi| xi * (1.0 - h));
+    x[14] += h;
+    array_to_comp(&x)
+}
+
+// AGA8 range exceeded by the hydrogen content of the blend
+fn hydrogen_range(gas_comp: &Composition, p: f64, t: f64) -> Option<Range> {
+    check_state(gas_comp, p, t).into_iter()
+        .find(|violation| violation.quantity == "Hydrogen")
+        .map(|violation| violation.range)
+}
+
+// Heating value, Wobbe index and relative density at the base conditions, and density
+// and speed of sound at the current conditions, of the working gas with increasing
+// hydrogen injection.
+pub fn hydrogen_blending_sweep(program_state: &mut ProgramState) {
+    let h_max = read_f64("Enter maximum hydrogen injection (mol% of the blend):") / 100.0;
+    let step = read_f64("Enter step (mol%):") / 100.0;
+    if !(h_max > 0.0 && h_max <= 1.0 && step > 0.0) {
+        outln!("{}", "** Maximum must be between 0 and 100 mol% and the step above 0 **".red().bold());
+        print_gas_state(program_state);
+        return;
+    }
+    let (p, t) = (program_state.gas_state.p, program_state.gas_state.t);
+    let (p_base, t_base) = (program_state.base_pressure, program_state.base_temperature);
+    let density_unit = program_state.units.density;
+
+    outln!();
+    outln!("{}", format!("Hydrogen blending of {}", program_state.gas).bold());
+    outln!("{:>8} {:>8} {:>10} {:>10} {:>8} {:>10} {:>10}  AGA8 H2 range",
+        "H2 inj", "H2 tot", "HV", "Wobbe", "RD", "Density", "SoS");
+    outln!("{:>8} {:>8} {:>10} {:>10} {:>8} {:>10} {:>10}",
+        "mol%", "mol%", "MJ/m3", "MJ/m3", "[]", density_unit.label(), "m/s");
+    // The last row is the maximum even when the step does not divide it
+    let steps = (h_max / step - 1.0e-9).ceil() as usize;
+    let mut first_exceeded = None;
+    for i in 0..=steps {
+        let h = (i as f64 * step).min(h_max);
+        let blend = hydrogen_blend(&program_state.gas_comp, h);
+        let hv = gross_hv_volumetric(&blend, p_base, t_base);
+        let rd = relative_density(&blend, p_base, t_base);
+        let range = hydrogen_range(&blend, p, t);
+        if range.is_some() && first_exceeded.is_none() {
+            first_exceeded = Some(h);
+        }
+        let flag = match range {
+            None => String::from("-"),
+            Some(Range::Normal) => "above normal".yellow().to_string(),
+            Some(Range::Extended) => "above extended".red().bold().to_string(),
+        };
+        let flowing = match try_gas_state(&blend, p, t) {
+            Some(state) => format!("{:10.4} {:10.4}", get_density(&state, density_unit), state.w),
+            None => format!("{:>21}", "no solution"),
+        };
+        outln!("{:8.2} {:8.2} {:10.4} {:10.4} {:8.4} {}  {}",
+            h * 100.0, blend.hydrogen * 100.0, hv, hv / rd.sqrt(), rd, flowing, flag);
+    }
+    if let Some(h) = first_exceeded {
+        outln!("{}", format!("* Hydrogen exceeds the AGA8 normal range from {:.2} mol% injection", h * 100.0).yellow());
+    }
+    print_gas_state(program_state);
+}
@@ -0,0 +1,77 @@
+use aga8::composition::Composition;
+use colored::Colorize;
+
+use crate::{print_gas_state, read_f64, read_pressure, try_gas_state, Pressure, ProgramState, Temperature};
+
+const T_SCAN_MIN: f64 = 120.0;
+const T_SCAN_MAX: f64 = 1200.0;
+const T_SCAN_STEP: f64 = 10.0;
+
+fn jt_coefficient(gas_comp: &Composition, p: f64, t: f64) -> f64 {
+    try_gas_state(gas_comp, p, t).map_or(f64::NAN, |state| state.jt)
+}
+
+// Bisects the JT coefficient sign change between t_low and t_high.
+fn bisect_inversion(gas_comp: &Composition, p: f64, mut t_low: f64, mut t_high: f64) -> f64 {
+    let mut jt_low = jt_coefficient(gas_comp, p, t_low);
+    for _ in 0..50 {
+        let t_mid = 0.5 * (t_low + t_high);
+        let jt_mid = jt_coefficient(gas_comp, p, t_mid);
+        if jt_mid.signum() == jt_low.signum() {
+            t_low = t_mid;
+            jt_low = jt_mid;
+        } else {
+            t_high = t_mid;
+        }
+        if t_high - t_low < 1.0e-4 {
+            break;
+        }
+    }
+    0.5 * (t_low + t_high)
+}
+
+// Returns every temperature at which the JT coefficient changes sign at pressure p.
+pub fn inversion_temperatures(gas_comp: &Composition, p: Pressure) -> Vec<Temperature> {
+    let p = p.kpa();
+    let mut temperatures = Vec::new();
+    let mut t = T_SCAN_MIN;
+    let mut jt_prev = jt_coefficient(gas_comp, p, t);
+    while t < T_SCAN_MAX {
+        let t_next = t + T_SCAN_STEP;
+        let jt_next = jt_coefficient(gas_comp, p, t_next);
+        if jt_prev.is_finite() && jt_next.is_finite() && jt_prev.signum() != jt_next.signum() {
+            temperatures.push(Temperature::from_kelvin(bisect_inversion(gas_comp, p, t, t_next)));
+        }
+        t = t_next;
+        jt_prev = jt_next;
+    }
+    temperatures
+}
+
+pub fn jt_inversion_curve(program_state: &mut ProgramState) {
+    outln!();
+    outln!("{}", "Joule-Thomson Inversion Curve".cyan().bold());
+    let p_start = read_pressure(program_state, "start pressure");
+    let p_end = read_pressure(program_state, "end pressure");
+    let steps = read_f64("Enter number of pressure steps:").max(1.0) as usize;
+
+    let p_unit = program_state.units.pressure.label();
+    let t_unit = program_state.units.temp.label();
+    outln!();
+    outln!("{:>14} {:>14} {:>14}", format!("P ({})", p_unit), format!("Lower T ({})", t_unit), format!("Upper T ({})", t_unit));
+    for i in 0..=steps {
+        let p = p_start + (p_end - p_start) * (i as f64 / steps as f64);
+        let temperatures = inversion_temperatures(&program_state.gas_comp, p);
+        let p_display = p.value(program_state.units.pressure);
+        match temperatures.as_slice() {
+            [] => outln!("{:>14.4} {:>14} {:>14}", p_display, "-", "-"),
+            [upper] => outln!("{:>14.4} {:>14} {:>14.4}", p_display, "-", upper.value(program_state.units.temp)),
+            [lower, .., upper] => outln!("{:>14.4} {:>14.4} {:>14.4}", p_display,
+                lower.value(program_state.units.temp), upper.value(program_state.units.temp)),
+        }
+    }
+    outln!();
+    outln!("Between the lower and upper inversion temperatures throttling cools the gas; outside them it heats the gas.");
+
+    print_gas_state(program_state);
+}
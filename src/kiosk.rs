@@ -0,0 +1,298 @@
+use aga8::composition::Composition;
+use colored::Colorize;
+use serde::Deserialize;
+
+use crate::components::composition_serde;
+use crate::modbus::{ModbusPoller, ModbusSource};
+use crate::mqtt::{parse_payload, MqttClient};
+use crate::quality::gross_hv_volumetric;
+use crate::{try_gas_state, Pressure, Temperature};
+
+use std::fs;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// Glyphs of the large digits, five rows of three cells
+const GLYPH_ROWS: usize = 5;
+// First and longest delay before reconnecting to the MQTT broker
+const MQTT_RETRY: Duration = Duration::from_secs(1);
+const MQTT_RETRY_MAX: Duration = Duration::from_secs(60);
+
+fn glyph(c: char) -> [&'static str; GLYPH_ROWS] {
+    match c {
+        '0' => ["###", "# #", "# #", "# #", "###"],
+        '1' => ["  #", "  #", "  #", "  #", "  #"],
+        '2' => ["###", "  #", "###", "#  ", "###"],
+        '3' => ["###", "  #", "###", "  #", "###"],
+        '4' => ["# #", "# #", "###", "  #", "  #"],
+        '5' => ["###", "#  ", "###", "  #", "###"],
+        '6' => ["###", "#  ", "###", "# #", "###"],
+        '7' => ["###", "  #", "  #", "  #", "  #"],
+        '8' => ["###", "# #", "###", "# #", "###"],
+        '9' => ["###", "# #", "###", "  #", "###"],
+        '.' => ["   ", "   ", "   ", "   ", " # "],
+        '-' => ["   ", "   ", "###", "   ", "   "],
+        _ => ["   ", "   ", "   ", "   ", "   "],
+    }
+}
+
+// Renders text as five lines of block characters for reading across a control room
+pub fn big_text(text: &str) -> [String; GLYPH_ROWS] {
+    let mut rows: [String; GLYPH_ROWS] = Default::default();
+    for c in text.chars() {
+        for (row, cells) in rows.iter_mut().zip(glyph(c)) {
+            row.push_str(&cells.replace('#', "\u{2588}\u{2588}").replace(' ', "  "));
+            row.push_str("  ");
+        }
+    }
+    rows
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct KioskMqtt {
+    pub broker: String,
+    #[serde(default = "default_client_id")]
+    pub client_id: String,
+}
+
+// One displayed stream.  Pressure in kPa and temperature in K; with topics or a Modbus
+// source they are the starting values until the first update arrives.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct KioskStream {
+    pub name: String,
+    #[serde(with = "composition_serde")]
+    pub composition: Composition,
+    pub p: Option<f64>,
+    pub t: Option<f64>,
+    pub pressure_topic: Option<String>,
+    pub temperature_topic: Option<String>,
+    pub modbus: Option<ModbusSource>,
+}
+
+impl KioskStream {
+    fn is_live(&self) -> bool {
+        self.pressure_topic.is_some() || self.temperature_topic.is_some() || self.modbus.is_some()
+    }
+}
+
+// Kiosk settings read from TOML
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct KioskConfig {
+    // Seconds each stream is shown
+    #[serde(default = "default_interval")]
+    pub interval: u64,
+    // Seconds without an update before live values are marked stale
+    #[serde(default = "default_stale_after")]
+    pub stale_after: u64,
+    #[serde(default = "default_base_pressure")]
+    pub base_pressure: Pressure,
+    #[serde(default = "default_base_temperature")]
+    pub base_temperature: Temperature,
+    pub mqtt: Option<KioskMqtt>,
+    #[serde(rename = "stream")]
+    pub streams: Vec<KioskStream>,
+}
+
+fn default_client_id() -> String {
+    String::from("comp_perf-kiosk")
+}
+
+fn default_interval() -> u64 {
+    10
+}
+
+fn default_stale_after() -> u64 {
+    60
+}
+
+fn default_base_pressure() -> Pressure {
+    Pressure::from_kpa(101.325)
+}
+
+fn default_base_temperature() -> Temperature {
+    Temperature::from_kelvin(288.15)
+}
+
+pub fn read_kiosk_config(path: &str) -> Result<KioskConfig, String> {
+    let text = fs::read_to_string(path).map_err(|err| format!("Unable to read {}: {}", path, err))?;
+    let config: KioskConfig = toml::from_str(&text).map_err(|err| format!("Invalid kiosk configuration {}: {}", path, err))?;
+    if config.streams.is_empty() {
+        return Err(format!("No [[stream]] entries in {}", path));
+    }
+    let live = config.streams.iter().any(|stream| stream.pressure_topic.is_some() || stream.temperature_topic.is_some());
+    if live && config.mqtt.is_none() {
+        return Err(String::from("Streams with topics need an [mqtt] broker"));
+    }
+    for stream in &config.streams {
+        if let Some(modbus) = &stream.modbus {
+            modbus.check().map_err(|err| format!("Stream {}: {}", stream.name, err))?;
+        }
+    }
+    Ok(config)
+}
+
+// Latest conditions of a stream and when they were last updated by an input
+#[derive(Clone, Copy, Default)]
+pub struct LiveValues {
+    pub p: Option<f64>,
+    pub t: Option<f64>,
+    pub updated: Option<Instant>,
+}
+
+// Feeds MQTT updates into the live values, reconnecting after the broker is lost with a
+// delay that doubles after each failed attempt.  Errors are shown until the next
+// successful subscription.
+fn mqtt_input(mqtt: &KioskMqtt, topics: &[(String, usize, bool)], values: &Mutex<Vec<LiveValues>>, input_error: &Mutex<Option<String>>) {
+    let prefix = format!("MQTT {}", mqtt.broker);
+    let mut delay = MQTT_RETRY;
+    loop {
+        let err = mqtt_session(mqtt, topics, values, || {
+            delay = MQTT_RETRY;
+            input_error.lock().unwrap().take_if(|err| err.starts_with(&prefix));
+        }).unwrap_err();
+        *input_error.lock().unwrap() = Some(format!("{}: {} - retrying in {} s", prefix, err, delay.as_secs()));
+        thread::sleep(delay);
+        delay = (delay * 2).min(MQTT_RETRY_MAX);
+    }
+}
+
+// One connection to the broker, until it is lost.  connected runs once subscribed.
+fn mqtt_session(mqtt: &KioskMqtt, topics: &[(String, usize, bool)], values: &Mutex<Vec<LiveValues>>, connected: impl FnOnce()) -> Result<(), String> {
+    let mut client = MqttClient::connect(&mqtt.broker, &mqtt.client_id).map_err(|err| format!("Unable to connect: {}", err))?;
+    let filters: Vec<&str> = topics.iter().map(|(topic, _, _)| topic.as_str()).collect();
+    client.subscribe(&filters).map_err(|err| format!("Unable to subscribe: {}", err))?;
+    connected();
+    loop {
+        let (topic, payload) = client.next_message().map_err(|err| format!("Connection lost: {}", err))?;
+        let Some(value) = parse_payload(&payload) else {
+            continue;
+        };
+        let mut values = values.lock().unwrap();
+        for (_, index, pressure) in topics.iter().filter(|(name, _, _)| *name == topic) {
+            let live = &mut values[*index];
+            if *pressure {
+                live.p = Some(value);
+            } else {
+                live.t = Some(value);
+            }
+            live.updated = Some(Instant::now());
+        }
+    }
+}
+
+// Feeds Modbus readings of one stream into the live values.  Errors are shown until the
+// next successful poll.
+fn modbus_input(source: &ModbusSource, index: usize, values: &Mutex<Vec<LiveValues>>, input_error: &Mutex<Option<String>>) {
+    let mut poller = ModbusPoller::new(source);
+    loop {
+        let polled = Instant::now();
+        match poller.poll() {
+            Ok((p, t)) => {
+                values.lock().unwrap()[index] = LiveValues { p: Some(p.kpa()), t: Some(t.kelvin()), updated: Some(Instant::now()) };
+                input_error.lock().unwrap().take_if(|err| err.starts_with(&source.address));
+            },
+            Err(err) => *input_error.lock().unwrap() = Some(format!("{}: {}", source.address, err)),
+        }
+        thread::sleep(source.interval().saturating_sub(polled.elapsed()));
+    }
+}
+
+fn draw(config: &KioskConfig, stream: &KioskStream, live: LiveValues, position: (usize, usize), input_error: Option<&str>) {
+    let mut out = String::from("\x1b[2J\x1b[H");
+    out.push_str(&format!("{}   {}\n\n", stream.name.to_uppercase().bold(), format!("{} / {}", position.0 + 1, position.1).dimmed()));
+    let state = live.p.zip(live.t).and_then(|(p, t)| try_gas_state(&stream.composition, p, t));
+    match (&state, live.p, live.t) {
+        (Some(state), _, _) => {
+            let big = [
+                ("PRESSURE", "kPa", format!("{:.1}", state.p)),
+                ("TEMPERATURE", "C", format!("{:.1}", state.t - 273.15)),
+                ("DENSITY", "kg/m3", format!("{:.2}", state.d * state.mm)),
+            ];
+            for (label, unit, value) in big {
+                out.push_str(&format!("{} {}\n", label.bold(), unit.dimmed()));
+                for row in big_text(&value) {
+                    out.push_str(&format!("    {}\n", row));
+                }
+                out.push('\n');
+            }
+            out.push_str(&format!("{:<30} {:10.4}\n", "Compressibility Z: ", state.z));
+            out.push_str(&format!("{:<30} {:10.4} {:10}\n", "Molar Mass: ", state.mm, "g/mol"));
+            out.push_str(&format!("{:<30} {:10.4} {:10}\n", "Speed of Sound: ", state.w, "m/s"));
+            out.push_str(&format!("{:<30} {:10.4} {:10}\n", "Gross Heating Value: ", gross_hv_volumetric(&stream.composition, config.base_pressure, config.base_temperature), "MJ/m3"));
+        },
+        (None, Some(_), Some(_)) => out.push_str(&format!("{}\n", "** No solution at the current conditions **".red().bold())),
+        _ => out.push_str(&format!("{}\n", "Waiting for pressure and temperature".yellow())),
+    }
+    out.push('\n');
+    if stream.is_live() {
+        match live.updated {
+            Some(updated) if updated.elapsed().as_secs() >= config.stale_after => {
+                out.push_str(&format!("{}\n", format!("STALE - last update {} s ago", updated.elapsed().as_secs()).red().bold()));
+            },
+            Some(updated) => out.push_str(&format!("{}\n", format!("Live - updated {} s ago", updated.elapsed().as_secs()).dimmed())),
+            None => out.push_str(&format!("{}\n", "No live data received".yellow())),
+        }
+    }
+    if let Some(err) = input_error {
+        out.push_str(&format!("{}\n", format!("** {} **", err).red().bold()));
+    }
+    out!("{}", out);
+}
+
+// Full screen, read-only display cycling through the configured streams.  Runs until
+// interrupted.  Usage: comp_perf kiosk <config.toml>
+pub fn run_kiosk(args: &[String]) -> i32 {
+    let Some(path) = args.first() else {
+        eprintln!("Usage: comp_perf kiosk <config.toml>");
+        return 2;
+    };
+    let config = match read_kiosk_config(path) {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("{}", err);
+            return 1;
+        }
+    };
+    let values = Arc::new(Mutex::new(
+        config.streams.iter().map(|stream| LiveValues { p: stream.p, t: stream.t, updated: None }).collect::<Vec<_>>(),
+    ));
+    let input_error = Arc::new(Mutex::new(None));
+    if let Some(mqtt) = &config.mqtt {
+        let mut topics = Vec::new();
+        for (index, stream) in config.streams.iter().enumerate() {
+            topics.extend(stream.pressure_topic.clone().map(|topic| (topic, index, true)));
+            topics.extend(stream.temperature_topic.clone().map(|topic| (topic, index, false)));
+        }
+        let mqtt = KioskMqtt { broker: mqtt.broker.clone(), client_id: mqtt.client_id.clone() };
+        let (values, input_error) = (Arc::clone(&values), Arc::clone(&input_error));
+        thread::spawn(move || mqtt_input(&mqtt, &topics, &values, &input_error));
+    }
+
+    let config = Arc::new(config);
+    for (index, stream) in config.streams.iter().enumerate() {
+        if stream.modbus.is_some() {
+            let (config, values, input_error) = (Arc::clone(&config), Arc::clone(&values), Arc::clone(&input_error));
+            thread::spawn(move || {
+                let source = config.streams[index].modbus.as_ref().unwrap();
+                modbus_input(source, index, &values, &input_error);
+            });
+        }
+    }
+
+    let interval = Duration::from_secs(config.interval.max(1));
+    let count = config.streams.len();
+    for index in (0..count).cycle() {
+        let shown = Instant::now();
+        while shown.elapsed() < interval {
+            let live = values.lock().unwrap()[index];
+            let err = input_error.lock().unwrap().clone();
+            draw(&config, &config.streams[index], live, (index, count), err.as_deref());
+            thread::sleep(Duration::from_secs(1));
+        }
+    }
+    0
+}
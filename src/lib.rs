@@ -0,0 +1,12 @@
+// Core calculations shared by the comp_perf program, the C API in ffi.rs, the Python
+// module in python.rs and the WebAssembly bindings in wasm.rs.  Nothing here reads stdin
+// or writes stdout except the Console front end.
+pub mod components;
+pub mod ffi;
+pub mod frontend;
+#[cfg(feature = "python")]
+pub mod python;
+pub mod thermo;
+pub mod units;
+#[cfg(feature = "wasm")]
+pub mod wasm;
@@ -0,0 +1,165 @@
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+
+use crate::components::{COMPONENT_LABELS, NUM_COMPONENTS};
+use crate::{output, print_gas_state, read_input, ProgramState};
+
+// Assigns a component AGA8 does not model, by report name or CAS number, to the
+// supported component it is added to
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct LumpingRule {
+    pub key: String,
+    pub component: usize,
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct LumpingRules {
+    pub rules: Vec<LumpingRule>,
+}
+
+// Other hydrocarbons go to the normal alkane of the same carbon number, the plus
+// fractions to their lightest component
+const DEFAULT_RULES: [(&str, usize); 24] = [
+    ("ethylene", 3),
+    ("propylene", 4),
+    ("propene", 4),
+    ("neopentane", 8),
+    ("463-82-1", 8),
+    ("2-methylpentane", 9),
+    ("3-methylpentane", 9),
+    ("cyclopentane", 9),
+    ("methylcyclopentane", 9),
+    ("cyclohexane", 9),
+    ("benzene", 9),
+    ("71-43-2", 9),
+    ("C6+", 9),
+    ("hexanes plus", 9),
+    ("methylcyclohexane", 10),
+    ("toluene", 10),
+    ("108-88-3", 10),
+    ("C7+", 10),
+    ("heptanes plus", 10),
+    ("ethylbenzene", 11),
+    ("xylenes", 11),
+    ("C8+", 11),
+    ("C9+", 12),
+    ("C10+", 13),
+];
+
+impl Default for LumpingRules {
+    fn default() -> LumpingRules {
+        LumpingRules {
+            rules: DEFAULT_RULES.iter().map(|(key, component)| LumpingRule { key: key.to_string(), component: *component }).collect(),
+        }
+    }
+}
+
+// Lower case without spaces, dashes or underscores, so "n-Butane" matches "n butane"
+pub fn name_key(name: &str) -> String {
+    name.chars().filter(|c| !matches!(c, ' ' | '-' | '_')).flat_map(char::to_lowercase).collect()
+}
+
+impl LumpingRules {
+    // Component an unsupported component is lumped into, matched by name before CAS number
+    pub fn component(&self, name: &str, cas: Option<&str>) -> Option<usize> {
+        let keys = [Some(name_key(name)), cas.map(name_key).filter(|cas| !cas.is_empty())];
+        keys.iter().flatten()
+            .find_map(|key| self.rules.iter().find(|rule| name_key(&rule.key) == *key))
+            .map(|rule| rule.component)
+    }
+
+    // Adds a rule or replaces the rule for the same name
+    pub fn set(&mut self, key: &str, component: usize) {
+        self.rules.retain(|rule| name_key(&rule.key) != name_key(key));
+        self.rules.push(LumpingRule { key: key.to_string(), component });
+    }
+}
+
+fn read_component() -> Option<usize> {
+    for (i, label) in COMPONENT_LABELS.iter().enumerate() {
+        outln!("{} - {}", i + 1, label);
+    }
+    outln!("Lump into component:");
+    read_input().parse::<usize>().ok().filter(|i| (1..=NUM_COMPONENTS).contains(i)).map(|i| i - 1)
+}
+
+pub fn lumping_menu(program_state: &mut ProgramState) {
+    loop {
+        if !output::quiet() {
+            outln!();
+            outln!("Trace Component Lumping");
+            outln!("-----------------------");
+            for (i, rule) in program_state.lumping.rules.iter().enumerate() {
+                outln!("{:>3} - {:<24} -> {}", i + 1, rule.key, COMPONENT_LABELS[rule.component]);
+            }
+            outln!("a - Add or change a rule");
+            outln!("r - Remove a rule");
+            outln!("d - Restore the default rules");
+            outln!("b - Back");
+        }
+        match read_input().as_str() {
+            "a" => {
+                outln!("Enter component name or CAS number as reported:");
+                let key = read_input();
+                match read_component() {
+                    Some(component) if !key.is_empty() => program_state.lumping.set(&key, component),
+                    _ => outln!("{}", "**Invalid rule!**".bold().red()),
+                }
+            },
+            "r" => {
+                outln!("Enter rule number to remove:");
+                let rules = &mut program_state.lumping.rules;
+                match read_input().parse::<usize>().ok().filter(|i| (1..=rules.len()).contains(i)) {
+                    Some(i) => {
+                        rules.remove(i - 1);
+                    },
+                    None => outln!("{}", "**Invalid rule number!**".bold().red()),
+                }
+            },
+            "d" => program_state.lumping = LumpingRules::default(),
+            "b" => {
+                print_gas_state(program_state);
+                return;
+            },
+            _ => (),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_rules_by_name_and_cas_number() {
+        let rules = LumpingRules::default();
+        assert_eq!(rules.component("Propylene", None), Some(4));
+        assert_eq!(rules.component("C6 +", None), Some(9));
+        assert_eq!(rules.component("Hexanes_Plus", None), Some(9));
+        assert_eq!(rules.component("Peak 12", Some("108-88-3")), Some(10));
+        assert_eq!(rules.component("Peak 12", Some("")), None);
+        assert_eq!(rules.component("Methanol", Some("67-56-1")), None);
+    }
+
+    // A name rule applies before a CAS number rule
+    #[test]
+    fn name_before_cas_number() {
+        let mut rules = LumpingRules::default();
+        rules.set("Benzene", 13);
+        assert_eq!(rules.component("benzene", Some("108-88-3")), Some(13));
+        assert_eq!(rules.component("Toluene", Some("71-43-2")), Some(10));
+    }
+
+    // Setting a rule for the same name in another spelling replaces it
+    #[test]
+    fn set_replaces_the_rule() {
+        let mut rules = LumpingRules::default();
+        let count = rules.rules.len();
+        rules.set("c7 +", 11);
+        assert_eq!(rules.rules.len(), count);
+        assert_eq!(rules.component("C7+", None), Some(11));
+        rules.set("Methanol", 0);
+        assert_eq!(rules.rules.len(), count + 1);
+        assert_eq!(rules.component("methanol", None), Some(0));
+    }
+}
@@ -1,122 +1,457 @@
 use colored::Colorize;
 use aga8::composition::Composition;
 use aga8::detail::Detail;
-use std::io;
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+use precision::Quantity;
+
+use comp_perf::components;
+use comp_perf::thermo::{chemical_potentials, flow_exergy, ideal_gas_state, residual_properties, try_gas_state};
+use comp_perf::units::{
+    compressibility_in_units, d2p_dd2_in_units, d2p_dd2_label, density_in_units, dp_dd_in_units, dp_dd_label, dp_dt_in_units, dp_dt_label,
+    energy_in_units, entropy_in_units, entropy_label, expansivity_in_units, get_power, get_pressure, get_temperature, get_temperature_difference,
+    internal_energy_in_units,
+    jt_in_units, jt_label, power_to_kw, pressure_to_kpa, temperature_to_kelvin, unit_mix, UnitDensity, UnitInternalEnergy, UnitPower, UnitPressure,
+    UnitSystem, UnitTemp, Pressure, Temperature, TemperatureDifference, DENSITY_UNITS, ENERGY_UNITS, POWER_UNITS, PRESSURE_UNITS,
+};
+
+#[macro_use]
+mod output;
+mod batch;
+mod bench;
+mod blending;
+mod blowdown;
+#[cfg(feature = "reports")]
+mod calc_log;
+mod co2_spec;
+mod combustion;
+mod comparison;
+mod control_valve;
+mod custom_composition;
+mod csv;
+mod energy_flow;
+mod envelope;
+mod exchanger;
+#[cfg(feature = "reports")]
+mod export;
+mod gc_average;
+mod gc_delta;
+mod gc_report;
+mod gross;
+mod heater;
+mod history;
+mod hydrate;
+mod hydrogen_blend;
+mod inversion;
+#[cfg(all(feature = "tui", feature = "server"))]
+mod kiosk;
+mod lumping;
+#[cfg(feature = "server")]
+mod modbus;
+#[cfg(feature = "server")]
+mod mqtt;
+mod nozzle;
+mod orifice;
+mod peng_robinson;
+mod phase_envelope;
+mod pipeline;
+mod precision;
+mod process;
+mod proving;
+mod pseudo_critical;
+mod quality;
+#[cfg(feature = "server")]
+mod realtime;
+mod recycle;
+mod reconciliation;
+mod relief_valve;
+mod release;
+mod script;
+#[cfg(feature = "server")]
+mod server;
+mod similarity;
+#[cfg(test)]
+mod snapshot_tests;
+mod sos_inference;
+mod storage;
+mod tags;
+mod tariff;
+#[cfg(feature = "reports")]
+mod template;
+#[cfg(feature = "tui")]
+mod tui;
+mod tutorial;
+mod validation;
+mod virial;
+mod water;
 
 struct ProgramState {
     gas: String,
     gas_state: Detail,
+    // Chemical potentials of the components of gas_state in J/mol, kept with gas_state
+    // since each takes a DETAIL solve
+    chemical_potentials: Option<Vec<(usize, f64)>>,
     gas_comp: Composition,
-    unit_text: UnitText,
     units : Units,
     inlet_state: Detail,
     discharge_state: Detail,
     show_inlet_state: bool,
     show_discharge_state: bool,
+    base_pressure: Pressure,
+    base_temperature: Temperature,
+    normalize_tolerance: f64,
+    // Assignment of unsupported components in imported analyses
+    lumping: lumping::LumpingRules,
+    // Temperature margin above the hydrate formation temperature below which to warn
+    hydrate_margin: TemperatureDifference,
+    // Wichert-Aziz sour gas correction of the pseudo-critical properties
+    wichert_aziz: bool,
+    // Dead state of the flow exergy
+    dead_state_pressure: Pressure,
+    dead_state_temperature: Temperature,
+    // Equipment operating envelope as polygon vertices
+    envelope: Vec<(Pressure, Temperature)>,
+    discharge_link: Option<DischargeLink>,
+    history: history::History,
+    tags: tags::StreamTags,
+    precision: precision::Precision,
+    // Calculation log file, appended with every new state
+    #[cfg(feature = "reports")]
+    log_path: Option<String>,
+    // Changes to gas_comp and the conditions not yet applied to gas_state
+    dirty: Dirty,
+}
+
+// Set by change_gas_comp and change_conditions and cleared by update_gas_state
+#[derive(Clone, Copy, Default)]
+struct Dirty {
+    composition: bool,
+    conditions: bool,
 }
 
+// Discharge pressure defined relative to the inlet pressure
+#[derive(Clone, Copy, PartialEq)]
+enum DischargeLink {
+    Ratio(f64),
+    // Pressure rise
+    Delta(Pressure),
+}
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
 struct Units {
     pressure: UnitPressure,
     temp: UnitTemp,
     internal_energy: UnitInternalEnergy,
+    power: UnitPower,
+    density: UnitDensity,
 }
 
-#[derive(Clone, Copy)]
-enum UnitPressure {
-    kPa,
-    PSI,
-    Bar,
-}
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    output::select_color(&args);
+    match args.get(1).map(String::as_str) {
+        Some("bench") => {
+            bench::run_bench();
+            return;
+        },
+        Some("batch") => std::process::exit(batch::run_batch(&args[2..])),
+        #[cfg(all(feature = "tui", feature = "server"))]
+        Some("kiosk") => std::process::exit(kiosk::run_kiosk(&args[2..])),
+        #[cfg(feature = "server")]
+        Some("modbus") => std::process::exit(modbus::run_modbus(&args[2..])),
+        #[cfg(feature = "server")]
+        Some("mqtt") => std::process::exit(mqtt::run_mqtt(&args[2..])),
+        #[cfg(feature = "server")]
+        Some("serve") => std::process::exit(server::run_server(&args[2..])),
+        #[cfg(feature = "server")]
+        Some("realtime") => std::process::exit(realtime::run_realtime(&mut comp_perf::frontend::Console)),
+        #[cfg(feature = "tui")]
+        Some("tui") => {
+            if !tui::run_tui(&mut initial_program_state()) {
+                eprintln!("Full screen mode needs an interactive terminal");
+                std::process::exit(1);
+            }
+            return;
+        },
+        _ => (),
+    }
 
-#[derive(Clone, Copy)]
-enum UnitTemp {
-    C,
-    K,
-    F,
-    R,
-}
+    if let Some(path) = args.iter().position(|arg| arg == "--script").map(|i| args.get(i + 1)) {
+        let Some(path) = path else {
+            eprintln!("--script needs a file path");
+            std::process::exit(1);
+        };
+        if let Err(err) = script::run_file(path) {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+    }
 
-#[derive(Clone, Copy)]
-enum UnitInternalEnergy {
-    J_mol,
-    kJ_kg,
-    BTU_lbm,
+    output::select_mode(&args);
+    let mut program_state = Box::new(initial_program_state());
+    #[cfg(feature = "reports")]
+    if let Some(i) = args.iter().position(|arg| arg == "--log") {
+        let Some(path) = args.get(i + 1) else {
+            eprintln!("--log needs a file path");
+            std::process::exit(1);
+        };
+        program_state.log_path = Some(path.clone());
+    }
+    // On a terminal the full screen view is the front end; --classic keeps the line menus
+    #[cfg(feature = "tui")]
+    if !output::quiet() && !args.iter().any(|arg| arg == "--classic" || arg == "--script") && tui::run_tui(&mut program_state) {
+        quit();
+    }
+    if !output::quiet() {
+        outln!();
+        outln!("{}", "Thermodynamic Properties Calculator".blue().bold());
+        outln!("{}", "Frank Pereny - 2025".blue().italic());
+        outln!("{}", "-----------------------------------".blue());
+    }
+    print_gas_state(&mut program_state);
+    loop {
+        main_menu(&mut program_state);
+    }
 }
 
-fn main() {
-
+// Air at 100 kPa and 273.15 K in SI units
+fn initial_program_state() -> ProgramState {
     let gas = String::from("Air");
-    let initial_pressure= 100.0;
-    let initial_temperature = 273.15;
-
-    let unit_text = UnitText {
-        pressure: "kPa",
-        temperature: "K",
-        internal_energy: "J/mol",
-    };
+    let initial_pressure = Pressure::from_kpa(100.0);
+    let initial_temperature = Temperature::from_kelvin(273.15);
 
     let units = Units {
         pressure: UnitPressure::kPa,
         temp: UnitTemp::K,
         internal_energy: UnitInternalEnergy::J_mol,
+        power: UnitPower::kW,
+        density: UnitDensity::mol_l,
     };
     
-    let mut program_state = Box::new(ProgramState {
-        gas: gas,
-        gas_state: Detail::default(),
+    let mut program_state = ProgramState {
+        gas,
+        gas_state: Detail::new(),
         gas_comp: get_gas_comp(GasComp::Air),
-        unit_text: unit_text,
-        units: units,
-        inlet_state: Detail::default(),
-        discharge_state: Detail::default(),
+        units,
+        inlet_state: Detail::new(),
+        discharge_state: Detail::new(),
         show_inlet_state: false,
         show_discharge_state: false,
-    });
-
-    program_state.gas_state.set_composition(&program_state.gas_comp).unwrap();
-    program_state.gas_state.p = initial_pressure;
-    program_state.gas_state.t = initial_temperature;
-    calculate_state(&mut program_state.gas_state);
-    println!();
-    println!("{}", "Thermodynamic Properties Calculator".blue().bold());
-    println!("{}", "Frank Pereny - 2025".blue().italic());
-    println!("{}", "-----------------------------------".blue());
-    print_gas_state(&mut program_state);
+        base_pressure: Pressure::from_kpa(101.325),
+        base_temperature: Temperature::from_kelvin(288.15),
+        normalize_tolerance: 0.01,
+        lumping: lumping::LumpingRules::default(),
+        hydrate_margin: TemperatureDifference::from_kelvin(5.0),
+        wichert_aziz: false,
+        dead_state_pressure: Pressure::from_kpa(101.325),
+        dead_state_temperature: Temperature::from_kelvin(298.15),
+        envelope: Vec::new(),
+        discharge_link: None,
+        history: history::History::default(),
+        tags: tags::StreamTags::default(),
+        precision: precision::Precision::default(),
+        #[cfg(feature = "reports")]
+        log_path: None,
+        dirty: Dirty { composition: true, conditions: true },
+        chemical_potentials: None,
+    };
+
+    change_conditions(&mut program_state, initial_pressure, initial_temperature);
+    print_state_errors(&update_gas_state(&mut program_state));
+    program_state
 }
 
-fn quit() {
+fn quit() -> ! {
     std::process::exit(0);
 }
 
-fn calculate_state(gas_state: &mut Detail) {
-    let density = gas_state.density();
-    match density {
-        Ok(()) => (),
-        Err(_err) => println!("{}", "** Error calculating density.  Pressure or temperature out of bounds?? **".red().bold().italic()),
+// Calculates the properties at the pressure and temperature of gas_state.  Returns the
+// density error and the extended range limits it breaks, if the density could not be
+// solved, for the caller to show.
+fn calculate_state(gas_state: &mut Detail) -> Vec<String> {
+    let mut errors = Vec::new();
+    if let Err(err) = gas_state.density() {
+        errors.push(format!("** Error calculating density: {} **", validation::density_error_message(&err)));
+        let violations = validation::check_state(&components::array_to_comp(&gas_state.x), gas_state.p, gas_state.t);
+        errors.extend(violations.iter().filter(|violation| violation.range == validation::Range::Extended)
+            .map(validation::violation_text));
     }
     gas_state.properties();
+    errors
+}
+
+fn print_state_errors(errors: &[String]) {
+    for error in errors {
+        outln!("{}", error.red().bold());
+    }
+}
+
+// Replaces the current gas.  gas_state is updated by the next update_gas_state, which
+// skips the composition setup when the new gas is the same as the old one.
+fn change_gas_comp(program_state: &mut ProgramState, gas_comp: Composition) {
+    if components::comp_to_array(&gas_comp) != components::comp_to_array(&program_state.gas_comp) {
+        program_state.dirty.composition = true;
+    }
+    program_state.gas_comp = gas_comp;
+}
+
+// Sets the pressure and temperature of the current state for the next update_gas_state
+fn change_conditions(program_state: &mut ProgramState, p: Pressure, t: Temperature) {
+    let (p, t) = (p.kpa(), t.kelvin());
+    if p != program_state.gas_state.p || t != program_state.gas_state.t {
+        program_state.gas_state.p = p;
+        program_state.gas_state.t = t;
+        program_state.dirty.conditions = true;
+    }
+}
+
+// Recalculates the current state after changes to the gas or the conditions.  Nothing is
+// recomputed when neither changed.  Returns the errors of calculate_state.
+fn update_gas_state(program_state: &mut ProgramState) -> Vec<String> {
+    let dirty = std::mem::take(&mut program_state.dirty);
+    if dirty.composition {
+        program_state.gas_state.set_composition(&program_state.gas_comp).unwrap();
+    }
+    if dirty.composition || dirty.conditions {
+        let errors = calculate_state(&mut program_state.gas_state);
+        update_chemical_potentials(program_state);
+        return errors;
+    }
+    Vec::new()
+}
+
+// Makes an already calculated state, such as the end of a process, the current state
+fn set_gas_state(program_state: &mut ProgramState, state: Detail) {
+    program_state.gas_state = state;
+    update_chemical_potentials(program_state);
+}
+
+fn update_chemical_potentials(program_state: &mut ProgramState) {
+    let state = &program_state.gas_state;
+    program_state.chemical_potentials = chemical_potentials(&program_state.gas_comp, state.p, state.t);
+}
+
+// Builds a fully calculated state for the given composition, pressure (kPa) and
+// temperature (K), with the errors of calculate_state.
+fn solve_gas_state(gas_comp: &Composition, p: f64, t: f64) -> (Detail, Vec<String>) {
+    let mut state = Detail::new();
+    state.set_composition(gas_comp).unwrap();
+    state.p = p;
+    state.t = t;
+    let errors = calculate_state(&mut state);
+    (state, errors)
+}
+
+// solve_gas_state for the line menus, which print the errors
+fn new_gas_state(gas_comp: &Composition, p: f64, t: f64) -> Detail {
+    let (state, errors) = solve_gas_state(gas_comp, p, t);
+    print_state_errors(&errors);
+    state
+}
+
+// Exits at the end of input, which ends a piped session.
+fn read_stdin_line() -> String {
+    output::read_line().unwrap_or_else(|| quit())
+}
+
+// Reads the next command, taking queued script lines before the keyboard.  Script lines
+// starting with '#' are narration and are printed, "!pause" waits for the Enter key.
+fn read_input() -> String {
+    while let Some(line) = script::next_line() {
+        if let Some(text) = line.strip_prefix('#') {
+            if !output::quiet() {
+                outln!("{}", text.strip_prefix(' ').unwrap_or(text).italic());
+            }
+        } else if line == "!pause" {
+            if !output::quiet() {
+                outln!("{}", "(press Enter to continue)".dimmed());
+                read_stdin_line();
+            }
+        } else {
+            if !output::quiet() {
+                outln!("> {}", line);
+            }
+            return line;
+        }
+    }
+    read_stdin_line()
+}
+
+fn read_f64(prompt: &str) -> f64 {
+    loop {
+        if !output::quiet() {
+            outln!("{}", prompt);
+        }
+        match read_input().parse::<f64>() {
+            Ok(num) => return num,
+            Err(_) => outln!("{}", "**Invalid number!**".bold().red()),
+        }
+    }
+}
+
+// Reads a value that may carry its own unit, e.g. "150 psig", until it parses
+fn read_quantity<T>(prompt: &str, parse: impl Fn(&str) -> Result<T, String>) -> T {
+    loop {
+        if !output::quiet() {
+            outln!("{}", prompt);
+        }
+        match parse(&read_input()) {
+            Ok(value) => return value,
+            Err(err) => outln!("{}", format!("**{}!**", err).bold().red()),
+        }
+    }
 }
 
+fn read_pressure(program_state: &ProgramState, label: &str) -> Pressure {
+    let unit = program_state.units.pressure;
+    read_quantity(&format!("Enter {} ({}):", label, unit.label()), |input| Pressure::parse(input, unit))
+}
 
-fn print_main_menu(program_state: &mut ProgramState) {
-    println!();
-    println!("{}", "Main Menu".blue());
-    println!("{}", "---------".blue());
-    println!("{}", "g - Select Gas Composition".green());
-    println!("{}", "p - Set Pressure".yellow());
-    println!("{}", "t - Set Temperature".red());
-    println!("---------");
-    println!("{}", "1 - Set as inlet condition".cyan());
-    println!("{}", "2 - Set as discharge condition".cyan());
-    println!("{}", "u - Change Units");
-    println!("{}", "c - Clear inlet and discharge condistions".red().bold());
-    println!("---------");
-    println!("q - Quit Program");
-    println!();
-
-    let mut input = String::new();
-    io::stdin().read_line(&mut input).expect("Unable to read input");
-    let input = input.trim();
+fn read_temperature(program_state: &ProgramState, label: &str) -> Temperature {
+    let unit = program_state.units.temp;
+    read_quantity(&format!("Enter {} ({}):", label, unit.label()), |input| Temperature::parse(input, unit))
+}
+
+// Shows the main menu and runs one command.  Commands print the state when they are done
+// and return here rather than calling the menu again, so a long session does not grow
+// the stack.
+fn main_menu(program_state: &mut ProgramState) {
+    if !output::quiet() {
+        outln!();
+        outln!("{}", "Main Menu".blue());
+        outln!("{}", "---------".blue());
+        outln!("{}", "g - Select Gas Composition".green());
+        outln!("{}", "p - Set Pressure".yellow());
+        outln!("{}", "t - Set Temperature".red());
+        outln!("---------");
+        outln!("{}", "1 - Set as inlet condition".cyan());
+        outln!("{}", "2 - Set as discharge condition".cyan());
+        outln!("u - Change Units");
+        outln!("{}", "f - Flow Measurement".magenta());
+        outln!("{}", "a - Analysis Tools".cyan());
+        outln!("{}", "e - Equipment".yellow());
+        outln!("{}", "x - Processes".green());
+        outln!("s - Settings");
+        outln!("{}", "l - Link discharge pressure to inlet".cyan());
+        outln!("{}", "c - Clear inlet and discharge condistions".red().bold());
+        outln!("z - Undo last change");
+        outln!("y - Redo");
+        #[cfg(feature = "tui")]
+        outln!("v - Full screen view");
+        #[cfg(feature = "reports")]
+        outln!("o - Render output template");
+        outln!("m - Stream tags (tag number, service, case)");
+        outln!("---------");
+        outln!("tutorial - Guided worked example");
+        outln!("run <file> - Run a script of menu commands");
+        #[cfg(feature = "reports")]
+        outln!("export [file] - Export a Markdown or HTML report");
+        outln!("q - Quit Program");
+        outln!();
+    }
+
+    let input = read_input();
+    let input = input.as_str();
     
     if input== "q" {
         quit();
@@ -129,232 +464,802 @@ fn print_main_menu(program_state: &mut ProgramState) {
         "1" => set_inlet(program_state),
         "2" => set_discharge(program_state),
         "c" => clear_inlet_discharge(program_state),
-        "q" => quit(),
-        _ => {
-            println!("{}", "**Invalid selection!**".bold().red());
-            print_main_menu(program_state);
+        "l" => link_discharge(program_state),
+        "z" => {
+            if history::undo(program_state) {
+                #[cfg(feature = "reports")]
+                calc_log::append(program_state);
+            } else {
+                outln!("{}", "* Nothing to undo".yellow());
+            }
+            print_gas_state(program_state);
+        },
+        #[cfg(feature = "reports")]
+        "o" => template::render_user_template(program_state),
+        "m" => tags::edit_tags(program_state),
+        #[cfg(feature = "tui")]
+        "v" => {
+            if !tui::run_tui(program_state) {
+                outln!("{}", "** Full screen mode needs an interactive terminal **".red().bold());
+            }
+            print_gas_state(program_state);
+        },
+        "y" => {
+            if history::redo(program_state) {
+                #[cfg(feature = "reports")]
+                calc_log::append(program_state);
+            } else {
+                outln!("{}", "* Nothing to redo".yellow());
+            }
+            print_gas_state(program_state);
+        },
+        "f" => flow_menu(program_state),
+        "a" => analysis_menu(program_state),
+        "e" => equipment_menu(program_state),
+        "x" => process_menu(program_state),
+        "s" => settings_menu(program_state),
+        "tutorial" => tutorial::start_tutorial(program_state),
+        #[cfg(feature = "reports")]
+        "export" => export::export_report(program_state, ""),
+        #[cfg(feature = "reports")]
+        _ if input.starts_with("export ") => export::export_report(program_state, input["export ".len()..].trim()),
+        _ if input.starts_with("run ") => {
+            if let Err(err) = script::run_file(input["run ".len()..].trim()) {
+                outln!("{}", format!("** {} **", err).red().bold());
+            }
+            print_gas_state(program_state);
         },
+        "q" => quit(),
+        _ => outln!("{}", "**Invalid selection!**".bold().red()),
+    }
+}
+
+fn flow_menu(program_state: &mut ProgramState) {
+    loop {
+        if !output::quiet() {
+            outln!();
+            outln!("{}", "Flow Measurement".magenta());
+            outln!("{}", "----------------".magenta());
+            outln!("1 - Meter proving correction");
+            outln!("2 - Energy imbalance reconciliation (CSV)");
+            outln!("3 - Sonic nozzle choked flow");
+            outln!("4 - Orifice meter flow (AGA-3 / ISO 5167)");
+            outln!("5 - DP transmitter range advisor");
+            outln!("6 - Energy flow rate from standard volume flow");
+            outln!("b - Back");
+        }
+
+        match read_input().as_str() {
+            "1" => proving::meter_proving(program_state),
+            "2" => reconciliation::energy_reconciliation(program_state),
+            "3" => nozzle::sonic_nozzle(program_state),
+            "4" => orifice::orifice_meter(program_state),
+            "5" => orifice::dp_transmitter_ranges(program_state),
+            "6" => energy_flow::energy_flow_report(program_state),
+            "b" => print_gas_state(program_state),
+            _ => continue,
+        }
+        break;
+    }
+}
+
+fn analysis_menu(program_state: &mut ProgramState) {
+    loop {
+        if !output::quiet() {
+            outln!();
+            outln!("{}", "Analysis Tools".cyan());
+            outln!("{}", "--------------".cyan());
+            outln!("1 - Joule-Thomson inversion curve");
+            outln!("2 - Critical flow function C* table");
+            outln!("3 - Gas quality from measured speed of sound");
+            outln!("4 - Compare DETAIL, GERG-2008 and Gross");
+            outln!("5 - Pressure-temperature operating envelope");
+            outln!("6 - Gas quality tariff compliance (TOML)");
+            outln!("7 - CO2 transport / storage specification report");
+            outln!("8 - GC delta-composition impact (analyzer validation)");
+            outln!("9 - Mixture virial coefficients B and C");
+            outln!("10 - Dimensionless groups (Reynolds, Mach, Euler)");
+            outln!("11 - Combustion air and flue gas");
+            outln!("12 - Hydrogen blending sweep");
+            outln!("13 - Hydrate formation temperature");
+            outln!("14 - Water dew point from water content");
+            outln!("15 - Hydrocarbon dew point and cricondentherm");
+            outln!("16 - Phase envelope (Peng-Robinson)");
+            outln!("17 - Peng-Robinson properties (cubic EOS estimate)");
+            outln!("18 - Pseudo-critical properties and reduced conditions");
+            outln!("b - Back");
+        }
+
+        match read_input().as_str() {
+            "1" => inversion::jt_inversion_curve(program_state),
+            "2" => nozzle::critical_flow_table(program_state),
+            "3" => sos_inference::sound_speed_inference(program_state),
+            "4" => comparison::eos_comparison(program_state),
+            "5" => envelope::operating_envelope(program_state),
+            "6" => tariff::tariff_compliance(program_state),
+            "7" => co2_spec::co2_spec_report(program_state),
+            "8" => gc_delta::composition_delta_report(program_state),
+            "9" => virial::virial_report(program_state),
+            "10" => similarity::dimensionless_groups_report(program_state),
+            "11" => combustion::combustion_report(program_state),
+            "12" => hydrogen_blend::hydrogen_blending_sweep(program_state),
+            "13" => hydrate::hydrate_report(program_state),
+            "14" => water::water_dew_point_report(program_state),
+            "15" => peng_robinson::hydrocarbon_dew_point_report(program_state),
+            "16" => phase_envelope::phase_envelope_report(program_state),
+            "17" => peng_robinson::peng_robinson_report(program_state),
+            "18" => pseudo_critical::pseudo_critical_report(program_state),
+            "b" => print_gas_state(program_state),
+            _ => continue,
+        }
+        break;
+    }
+}
+
+fn equipment_menu(program_state: &mut ProgramState) {
+    loop {
+        if !output::quiet() {
+            outln!();
+            outln!("{}", "Equipment".yellow());
+            outln!("{}", "---------".yellow());
+            outln!("1 - Control valve aerodynamic noise");
+            outln!("2 - Fuel gas heater sizing");
+            outln!("3 - Vent / leak release source term export");
+            outln!("4 - Relief valve sizing (API 520)");
+            outln!("5 - Control valve Cv/Kv sizing (IEC 60534-2-1)");
+            outln!("6 - Heat exchanger duty");
+            outln!("7 - Vessel blowdown (depressurization)");
+            outln!("8 - Vessel filling (CNG fast fill)");
+            outln!("9 - TEG dehydration quick screen");
+            outln!("10 - Molecular sieve regeneration gas");
+            outln!("b - Back");
+        }
+
+        match read_input().as_str() {
+            "1" => control_valve::valve_noise(program_state),
+            "2" => heater::fuel_gas_heater(program_state),
+            "3" => release::release_export(program_state),
+            "4" => relief_valve::relief_valve_sizing(program_state),
+            "5" => control_valve::valve_sizing(program_state),
+            "6" => exchanger::exchanger_duty(program_state),
+            "7" => blowdown::vessel_blowdown(program_state),
+            "8" => blowdown::vessel_filling(program_state),
+            "9" => water::teg_screen(program_state),
+            "10" => water::mol_sieve_regeneration(program_state),
+            "b" => print_gas_state(program_state),
+            _ => continue,
+        }
+        break;
+    }
+}
+
+fn process_menu(program_state: &mut ProgramState) {
+    loop {
+        if !output::quiet() {
+            outln!();
+            outln!("{}", "Processes".green());
+            outln!("{}", "---------".green());
+            outln!("1 - Adiabatic mixing of streams");
+            outln!("2 - Isenthalpic throttling (JT valve)");
+            outln!("3 - Storage inventory simulation (CSV schedule)");
+            outln!("4 - Pipeline pressure drop / capacity");
+            outln!("5 - Turboexpander");
+            outln!("6 - Isochoric (constant volume) heating / cooling");
+            outln!("7 - Constant pressure heating / cooling duty");
+            outln!("8 - Gas inventory / line pack");
+            outln!("9 - Dehydration / humidification load");
+            outln!("10 - Compressor recycle loop");
+            outln!("11 - Pipeline elevation profile (CSV route)");
+            outln!("b - Back");
+        }
+
+        match read_input().as_str() {
+            "1" => blending::stream_mixing(program_state),
+            "2" => process::throttling(program_state),
+            "3" => storage::storage_simulation(program_state),
+            "4" => pipeline::pipeline_calculation(program_state),
+            "5" => process::turboexpander(program_state),
+            "6" => process::isochoric_process(program_state),
+            "7" => process::isobaric_process(program_state),
+            "8" => storage::gas_inventory(program_state),
+            "9" => water::dehydration_load(program_state),
+            "10" => recycle::recycle_loop(program_state),
+            "11" => pipeline::pipeline_elevation_profile(program_state),
+            "b" => print_gas_state(program_state),
+            _ => continue,
+        }
+        break;
+    }
+}
+
+fn settings_menu(program_state: &mut ProgramState) {
+    loop {
+        if !output::quiet() {
+            outln!();
+            outln!("Settings");
+            outln!("--------");
+            outln!("1 - Base conditions ({:.4} {}, {:.2} {})",
+                program_state.base_pressure.value(program_state.units.pressure), program_state.units.pressure.label(),
+                program_state.base_temperature.value(program_state.units.temp), program_state.units.temp.label());
+            outln!("2 - Composition normalization tolerance ({} mole fraction)", program_state.normalize_tolerance);
+            outln!("3 - Colored output ({})", if output::color() { "on" } else { "off" });
+            outln!("4 - Number format (precision, scientific notation, thousands separators)");
+            #[cfg(feature = "reports")]
+            outln!("5 - Calculation log ({})", program_state.log_path.as_deref().unwrap_or("off"));
+            outln!("6 - Trace component lumping rules ({} rules)", program_state.lumping.rules.len());
+            outln!("7 - Hydrate warning margin ({:.2} {})",
+                program_state.hydrate_margin.value(program_state.units.temp), program_state.units.temp.label());
+            outln!("8 - Wichert-Aziz sour gas correction of reduced conditions ({})", if program_state.wichert_aziz { "on" } else { "off" });
+            outln!("9 - Exergy dead state ({:.4} {}, {:.2} {})",
+                program_state.dead_state_pressure.value(program_state.units.pressure), program_state.units.pressure.label(),
+                program_state.dead_state_temperature.value(program_state.units.temp), program_state.units.temp.label());
+            outln!("b - Back");
+        }
+
+        match read_input().as_str() {
+            "1" => {
+                program_state.base_pressure = read_pressure(program_state, "base pressure");
+                program_state.base_temperature = read_temperature(program_state, "base temperature");
+                print_gas_state(program_state);
+            },
+            "2" => {
+                program_state.normalize_tolerance = read_f64("Enter sum deviation normalized without asking (mole fraction):").abs();
+                print_gas_state(program_state);
+            },
+            "3" => {
+                output::set_color(!output::color());
+                print_gas_state(program_state);
+            },
+            "4" => precision::precision_menu(program_state),
+            #[cfg(feature = "reports")]
+            "5" => calc_log::log_settings(program_state),
+            "6" => lumping::lumping_menu(program_state),
+            "7" => {
+                let unit = program_state.units.temp;
+                let margin = read_f64(&format!("Enter margin above the hydrate temperature ({}):", unit.label()));
+                program_state.hydrate_margin = TemperatureDifference::new(margin, unit);
+                print_gas_state(program_state);
+            },
+            "8" => {
+                program_state.wichert_aziz = !program_state.wichert_aziz;
+                print_gas_state(program_state);
+            },
+            "9" => {
+                program_state.dead_state_pressure = read_pressure(program_state, "dead state pressure");
+                program_state.dead_state_temperature = read_temperature(program_state, "dead state temperature");
+                print_gas_state(program_state);
+            },
+            "b" => print_gas_state(program_state),
+            _ => continue,
+        }
+        break;
     }
 }
 
 fn set_inlet(program_state: &mut ProgramState) {
+    print_state_errors(&store_inlet(program_state));
+    print_gas_state(program_state);
+}
+
+fn store_inlet(program_state: &mut ProgramState) -> Vec<String> {
     program_state.show_inlet_state = true;
-    program_state.inlet_state.set_composition(&program_state.gas_comp);
+    program_state.inlet_state.set_composition(&program_state.gas_comp).unwrap();
     program_state.inlet_state.p = program_state.gas_state.p;
-    println!("{}", program_state.inlet_state.p);
     program_state.inlet_state.t = program_state.gas_state.t;
-    println!("{}", program_state.inlet_state.t);
-    calculate_state(&mut program_state.inlet_state);
-    print_gas_state(program_state);
+    let mut errors = calculate_state(&mut program_state.inlet_state);
+    program_state.tags.inlet = program_state.tags.current.clone();
+    errors.extend(update_linked_discharge(program_state));
+    errors
 }
 
 fn set_discharge(program_state: &mut ProgramState) {
+    print_state_errors(&store_discharge(program_state));
+    print_gas_state(program_state);
+}
+
+fn store_discharge(program_state: &mut ProgramState) -> Vec<String> {
+    program_state.discharge_link = None;
     program_state.show_discharge_state = true;
-    program_state.discharge_state = Detail::default();
-    program_state.discharge_state.set_composition(&program_state.gas_comp);
+    program_state.discharge_state = Detail::new();
+    program_state.discharge_state.set_composition(&program_state.gas_comp).unwrap();
     program_state.discharge_state.p = program_state.gas_state.p;
     program_state.discharge_state.t = program_state.gas_state.t;
-    calculate_state(&mut program_state.discharge_state);
-    print_gas_state(program_state);
+    let errors = calculate_state(&mut program_state.discharge_state);
+    program_state.tags.discharge = program_state.tags.current.clone();
+    errors
 }
 
 fn clear_inlet_discharge(program_state: &mut ProgramState) {
-    program_state.inlet_state = Detail::default();
+    clear_stored_states(program_state);
+    print_gas_state(program_state);
+}
+
+fn clear_stored_states(program_state: &mut ProgramState) {
+    program_state.inlet_state = Detail::new();
     program_state.show_inlet_state = false;
-    program_state.discharge_state = Detail::default();
+    program_state.discharge_state = Detail::new();
     program_state.show_discharge_state = false;
+    program_state.discharge_link = None;
+    program_state.tags.inlet = tags::StreamTag::default();
+    program_state.tags.discharge = tags::StreamTag::default();
+}
+
+// Recomputes the discharge from the inlet pressure at the last discharge temperature,
+// or the current temperature when no discharge has been set.
+fn update_linked_discharge(program_state: &mut ProgramState) -> Vec<String> {
+    let (Some(link), true) = (program_state.discharge_link, program_state.show_inlet_state) else {
+        return Vec::new();
+    };
+    let p = match link {
+        DischargeLink::Ratio(ratio) => program_state.inlet_state.p * ratio,
+        DischargeLink::Delta(delta) => program_state.inlet_state.p + delta.kpa(),
+    };
+    let t = if program_state.show_discharge_state { program_state.discharge_state.t } else { program_state.gas_state.t };
+    program_state.show_discharge_state = true;
+    program_state.discharge_state = Detail::new();
+    program_state.discharge_state.set_composition(&program_state.gas_comp).unwrap();
+    program_state.discharge_state.p = p;
+    program_state.discharge_state.t = t;
+    calculate_state(&mut program_state.discharge_state)
+}
+
+fn link_discharge(program_state: &mut ProgramState) {
+    loop {
+        if !output::quiet() {
+            outln!();
+            outln!("Discharge pressure follows every new inlet condition (discharge temperature is kept).");
+            outln!("1 - Pressure ratio over the inlet");
+            outln!("2 - Pressure rise over the inlet");
+            outln!("3 - Remove the link");
+            outln!("b - Back");
+        }
+        match read_input().as_str() {
+            "1" => program_state.discharge_link = Some(DischargeLink::Ratio(read_f64("Enter pressure ratio (discharge / inlet):"))),
+            "2" => {
+                let delta = read_f64(&format!("Enter pressure rise ({}):", program_state.units.pressure.label()));
+                // Differences carry no offset, so scale by the unit conversion alone
+                program_state.discharge_link = Some(DischargeLink::Delta(Pressure::new(delta, program_state.units.pressure)));
+            },
+            "3" => program_state.discharge_link = None,
+            "b" => (),
+            _ => continue,
+        }
+        break;
+    }
+    print_state_errors(&update_linked_discharge(program_state));
     print_gas_state(program_state);
 }
 
 fn set_gas_comp(program_state: &mut ProgramState) {
-    println!();
-    println!("Select Gas:");
-    println!("1 - Air");
-    println!("2 - Argon");
-    println!("3 - Nitrogen");
-    println!("4 - Oxygen");
-
-    let mut choice = String::new();
-    io::stdin().read_line(&mut choice).unwrap();
-    let choice = choice.trim();
-
-    let mut new_gas_comp = Composition::default();
-    match choice {
-        "1" => {
-            program_state.gas = "Air".to_string();
-            new_gas_comp = get_gas_comp(GasComp::Air);
-        },
-        "2" => {
-            program_state.gas = "Argon".to_string();
-            new_gas_comp = get_gas_comp(GasComp::Argon);
-        },
-        "3" => {
-            program_state.gas = "Nitrogen".to_string();
-            new_gas_comp = get_gas_comp(GasComp::Nitrogen);
-        },
-        "4" => {
-            program_state.gas = "Oxygen".to_string();
-            new_gas_comp = get_gas_comp(GasComp::Oxygen);
-        },
-        _ => set_gas_comp(program_state),
+    let new_gas_comp;
+    loop {
+        if !output::quiet() {
+            outln!();
+            outln!("Select Gas:");
+            outln!("1 - Air");
+            outln!("2 - Argon");
+            outln!("3 - Nitrogen");
+            outln!("4 - Oxygen");
+            outln!("5 - Gross characterization (HV, relative density, CO2, H2)");
+            outln!("6 - Custom composition");
+            outln!("7 - Natural Gas (typical pipeline)");
+            outln!("8 - Methane");
+            outln!("9 - Hydrogen");
+            outln!("10 - Blend of gases");
+            outln!("11 - Mean of duplicate GC analyses (CSV)");
+            outln!("12 - Import GC report (CSV)");
+            outln!("13 - Humid air (relative humidity or dew point)");
+            outln!("b - Back");
+        }
+
+        match read_input().as_str() {
+            "1" => {
+                program_state.gas = "Air".to_string();
+                new_gas_comp = get_gas_comp(GasComp::Air);
+            },
+            "2" => {
+                program_state.gas = "Argon".to_string();
+                new_gas_comp = get_gas_comp(GasComp::Argon);
+            },
+            "3" => {
+                program_state.gas = "Nitrogen".to_string();
+                new_gas_comp = get_gas_comp(GasComp::Nitrogen);
+            },
+            "4" => {
+                program_state.gas = "Oxygen".to_string();
+                new_gas_comp = get_gas_comp(GasComp::Oxygen);
+            },
+            "5" => {
+                let inputs = gross::GrossInputs {
+                    heating_value: read_f64("Enter gross heating value (MJ/m3 at base conditions):"),
+                    relative_density: read_f64("Enter relative density (air = 1):"),
+                    carbon_dioxide: read_f64("Enter CO2 mole fraction:"),
+                    hydrogen: read_f64("Enter H2 mole fraction:"),
+                };
+                match gross::gross_composition(&inputs, program_state.base_pressure, program_state.base_temperature) {
+                    Ok(gross_comp) => {
+                        gross::print_equivalent_composition(&gross_comp);
+                        program_state.gas = format!("Gross (HV {:.2}, RD {:.4})", inputs.heating_value, inputs.relative_density);
+                        new_gas_comp = gross_comp;
+                    },
+                    Err(err) => {
+                        outln!("{}", format!("** {} **", err).red().bold());
+                        continue;
+                    },
+                }
+            },
+            "6" => {
+                match custom_composition::enter_custom_composition(program_state.normalize_tolerance) {
+                    Some(custom_comp) => {
+                        custom_composition::print_composition(&custom_comp);
+                        program_state.gas = "Custom".to_string();
+                        new_gas_comp = custom_comp;
+                    },
+                    None => continue,
+                }
+            },
+            "7" => {
+                program_state.gas = "Natural Gas".to_string();
+                new_gas_comp = get_gas_comp(GasComp::NaturalGas);
+            },
+            "8" => {
+                program_state.gas = "Methane".to_string();
+                new_gas_comp = get_gas_comp(GasComp::Methane);
+            },
+            "9" => {
+                program_state.gas = "Hydrogen".to_string();
+                new_gas_comp = get_gas_comp(GasComp::Hydrogen);
+            },
+            "10" => {
+                match blending::blend_gases(program_state) {
+                    Some((name, blend_comp)) => {
+                        custom_composition::print_composition(&blend_comp);
+                        program_state.gas = name;
+                        new_gas_comp = blend_comp;
+                    },
+                    None => continue,
+                }
+            },
+            "11" => {
+                match gc_average::average_gc_analyses(program_state.normalize_tolerance) {
+                    Some((name, mean_comp)) => {
+                        custom_composition::print_composition(&mean_comp);
+                        program_state.gas = name;
+                        new_gas_comp = mean_comp;
+                    },
+                    None => continue,
+                }
+            },
+            "12" => {
+                match gc_report::import_gc_report(program_state.normalize_tolerance, &program_state.lumping) {
+                    Some((name, report_comp)) => {
+                        custom_composition::print_composition(&report_comp);
+                        program_state.gas = name;
+                        new_gas_comp = report_comp;
+                    },
+                    None => continue,
+                }
+            },
+            "13" => {
+                match water::humid_air(program_state, &get_gas_comp(GasComp::Air)) {
+                    Some((name, humid_comp)) => {
+                        custom_composition::print_composition(&humid_comp);
+                        program_state.gas = name;
+                        new_gas_comp = humid_comp;
+                    },
+                    None => continue,
+                }
+            },
+            "b" => {
+                print_gas_state(program_state);
+                return;
+            },
+            _ => continue,
+        }
+        break;
+    }
+    change_gas_comp(program_state, new_gas_comp);
+    if program_state.show_inlet_state || program_state.show_discharge_state {
+        outln!("{}", "* Inlet and discharge conditions cleared for the new gas (z to undo)".yellow());
     }
-    program_state.gas_state.set_composition(&new_gas_comp).unwrap();
-    program_state.gas_comp = new_gas_comp;
     program_state.show_inlet_state = false;
     program_state.show_discharge_state = false;
-    calculate_state(&mut program_state.gas_state);
+    print_state_errors(&update_gas_state(program_state));
     print_gas_state(program_state);
-
 }
 
 
 fn set_pressure(program_state: &mut ProgramState) {
-    println!();
-    println!("Enter pressure ({}):", program_state.unit_text.pressure);
-    let mut input = String::new();
-    io::stdin().read_line(&mut input).unwrap();
-
-    let input = input.trim().parse::<f64>();
-    let p = match input {
-        Ok(num) => num,
-        Err(_) => {
-            set_pressure(program_state);
-            0.0
-        }
-    };
-    match program_state.units.pressure {
-        UnitPressure::kPa => program_state.gas_state.p = p,
-        UnitPressure::Bar => program_state.gas_state.p = p / 0.01,
-        UnitPressure::PSI => program_state.gas_state.p = p / 0.145038,
+    if !output::quiet() {
+        outln!();
     }
-    calculate_state(&mut program_state.gas_state);
+    let p = read_pressure(program_state, "pressure");
+    let t = Temperature::from_kelvin(program_state.gas_state.t);
+    change_conditions(program_state, p, t);
+    print_state_errors(&update_gas_state(program_state));
     print_gas_state(program_state);
 }
 
 
 fn set_temperature(program_state: &mut ProgramState) {
-    println!();
-    println!("Enter temperature ({}):", program_state.unit_text.temperature);
-    let mut input = String::new();
-    io::stdin().read_line(&mut input).unwrap();
-
-    let input = input.trim().parse::<f64>();
-    let t = match input {
-        Ok(num) => num,
-        Err(_) => {
-            set_temperature(program_state);
-            0.0
-        }
-    };
-
-    match program_state.units.temp {
-        UnitTemp::K => program_state.gas_state.t = t,
-        UnitTemp::C => program_state.gas_state.t = t + 273.15,
-        UnitTemp::F => program_state.gas_state.t = (t - 32.0) * 5.0 / 9.0 + 273.15,
-        UnitTemp::R => program_state.gas_state.t = t * 5.0 / 9.0,
+    if !output::quiet() {
+        outln!();
     }
-
-    calculate_state(&mut program_state.gas_state);
+    let t = read_temperature(program_state, "temperature");
+    let p = Pressure::from_kpa(program_state.gas_state.p);
+    change_conditions(program_state, p, t);
+    print_state_errors(&update_gas_state(program_state));
     print_gas_state(program_state);
 }
 
-fn get_pressure(pressure: f64, unit: UnitPressure) -> f64 {
-    match unit {
-        UnitPressure::kPa => pressure,
-        UnitPressure::Bar => pressure * 0.01,
-        UnitPressure::PSI => pressure * 0.145038,
-    }
+fn get_density(state: &Detail, unit: UnitDensity) -> f64 {
+    density_in_units(state.d, state.mm, unit)
 }
 
-fn get_temperature(temperature: f64, unit: UnitTemp) -> f64 {
-    match unit {
-        UnitTemp::K => temperature,
-        UnitTemp::C => temperature - 273.15,
-        UnitTemp::F => (temperature - 273.15) * 9.0 / 5.0 + 32.0,
-        UnitTemp::R => temperature * 9.0 / 5.0,
-    }
+fn get_internal_energy(program_state: &ProgramState) -> f64 {
+    internal_energy_in_units(&program_state.gas_state, program_state.units.internal_energy)
+}
+
+// Isothermal speed of sound sqrt((dp/drho)_T) in m/s; dp_dd in kPa/(mol/l) is J/mol
+fn isothermal_sound_speed(state: &Detail) -> f64 {
+    (state.dp_dd / state.mm * 1000.0).sqrt()
 }
 
-fn get_internal_energy(program_state: &mut ProgramState) -> f64 {
-    let internal_energy = program_state.gas_state.u;
-    match program_state.units.internal_energy {
-        UnitInternalEnergy::J_mol => internal_energy,
-        UnitInternalEnergy::kJ_kg => internal_energy / program_state.gas_state.mm,
-        UnitInternalEnergy::BTU_lbm => internal_energy / program_state.gas_state.mm * 0.429923,
+// Isothermal compressibility -(1/V)(dV/dP)_T = 1/(rho (dp/drho)_T) in 1/kPa
+fn isothermal_compressibility(state: &Detail) -> f64 {
+    1.0 / (state.d * state.dp_dd)
+}
+
+// Isentropic compressibility 1/(rho w^2) in 1/kPa, with the mass density in kg/m3
+fn isentropic_compressibility(state: &Detail) -> f64 {
+    1000.0 / (state.d * state.mm * state.w * state.w)
+}
+
+// Characteristic acoustic impedance rho w in kg/(m2-s)
+fn acoustic_impedance(state: &Detail) -> f64 {
+    state.d * state.mm * state.w
+}
+
+// Helmholtz energy a = u - Ts in J/mol
+fn helmholtz_energy(state: &Detail) -> f64 {
+    state.u - state.t * state.s
+}
+
+// Flow exergy (J/mol) of a state relative to the dead state in the settings
+fn exergy(program_state: &ProgramState, state: &Detail) -> Option<f64> {
+    flow_exergy(&program_state.gas_comp, state, program_state.dead_state_pressure.kpa(), program_state.dead_state_temperature.kelvin())
+}
+
+// Chemical potentials in the energy unit, per mole or per unit mass of each component
+fn write_chemical_potentials(out: &mut String, program_state: &ProgramState) {
+    let unit = program_state.units.internal_energy;
+    if let Some(potentials) = &program_state.chemical_potentials {
+        for &(i, mu) in potentials {
+            let mu = energy_in_units(mu, components::component_molar_mass(i), unit);
+            writeln!(out, "{:<30} {:>10} {:10}", format!("Chem. Pot. {}: ", components::COMPONENT_LABELS[i]),
+                program_state.precision.format(Quantity::Energy, mu), unit.label()).unwrap();
+        }
     }
 }
 
-fn print_gas_state(program_state: &mut ProgramState) {
-    println!();
+// Volume expansivity (1/V)(dV/dT)_P = (dp/dT)_rho / (rho (dp/drho)_T) in 1/K
+fn volume_expansivity(state: &Detail) -> f64 {
+    state.dp_dt / (state.d * state.dp_dd)
+}
+
+
+
+fn format_gas_state(program_state: &ProgramState) -> String {
+    format_state(program_state) + &format_comparison(program_state)
+}
+
+// Current state, the inlet and discharge conditions and the range warnings
+fn format_state(program_state: &ProgramState) -> String {
+    let mut out = String::new();
+    let number = |quantity, value| program_state.precision.format(quantity, value);
+    let ideal_cp = ideal_gas_state(&program_state.gas_comp, program_state.gas_state.t).map_or(f64::NAN, |ideal| ideal.cp);
+    let units = program_state.units;
+    let entropy = |value| entropy_in_units(value, units.temp);
+    let entropy_unit = entropy_label(units.temp);
+    let energy = |value| energy_in_units(value, program_state.gas_state.mm, units.internal_energy);
+    let energy_unit = units.internal_energy.label();
+    let jt_unit = jt_label(units.temp, units.pressure);
+    let pseudo_critical = pseudo_critical::pseudo_critical(program_state);
+    writeln!(out).unwrap();
     if program_state.show_inlet_state || program_state.show_discharge_state {
-        println!("{:<32} {:21} {:23} {:10}", "Gas: ", program_state.gas, "Inlet", "Discharge");
-        println!("{:<30} {:10.4} {:10} {:10.4} {:10} {:10.4} {:10}", 
-            "Absolute Pressure: ", get_pressure(program_state.gas_state.p, program_state.units.pressure), program_state.unit_text.pressure,
-            get_pressure(program_state.inlet_state.p, program_state.units.pressure), program_state.unit_text.pressure,
-            get_pressure(program_state.discharge_state.p, program_state.units.pressure), program_state.unit_text.pressure);
-        println!("{:<30} {:10.4} {:10} {:10.4} {:10} {:10.4} {:10}",
-            "Absolute Temperature: ", get_temperature(program_state.gas_state.t, program_state.units.temp), program_state.unit_text.temperature,
-            get_temperature(program_state.inlet_state.t, program_state.units.temp), program_state.unit_text.temperature,
-            get_temperature(program_state.discharge_state.t, program_state.units.temp), program_state.unit_text.temperature);
-        println!("{:<30} {:10.4} {:10}", "Density: ", program_state.gas_state.d, "mol/l");
-        println!("{:<30} {:10.4} {:10}", "Molar Mass ", program_state.gas_state.mm, "g/mol");
-        println!("{:<30} {:10.4} {:10}", "Internal Energy u: ", get_internal_energy(program_state), program_state.unit_text.internal_energy);
-        println!("{:<30} {:10.4} {:10}", "Enthalpy: ", program_state.gas_state.h, "J/mol");
-        println!("{:<30} {:10.4} {:10}", "Entropy: ", program_state.gas_state.s, format!("J/(mol-{})", program_state.unit_text.temperature));
-        println!("{:<30} {:10.4} {:10}", "Cp: ", program_state.gas_state.cp, format!("J/(mol-{})", program_state.unit_text.temperature));
-        println!("{:<30} {:10.4} {:10}", "Cv: ", program_state.gas_state.cv, format!("J/(mol-{})", program_state.unit_text.temperature));
-        println!("{:<30} {:10.4} {:10}", "Cp/Cv: ", program_state.gas_state.cp / program_state.gas_state.cv, "[]");
-        println!("{:<30} {:10.4} {:10}", "Compressibility Z: ", program_state.gas_state.z, "[]");
-        println!("{:<30} {:10.4} {:10}", "Isentropic Exponent k: ", program_state.gas_state.kappa, "[]");
-        println!("{:<30} {:10.4} {:10}", "Speed of Sound w: ", program_state.gas_state.w, "m/s");
-        println!("{:<30} {:10.4} {:10}", "Gibbs Energy: ", program_state.gas_state.g, "J/mol");
-        println!("{:<30} {:10.4} {:10}", "Joule-Thompson Coefficient: ", program_state.gas_state.jt, format!("{}/kPa", program_state.unit_text.temperature));
-        println!();
+        writeln!(out, "{:<32} {:21} {:23} {:10}", "Gas: ", program_state.gas, "Inlet", "Discharge").unwrap();
+        if !program_state.tags.is_empty() {
+            writeln!(out, "{:<32} {:21} {:23} {:10}", "Tag: ", program_state.tags.current.tag,
+                program_state.tags.inlet.tag, program_state.tags.discharge.tag).unwrap();
+        }
+        let pressure = |state: &Detail| number(Quantity::Pressure, get_pressure(state.p, program_state.units.pressure));
+        let temperature = |state: &Detail| number(Quantity::Temperature, get_temperature(state.t, program_state.units.temp));
+        writeln!(out, "{:<30} {:>10} {:10} {:>10} {:10} {:>10} {:10}", 
+            "Absolute Pressure: ", pressure(&program_state.gas_state), program_state.units.pressure.label(),
+            pressure(&program_state.inlet_state), program_state.units.pressure.label(),
+            pressure(&program_state.discharge_state), program_state.units.pressure.label()).unwrap();
+        writeln!(out, "{:<30} {:>10} {:10} {:>10} {:10} {:>10} {:10}",
+            "Absolute Temperature: ", temperature(&program_state.gas_state), program_state.units.temp.label(),
+            temperature(&program_state.inlet_state), program_state.units.temp.label(),
+            temperature(&program_state.discharge_state), program_state.units.temp.label()).unwrap();
+        writeln!(out, "{:<30} {:>10} {:10}", "Density: ", number(Quantity::Density, get_density(&program_state.gas_state, program_state.units.density)), program_state.units.density.label()).unwrap();
+        writeln!(out, "{:<30} {:>10} {:10}", "Molar Mass ", number(Quantity::MolarMass, program_state.gas_state.mm), "g/mol").unwrap();
+        writeln!(out, "{:<30} {:>10} {:10}", "Internal Energy u: ", number(Quantity::Energy, get_internal_energy(program_state)), program_state.units.internal_energy.label()).unwrap();
+        writeln!(out, "{:<30} {:>10} {:10}", "Enthalpy: ", number(Quantity::Energy, energy(program_state.gas_state.h)), energy_unit).unwrap();
+        writeln!(out, "{:<30} {:>10} {:10}", "Entropy: ", number(Quantity::Entropy, entropy(program_state.gas_state.s)), &entropy_unit).unwrap();
+        writeln!(out, "{:<30} {:>10} {:10}", "Cp: ", number(Quantity::Entropy, entropy(program_state.gas_state.cp)), &entropy_unit).unwrap();
+        writeln!(out, "{:<30} {:>10} {:10}", "Ideal Gas Cp°: ", number(Quantity::Entropy, entropy(ideal_cp)), &entropy_unit).unwrap();
+        writeln!(out, "{:<30} {:>10} {:10}", "Real Gas Correction Cp-Cp°: ", number(Quantity::Entropy, entropy(program_state.gas_state.cp - ideal_cp)), &entropy_unit).unwrap();
+        writeln!(out, "{:<30} {:>10} {:10}", "Cv: ", number(Quantity::Entropy, entropy(program_state.gas_state.cv)), &entropy_unit).unwrap();
+        writeln!(out, "{:<30} {:>10} {:10}", "Cp/Cv: ", number(Quantity::Dimensionless, program_state.gas_state.cp / program_state.gas_state.cv), "[]").unwrap();
+        writeln!(out, "{:<30} {:>10} {:10}", "Compressibility Z: ", number(Quantity::Dimensionless, program_state.gas_state.z), "[]").unwrap();
+        writeln!(out, "{:<30} {:>10} {:10}", "Reduced Pressure Pr: ", number(Quantity::Dimensionless, program_state.gas_state.p / pseudo_critical.p), "[]").unwrap();
+        writeln!(out, "{:<30} {:>10} {:10}", "Reduced Temperature Tr: ", number(Quantity::Dimensionless, program_state.gas_state.t / pseudo_critical.t), "[]").unwrap();
+        writeln!(out, "{:<30} {:>10} {:10}", "Isentropic Exponent k: ", number(Quantity::Dimensionless, program_state.gas_state.kappa), "[]").unwrap();
+        writeln!(out, "{:<30} {:>10} {:10}", "Speed of Sound w: ", number(Quantity::SpeedOfSound, program_state.gas_state.w), "m/s").unwrap();
+        writeln!(out, "{:<30} {:>10} {:10}", "Isothermal Speed of Sound: ", number(Quantity::SpeedOfSound, isothermal_sound_speed(&program_state.gas_state)), "m/s").unwrap();
+        writeln!(out, "{:<30} {:>10} {:10}", "Sound Speed Ratio w/wT: ", number(Quantity::Dimensionless, program_state.gas_state.w / isothermal_sound_speed(&program_state.gas_state)), "[]").unwrap();
+        writeln!(out, "{:<30} {:>10} {:10}", "Isentropic Compress. κs: ", number(Quantity::Coefficient,
+            compressibility_in_units(isentropic_compressibility(&program_state.gas_state), units.pressure)), format!("1/{}", units.pressure.label())).unwrap();
+        writeln!(out, "{:<30} {:>10} {:10}", "Acoustic Impedance ρw: ", number(Quantity::AcousticImpedance, acoustic_impedance(&program_state.gas_state)), "kg/(m2-s)").unwrap();
+        writeln!(out, "{:<30} {:>10} {:10}", "Gibbs Energy: ", number(Quantity::Energy, energy(program_state.gas_state.g)), energy_unit).unwrap();
+        writeln!(out, "{:<30} {:>10} {:10}", "Helmholtz Energy: ", number(Quantity::Energy, energy(helmholtz_energy(&program_state.gas_state))), energy_unit).unwrap();
+        if let Some(ex) = exergy(program_state, &program_state.gas_state) {
+            writeln!(out, "{:<30} {:>10} {:10}", "Flow Exergy: ", number(Quantity::Energy, energy(ex)), energy_unit).unwrap();
+        }
+        writeln!(out, "{:<30} {:>10} {:10}", "Joule-Thompson Coefficient: ", number(Quantity::JouleThomson, jt_in_units(program_state.gas_state.jt, units.temp, units.pressure)), &jt_unit).unwrap();
+        writeln!(out, "{:<30} {:>10} {:10}", "dP/dT (Constant Density): ", number(Quantity::Derivative,
+            dp_dt_in_units(program_state.gas_state.dp_dt, units.pressure, units.temp)), dp_dt_label(units.pressure, units.temp)).unwrap();
+        writeln!(out, "{:<30} {:>10} {:10}", "dP/dρ (Constant T): ", number(Quantity::Derivative,
+            dp_dd_in_units(program_state.gas_state.dp_dd, program_state.gas_state.mm, units.pressure, units.density)), dp_dd_label(units.pressure, units.density)).unwrap();
+        writeln!(out, "{:<30} {:>10} {:10}", "d²P/dρ² (Constant T): ", number(Quantity::Derivative,
+            d2p_dd2_in_units(program_state.gas_state.d2p_dd2, program_state.gas_state.mm, units.pressure, units.density)), d2p_dd2_label(units.pressure, units.density)).unwrap();
+        writeln!(out, "{:<30} {:>10} {:10}", "Isothermal Compress. κT: ", number(Quantity::Coefficient,
+            compressibility_in_units(isothermal_compressibility(&program_state.gas_state), units.pressure)), format!("1/{}", units.pressure.label())).unwrap();
+        writeln!(out, "{:<30} {:>10} {:10}", "Volume Expansivity β: ", number(Quantity::Coefficient,
+            expansivity_in_units(volume_expansivity(&program_state.gas_state), units.temp)), format!("1/{}", units.temp.label())).unwrap();
+        if let Some(residual) = residual_properties(&program_state.gas_comp, &program_state.gas_state) {
+            writeln!(out, "{:<30} {:>10} {:10}", "Residual Enthalpy: ", number(Quantity::Energy, energy(residual.h)), energy_unit).unwrap();
+            writeln!(out, "{:<30} {:>10} {:10}", "Residual Entropy: ", number(Quantity::Entropy, entropy(residual.s)), &entropy_unit).unwrap();
+            writeln!(out, "{:<30} {:>10} {:10}", "Residual Internal Energy: ", number(Quantity::Energy, energy(residual.u)), energy_unit).unwrap();
+        }
+        write_chemical_potentials(&mut out, program_state);
+        writeln!(out).unwrap();
     } else {
-        println!("{}", "Current State".italic().bold());
-        println!("{:<32} {:20}", "Gas: ", program_state.gas);
-        println!("{:<30} {:10.4} {:10}", "Absolute Pressure: ", get_pressure(program_state.gas_state.p, program_state.units.pressure), program_state.unit_text.pressure);
-        println!("{:<30} {:10.4} {:10}", "Absolute Temperature: ", get_temperature(program_state.gas_state.t, program_state.units.temp), program_state.unit_text.temperature);
-        println!("{:<30} {:10.4} {:10}", "Density: ", program_state.gas_state.d, "mol/l");
-        println!("{:<30} {:10.4} {:10}", "Molar Mass ", program_state.gas_state.mm, "g/mol");
-        println!("{:<30} {:10.4} {:10}", "Internal Energy u: ", get_internal_energy(program_state), program_state.unit_text.internal_energy);
-        println!("{:<30} {:10.4} {:10}", "Enthalpy: ", program_state.gas_state.h, "J/mol");
-        println!("{:<30} {:10.4} {:10}", "Entropy: ", program_state.gas_state.s, format!("J/(mol-{})", program_state.unit_text.temperature));
-        println!("{:<30} {:10.4} {:10}", "Cp: ", program_state.gas_state.cp, format!("J/(mol-{})", program_state.unit_text.temperature));
-        println!("{:<30} {:10.4} {:10}", "Cv: ", program_state.gas_state.cv, format!("J/(mol-{})", program_state.unit_text.temperature));
-        println!("{:<30} {:10.4} {:10}", "Compressibility Z: ", program_state.gas_state.z, "[]");
-        println!("{:<30} {:10.4} {:10}", "Isentropic Exponent k: ", program_state.gas_state.kappa, "[]");
-        println!("{:<30} {:10.4} {:10}", "Speed of Sound w: ", program_state.gas_state.w, "m/s");
-        println!("{:<30} {:10.4} {:10}", "Gibbs Energy: ", program_state.gas_state.g, "J/mol");
-        println!("{:<30} {:10.4} {:10}", "Joule-Thompson Coefficient: ", program_state.gas_state.jt, format!("{}/kPa", program_state.unit_text.temperature));
-        println!();
+        writeln!(out, "{}", "Current State".italic().bold()).unwrap();
+        writeln!(out, "{:<32} {:20}", "Gas: ", program_state.gas).unwrap();
+        if !program_state.tags.current.is_empty() {
+            writeln!(out, "{:<32} {:20}", "Tag: ", program_state.tags.current.label()).unwrap();
+        }
+        writeln!(out, "{:<30} {:>10} {:10}", "Absolute Pressure: ", number(Quantity::Pressure, get_pressure(program_state.gas_state.p, program_state.units.pressure)), program_state.units.pressure.label()).unwrap();
+        writeln!(out, "{:<30} {:>10} {:10}", "Absolute Temperature: ", number(Quantity::Temperature, get_temperature(program_state.gas_state.t, program_state.units.temp)), program_state.units.temp.label()).unwrap();
+        writeln!(out, "{:<30} {:>10} {:10}", "Density: ", number(Quantity::Density, get_density(&program_state.gas_state, program_state.units.density)), program_state.units.density.label()).unwrap();
+        writeln!(out, "{:<30} {:>10} {:10}", "Molar Mass ", number(Quantity::MolarMass, program_state.gas_state.mm), "g/mol").unwrap();
+        writeln!(out, "{:<30} {:>10} {:10}", "Internal Energy u: ", number(Quantity::Energy, get_internal_energy(program_state)), program_state.units.internal_energy.label()).unwrap();
+        writeln!(out, "{:<30} {:>10} {:10}", "Enthalpy: ", number(Quantity::Energy, energy(program_state.gas_state.h)), energy_unit).unwrap();
+        writeln!(out, "{:<30} {:>10} {:10}", "Entropy: ", number(Quantity::Entropy, entropy(program_state.gas_state.s)), &entropy_unit).unwrap();
+        writeln!(out, "{:<30} {:>10} {:10}", "Cp: ", number(Quantity::Entropy, entropy(program_state.gas_state.cp)), &entropy_unit).unwrap();
+        writeln!(out, "{:<30} {:>10} {:10}", "Ideal Gas Cp°: ", number(Quantity::Entropy, entropy(ideal_cp)), &entropy_unit).unwrap();
+        writeln!(out, "{:<30} {:>10} {:10}", "Real Gas Correction Cp-Cp°: ", number(Quantity::Entropy, entropy(program_state.gas_state.cp - ideal_cp)), &entropy_unit).unwrap();
+        writeln!(out, "{:<30} {:>10} {:10}", "Cv: ", number(Quantity::Entropy, entropy(program_state.gas_state.cv)), &entropy_unit).unwrap();
+        writeln!(out, "{:<30} {:>10} {:10}", "Compressibility Z: ", number(Quantity::Dimensionless, program_state.gas_state.z), "[]").unwrap();
+        writeln!(out, "{:<30} {:>10} {:10}", "Reduced Pressure Pr: ", number(Quantity::Dimensionless, program_state.gas_state.p / pseudo_critical.p), "[]").unwrap();
+        writeln!(out, "{:<30} {:>10} {:10}", "Reduced Temperature Tr: ", number(Quantity::Dimensionless, program_state.gas_state.t / pseudo_critical.t), "[]").unwrap();
+        writeln!(out, "{:<30} {:>10} {:10}", "Isentropic Exponent k: ", number(Quantity::Dimensionless, program_state.gas_state.kappa), "[]").unwrap();
+        writeln!(out, "{:<30} {:>10} {:10}", "Speed of Sound w: ", number(Quantity::SpeedOfSound, program_state.gas_state.w), "m/s").unwrap();
+        writeln!(out, "{:<30} {:>10} {:10}", "Isothermal Speed of Sound: ", number(Quantity::SpeedOfSound, isothermal_sound_speed(&program_state.gas_state)), "m/s").unwrap();
+        writeln!(out, "{:<30} {:>10} {:10}", "Sound Speed Ratio w/wT: ", number(Quantity::Dimensionless, program_state.gas_state.w / isothermal_sound_speed(&program_state.gas_state)), "[]").unwrap();
+        writeln!(out, "{:<30} {:>10} {:10}", "Isentropic Compress. κs: ", number(Quantity::Coefficient,
+            compressibility_in_units(isentropic_compressibility(&program_state.gas_state), units.pressure)), format!("1/{}", units.pressure.label())).unwrap();
+        writeln!(out, "{:<30} {:>10} {:10}", "Acoustic Impedance ρw: ", number(Quantity::AcousticImpedance, acoustic_impedance(&program_state.gas_state)), "kg/(m2-s)").unwrap();
+        writeln!(out, "{:<30} {:>10} {:10}", "Gibbs Energy: ", number(Quantity::Energy, energy(program_state.gas_state.g)), energy_unit).unwrap();
+        writeln!(out, "{:<30} {:>10} {:10}", "Helmholtz Energy: ", number(Quantity::Energy, energy(helmholtz_energy(&program_state.gas_state))), energy_unit).unwrap();
+        if let Some(ex) = exergy(program_state, &program_state.gas_state) {
+            writeln!(out, "{:<30} {:>10} {:10}", "Flow Exergy: ", number(Quantity::Energy, energy(ex)), energy_unit).unwrap();
+        }
+        writeln!(out, "{:<30} {:>10} {:10}", "Joule-Thompson Coefficient: ", number(Quantity::JouleThomson, jt_in_units(program_state.gas_state.jt, units.temp, units.pressure)), &jt_unit).unwrap();
+        writeln!(out, "{:<30} {:>10} {:10}", "dP/dT (Constant Density): ", number(Quantity::Derivative,
+            dp_dt_in_units(program_state.gas_state.dp_dt, units.pressure, units.temp)), dp_dt_label(units.pressure, units.temp)).unwrap();
+        writeln!(out, "{:<30} {:>10} {:10}", "dP/dρ (Constant T): ", number(Quantity::Derivative,
+            dp_dd_in_units(program_state.gas_state.dp_dd, program_state.gas_state.mm, units.pressure, units.density)), dp_dd_label(units.pressure, units.density)).unwrap();
+        writeln!(out, "{:<30} {:>10} {:10}", "d²P/dρ² (Constant T): ", number(Quantity::Derivative,
+            d2p_dd2_in_units(program_state.gas_state.d2p_dd2, program_state.gas_state.mm, units.pressure, units.density)), d2p_dd2_label(units.pressure, units.density)).unwrap();
+        writeln!(out, "{:<30} {:>10} {:10}", "Isothermal Compress. κT: ", number(Quantity::Coefficient,
+            compressibility_in_units(isothermal_compressibility(&program_state.gas_state), units.pressure)), format!("1/{}", units.pressure.label())).unwrap();
+        writeln!(out, "{:<30} {:>10} {:10}", "Volume Expansivity β: ", number(Quantity::Coefficient,
+            expansivity_in_units(volume_expansivity(&program_state.gas_state), units.temp)), format!("1/{}", units.temp.label())).unwrap();
+        if let Some(residual) = residual_properties(&program_state.gas_comp, &program_state.gas_state) {
+            writeln!(out, "{:<30} {:>10} {:10}", "Residual Enthalpy: ", number(Quantity::Energy, energy(residual.h)), energy_unit).unwrap();
+            writeln!(out, "{:<30} {:>10} {:10}", "Residual Entropy: ", number(Quantity::Entropy, entropy(residual.s)), &entropy_unit).unwrap();
+            writeln!(out, "{:<30} {:>10} {:10}", "Residual Internal Energy: ", number(Quantity::Energy, energy(residual.u)), energy_unit).unwrap();
+        }
+        write_chemical_potentials(&mut out, program_state);
+        writeln!(out).unwrap();
+    }
+
+    let violations = validation::check_state(&program_state.gas_comp, program_state.gas_state.p, program_state.gas_state.t);
+    let mut warnings = validation::method_warnings(&program_state.gas_comp);
+    warnings.extend(hydrate::hydrate_warning(program_state));
+    if !violations.is_empty() || !warnings.is_empty() {
+        out.push_str(&validation::format_violations(&violations));
+        out.push_str(&validation::format_method_warnings(&warnings));
+        writeln!(out).unwrap();
     }
 
+    out
+}
+
+// Changes from the inlet to the discharge condition, empty until both are set
+fn format_comparison(program_state: &ProgramState) -> String {
+    let mut out = String::new();
+    let number = |quantity, value| program_state.precision.format(quantity, value);
+    let units = program_state.units;
+    let entropy = |value| entropy_in_units(value, units.temp);
+    let entropy_unit = entropy_label(units.temp);
+    let energy = |value| energy_in_units(value, program_state.inlet_state.mm, units.internal_energy);
+    let energy_unit = units.internal_energy.label();
     if program_state.show_inlet_state && program_state.show_discharge_state {
         let pr = program_state.discharge_state.p / program_state.inlet_state.p;
-        let inlet_temp_K = program_state.inlet_state.t;
-        let discharge_temp_K = program_state.discharge_state.t;
-        let tr = discharge_temp_K / inlet_temp_K;
-        let td_K = discharge_temp_K - inlet_temp_K;
-        let inlet_temp = get_temperature(inlet_temp_K, program_state.units.temp);
-        let discharge_temp = get_temperature(discharge_temp_K, program_state.units.temp);
+        let inlet_temp_k = program_state.inlet_state.t;
+        let discharge_temp_k = program_state.discharge_state.t;
+        let tr = discharge_temp_k / inlet_temp_k;
+        let td_k = discharge_temp_k - inlet_temp_k;
+        let inlet_temp = get_temperature(inlet_temp_k, program_state.units.temp);
+        let discharge_temp = get_temperature(discharge_temp_k, program_state.units.temp);
         let td = discharge_temp - inlet_temp;
-        let ud = program_state.discharge_state.u - program_state.inlet_state.u;
+        let ud = energy_in_units(program_state.discharge_state.u - program_state.inlet_state.u, program_state.inlet_state.mm, program_state.units.internal_energy);
         let hd = program_state.discharge_state.h - program_state.inlet_state.h;
-        let sd = program_state.discharge_state.h - program_state.inlet_state.s;
-        let cpcv_ave = (program_state.inlet_state.kappa + program_state.discharge_state.kappa) / 2.0;
-        let isentropic_eff = (pr.powf((cpcv_ave - 1.0) / cpcv_ave) - 1.0) * inlet_temp_K / td_K;
-        println!("{:<30} {:10.4} {:10}", "Pressure Ratio: ", pr, "[]");
-        println!("{:<30} {:10.4} {:10}", "Temperature Ratio: ", tr, "[]");
-        println!("{:<30} {:10.4} {:10}", "Temperature Change: ", td, program_state.unit_text.temperature);
-        println!("{:<30} {:10.4} {:10}", "Internal Energy Change: ", ud, program_state.unit_text.internal_energy);
-        println!("{:<30} {:10.4} {:10}", "Enthalpy Change: ", hd, "TBD");
-        println!("{:<30} {:10.4} {:10}", "Entropy Change: ", sd, "TBD");
-        println!("{:<30} {:10.4} {:10}", "Average Cp/Cv: ", cpcv_ave, "[]");
-        println!("{:<30} {:10.4} {:10}", "Isentropic Efficiency: ", isentropic_eff, "[]");
+        let sd = program_state.discharge_state.s - program_state.inlet_state.s;
+        let cpcv_ave = (program_state.inlet_state.cp / program_state.inlet_state.cv
+            + program_state.discharge_state.cp / program_state.discharge_state.cv) / 2.0;
+        let k_ave = (program_state.inlet_state.kappa + program_state.discharge_state.kappa) / 2.0;
+        let z_ave = (program_state.inlet_state.z + program_state.discharge_state.z) / 2.0;
+        let isentropic_eff = (pr.powf((k_ave - 1.0) / k_ave) - 1.0) * inlet_temp_k / td_k;
+        match program_state.discharge_link {
+            Some(DischargeLink::Ratio(ratio)) => writeln!(out, "{:<30} {:>10} {:10}", "Discharge Linked: ", format!("x {}", ratio), "").unwrap(),
+            Some(DischargeLink::Delta(delta)) => writeln!(out, "{:<30} {:>10} {:10}", "Discharge Linked: ",
+                format!("+ {}", delta.value(program_state.units.pressure)), program_state.units.pressure.label()).unwrap(),
+            None => (),
+        }
+        writeln!(out, "{:<30} {:>10} {:10}", "Pressure Ratio: ", number(Quantity::Dimensionless, pr), "[]").unwrap();
+        writeln!(out, "{:<30} {:>10} {:10}", "Temperature Ratio: ", number(Quantity::Dimensionless, tr), "[]").unwrap();
+        writeln!(out, "{:<30} {:>10} {:10}", "Temperature Change: ", number(Quantity::Temperature, td), program_state.units.temp.label()).unwrap();
+        writeln!(out, "{:<30} {:>10} {:10}", "Internal Energy Change: ", number(Quantity::Energy, ud), program_state.units.internal_energy.label()).unwrap();
+        writeln!(out, "{:<30} {:>10} {:10}", "Enthalpy Change: ", number(Quantity::Energy, energy(hd)), energy_unit).unwrap();
+        writeln!(out, "{:<30} {:>10} {:10}", "Entropy Change: ", number(Quantity::Entropy, entropy(sd)), &entropy_unit).unwrap();
+        writeln!(out, "{:<30} {:>10} {:10}", "Average Cp/Cv: ", number(Quantity::Dimensionless, cpcv_ave), "[]").unwrap();
+        writeln!(out, "{:<30} {:>10} {:10}", "Average Isentropic Exp. k: ", number(Quantity::Dimensionless, k_ave), "[]").unwrap();
+        writeln!(out, "{:<30} {:>10} {:10}", "Average Compressibility Z: ", number(Quantity::Dimensionless, z_ave), "[]").unwrap();
+        writeln!(out, "{:<30} {:>10} {:10}", "Isentropic Efficiency: ", number(Quantity::Dimensionless, isentropic_eff), "[]").unwrap();
+        writeln!(out, "{:<30} {:>10} {:10}", "Specific Compression Work: ", number(Quantity::Energy, energy(hd)), energy_unit).unwrap();
+        if let (Some(ex_inlet), Some(ex_discharge)) = (exergy(program_state, &program_state.inlet_state), exergy(program_state, &program_state.discharge_state)) {
+            // Adiabatic machine: the destruction is T0 times the entropy generated, and the
+            // exergetic efficiency is the exergy recovered over the work (or the reverse
+            // for an expansion)
+            let exd = ex_discharge - ex_inlet;
+            let destruction = program_state.dead_state_temperature.kelvin() * sd;
+            let exergetic_eff = if hd > 0.0 { exd / hd } else { hd / exd };
+            writeln!(out, "{:<30} {:>10} {:10}", "Flow Exergy Change: ", number(Quantity::Energy, energy(exd)), energy_unit).unwrap();
+            writeln!(out, "{:<30} {:>10} {:10}", "Exergy Destruction: ", number(Quantity::Energy, energy(destruction)), energy_unit).unwrap();
+            writeln!(out, "{:<30} {:>10} {:10}", "Exergetic Efficiency: ", number(Quantity::Dimensionless, exergetic_eff), "[]").unwrap();
+        }
+    }
+
+
+    out
+}
+
+// Records the state for undo and, when it changed, in the calculation log
+fn record_state(program_state: &mut ProgramState) {
+    if history::record(program_state) {
+        #[cfg(feature = "reports")]
+        calc_log::append(program_state);
     }
+}
 
-    print_main_menu(program_state);
+fn print_gas_state(program_state: &mut ProgramState) {
+    record_state(program_state);
+    out!("{}", format_gas_state(program_state));
 }
 
 enum GasComp {
@@ -362,6 +1267,9 @@ enum GasComp {
     Argon,
     Nitrogen,
     Oxygen,
+    NaturalGas,
+    Methane,
+    Hydrogen,
 }
 
 fn get_gas_comp(gas_comp: GasComp) -> Composition{
@@ -388,117 +1296,198 @@ fn get_gas_comp(gas_comp: GasComp) -> Composition{
             oxygen: 1.0,
             ..Default::default()
         },
+
+        GasComp::NaturalGas => Composition {
+            methane: 0.9,
+            ethane: 0.05,
+            propane: 0.02,
+            isobutane: 0.0035,
+            n_butane: 0.0035,
+            isopentane: 0.001,
+            n_pentane: 0.001,
+            hexane: 0.0005,
+            nitrogen: 0.01,
+            carbon_dioxide: 0.01,
+            ..Default::default()
+        },
+
+        GasComp::Methane => Composition {
+            methane: 1.0,
+            ..Default::default()
+        },
+
+        GasComp::Hydrogen => Composition {
+            hydrogen: 1.0,
+            ..Default::default()
+        },
     }
 
 }
 
-struct UnitText {
-    pressure: &'static str,
-    temperature: &'static str,
-    internal_energy: &'static str,
+// Suggests a coherent set after a unit change when the selection mixes SI and US
+// customary units.
+fn units_changed(program_state: &mut ProgramState) {
+    let units = program_state.units;
+    if let Some((mix, system)) = unit_mix(units.pressure, units.temp, units.internal_energy, units.power, units.density) {
+        outln!("{}", format!("* {} mixes SI and US customary units; select u, 4 for a coherent {} set", mix, system.name()).yellow());
+    }
+    print_gas_state(program_state);
 }
 
+fn change_unit_system(program_state: &mut ProgramState) {
+    let system = loop {
+        if !output::quiet() {
+            outln!("Select Unit System:");
+            outln!("1 - SI (kPa, K, kJ/kg, kW, kg/m3)");
+            outln!("2 - US customary (PSI, F, BTU/lbm, hp, lb/ft3)");
+        }
+        match read_input().as_str() {
+            "1" => break UnitSystem::SI,
+            "2" => break UnitSystem::USCustomary,
+            _ => (),
+        }
+    };
+    program_state.units = match system {
+        UnitSystem::SI => Units {
+            pressure: UnitPressure::kPa, temp: UnitTemp::K, internal_energy: UnitInternalEnergy::kJ_kg, power: UnitPower::kW, density: UnitDensity::kg_m3,
+        },
+        UnitSystem::USCustomary => Units {
+            pressure: UnitPressure::PSI, temp: UnitTemp::F, internal_energy: UnitInternalEnergy::BTU_lbm, power: UnitPower::hp, density: UnitDensity::lb_ft3,
+        },
+    };
+    units_changed(program_state);
+}
 
 fn change_units(program_state: &mut ProgramState) {
-    println!();
-    println!("Select Unit:");
-    println!("1 - Pressure ({})", program_state.unit_text.pressure);
-    println!("2 - Temperature ({})", program_state.unit_text.temperature);
-    println!("3 - Internal Energy ({})", program_state.unit_text.internal_energy);
+    loop {
+        if !output::quiet() {
+            outln!();
+            outln!("Select Unit:");
+            outln!("1 - Pressure ({})", program_state.units.pressure.label());
+            outln!("2 - Temperature ({})", program_state.units.temp.label());
+            outln!("3 - Internal Energy ({})", program_state.units.internal_energy.label());
+            outln!("4 - Coherent unit set (SI or US customary)");
+            outln!("5 - Power ({})", program_state.units.power.label());
+            outln!("6 - Density ({})", program_state.units.density.label());
+        }
 
     
-    let mut choice = String::new();
-    io::stdin().read_line(&mut choice).unwrap();
-    let choice = choice.trim();
+        let choice = read_input();
+        let choice = choice.as_str();
 
-    match choice {
-        "1" => change_unit_pressure(program_state),
-        "2" => change_unit_temperature(program_state),
-        "3" => change_unit_internal_energy(program_state),
-        _ => change_units(program_state),
+        match choice {
+            "1" => change_unit_pressure(program_state),
+            "2" => change_unit_temperature(program_state),
+            "3" => change_unit_internal_energy(program_state),
+            "4" => change_unit_system(program_state),
+            "5" => change_unit_power(program_state),
+            "6" => change_unit_density(program_state),
+            _ => continue,
+        }
+        break;
     }
 }
 
 fn change_unit_pressure(program_state: &mut ProgramState) {
-    println!("Select Pressure Unit:");
-    println!("1 - kPa");
-    println!("2 - Bar");
-    println!("3 - PSI");
-    let mut choice = String::new();
-    io::stdin().read_line(&mut choice).unwrap();
-    let choice = choice.trim();
-    match choice {
-        "1" => {
-            program_state.unit_text.pressure = "kPa";
-            program_state.units.pressure = UnitPressure::kPa;
-        },
-        "2" => {
-            program_state.unit_text.pressure = "Bar";
-            program_state.units.pressure = UnitPressure::Bar;
-        },
-        "3" => {
-            program_state.unit_text.pressure = "PSI";
-            program_state.units.pressure = UnitPressure::PSI;
-        },
-        _ => change_unit_temperature(program_state),
+    loop {
+        if !output::quiet() {
+            outln!("Select Pressure Unit:");
+            for (i, unit) in PRESSURE_UNITS.iter().enumerate() {
+                outln!("{} - {}", i + 1, unit.label());
+            }
+        }
+        let choice = read_input();
+        match choice.parse::<usize>().ok().and_then(|i| PRESSURE_UNITS.get(i.wrapping_sub(1))) {
+            Some(unit) => program_state.units.pressure = *unit,
+            None => continue,
+        }
+        break;
     }
-    print_gas_state(program_state);
+    units_changed(program_state);
 }
 
 fn change_unit_temperature(program_state: &mut ProgramState) {
+    loop {
     
-    println!("Select Temperature Unit:");
-    println!("1 - Celcius C");
-    println!("2 - Kelvin K");
-    println!("3 - Fahrenheit F");
-    println!("4 - Rankine R");    
-    let mut choice = String::new();
-    io::stdin().read_line(&mut choice).unwrap();
-    let choice = choice.trim();
-    match choice {
-        "1" => {
-            program_state.unit_text.temperature = "C";
-            program_state.units.temp = UnitTemp::C;
-        },
-        "2" => {
-            program_state.unit_text.temperature = "K";
-            program_state.units.temp = UnitTemp::K;
-        },
-        "3" => {
-            program_state.unit_text.temperature = "F";
-            program_state.units.temp = UnitTemp::F;
-        },
-        "4" => {
-            program_state.unit_text.temperature = "R";
-            program_state.units.temp = UnitTemp::R;
-        },
-        _ => change_unit_temperature(program_state),
+        if !output::quiet() {
+            outln!("Select Temperature Unit:");
+            outln!("1 - Celcius C");
+            outln!("2 - Kelvin K");
+            outln!("3 - Fahrenheit F");
+            outln!("4 - Rankine R");    
+        }
+        let choice = read_input();
+        let choice = choice.as_str();
+        match choice {
+            "1" => {
+                program_state.units.temp = UnitTemp::C;
+            },
+            "2" => {
+                program_state.units.temp = UnitTemp::K;
+            },
+            "3" => {
+                program_state.units.temp = UnitTemp::F;
+            },
+            "4" => {
+                program_state.units.temp = UnitTemp::R;
+            },
+            _ => continue,
+        }
+        break;
     }
-    print_gas_state(program_state);
+    units_changed(program_state);
 }
 
 fn change_unit_internal_energy(program_state: &mut ProgramState) {
-    println!("Select Internal Energy Unit:");
-    println!("1 - J/mol");
-    println!("2 - kJ/kg");
-    println!("3 - BTU/lbm");
-    let mut choice = String::new();
-    io::stdin().read_line(&mut choice).unwrap();
-    let choice = choice.trim();
-    match choice {
-        "1" => {
-            program_state.unit_text.internal_energy = "J/mol";
-            program_state.units.internal_energy = UnitInternalEnergy::J_mol;
-        },
-        "2" => {
-            program_state.unit_text.internal_energy = "kJ/kg";
-            program_state.units.internal_energy = UnitInternalEnergy::kJ_kg;
-        },
-        "3" => {
-            program_state.unit_text.internal_energy = "BTU/lbm";
-            program_state.units.internal_energy = UnitInternalEnergy::BTU_lbm;
-        },
-        _ => change_unit_internal_energy(program_state),
+    loop {
+        if !output::quiet() {
+            outln!("Select Internal Energy Unit:");
+            for (i, unit) in ENERGY_UNITS.iter().enumerate() {
+                outln!("{} - {}", i + 1, unit.label());
+            }
+        }
+        let choice = read_input();
+        match choice.parse::<usize>().ok().and_then(|i| ENERGY_UNITS.get(i.wrapping_sub(1))) {
+            Some(unit) => program_state.units.internal_energy = *unit,
+            None => continue,
+        }
+        break;
     }
-    print_gas_state(program_state);
-}
\ No newline at end of file
+    units_changed(program_state);
+}
+
+fn change_unit_density(program_state: &mut ProgramState) {
+    loop {
+        if !output::quiet() {
+            outln!("Select Density Unit:");
+            for (i, unit) in DENSITY_UNITS.iter().enumerate() {
+                outln!("{} - {}", i + 1, unit.label());
+            }
+        }
+        let choice = read_input();
+        match choice.parse::<usize>().ok().and_then(|i| DENSITY_UNITS.get(i.wrapping_sub(1))) {
+            Some(unit) => program_state.units.density = *unit,
+            None => continue,
+        }
+        break;
+    }
+    units_changed(program_state);
+}
+
+fn change_unit_power(program_state: &mut ProgramState) {
+    loop {
+        if !output::quiet() {
+            outln!("Select Power Unit:");
+            for (i, unit) in POWER_UNITS.iter().enumerate() {
+                outln!("{} - {}", i + 1, unit.label());
+            }
+        }
+        let choice = read_input();
+        match choice.parse::<usize>().ok().and_then(|i| POWER_UNITS.get(i.wrapping_sub(1))) {
+            Some(unit) => program_state.units.power = *unit,
+            None => continue,
+        }
+        break;
+    }
+    units_changed(program_state);
+}
@@ -0,0 +1,395 @@
+use aga8::composition::Composition;
+use colored::Colorize;
+use serde::Deserialize;
+
+use crate::components::{array_to_comp, comp_to_array, composition_serde};
+use crate::realtime::{format_table_state, RealtimeEvaluator, TableSpec};
+use crate::{
+    change_conditions, change_gas_comp, format_gas_state, initial_program_state, print_state_errors, update_gas_state, Pressure, Temperature,
+    UnitPressure, UnitTemp,
+};
+
+use std::fs;
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const READ_HOLDING_REGISTERS: u8 = 0x03;
+const READ_INPUT_REGISTERS: u8 = 0x04;
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Clone, Copy, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RegisterKind {
+    Holding,
+    Input,
+}
+
+// Encoding of the value in the register(s).  f32 takes two registers, high word first;
+// f32_swapped has the low word first, as many flow computers send it.
+#[derive(Clone, Copy, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RegisterFormat {
+    F32,
+    F32Swapped,
+    I16,
+    U16,
+}
+
+impl RegisterFormat {
+    fn count(self) -> u16 {
+        match self {
+            RegisterFormat::F32 | RegisterFormat::F32Swapped => 2,
+            RegisterFormat::I16 | RegisterFormat::U16 => 1,
+        }
+    }
+
+    fn decode(self, registers: &[u16]) -> f64 {
+        match self {
+            RegisterFormat::F32 => f64::from(f32::from_bits(u32::from(registers[0]) << 16 | u32::from(registers[1]))),
+            RegisterFormat::F32Swapped => f64::from(f32::from_bits(u32::from(registers[1]) << 16 | u32::from(registers[0]))),
+            RegisterFormat::I16 => f64::from(registers[0] as i16),
+            RegisterFormat::U16 => f64::from(registers[0]),
+        }
+    }
+}
+
+// One measured value: the engineering value is raw * scale + offset, in the given unit
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RegisterValue {
+    pub register: u16,
+    #[serde(default = "default_kind")]
+    pub kind: RegisterKind,
+    #[serde(default = "default_format")]
+    pub format: RegisterFormat,
+    #[serde(default = "default_scale")]
+    pub scale: f64,
+    #[serde(default)]
+    pub offset: f64,
+    pub unit: String,
+}
+
+fn default_kind() -> RegisterKind {
+    RegisterKind::Holding
+}
+
+fn default_format() -> RegisterFormat {
+    RegisterFormat::F32
+}
+
+fn default_scale() -> f64 {
+    1.0
+}
+
+// Device and registers for live pressure and temperature, the [modbus] table of the
+// Modbus mode and kiosk stream configurations
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ModbusSource {
+    pub address: String,
+    #[serde(default = "default_unit_id")]
+    pub unit_id: u8,
+    // Seconds between polls
+    #[serde(default = "default_poll_interval")]
+    pub poll_interval: f64,
+    pub pressure: RegisterValue,
+    pub temperature: RegisterValue,
+}
+
+impl ModbusSource {
+    // Checks the unit labels and the poll interval, which TOML allows to be inf or nan
+    pub fn check(&self) -> Result<(), String> {
+        pressure_unit(&self.pressure.unit)?;
+        temperature_unit(&self.temperature.unit)?;
+        if !(self.poll_interval.is_finite() && self.poll_interval > 0.0) {
+            return Err(format!("Poll interval must be a positive number of seconds, not {}", self.poll_interval));
+        }
+        Ok(())
+    }
+
+    pub fn interval(&self) -> Duration {
+        Duration::from_secs_f64(self.poll_interval.max(0.1))
+    }
+}
+
+// Modbus mode settings read from TOML
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ModbusConfig {
+    pub gas: Option<String>,
+    // Mole fractions by component name
+    #[serde(with = "composition_serde")]
+    pub composition: Composition,
+    pub modbus: ModbusSource,
+}
+
+fn default_unit_id() -> u8 {
+    1
+}
+
+fn default_poll_interval() -> f64 {
+    2.0
+}
+
+pub fn read_modbus_config(path: &str) -> Result<ModbusConfig, String> {
+    let text = fs::read_to_string(path).map_err(|err| format!("Unable to read {}: {}", path, err))?;
+    let config: ModbusConfig = toml::from_str(&text).map_err(|err| format!("Invalid Modbus configuration {}: {}", path, err))?;
+    config.modbus.check()?;
+    Ok(config)
+}
+
+fn pressure_unit(label: &str) -> Result<UnitPressure, String> {
+    UnitPressure::from_label(label).ok_or(format!("Unknown pressure unit '{}'", label))
+}
+
+fn temperature_unit(label: &str) -> Result<UnitTemp, String> {
+    UnitTemp::from_label(label).ok_or(format!("Unknown temperature unit '{}'", label))
+}
+
+// Modbus TCP client for reading registers from a transmitter or flow computer
+pub struct ModbusClient {
+    stream: TcpStream,
+    unit_id: u8,
+    transaction: u16,
+}
+
+impl ModbusClient {
+    pub fn connect(address: &str, unit_id: u8) -> io::Result<ModbusClient> {
+        // Each resolved address in turn, as TcpStream::connect does, but without waiting
+        // longer than TIMEOUT on an unreachable device
+        let mut result = Err(io::Error::new(ErrorKind::InvalidInput, format!("No address for {}", address)));
+        for socket_address in address.to_socket_addrs()? {
+            result = TcpStream::connect_timeout(&socket_address, TIMEOUT);
+            if result.is_ok() {
+                break;
+            }
+        }
+        let stream = result?;
+        stream.set_read_timeout(Some(TIMEOUT))?;
+        Ok(ModbusClient { stream, unit_id, transaction: 0 })
+    }
+
+    pub fn read_registers(&mut self, kind: RegisterKind, start: u16, count: u16) -> io::Result<Vec<u16>> {
+        let function = match kind {
+            RegisterKind::Holding => READ_HOLDING_REGISTERS,
+            RegisterKind::Input => READ_INPUT_REGISTERS,
+        };
+        self.transaction = self.transaction.wrapping_add(1);
+        // MBAP header (transaction, protocol 0, length of unit id and PDU, unit id), then
+        // the function, start register and count
+        let mut request = Vec::with_capacity(12);
+        request.extend_from_slice(&self.transaction.to_be_bytes());
+        request.extend_from_slice(&[0, 0, 0, 6, self.unit_id, function]);
+        request.extend_from_slice(&start.to_be_bytes());
+        request.extend_from_slice(&count.to_be_bytes());
+        self.stream.write_all(&request)?;
+
+        let mut header = [0u8; 7];
+        self.stream.read_exact(&mut header)?;
+        let length = usize::from(u16::from_be_bytes([header[4], header[5]]));
+        if length < 2 {
+            return Err(io::Error::new(ErrorKind::InvalidData, "Invalid Modbus response length"));
+        }
+        let mut pdu = vec![0; length - 1];
+        self.stream.read_exact(&mut pdu)?;
+        if u16::from_be_bytes([header[0], header[1]]) != self.transaction {
+            return Err(io::Error::new(ErrorKind::InvalidData, "Modbus response to another request"));
+        }
+        if pdu[0] == function | 0x80 {
+            let code = pdu.get(1).copied().unwrap_or(0);
+            return Err(io::Error::new(ErrorKind::InvalidData, format!("Modbus exception {}", code)));
+        }
+        let data = pdu.get(2..2 + 2 * usize::from(count))
+            .filter(|_| pdu[0] == function)
+            .ok_or_else(|| io::Error::new(ErrorKind::InvalidData, "Truncated Modbus response"))?;
+        Ok(data.chunks(2).map(|word| u16::from_be_bytes([word[0], word[1]])).collect())
+    }
+
+    pub fn read_value(&mut self, value: &RegisterValue) -> io::Result<f64> {
+        let registers = self.read_registers(value.kind, value.register, value.format.count())?;
+        Ok(value.format.decode(&registers) * value.scale + value.offset)
+    }
+}
+
+// Pressure and temperature read from the configured registers
+pub fn poll_conditions(client: &mut ModbusClient, source: &ModbusSource) -> io::Result<(Pressure, Temperature)> {
+    let p = client.read_value(&source.pressure)?;
+    let t = client.read_value(&source.temperature)?;
+    let p_unit = pressure_unit(&source.pressure.unit).map_err(|err| io::Error::new(ErrorKind::InvalidInput, err))?;
+    let t_unit = temperature_unit(&source.temperature.unit).map_err(|err| io::Error::new(ErrorKind::InvalidInput, err))?;
+    Ok((Pressure::new(p, p_unit), Temperature::new(t, t_unit)))
+}
+
+// Connection to a source that reconnects on the next poll after an error
+pub struct ModbusPoller<'a> {
+    source: &'a ModbusSource,
+    client: Option<ModbusClient>,
+}
+
+impl ModbusPoller<'_> {
+    pub fn new(source: &ModbusSource) -> ModbusPoller<'_> {
+        ModbusPoller { source, client: None }
+    }
+
+    pub fn poll(&mut self) -> io::Result<(Pressure, Temperature)> {
+        let client = match self.client.as_mut() {
+            Some(client) => client,
+            None => self.client.insert(ModbusClient::connect(&self.source.address, self.source.unit_id)?),
+        };
+        let result = poll_conditions(client, self.source);
+        if result.is_err() {
+            self.client = None;
+        }
+        result
+    }
+}
+
+// Polls pressure and temperature and keeps the state display up to date.  Reconnects
+// after errors.  With --realtime the properties are interpolated from a property table
+// instead of solved with DETAIL on every poll.
+// Usage: comp_perf modbus <config.toml> [--realtime]
+pub fn run_modbus(args: &[String]) -> i32 {
+    let realtime = args.iter().any(|arg| arg == "--realtime");
+    let Some(path) = args.iter().find(|arg| *arg != "--realtime") else {
+        eprintln!("Usage: comp_perf modbus <config.toml> [--realtime]");
+        return 2;
+    };
+    let config = match read_modbus_config(path) {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("{}", err);
+            return 1;
+        }
+    };
+    let gas_comp = array_to_comp(&comp_to_array(&config.composition));
+    let mut program_state = initial_program_state();
+    program_state.gas = config.gas.clone().unwrap_or_else(|| String::from("Modbus"));
+    change_gas_comp(&mut program_state, gas_comp);
+    print_state_errors(&update_gas_state(&mut program_state));
+    let evaluator = realtime.then(|| RealtimeEvaluator::new(&config.composition, TableSpec::default()));
+
+    let source = &config.modbus;
+    let mut poller = ModbusPoller::new(source);
+    let mut last_update: Option<Instant> = None;
+    let mut conditions: Option<(Pressure, Temperature)> = None;
+    loop {
+        let polled = Instant::now();
+        let result = poller.poll();
+        let mut out = String::from("\x1b[2J\x1b[H");
+        match result {
+            Ok((p, t)) => {
+                if evaluator.is_none() {
+                    change_conditions(&mut program_state, p, t);
+                    for error in update_gas_state(&mut program_state) {
+                        out.push_str(&format!("{}\n", error.red().bold()));
+                    }
+                }
+                conditions = Some((p, t));
+                last_update = Some(Instant::now());
+            },
+            Err(err) => out.push_str(&format!("{}\n", format!("** Modbus {}: {} **", source.address, err).red().bold())),
+        }
+        match (&evaluator, conditions) {
+            (Some(evaluator), Some((p, t))) => {
+                out.push_str(&format!("{:<30} {:10.4} {:10}\n", "Pressure", p.kpa(), "kPa"));
+                out.push_str(&format!("{:<30} {:10.4} {:10}\n", "Temperature", t.kelvin(), "K"));
+                out.push_str(&format_table_state(evaluator, p.kpa(), t.kelvin()));
+            },
+            (Some(_), None) => {},
+            (None, _) => out.push_str(&format_gas_state(&program_state)),
+        }
+        match last_update {
+            Some(updated) => out.push_str(&format!("\nModbus {} - updated {:.1} s ago\n", source.address, updated.elapsed().as_secs_f64())),
+            None => out.push_str(&format!("\nModbus {} - no data yet\n", source.address)),
+        }
+        out!("{}", out);
+        thread::sleep(source.interval().saturating_sub(polled.elapsed()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    // A client on one end of a loopback connection and the device's end of it
+    fn connected() -> (ModbusClient, TcpStream) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let stream = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (device, _) = listener.accept().unwrap();
+        (ModbusClient { stream, unit_id: 7, transaction: 0 }, device)
+    }
+
+    fn response(transaction: u16, pdu: &[u8]) -> Vec<u8> {
+        let mut response = transaction.to_be_bytes().to_vec();
+        response.extend_from_slice(&[0, 0]);
+        response.extend_from_slice(&(pdu.len() as u16 + 1).to_be_bytes());
+        response.push(7);
+        response.extend_from_slice(pdu);
+        response
+    }
+
+    // 101.325 as an f32 is 0x42CA_A666
+    #[test]
+    fn register_formats() {
+        assert_eq!(RegisterFormat::F32.decode(&[0x42ca, 0xa666]), f64::from(101.325_f32));
+        assert_eq!(RegisterFormat::F32Swapped.decode(&[0xa666, 0x42ca]), f64::from(101.325_f32));
+        assert_eq!(RegisterFormat::I16.decode(&[0xfffe]), -2.0);
+        assert_eq!(RegisterFormat::U16.decode(&[0xfffe]), 65534.0);
+        assert_eq!((RegisterFormat::F32.count(), RegisterFormat::U16.count()), (2, 1));
+    }
+
+    #[test]
+    fn read_registers_request_and_response() {
+        let (mut client, mut device) = connected();
+        device.write_all(&response(1, &[READ_INPUT_REGISTERS, 4, 0x42, 0xca, 0xa6, 0x66])).unwrap();
+        assert_eq!(client.read_registers(RegisterKind::Input, 40, 2).unwrap(), [0x42ca, 0xa666]);
+        let mut request = [0u8; 12];
+        device.read_exact(&mut request).unwrap();
+        assert_eq!(request, [0, 1, 0, 0, 0, 6, 7, READ_INPUT_REGISTERS, 0, 40, 0, 2]);
+    }
+
+    // Exceptions, responses to another transaction and short data are errors
+    #[test]
+    fn read_registers_errors() {
+        let (mut client, mut device) = connected();
+        device.write_all(&response(1, &[READ_HOLDING_REGISTERS | 0x80, 2])).unwrap();
+        let err = client.read_registers(RegisterKind::Holding, 0, 1).unwrap_err();
+        assert_eq!(err.to_string(), "Modbus exception 2");
+
+        device.write_all(&response(9, &[READ_HOLDING_REGISTERS, 2, 0, 1])).unwrap();
+        let err = client.read_registers(RegisterKind::Holding, 0, 1).unwrap_err();
+        assert_eq!(err.to_string(), "Modbus response to another request");
+
+        device.write_all(&response(3, &[READ_HOLDING_REGISTERS, 2, 0, 1])).unwrap();
+        let err = client.read_registers(RegisterKind::Holding, 0, 2).unwrap_err();
+        assert_eq!(err.to_string(), "Truncated Modbus response");
+
+        device.write_all(&response(4, &[READ_INPUT_REGISTERS, 2, 0, 1])).unwrap();
+        let err = client.read_registers(RegisterKind::Holding, 0, 1).unwrap_err();
+        assert_eq!(err.to_string(), "Truncated Modbus response");
+
+        device.write_all(&[0, 5, 0, 0, 0, 1, 7]).unwrap();
+        let err = client.read_registers(RegisterKind::Holding, 0, 1).unwrap_err();
+        assert_eq!(err.to_string(), "Invalid Modbus response length");
+    }
+
+    fn source(poll_interval: &str, pressure_unit: &str) -> ModbusSource {
+        toml::from_str(&format!(
+            "address = \"127.0.0.1:502\"\npoll_interval = {}\npressure = {{ register = 0, unit = \"{}\" }}\n\
+             temperature = {{ register = 2, unit = \"C\" }}\n",
+            poll_interval, pressure_unit,
+        )).unwrap()
+    }
+
+    #[test]
+    fn poll_interval_and_units_are_checked() {
+        assert!(source("0.5", "psi").check().is_ok());
+        for interval in ["inf", "nan", "0.0", "-1.0"] {
+            assert!(source(interval, "kPa").check().is_err(), "{}", interval);
+        }
+        assert_eq!(source("2.0", "psig").check().unwrap_err(), "Unknown pressure unit 'psig'");
+    }
+}
@@ -0,0 +1,343 @@
+use aga8::composition::Composition;
+use serde::Deserialize;
+
+use crate::components::composition_serde;
+use crate::quality::{gross_hv_molar, gross_hv_volumetric};
+use crate::{try_gas_state, Pressure, Temperature};
+
+use std::fs;
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+const CONNECT: u8 = 0x10;
+const CONNACK: u8 = 0x20;
+const PUBLISH: u8 = 0x30;
+const SUBSCRIBE: u8 = 0x82;
+const SUBACK: u8 = 0x90;
+const PINGREQ: u8 = 0xc0;
+
+// Seconds; a ping is sent after half of this without traffic from the broker
+const KEEP_ALIVE: u16 = 60;
+
+// Minimal MQTT 3.1.1 client with QoS 0 subscriptions and publishing, enough for reading
+// transmitter values from a broker and publishing results.
+pub struct MqttClient {
+    stream: TcpStream,
+}
+
+fn encode_string(packet: &mut Vec<u8>, text: &str) {
+    packet.extend_from_slice(&(text.len() as u16).to_be_bytes());
+    packet.extend_from_slice(text.as_bytes());
+}
+
+// Fixed header with the variable length encoding of the remaining length
+fn frame(packet_type: u8, body: &[u8]) -> Vec<u8> {
+    let mut packet = vec![packet_type];
+    let mut length = body.len();
+    loop {
+        let mut byte = (length % 128) as u8;
+        length /= 128;
+        if length > 0 {
+            byte |= 0x80;
+        }
+        packet.push(byte);
+        if length == 0 {
+            break;
+        }
+    }
+    packet.extend_from_slice(body);
+    packet
+}
+
+fn protocol_error(message: &str) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, message.to_string())
+}
+
+impl MqttClient {
+    pub fn connect(broker: &str, client_id: &str) -> io::Result<MqttClient> {
+        let stream = TcpStream::connect(broker)?;
+        let mut client = MqttClient { stream };
+        let mut body = Vec::new();
+        encode_string(&mut body, "MQTT");
+        // Protocol level 4 (3.1.1) with a clean session
+        body.extend_from_slice(&[4, 0x02]);
+        body.extend_from_slice(&KEEP_ALIVE.to_be_bytes());
+        encode_string(&mut body, client_id);
+        client.stream.write_all(&frame(CONNECT, &body))?;
+        let (packet_type, body) = client.read_packet()?;
+        if packet_type != CONNACK || body.len() != 2 {
+            return Err(protocol_error("Expected CONNACK"));
+        }
+        if body[1] != 0 {
+            return Err(protocol_error(&format!("Connection refused by broker (code {})", body[1])));
+        }
+        client.stream.set_read_timeout(Some(Duration::from_secs(u64::from(KEEP_ALIVE) / 2)))?;
+        Ok(client)
+    }
+
+    pub fn subscribe(&mut self, topics: &[&str]) -> io::Result<()> {
+        // Packet identifier 1; only one subscription request is sent
+        let mut body = vec![0, 1];
+        for topic in topics {
+            encode_string(&mut body, topic);
+            body.push(0);
+        }
+        self.stream.write_all(&frame(SUBSCRIBE, &body))?;
+        loop {
+            match self.read_packet()? {
+                (SUBACK, body) if body.iter().skip(2).any(|code| *code == 0x80) => return Err(protocol_error("Subscription refused by broker")),
+                (SUBACK, _) => return Ok(()),
+                // Retained messages can arrive before the acknowledgement
+                _ => (),
+            }
+        }
+    }
+
+    // QoS 0 publish; retained so late subscribers see the latest value
+    pub fn publish(&mut self, topic: &str, payload: &str, retain: bool) -> io::Result<()> {
+        let mut body = Vec::new();
+        encode_string(&mut body, topic);
+        body.extend_from_slice(payload.as_bytes());
+        self.stream.write_all(&frame(PUBLISH | u8::from(retain), &body))
+    }
+
+    fn read_packet(&mut self) -> io::Result<(u8, Vec<u8>)> {
+        let mut header = [0u8; 1];
+        self.stream.read_exact(&mut header)?;
+        let mut length = 0usize;
+        let mut multiplier = 1;
+        loop {
+            let mut byte = [0u8; 1];
+            self.stream.read_exact(&mut byte)?;
+            length += usize::from(byte[0] & 0x7f) * multiplier;
+            if byte[0] & 0x80 == 0 {
+                break;
+            }
+            multiplier *= 128;
+            if multiplier > 128 * 128 * 128 {
+                return Err(protocol_error("Invalid remaining length"));
+            }
+        }
+        let mut body = vec![0; length];
+        self.stream.read_exact(&mut body)?;
+        Ok((header[0], body))
+    }
+
+    // Waits for the next published message and returns its topic and payload, pinging the
+    // broker while the connection is idle.
+    pub fn next_message(&mut self) -> io::Result<(String, Vec<u8>)> {
+        loop {
+            let (header, body) = match self.read_packet() {
+                Ok(packet) => packet,
+                Err(err) if matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                    self.stream.write_all(&frame(PINGREQ, &[]))?;
+                    continue;
+                },
+                Err(err) => return Err(err),
+            };
+            if header & 0xf0 != PUBLISH {
+                continue;
+            }
+            if body.len() < 2 {
+                return Err(protocol_error("Truncated PUBLISH"));
+            }
+            let topic_length = usize::from(u16::from_be_bytes([body[0], body[1]]));
+            let topic = body.get(2..2 + topic_length).ok_or_else(|| protocol_error("Truncated PUBLISH"))?;
+            let topic = String::from_utf8_lossy(topic).into_owned();
+            // QoS 1 and 2 messages carry a packet identifier after the topic
+            let payload_start = 2 + topic_length + if header & 0x06 != 0 { 2 } else { 0 };
+            return Ok((topic, body.get(payload_start..).unwrap_or_default().to_vec()));
+        }
+    }
+}
+
+// MQTT mode settings read from TOML.  Pressures are in kPa and temperatures in K, in the
+// subscribed payloads and the base conditions.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MqttConfig {
+    pub broker: String,
+    #[serde(default = "default_client_id")]
+    pub client_id: String,
+    pub pressure_topic: String,
+    pub temperature_topic: String,
+    // Results are published under this prefix, e.g. "<prefix>/density"
+    pub result_prefix: String,
+    // Mole fractions by component name
+    #[serde(with = "composition_serde")]
+    pub composition: Composition,
+    #[serde(default = "default_base_pressure")]
+    pub base_pressure: Pressure,
+    #[serde(default = "default_base_temperature")]
+    pub base_temperature: Temperature,
+}
+
+fn default_client_id() -> String {
+    String::from("comp_perf")
+}
+
+fn default_base_pressure() -> Pressure {
+    Pressure::from_kpa(101.325)
+}
+
+fn default_base_temperature() -> Temperature {
+    Temperature::from_kelvin(288.15)
+}
+
+pub fn read_mqtt_config(path: &str) -> Result<MqttConfig, String> {
+    let text = fs::read_to_string(path).map_err(|err| format!("Unable to read {}: {}", path, err))?;
+    toml::from_str(&text).map_err(|err| format!("Invalid MQTT configuration {}: {}", path, err))
+}
+
+// Result topics and values for the gas at p and t: density in kg/m3, Z, gross heating
+// value in MJ/m3 at base conditions and energy density in MJ per m3 at flowing
+// conditions.
+pub fn result_values(gas_comp: &Composition, p: Pressure, t: Temperature, p_base: Pressure, t_base: Temperature) -> Option<[(&'static str, f64); 4]> {
+    let state = try_gas_state(gas_comp, p.kpa(), t.kelvin())?;
+    // kJ/mol times mol/l is MJ/m3
+    let energy_density = gross_hv_molar(gas_comp) * state.d;
+    Some([
+        ("density", state.d * state.mm),
+        ("z", state.z),
+        ("hv", gross_hv_volumetric(gas_comp, p_base, t_base)),
+        ("energy_density", energy_density),
+    ])
+}
+
+// A finite number, or None for anything else
+pub fn parse_payload(payload: &[u8]) -> Option<f64> {
+    std::str::from_utf8(payload).ok()?.trim().parse::<f64>().ok().filter(|value| value.is_finite())
+}
+
+fn run(config: &MqttConfig) -> Result<(), String> {
+    let mut client = MqttClient::connect(&config.broker, &config.client_id)
+        .map_err(|err| format!("Unable to connect to {}: {}", config.broker, err))?;
+    client.subscribe(&[&config.pressure_topic, &config.temperature_topic])
+        .map_err(|err| format!("Unable to subscribe: {}", err))?;
+    eprintln!("Connected to {}", config.broker);
+
+    let (mut p, mut t) = (None, None);
+    loop {
+        let (topic, payload) = client.next_message().map_err(|err| format!("Connection lost: {}", err))?;
+        let Some(value) = parse_payload(&payload) else {
+            eprintln!("Ignoring non-numeric payload on {}", topic);
+            continue;
+        };
+        if topic == config.pressure_topic {
+            p = Some(value);
+        } else if topic == config.temperature_topic {
+            t = Some(value);
+        }
+        // Nothing is published until both values have been received
+        let (Some(p), Some(t)) = (p, t) else {
+            continue;
+        };
+        let publish = |client: &mut MqttClient, name: &str, payload: &str| {
+            client.publish(&format!("{}/{}", config.result_prefix, name), payload, true)
+                .map_err(|err| format!("Unable to publish: {}", err))
+        };
+        match result_values(&config.composition, Pressure::from_kpa(p), Temperature::from_kelvin(t), config.base_pressure, config.base_temperature) {
+            Some(values) => {
+                for (name, value) in values {
+                    publish(&mut client, name, &value.to_string())?;
+                }
+                publish(&mut client, "status", "ok")?;
+            },
+            None => publish(&mut client, "status", &format!("no solution at {} kPa, {} K", p, t))?,
+        }
+    }
+}
+
+// Subscribes to the pressure and temperature topics and republishes the properties on
+// every update.  Usage: comp_perf mqtt <config.toml>
+pub fn run_mqtt(args: &[String]) -> i32 {
+    let Some(path) = args.first() else {
+        eprintln!("Usage: comp_perf mqtt <config.toml>");
+        return 2;
+    };
+    let result = read_mqtt_config(path).and_then(|config| run(&config));
+    match result {
+        Ok(()) => 0,
+        Err(err) => {
+            eprintln!("{}", err);
+            1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    // A client on one end of a loopback connection and the broker's end of it
+    fn connected() -> (MqttClient, TcpStream) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let stream = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (broker, _) = listener.accept().unwrap();
+        (MqttClient { stream }, broker)
+    }
+
+    // The remaining length takes one more byte at each multiple of 128
+    #[test]
+    fn remaining_length_encoding() {
+        assert_eq!(frame(PINGREQ, &[]), [PINGREQ, 0]);
+        assert_eq!(frame(PUBLISH, &[0; 127])[..2], [PUBLISH, 127]);
+        assert_eq!(frame(PUBLISH, &[0; 128])[..3], [PUBLISH, 0x80, 1]);
+        assert_eq!(frame(PUBLISH, &[0; 16_383])[..3], [PUBLISH, 0xff, 0x7f]);
+        let packet = frame(PUBLISH, &[0; 16_384]);
+        assert_eq!(packet[..4], [PUBLISH, 0x80, 0x80, 1]);
+        assert_eq!(packet.len(), 4 + 16_384);
+    }
+
+    #[test]
+    fn read_packet_round_trip() {
+        let (mut client, mut broker) = connected();
+        for length in [0, 127, 128, 16_384] {
+            let body: Vec<u8> = (0..length).map(|i| i as u8).collect();
+            broker.write_all(&frame(SUBACK, &body)).unwrap();
+            assert_eq!(client.read_packet().unwrap(), (SUBACK, body));
+        }
+    }
+
+    // At most four length bytes
+    #[test]
+    fn overlong_remaining_length_is_rejected() {
+        let (mut client, mut broker) = connected();
+        broker.write_all(&[PUBLISH, 0xff, 0xff, 0xff, 0xff, 0x7f]).unwrap();
+        assert_eq!(client.read_packet().unwrap_err().kind(), ErrorKind::InvalidData);
+    }
+
+    // QoS 1 messages carry a packet identifier between the topic and the payload; other
+    // packets are skipped and a topic longer than the packet is an error
+    #[test]
+    fn next_message_topics_and_payloads() {
+        let (mut client, mut broker) = connected();
+        let mut body = Vec::new();
+        encode_string(&mut body, "p");
+        body.extend_from_slice(b"101.3");
+        broker.write_all(&frame(0xd0, &[])).unwrap();
+        broker.write_all(&frame(PUBLISH, &body)).unwrap();
+        assert_eq!(client.next_message().unwrap(), (String::from("p"), b"101.3".to_vec()));
+
+        let mut body = Vec::new();
+        encode_string(&mut body, "t");
+        body.extend_from_slice(&[0, 7]);
+        body.extend_from_slice(b"300");
+        broker.write_all(&frame(PUBLISH | 0x02, &body)).unwrap();
+        assert_eq!(client.next_message().unwrap(), (String::from("t"), b"300".to_vec()));
+
+        broker.write_all(&frame(PUBLISH, &[0, 9, b't'])).unwrap();
+        assert_eq!(client.next_message().unwrap_err().kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn payloads_must_be_finite_numbers() {
+        assert_eq!(parse_payload(b" 101.325\n"), Some(101.325));
+        assert_eq!(parse_payload(b"NaN"), None);
+        assert_eq!(parse_payload(b"inf"), None);
+        assert_eq!(parse_payload(b"high"), None);
+        assert_eq!(parse_payload(&[0xff]), None);
+    }
+}
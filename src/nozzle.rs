@@ -0,0 +1,187 @@
+use aga8::composition::Composition;
+use colored::Colorize;
+
+use crate::csv::write_csv;
+use crate::process::isentropic_state;
+use crate::quality::R;
+use crate::{
+    get_temperature, print_gas_state, read_f64, read_input, read_pressure, read_temperature, try_gas_state, Pressure, ProgramState, Temperature,
+};
+
+use std::f64::consts::PI;
+
+pub struct CriticalFlow {
+    // Real-gas critical flow function C* (ISO 9300)
+    pub c_star: f64,
+    pub pressure_ratio: f64,
+    pub temperature_ratio: f64,
+    // Choked mass flux in kg/(m2-s)
+    pub mass_flux: f64,
+    // Throat velocity (local speed of sound) in m/s
+    pub velocity: f64,
+}
+
+// Isentropic expansion from stagnation conditions p0, t0 to the throat where the flow
+// velocity equals the local speed of sound.
+pub fn critical_flow(gas_comp: &Composition, p0: Pressure, t0: Temperature) -> Option<CriticalFlow> {
+    let (p0, t0) = (p0.kpa(), t0.kelvin());
+    let inlet = try_gas_state(gas_comp, p0, t0)?;
+    let mm = inlet.mm;
+
+    // Difference between the squared flow velocity (from the enthalpy drop) and the
+    // squared speed of sound at the given pressure ratio.
+    let mach_residual = |ratio: f64| -> Option<(f64, f64, f64, f64)> {
+        let throat = isentropic_state(gas_comp, p0 * ratio, inlet.s, t0 * ratio.powf(0.25))?;
+        let velocity_sq = 2.0 * (inlet.h - throat.h) * 1000.0 / mm;
+        Some((velocity_sq - throat.w * throat.w, throat.t, throat.d, velocity_sq.max(0.0).sqrt()))
+    };
+
+    // Bracket around the ideal gas critical pressure ratio, widened if it does not
+    // contain the root.  Low ratios from dense states can fall into the two-phase region.
+    let k = inlet.kappa;
+    let ideal_ratio = (2.0 / (k + 1.0)).powf(k / (k - 1.0));
+    let mut low = (0.75 * ideal_ratio).max(0.2);
+    let mut high = (1.25 * ideal_ratio).min(0.99);
+    let (mut f_low, ..) = mach_residual(low)?;
+    let (f_high, ..) = mach_residual(high)?;
+    if f_low.signum() == f_high.signum() {
+        low = 0.2;
+        high = 0.99;
+        f_low = mach_residual(low)?.0;
+    }
+    for _ in 0..60 {
+        let mid = 0.5 * (low + high);
+        let (f_mid, ..) = mach_residual(mid)?;
+        if f_mid.signum() == f_low.signum() {
+            low = mid;
+            f_low = f_mid;
+        } else {
+            high = mid;
+        }
+        if high - low < 1.0e-9 {
+            break;
+        }
+    }
+    let ratio = 0.5 * (low + high);
+    let (_, t_throat, d_throat, velocity) = mach_residual(ratio)?;
+    let mass_flux = d_throat * mm * velocity;
+    let c_star = mass_flux * (R * t0 / (mm / 1000.0)).sqrt() / (p0 * 1000.0);
+
+    Some(CriticalFlow {
+        c_star,
+        pressure_ratio: ratio,
+        temperature_ratio: t_throat / t0,
+        mass_flux,
+        velocity,
+    })
+}
+
+pub fn critical_flow_table(program_state: &mut ProgramState) {
+    outln!();
+    outln!("{}", "Critical Flow Function C* Table".cyan().bold());
+    let p_start = read_pressure(program_state, "start upstream pressure");
+    let p_end = read_pressure(program_state, "end upstream pressure");
+    let p_steps = read_f64("Enter number of pressure steps:").max(1.0) as usize;
+    let t_start = read_temperature(program_state, "start upstream temperature");
+    let t_end = read_temperature(program_state, "end upstream temperature");
+    let t_steps = read_f64("Enter number of temperature steps:").max(1.0) as usize;
+
+    let p_label = format!("P0 ({})", program_state.units.pressure.label());
+    let t_label = format!("T0 ({})", program_state.units.temp.label());
+    let headers = [p_label.as_str(), t_label.as_str(), "C*", "P*/P0", "T*/T0", "G* (kg/m2-s)"];
+    let mut rows = Vec::new();
+
+    outln!();
+    outln!("{:>12} {:>12} {:>10} {:>10} {:>10} {:>14}", headers[0], headers[1], headers[2], headers[3], headers[4], headers[5]);
+    for i in 0..=p_steps {
+        let p0 = p_start + (p_end - p_start) * (i as f64 / p_steps as f64);
+        for j in 0..=t_steps {
+            let t0 = t_start + (t_end - t_start) * (j as f64 / t_steps as f64);
+            let p_display = p0.value(program_state.units.pressure);
+            let t_display = t0.value(program_state.units.temp);
+            match critical_flow(&program_state.gas_comp, p0, t0) {
+                Some(flow) => {
+                    outln!("{:12.4} {:12.4} {:10.6} {:10.6} {:10.6} {:14.4}",
+                        p_display, t_display, flow.c_star, flow.pressure_ratio, flow.temperature_ratio, flow.mass_flux);
+                    rows.push(vec![p_display, t_display, flow.c_star, flow.pressure_ratio, flow.temperature_ratio, flow.mass_flux]);
+                },
+                None => outln!("{:12.4} {:12.4} {:>10}", p_display, t_display, "failed"),
+            }
+        }
+    }
+
+    outln!();
+    outln!("Enter CSV file path to export the table (leave blank to skip):");
+    let path = read_input();
+    if !path.is_empty() {
+        match write_csv(&path, &headers, &rows) {
+            Ok(()) => outln!("Table written to {}", path),
+            Err(err) => outln!("{}", format!("** {} **", err).red().bold()),
+        }
+    }
+
+    print_gas_state(program_state);
+}
+
+// Choked flow through a sonic nozzle with the current state as stagnation conditions.
+pub fn sonic_nozzle(program_state: &mut ProgramState) {
+    outln!();
+    outln!("{}", "Sonic Nozzle (Choked Flow)".magenta().bold());
+    outln!("Stagnation conditions are the current state.");
+    let p0 = Pressure::from_kpa(program_state.gas_state.p);
+    let t0 = Temperature::from_kelvin(program_state.gas_state.t);
+    let flow = match critical_flow(&program_state.gas_comp, p0, t0) {
+        Some(flow) => flow,
+        None => {
+            outln!("{}", "** Unable to calculate the critical flow **".red().bold());
+            print_gas_state(program_state);
+            return;
+        }
+    };
+
+    let p_unit = program_state.units.pressure.label();
+    let t_unit = program_state.units.temp.label();
+    outln!();
+    outln!("{:<30} {:10.6} {:10}", "Critical Flow Function C*: ", flow.c_star, "[]");
+    outln!("{:<30} {:10.6} {:10}", "Critical Pressure Ratio: ", flow.pressure_ratio, "[]");
+    outln!("{:<30} {:10.6} {:10}", "Critical Temperature Ratio: ", flow.temperature_ratio, "[]");
+    outln!("{:<30} {:10.4} {:10}", "Throat Pressure: ", (p0 * flow.pressure_ratio).value(program_state.units.pressure), p_unit);
+    outln!("{:<30} {:10.4} {:10}", "Throat Temperature: ", get_temperature(t0.kelvin() * flow.temperature_ratio, program_state.units.temp), t_unit);
+    outln!("{:<30} {:10.4} {:10}", "Throat Velocity: ", flow.velocity, "m/s");
+    outln!("{:<30} {:10.4} {:10}", "Choked Mass Flux: ", flow.mass_flux, "kg/(m2-s)");
+
+    outln!();
+    let diameter = read_f64("Enter throat diameter (mm, 0 to skip):") / 1000.0;
+    if diameter > 0.0 {
+        let cd = read_f64("Enter discharge coefficient (e.g. 0.99 toroidal throat venturi):");
+        let p_back = read_pressure(program_state, "downstream (back) pressure");
+        let mass_flow = cd * flow.mass_flux * PI * diameter * diameter / 4.0;
+        outln!();
+        outln!("{:<30} {:10.6} {:10}", "Mass Flow: ", mass_flow, "kg/s");
+        if let Some(base) = try_gas_state(&program_state.gas_comp, program_state.base_pressure.kpa(), program_state.base_temperature.kelvin()) {
+            let base_density = base.d * base.mm;
+            outln!("{:<30} {:10.4} {:10}", "Flow at Base Conditions: ", mass_flow / base_density * 3600.0, "m3/h");
+        }
+        if p_back > p0 * flow.pressure_ratio {
+            outln!("{}", "** Back pressure is above the critical pressure - the nozzle is not choked **".red().bold());
+        }
+    }
+
+    print_gas_state(program_state);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // ISO 9300 critical flow function of nitrogen near atmospheric pressure, close to the
+    // ideal gas value 0.6847 for k 1.4 with the critical pressure ratio 0.5283
+    #[test]
+    fn nitrogen_critical_flow_function() {
+        let gas_comp = Composition { nitrogen: 1.0, ..Default::default() };
+        let flow = critical_flow(&gas_comp, Pressure::from_kpa(101.325), Temperature::from_kelvin(293.15)).unwrap();
+        assert!((flow.c_star - 0.6847).abs() < 1.0e-3);
+        assert!((flow.pressure_ratio - 0.5283).abs() < 1.0e-3);
+        assert!((flow.temperature_ratio - 2.0 / 2.4).abs() < 1.0e-3);
+    }
+}
@@ -0,0 +1,291 @@
+use colored::Colorize;
+
+use crate::{print_gas_state, read_f64, read_input, try_gas_state, Pressure, ProgramState, Temperature};
+
+use std::f64::consts::PI;
+
+// Reference temperature of the measured diameters in K (AGA-3 and ISO 5167)
+const REFERENCE_TEMPERATURE: f64 = 293.15;
+// Linear thermal expansion coefficients in 1/K (AGA-3)
+const MATERIALS: [(&str, f64); 3] = [
+    ("304 / 316 stainless steel", 16.65e-6),
+    ("Monel", 14.31e-6),
+    ("Carbon steel", 11.16e-6),
+];
+
+#[derive(Clone, Copy)]
+pub struct OrificeInputs {
+    // Pipe internal and orifice bore diameters in m at flowing temperature
+    pub pipe_diameter: f64,
+    pub bore_diameter: f64,
+    // Upstream static pressure and differential pressure
+    pub p1: Pressure,
+    pub dp: Pressure,
+    // Upstream density in kg/m3, isentropic exponent and viscosity in Pa-s
+    pub rho1: f64,
+    pub kappa: f64,
+    pub viscosity: f64,
+}
+
+pub struct OrificeResult {
+    pub beta: f64,
+    pub discharge_coefficient: f64,
+    pub expansion_factor: f64,
+    pub reynolds: f64,
+    // Mass flow in kg/s
+    pub mass_flow: f64,
+}
+
+// Reader-Harris/Gallagher discharge coefficient for flange taps (ISO 5167-2, AGA-3)
+fn discharge_coefficient(beta: f64, reynolds: f64, pipe_diameter: f64) -> f64 {
+    let l1 = 0.0254 / pipe_diameter;
+    let m2 = 2.0 * l1 / (1.0 - beta);
+    let a = (19_000.0 * beta / reynolds).powf(0.8);
+    let beta4 = beta.powi(4);
+    let mut c = 0.5961 + 0.0261 * beta * beta - 0.216 * beta.powi(8)
+        + 0.000521 * (1.0e6 * beta / reynolds).powf(0.7)
+        + (0.0188 + 0.0063 * a) * beta.powf(3.5) * (1.0e6 / reynolds).powf(0.3)
+        + (0.043 + 0.080 * (-10.0 * l1).exp() - 0.123 * (-7.0 * l1).exp()) * (1.0 - 0.11 * a) * beta4 / (1.0 - beta4)
+        - 0.031 * (m2 - 0.8 * m2.powf(1.1)) * beta.powf(1.3);
+    // Small pipe correction below 71.12 mm
+    if pipe_diameter < 0.07112 {
+        c += 0.011 * (0.75 - beta) * (2.8 - pipe_diameter / 0.0254);
+    }
+    c
+}
+
+// Orifice mass flow, iterating the discharge coefficient on the pipe Reynolds number.
+pub fn orifice_flow(inputs: &OrificeInputs) -> OrificeResult {
+    let d = inputs.bore_diameter;
+    let beta = d / inputs.pipe_diameter;
+    let beta4 = beta.powi(4);
+    let (p1, dp) = (inputs.p1.kpa(), inputs.dp.kpa());
+    let expansion_factor = 1.0 - (0.351 + 0.256 * beta4 + 0.93 * beta.powi(8)) * (1.0 - ((p1 - dp) / p1).powf(1.0 / inputs.kappa));
+    // Flow without the discharge coefficient, kg/s
+    let ideal_flow = expansion_factor / (1.0 - beta4).sqrt() * PI / 4.0 * d * d * (2.0 * dp * 1000.0 * inputs.rho1).sqrt();
+
+    let mut reynolds = 1.0e6;
+    let mut c = discharge_coefficient(beta, reynolds, inputs.pipe_diameter);
+    for _ in 0..50 {
+        reynolds = 4.0 * c * ideal_flow / (PI * inputs.viscosity * inputs.pipe_diameter);
+        let c_next = discharge_coefficient(beta, reynolds, inputs.pipe_diameter);
+        let converged = (c_next - c).abs() < 1.0e-10;
+        c = c_next;
+        if converged {
+            break;
+        }
+    }
+
+    OrificeResult {
+        beta,
+        discharge_coefficient: c,
+        expansion_factor,
+        reynolds,
+        mass_flow: c * ideal_flow,
+    }
+}
+
+// Diameter at the flowing temperature t from the diameter measured at 20 C
+pub fn thermal_expansion(diameter: f64, alpha: f64, t: Temperature) -> f64 {
+    diameter * (1.0 + alpha * (t.kelvin() - REFERENCE_TEMPERATURE))
+}
+
+fn read_material(part: &str) -> f64 {
+    loop {
+        outln!("Select {} material:", part);
+        for (i, (name, _)) in MATERIALS.iter().enumerate() {
+            outln!("{} - {}", i + 1, name);
+        }
+        if let Ok(choice) = read_input().parse::<usize>() && (1..=MATERIALS.len()).contains(&choice) {
+            return MATERIALS[choice - 1].1;
+        }
+    }
+}
+
+// Prompts for the pipe and bore diameters measured at 20 C and their materials, and
+// returns both diameters in m corrected to the flowing temperature t.
+fn read_meter_geometry(t: Temperature) -> (f64, f64) {
+    let pipe_reference = read_f64("Enter pipe internal diameter measured at 20 C (mm):") / 1000.0;
+    let pipe_alpha = read_material("pipe");
+    let bore_reference = read_f64("Enter orifice bore diameter measured at 20 C (mm):") / 1000.0;
+    let plate_alpha = read_material("orifice plate");
+    let pipe_diameter = thermal_expansion(pipe_reference, pipe_alpha, t);
+    let bore_diameter = thermal_expansion(bore_reference, plate_alpha, t);
+    outln!("{:<30} {:10.4} {:10}", "Flowing Pipe Diameter: ", pipe_diameter * 1000.0, "mm");
+    outln!("{:<30} {:10.4} {:10}", "Flowing Bore Diameter: ", bore_diameter * 1000.0, "mm");
+    (pipe_diameter, bore_diameter)
+}
+
+// Differential pressure that gives the mass flow (kg/s); inputs.dp is ignored.
+pub fn differential_for_flow(inputs: &OrificeInputs, mass_flow: f64) -> Option<Pressure> {
+    let p1 = inputs.p1.kpa();
+    let mut dp = 0.01 * p1;
+    for _ in 0..50 {
+        let flow = orifice_flow(&OrificeInputs { dp: Pressure::from_kpa(dp), ..*inputs }).mass_flow;
+        let dp_next = (dp * (mass_flow / flow).powi(2)).min(0.5 * (dp + p1));
+        if (dp_next - dp).abs() < 1.0e-9 * dp {
+            return Some(Pressure::from_kpa(dp_next));
+        }
+        dp = dp_next;
+    }
+    None
+}
+
+// Standard transmitter calibrated upper range values in kPa
+const TRANSMITTER_RANGES: [f64; 14] = [0.5, 1.0, 2.0, 2.5, 5.0, 10.0, 20.0, 25.0, 50.0, 100.0, 200.0, 250.0, 500.0, 1000.0];
+// Lowest usable fraction of the calibrated range
+const MIN_RANGE_FRACTION: f64 = 0.1;
+
+pub fn dp_transmitter_ranges(program_state: &mut ProgramState) {
+    outln!();
+    outln!("{}", "DP Transmitter Range Advisor (orifice meter)".magenta().bold());
+    outln!("Upstream tap conditions are the current state.");
+    let (pipe_diameter, bore_diameter) = read_meter_geometry(Temperature::from_kelvin(program_state.gas_state.t));
+    let viscosity = read_f64("Enter gas dynamic viscosity (cP, e.g. 0.0108 natural gas):") / 1000.0;
+    let flows = [
+        ("Minimum", read_f64("Enter minimum mass flow (kg/s):")),
+        ("Normal", read_f64("Enter normal mass flow (kg/s):")),
+        ("Maximum", read_f64("Enter maximum mass flow (kg/s):")),
+    ];
+
+    let upstream = &program_state.gas_state;
+    if bore_diameter <= 0.0 || bore_diameter >= pipe_diameter || viscosity <= 0.0 || flows.iter().any(|(_, flow)| *flow <= 0.0) {
+        outln!("{}", "** Invalid meter or flow inputs **".red().bold());
+        print_gas_state(program_state);
+        return;
+    }
+    let inputs = OrificeInputs {
+        pipe_diameter,
+        bore_diameter,
+        p1: Pressure::from_kpa(upstream.p),
+        dp: Pressure::from_kpa(0.0),
+        rho1: upstream.d * upstream.mm,
+        kappa: upstream.kappa,
+        viscosity,
+    };
+
+    outln!();
+    outln!("{:<10} {:>14} {:>10}", "Flow", "Mass kg/s", "DP kPa");
+    let mut dps = Vec::new();
+    for (label, flow) in flows {
+        match differential_for_flow(&inputs, flow) {
+            Some(dp) => {
+                outln!("{:<10} {:14.4} {:10.3}", label, flow, dp.kpa());
+                dps.push(dp.kpa());
+            },
+            None => outln!("{:<10} {:14.4} {:>10}", label, flow, "failed"),
+        }
+    }
+    if dps.len() < flows.len() {
+        print_gas_state(program_state);
+        return;
+    }
+    let (dp_min, dp_max) = (dps[0], dps[2]);
+
+    outln!();
+    let Some(&high_range) = TRANSMITTER_RANGES.iter().find(|range| **range >= dp_max) else {
+        outln!("{}", "** Maximum DP exceeds the largest standard range - use a smaller beta ratio **".red().bold());
+        print_gas_state(program_state);
+        return;
+    };
+    outln!("{:<30} {:10.3} {:10}", "Calibrated Range: ", high_range, "kPa");
+    outln!("{:<30} {:10.1} {:10}", "Maximum Flow DP: ", dp_max / high_range * 100.0, "% of range");
+    outln!("{:<30} {:10.1} {:10}", "Minimum Flow DP: ", dp_min / high_range * 100.0, "% of range");
+    if dp_min >= MIN_RANGE_FRACTION * high_range {
+        outln!("A single transmitter covers the flow range.");
+    } else {
+        // Low range transmitter sized so the minimum flow sits above its lowest usable DP
+        match TRANSMITTER_RANGES.iter().rev().find(|range| MIN_RANGE_FRACTION * **range <= dp_min && **range < high_range) {
+            Some(&low_range) => {
+                let switch_dp = 0.9 * low_range;
+                outln!("{}", "* Minimum flow is below 10 % of the calibrated range - stacked transmitters recommended".yellow());
+                outln!("{:<30} {:10.3} {:10}", "Low Range Transmitter: ", low_range, "kPa");
+                outln!("{:<30} {:10.3} {:10}", "High Range Transmitter: ", high_range, "kPa");
+                outln!("{:<30} {:10.3} {:10}", "Switch Over DP: ", switch_dp, "kPa");
+                if switch_dp < MIN_RANGE_FRACTION * high_range {
+                    outln!("{}", "* Switch over is below 10 % of the high range - a third transmitter may be required".yellow());
+                }
+            },
+            None => outln!("{}", "** Minimum flow DP is below the smallest standard range **".red().bold()),
+        }
+    }
+
+    print_gas_state(program_state);
+}
+
+pub fn orifice_meter(program_state: &mut ProgramState) {
+    outln!();
+    outln!("{}", "Orifice Meter Flow (AGA-3 / ISO 5167, flange taps)".magenta().bold());
+    outln!("Upstream tap conditions are the current state.");
+    let (pipe_diameter, bore_diameter) = read_meter_geometry(Temperature::from_kelvin(program_state.gas_state.t));
+    let dp = Pressure::from_kpa(read_f64("Enter differential pressure (kPa):"));
+    let viscosity = read_f64("Enter gas dynamic viscosity (cP, e.g. 0.0108 natural gas):") / 1000.0;
+
+    let upstream = &program_state.gas_state;
+    if bore_diameter <= 0.0 || bore_diameter >= pipe_diameter || dp.kpa() <= 0.0 || dp.kpa() >= upstream.p || viscosity <= 0.0 {
+        outln!("{}", "** Invalid orifice inputs **".red().bold());
+        print_gas_state(program_state);
+        return;
+    }
+    let result = orifice_flow(&OrificeInputs {
+        pipe_diameter,
+        bore_diameter,
+        p1: Pressure::from_kpa(upstream.p),
+        dp,
+        rho1: upstream.d * upstream.mm,
+        kappa: upstream.kappa,
+        viscosity,
+    });
+
+    outln!();
+    outln!("{:<30} {:10.4} {:10}", "Beta Ratio: ", result.beta, "[]");
+    outln!("{:<30} {:10.6} {:10}", "Discharge Coefficient Cd: ", result.discharge_coefficient, "[]");
+    outln!("{:<30} {:10.6} {:10}", "Expansion Factor Y: ", result.expansion_factor, "[]");
+    outln!("{:<30} {:10.0} {:10}", "Pipe Reynolds Number: ", result.reynolds, "[]");
+    outln!("{:<30} {:10.4} {:10}", "Mass Flow: ", result.mass_flow, "kg/s");
+    if let Some(base) = try_gas_state(&program_state.gas_comp, program_state.base_pressure.kpa(), program_state.base_temperature.kelvin()) {
+        let base_density = base.d * base.mm;
+        outln!("{:<30} {:10.4} {:10}", "Flow at Base Conditions: ", result.mass_flow / base_density * 3600.0, "m3/h");
+    }
+    if !(0.1..=0.75).contains(&result.beta) {
+        outln!("{}", "* Beta ratio is outside the 0.1 - 0.75 range of the discharge coefficient equation".yellow());
+    }
+    if dp.kpa() / upstream.p > 0.25 {
+        outln!("{}", "* Differential to static pressure ratio is above 0.25 - expansion factor is extrapolated".yellow());
+    }
+
+    print_gas_state(program_state);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Reader-Harris/Gallagher flange tap coefficients tabulated in ISO 5167-2 Annex A for
+    // D 100 mm at ReD 10^6: 0.6031 at beta 0.5 and 0.6060 at beta 0.6.  Below 71.12 mm the
+    // small pipe term applies: 0.6051 for D 50 mm at beta 0.5.
+    #[test]
+    fn reader_harris_gallagher_flange_taps() {
+        assert!((discharge_coefficient(0.5, 1.0e6, 0.1) - 0.6031).abs() < 1.0e-4);
+        assert!((discharge_coefficient(0.6, 1.0e6, 0.1) - 0.6060).abs() < 1.0e-4);
+        assert!((discharge_coefficient(0.5, 1.0e6, 0.05) - 0.6051).abs() < 1.0e-4);
+    }
+
+    // Differential pressure for a flow inverts the orifice flow
+    #[test]
+    fn differential_for_flow_round_trip() {
+        let inputs = OrificeInputs {
+            pipe_diameter: 0.1023,
+            bore_diameter: 0.05,
+            p1: Pressure::from_kpa(5000.0),
+            dp: Pressure::from_kpa(25.0),
+            rho1: 38.0,
+            kappa: 1.3,
+            viscosity: 1.1e-5,
+        };
+        let flow = orifice_flow(&inputs);
+        assert!(flow.expansion_factor < 1.0 && flow.expansion_factor > 0.99);
+        let dp = differential_for_flow(&inputs, flow.mass_flow).unwrap();
+        assert!((dp.kpa() - 25.0).abs() < 1.0e-6);
+    }
+}
@@ -0,0 +1,140 @@
+use comp_perf::frontend::{Console, Frontend};
+use std::io::IsTerminal;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, PoisonError};
+
+// Writes a line through the current front end, as println! would to stdout.
+macro_rules! outln {
+    () => {
+        $crate::output::write_line("")
+    };
+    ($($arg:tt)*) => {
+        $crate::output::write_line(&format!($($arg)*))
+    };
+}
+
+// Writes text without a line break through the current front end, as print! would.
+macro_rules! out {
+    ($($arg:tt)*) => {
+        $crate::output::write(&format!($($arg)*))
+    };
+}
+
+// Quiet mode lets another process drive the program through a pipe: menus, prompts and
+// colors are left out and only the results are written.
+static QUIET: AtomicBool = AtomicBool::new(false);
+// All colored text goes through the colored crate, so one override turns the ANSI
+// escapes on or off everywhere.
+static COLOR: AtomicBool = AtomicBool::new(true);
+// Menus, prompts and reports are read and written through one front end, the Console
+// unless set_frontend replaces it.
+static FRONTEND: Mutex<Option<Box<dyn Frontend + Send>>> = Mutex::new(None);
+
+#[cfg(test)]
+pub fn set_frontend(frontend: Box<dyn Frontend + Send>) {
+    *FRONTEND.lock().unwrap_or_else(PoisonError::into_inner) = Some(frontend);
+}
+
+fn with_frontend<R>(f: impl FnOnce(&mut dyn Frontend) -> R) -> R {
+    let mut frontend = FRONTEND.lock().unwrap_or_else(PoisonError::into_inner);
+    match frontend.as_deref_mut() {
+        Some(frontend) => f(frontend),
+        None => f(&mut Console),
+    }
+}
+
+// None at the end of input
+pub fn read_line() -> Option<String> {
+    with_frontend(|frontend| frontend.read_line())
+}
+
+pub fn write(text: &str) {
+    with_frontend(|frontend| frontend.write(text));
+}
+
+pub fn write_line(text: &str) {
+    with_frontend(|frontend| frontend.write_line(text));
+}
+
+pub fn quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+    if quiet {
+        set_color(false);
+    }
+}
+
+pub fn color() -> bool {
+    COLOR.load(Ordering::Relaxed)
+}
+
+pub fn set_color(color: bool) {
+    COLOR.store(color, Ordering::Relaxed);
+    colored::control::set_override(color);
+}
+
+// NO_COLOR (https://no-color.org) is set to any non-empty value
+fn no_color_env() -> bool {
+    std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty())
+}
+
+// Colors are off with --no-color, NO_COLOR or when stdout is redirected.
+pub fn select_color(args: &[String]) {
+    set_color(!args.iter().any(|arg| arg == "--no-color") && !no_color_env() && std::io::stdout().is_terminal());
+}
+
+// Quiet when stdin is not a terminal or with --quiet; --interactive keeps the menus for
+// piped input.
+pub fn select_mode(args: &[String]) {
+    let flag = |name: &str| args.iter().any(|arg| arg == name);
+    set_quiet(flag("--quiet") || (!flag("--interactive") && !std::io::stdin().is_terminal()));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{get_gas_comp, initial_program_state, set_gas_comp, GasComp};
+    use comp_perf::components::comp_to_array;
+    use std::collections::VecDeque;
+    use std::sync::Arc;
+
+    // Answers from a list of lines and keeps everything written
+    struct Scripted {
+        input: VecDeque<String>,
+        output: Arc<Mutex<String>>,
+    }
+
+    impl Frontend for Scripted {
+        fn read_line(&mut self) -> Option<String> {
+            self.input.pop_front()
+        }
+
+        fn write(&mut self, text: &str) {
+            self.output.lock().unwrap().push_str(text);
+        }
+
+        fn write_line(&mut self, text: &str) {
+            self.write(text);
+            self.write("\n");
+        }
+    }
+
+    #[test]
+    fn menus_are_driven_through_the_frontend() {
+        let output = Arc::new(Mutex::new(String::new()));
+        let input = ["zz", "9"].map(String::from).into();
+        set_frontend(Box::new(Scripted { input, output: Arc::clone(&output) }));
+        let mut program_state = initial_program_state();
+        set_gas_comp(&mut program_state);
+        set_frontend(Box::new(Console));
+
+        assert_eq!(program_state.gas, "Hydrogen");
+        assert_eq!(comp_to_array(&program_state.gas_comp), comp_to_array(&get_gas_comp(GasComp::Hydrogen)));
+        let output = output.lock().unwrap();
+        assert_eq!(output.matches("Select Gas:").count(), 2);
+        assert!(output.contains("Hydrogen"));
+    }
+}
@@ -0,0 +1,532 @@
+use aga8::composition::Composition;
+use colored::Colorize;
+
+use comp_perf::thermo::{ideal_gas_state, R_DETAIL};
+
+use crate::components::{
+    array_to_comp, comp_to_array, ACENTRIC_FACTOR, COMPONENT_LABELS, CRITICAL_PRESSURE, CRITICAL_TEMPERATURE, NUM_COMPONENTS,
+};
+use crate::{get_pressure, get_temperature, get_temperature_difference, print_gas_state, ProgramState};
+
+use std::f64::consts::{PI, SQRT_2};
+
+// Gas constant in kPa l/(mol K), so b is in l/mol with pressures in kPa
+const R: f64 = 8.314462618;
+const WATER: usize = 17;
+// Phase boundaries are searched from above the highest critical temperature down to
+// T_MIN (K) in steps of T_STEP, then refined by bisection
+const T_MIN: f64 = 60.0;
+const T_STEP: f64 = 2.0;
+const BISECTIONS: usize = 30;
+const MAX_ITERATIONS: usize = 300;
+// Pressures (kPa) searched for the cricondentherm
+const P_RANGE: (f64, f64) = (100.0, 20000.0);
+const P_STEPS: usize = 30;
+
+// Margin above the dew point (K) below which an outlet is flagged
+pub const DEW_POINT_MARGIN: f64 = 5.0;
+
+fn is_hydrocarbon(i: usize) -> bool {
+    i == 0 || (3..=13).contains(&i)
+}
+
+// Binary interaction parameters of nitrogen, carbon dioxide and hydrogen sulfide with the
+// hydrocarbons; all other pairs are zero
+fn interaction(i: usize, j: usize) -> f64 {
+    let inert = |k| match k {
+        1 => 0.08,
+        2 => 0.12,
+        18 => 0.07,
+        _ => 0.0,
+    };
+    match (is_hydrocarbon(i), is_hydrocarbon(j)) {
+        (true, false) => inert(j),
+        (false, true) => inert(i),
+        _ => 0.0,
+    }
+}
+
+// Real roots of the Peng-Robinson cubic in Z above the co-volume B
+fn z_roots(a: f64, b: f64) -> Vec<f64> {
+    let (c2, c1, c0) = (b - 1.0, a - 3.0 * b * b - 2.0 * b, -(a * b - b * b - b * b * b));
+    let p = c1 - c2 * c2 / 3.0;
+    let q = 2.0 * c2.powi(3) / 27.0 - c2 * c1 / 3.0 + c0;
+    let discriminant = q * q / 4.0 + p.powi(3) / 27.0;
+    let roots = if discriminant >= 0.0 {
+        let s = discriminant.sqrt();
+        vec![(-q / 2.0 + s).cbrt() + (-q / 2.0 - s).cbrt()]
+    } else {
+        let r = (-p / 3.0).sqrt();
+        let phi = (-q / (2.0 * r.powi(3))).clamp(-1.0, 1.0).acos();
+        (0..3).map(|k| 2.0 * r * ((phi - 2.0 * PI * k as f64) / 3.0).cos()).collect()
+    };
+    roots.into_iter().map(|root| root - c2 / 3.0).filter(|z| *z > b).collect()
+}
+
+// Residual Gibbs energy over RT, the log of the fugacity coefficient of the mixture
+fn residual_gibbs(z: f64, a: f64, b: f64) -> f64 {
+    z - 1.0 - (z - b).ln() - a / (2.0 * SQRT_2 * b) * ((z + (1.0 + SQRT_2) * b) / (z + (1.0 - SQRT_2) * b)).ln()
+}
+
+// Root with the lower Gibbs energy.  The cubic is -2B^2 at Z = B so there is always a root
+// above B; NaN only if rounding loses it.
+fn stable_root(a: f64, b: f64) -> f64 {
+    z_roots(a, b).into_iter().min_by(|z1, z2| residual_gibbs(*z1, a, b).total_cmp(&residual_gibbs(*z2, a, b))).unwrap_or(f64::NAN)
+}
+
+// Root of the Rachford-Rice equation for the vapor fraction with K-values k, by bisection
+// between the poles, clamped to 0..1
+fn rachford_rice(z: &[f64], k: &[f64]) -> f64 {
+    let k_max = k.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    let k_min = k.iter().copied().fold(f64::INFINITY, f64::min);
+    if k_min >= 1.0 {
+        return 1.0;
+    }
+    if k_max <= 1.0 {
+        return 0.0;
+    }
+    let (mut low, mut high) = (1.0 / (1.0 - k_max), 1.0 / (1.0 - k_min));
+    let residual = |beta: f64| z.iter().zip(k).map(|(z, k)| z * (k - 1.0) / (1.0 + beta * (k - 1.0))).sum::<f64>();
+    for _ in 0..100 {
+        let mid = 0.5 * (low + high);
+        // The residual falls with the vapor fraction between the poles
+        if residual(mid) > 0.0 {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+    (0.5 * (low + high)).clamp(0.0, 1.0)
+}
+
+// Properties of one phase: molar mass in g/mol, density in mol/l, energies in J/mol,
+// entropy and heat capacities in J/(mol-K), speed of sound in m/s and JT coefficient in
+// K/kPa.  The ideal gas part is the DETAIL ideal gas, so enthalpy and entropy share the
+// AGA8 reference state.
+pub struct PrPhase {
+    pub composition: Composition,
+    pub mm: f64,
+    pub d: f64,
+    pub z: f64,
+    pub h: f64,
+    pub s: f64,
+    pub cp: f64,
+    pub cv: f64,
+    pub w: f64,
+    pub kappa: f64,
+    pub jt: f64,
+}
+
+// Flash result at a pressure and temperature
+#[allow(clippy::large_enum_variant)]
+pub enum PrState {
+    SinglePhase(PrPhase),
+    // Molar vapor fraction and the equilibrium phases
+    TwoPhase { vapor_fraction: f64, vapor: PrPhase, liquid: PrPhase },
+}
+
+// Peng-Robinson (1976) equation of state for the components present in a composition
+pub struct PengRobinson {
+    components: Vec<usize>,
+    // Normalized mole fractions of the components present
+    z: Vec<f64>,
+    // a at the critical temperature, co-volume b and the alpha function slope m
+    ac: Vec<f64>,
+    b: Vec<f64>,
+    m: Vec<f64>,
+}
+
+impl PengRobinson {
+    pub fn new(gas_comp: &Composition) -> PengRobinson {
+        let x = comp_to_array(gas_comp);
+        let components: Vec<usize> = (0..x.len()).filter(|i| x[*i] > 0.0).collect();
+        let total: f64 = components.iter().map(|i| x[*i]).sum();
+        let z = components.iter().map(|i| x[*i] / total).collect();
+        let ac = components.iter().map(|i| 0.45724 * (R * CRITICAL_TEMPERATURE[*i]).powi(2) / CRITICAL_PRESSURE[*i]).collect();
+        let b = components.iter().map(|i| 0.07780 * R * CRITICAL_TEMPERATURE[*i] / CRITICAL_PRESSURE[*i]).collect();
+        let m = components.iter().map(|i| {
+            let w = ACENTRIC_FACTOR[*i];
+            0.37464 + 1.54226 * w - 0.26992 * w * w
+        }).collect();
+        PengRobinson { components, z, ac, b, m }
+    }
+
+    // Square roots of the component a parameters at t (K)
+    fn sqrt_a(&self, t: f64) -> Vec<f64> {
+        self.components.iter().enumerate().map(|(k, i)| {
+            let alpha = 1.0 + self.m[k] * (1.0 - (t / CRITICAL_TEMPERATURE[*i]).sqrt());
+            self.ac[k].sqrt() * alpha
+        }).collect()
+    }
+
+    // Mixture a with its first and second temperature derivatives, and b, for mole
+    // fractions x at t (K)
+    fn mixture(&self, x: &[f64], t: f64) -> (f64, f64, f64, f64) {
+        let sqrt_a = self.sqrt_a(t);
+        // Derivatives of the square roots of the component a parameters
+        let (d1, d2): (Vec<f64>, Vec<f64>) = self.components.iter().enumerate().map(|(k, i)| {
+            let c = self.ac[k].sqrt() * self.m[k] / (t * CRITICAL_TEMPERATURE[*i]).sqrt();
+            (-0.5 * c, 0.25 * c / t)
+        }).unzip();
+        let (mut a, mut da, mut d2a) = (0.0, 0.0, 0.0);
+        for i in 0..x.len() {
+            for j in 0..x.len() {
+                let factor = x[i] * x[j] * (1.0 - interaction(self.components[i], self.components[j]));
+                a += factor * sqrt_a[i] * sqrt_a[j];
+                da += factor * (d1[i] * sqrt_a[j] + sqrt_a[i] * d1[j]);
+                d2a += factor * (d2[i] * sqrt_a[j] + 2.0 * d1[i] * d1[j] + sqrt_a[i] * d2[j]);
+            }
+        }
+        let b = x.iter().zip(&self.b).map(|(xi, bi)| xi * bi).sum();
+        (a, da, d2a, b)
+    }
+
+    fn composition(&self, x: &[f64]) -> Composition {
+        let mut fractions = [0.0; NUM_COMPONENTS];
+        for (i, xi) in self.components.iter().zip(x) {
+            fractions[*i] = *xi;
+        }
+        array_to_comp(&fractions)
+    }
+
+    // Properties of a phase with mole fractions x at p (kPa) and t (K) from the residual
+    // properties of the equation and the DETAIL ideal gas
+    pub fn phase_properties(&self, x: &[f64], p: f64, t: f64) -> Option<PrPhase> {
+        let composition = self.composition(x);
+        let ideal = ideal_gas_state(&composition, t)?;
+        let (a, da, d2a, b) = self.mixture(x, t);
+        let z = stable_root(a * p / (R * t).powi(2), b * p / (R * t));
+        let v = z * R * t / p;
+        let log_term = ((v + (1.0 + SQRT_2) * b) / (v + (1.0 - SQRT_2) * b)).ln() / (2.0 * SQRT_2 * b);
+        let h = ideal.h + p * v - R * t + (t * da - a) * log_term;
+        let s = ideal.s - R_DETAIL * (p / ideal.p).ln() + R * ((v - b) * p / (R * t)).ln() + da * log_term;
+        let cv = ideal.cv + t * d2a * log_term;
+        let attraction = v * v + 2.0 * b * v - b * b;
+        let dp_dt = R / (v - b) - da / attraction;
+        let dp_dv = -R * t / (v - b).powi(2) + 2.0 * a * (v + b) / attraction.powi(2);
+        let cp = cv - t * dp_dt * dp_dt / dp_dv;
+        // kPa l/mol is J/mol, over the molar mass in kg/mol
+        let w = (-cp / cv * v * v * dp_dv / (ideal.mm / 1000.0)).sqrt();
+        let kappa = w * w * ideal.mm / 1000.0 / (p * v);
+        let jt = (-t * dp_dt / dp_dv - v) / cp;
+        Some(PrPhase { composition, mm: ideal.mm, d: 1.0 / v, z, h, s, cp, cv, w, kappa, jt })
+    }
+
+    // Isothermal flash at p (kPa) and t (K) by successive substitution, starting from the
+    // incipient phase of the stability test
+    pub fn flash(&self, p: f64, t: f64) -> Option<PrState> {
+        let Some(incipient) = self.incipient_phase(p, t) else {
+            return self.phase_properties(&self.z, p, t).map(PrState::SinglePhase);
+        };
+        let (_, z_feed) = self.ln_phi(&self.z, p, t);
+        let (_, z_incipient) = self.ln_phi(&incipient, p, t);
+        // K = y/x, with the feed standing in for the phase that is not incipient
+        let mut k: Vec<f64> = if z_incipient < z_feed {
+            self.z.iter().zip(&incipient).map(|(y, x)| y / x).collect()
+        } else {
+            incipient.iter().zip(&self.z).map(|(y, x)| y / x).collect()
+        };
+        let mut beta = 0.5;
+        let (mut x, mut y) = (self.z.clone(), self.z.clone());
+        for _ in 0..MAX_ITERATIONS {
+            beta = rachford_rice(&self.z, &k);
+            x = self.z.iter().zip(&k).map(|(z, k)| z / (1.0 + beta * (k - 1.0))).collect();
+            let total: f64 = x.iter().sum();
+            x.iter_mut().for_each(|xi| *xi /= total);
+            y = x.iter().zip(&k).map(|(x, k)| x * k).collect();
+            let total: f64 = y.iter().sum();
+            y.iter_mut().for_each(|yi| *yi /= total);
+            let (ln_phi_l, _) = self.ln_phi(&x, p, t);
+            let (ln_phi_v, _) = self.ln_phi(&y, p, t);
+            let next: Vec<f64> = ln_phi_l.iter().zip(&ln_phi_v).map(|(l, v)| (l - v).exp()).collect();
+            let change: f64 = next.iter().zip(&k).map(|(next, k)| (next.ln() - k.ln()).powi(2)).sum();
+            k = next;
+            if change < 1.0e-14 || change.is_nan() {
+                break;
+            }
+        }
+        // A flash that collapses to one phase or to the feed is reported as single phase
+        let trivial = k.iter().all(|k| (k - 1.0).abs() < 1.0e-4);
+        if trivial || beta <= 0.0 || beta >= 1.0 {
+            return self.phase_properties(&self.z, p, t).map(PrState::SinglePhase);
+        }
+        let vapor = self.phase_properties(&y, p, t)?;
+        let liquid = self.phase_properties(&x, p, t)?;
+        Some(PrState::TwoPhase { vapor_fraction: beta, vapor, liquid })
+    }
+
+    // Log fugacity coefficients of the components of a phase with mole fractions x at p
+    // (kPa) and t (K), and the compressibility factor of the phase
+    pub fn ln_phi(&self, x: &[f64], p: f64, t: f64) -> (Vec<f64>, f64) {
+        let sqrt_a = self.sqrt_a(t);
+        let n = x.len();
+        // Sum over j of x_j a_ij for each component
+        let sum_a: Vec<f64> = (0..n).map(|i| {
+            (0..n).map(|j| x[j] * sqrt_a[i] * sqrt_a[j] * (1.0 - interaction(self.components[i], self.components[j]))).sum()
+        }).collect();
+        let a_mix: f64 = x.iter().zip(&sum_a).map(|(xi, si)| xi * si).sum();
+        let b_mix: f64 = x.iter().zip(&self.b).map(|(xi, bi)| xi * bi).sum();
+        let a = a_mix * p / (R * t).powi(2);
+        let b = b_mix * p / (R * t);
+        let z = stable_root(a, b);
+        let log_term = ((z + (1.0 + SQRT_2) * b) / (z + (1.0 - SQRT_2) * b)).ln();
+        let ln_phi = (0..n).map(|i| {
+            self.b[i] / b_mix * (z - 1.0) - (z - b).ln()
+                - a / (2.0 * SQRT_2 * b) * (2.0 * sum_a[i] / a_mix - self.b[i] / b_mix) * log_term
+        }).collect();
+        (ln_phi, z)
+    }
+
+    fn wilson_k(&self, p: f64, t: f64) -> Vec<f64> {
+        self.components.iter().map(|i| {
+            CRITICAL_PRESSURE[*i] / p * (5.373 * (1.0 + ACENTRIC_FACTOR[*i]) * (1.0 - CRITICAL_TEMPERATURE[*i] / t)).exp()
+        }).collect()
+    }
+
+    // Mole fractions of an incipient second phase when the feed at p (kPa) and t (K) is
+    // unstable by the tangent plane criterion (Michelsen, 1982).  Liquid-like and
+    // vapor-like trial phases start from Wilson K-values.
+    pub fn incipient_phase(&self, p: f64, t: f64) -> Option<Vec<f64>> {
+        let (ln_phi_z, _) = self.ln_phi(&self.z, p, t);
+        let d: Vec<f64> = self.z.iter().zip(&ln_phi_z).map(|(z, ln_phi)| z.ln() + ln_phi).collect();
+        let k = self.wilson_k(p, t);
+        [true, false].into_iter().find_map(|liquid| {
+            let mut w: Vec<f64> = self.z.iter().zip(&k).map(|(z, k)| if liquid { z / k } else { z * k }).collect();
+            for _ in 0..MAX_ITERATIONS {
+                let total: f64 = w.iter().sum();
+                let x: Vec<f64> = w.iter().map(|w| w / total).collect();
+                let (ln_phi_w, _) = self.ln_phi(&x, p, t);
+                let next: Vec<f64> = d.iter().zip(&ln_phi_w).map(|(d, ln_phi)| (d - ln_phi).exp()).collect();
+                let change: f64 = next.iter().zip(&w).map(|(next, w)| (next.ln() - w.ln()).powi(2)).sum();
+                w = next;
+                if change < 1.0e-14 || change.is_nan() {
+                    break;
+                }
+            }
+            let total: f64 = w.iter().sum();
+            let x: Vec<f64> = w.iter().map(|w| w / total).collect();
+            let trivial = x.iter().zip(&self.z).map(|(x, z)| (x - z).powi(2)).sum::<f64>() < 1.0e-8;
+            (!trivial && total > 1.0 + 1.0e-8).then_some(x)
+        })
+    }
+
+    pub fn is_unstable(&self, p: f64, t: f64) -> bool {
+        self.incipient_phase(p, t).is_some()
+    }
+
+    // Vapor fraction on the phase boundary near t (K), a point just inside the two-phase
+    // region: 1 at a dew point, where the incipient phase is the denser one, and 0 at a
+    // bubble point
+    pub fn boundary_vapor_fraction(&self, p: f64, t: f64) -> Option<f64> {
+        let x = self.incipient_phase(p, t)?;
+        let (_, z_feed) = self.ln_phi(&self.z, p, t);
+        let (_, z_incipient) = self.ln_phi(&x, p, t);
+        Some(if z_incipient < z_feed { 1.0 } else { 0.0 })
+    }
+
+    fn t_max(&self) -> f64 {
+        1.1 * self.components.iter().map(|i| CRITICAL_TEMPERATURE[*i]).fold(T_MIN, f64::max)
+    }
+
+    // First change of phase stability at p (kPa) scanning down from t (K), refined by
+    // bisection.  None when there is no change above T_MIN.
+    pub fn next_boundary(&self, p: f64, mut t: f64) -> Option<f64> {
+        let start = self.is_unstable(p, t);
+        while t - T_STEP > T_MIN {
+            let (mut high, mut low) = (t, t - T_STEP);
+            if self.is_unstable(p, low) != start {
+                for _ in 0..BISECTIONS {
+                    let mid = 0.5 * (high + low);
+                    if self.is_unstable(p, mid) == start {
+                        high = mid;
+                    } else {
+                        low = mid;
+                    }
+                }
+                return Some(0.5 * (high + low));
+            }
+            t = low;
+        }
+        None
+    }
+
+    // Upper phase boundary (K) at p (kPa): the dew point of a gas.  None above the
+    // cricondenbar.
+    pub fn dew_point(&self, p: f64) -> Option<f64> {
+        self.next_boundary(p, self.t_max())
+    }
+
+    // Pressure (kPa) and temperature (K) of the highest dew point, by a scan over the
+    // pressure range refined by golden section search
+    pub fn cricondentherm(&self) -> Option<(f64, f64)> {
+        let ratio = (P_RANGE.1 / P_RANGE.0).powf(1.0 / P_STEPS as f64);
+        let pressures: Vec<f64> = (0..=P_STEPS).map(|k| P_RANGE.0 * ratio.powi(k as i32)).collect();
+        let dew = |p: f64| self.dew_point(p).unwrap_or(f64::NEG_INFINITY);
+        let dew_points: Vec<f64> = pressures.iter().map(|p| dew(*p)).collect();
+        let best = (0..pressures.len()).max_by(|k1, k2| dew_points[*k1].total_cmp(&dew_points[*k2]))?;
+        if dew_points[best] == f64::NEG_INFINITY {
+            return None;
+        }
+        let (mut low, mut high) = (pressures[best.saturating_sub(1)], pressures[(best + 1).min(P_STEPS)]);
+        let golden = (5.0_f64.sqrt() - 1.0) / 2.0;
+        for _ in 0..20 {
+            let p1 = high - golden * (high - low);
+            let p2 = low + golden * (high - low);
+            if dew(p1) > dew(p2) {
+                high = p2;
+            } else {
+                low = p1;
+            }
+        }
+        let p = 0.5 * (low + high);
+        let t = dew(p);
+        Some(if t > dew_points[best] { (p, t) } else { (pressures[best], dew_points[best]) })
+    }
+}
+
+// The composition on a water-free basis, leaving water to the water dew point
+pub fn water_free_model(gas_comp: &Composition) -> PengRobinson {
+    let mut x = comp_to_array(gas_comp);
+    x[WATER] = 0.0;
+    PengRobinson::new(&array_to_comp(&x))
+}
+
+// Model for the hydrocarbon dew point.  None without hydrocarbons heavier than methane.
+pub fn hydrocarbon_model(gas_comp: &Composition) -> Option<PengRobinson> {
+    let x = comp_to_array(gas_comp);
+    if !x.iter().enumerate().any(|(i, xi)| *xi > 0.0 && i != 0 && is_hydrocarbon(i)) {
+        return None;
+    }
+    Some(water_free_model(gas_comp))
+}
+
+pub fn hydrocarbon_dew_point(gas_comp: &Composition, p: f64) -> Option<f64> {
+    hydrocarbon_model(gas_comp)?.dew_point(p)
+}
+
+// Estimated hydrocarbon dew point at p (kPa) and the margin of t (K) above it, with a
+// warning when AGA8 results at the state are likely invalid
+pub fn print_hydrocarbon_dew_point_margin(program_state: &ProgramState, p: f64, t: f64) {
+    let Some(t_dew) = hydrocarbon_dew_point(&program_state.gas_comp, p) else {
+        return;
+    };
+    print_margin(program_state, t, t_dew);
+}
+
+fn print_margin(program_state: &ProgramState, t: f64, t_dew: f64) {
+    let unit = program_state.units.temp;
+    let margin = t - t_dew;
+    outln!("{:<30} {:10.4} {:10}", "HC Dew Point (PR): ", get_temperature(t_dew, unit), unit.label());
+    outln!("{:<30} {:10.4} {:10}", "Margin Above HC Dew Point: ", get_temperature_difference(margin, unit), unit.label());
+    if margin < 0.0 {
+        outln!("{}", "** Below the hydrocarbon dew point - two-phase region, AGA8 results assume single phase **".red().bold());
+    } else if margin < DEW_POINT_MARGIN {
+        outln!("{}", "* Approaching the hydrocarbon dew point".yellow());
+    }
+}
+
+// Hydrocarbon dew point at the current pressure and the cricondentherm of the current
+// composition
+pub fn hydrocarbon_dew_point_report(program_state: &mut ProgramState) {
+    outln!();
+    outln!("{}", "Hydrocarbon Dew Point (Peng-Robinson estimate)".cyan().bold());
+    let Some(model) = hydrocarbon_model(&program_state.gas_comp) else {
+        outln!("{}", "** No hydrocarbons heavier than methane - no hydrocarbon dew point **".red().bold());
+        print_gas_state(program_state);
+        return;
+    };
+    let state = &program_state.gas_state;
+    let units = program_state.units;
+    outln!("{:<30} {:10.4} {:10}", "Pressure: ", get_pressure(state.p, units.pressure), units.pressure.label());
+    match model.dew_point(state.p) {
+        Some(t_dew) => print_margin(program_state, state.t, t_dew),
+        None => outln!("{}", "* No dew point at this pressure - above the cricondenbar".yellow()),
+    }
+    match model.cricondentherm() {
+        Some((p, t)) => {
+            outln!("{:<30} {:10.4} {:10}", "Cricondentherm: ", get_temperature(t, units.temp), units.temp.label());
+            outln!("{:<30} {:10.4} {:10}", "Cricondentherm Pressure: ", get_pressure(p, units.pressure), units.pressure.label());
+        },
+        None => outln!("{}", "* No dew point found in the pressure range".yellow()),
+    }
+    outln!("{}", "* Cubic equation estimate; heavy end characterization dominates the hydrocarbon dew point".yellow());
+    print_gas_state(program_state);
+}
+
+fn print_phase(phase: &PrPhase) {
+    outln!("{:<30} {:10.4} {:10}", "Density: ", phase.d, "mol/l");
+    outln!("{:<30} {:10.4} {:10}", "Density: ", phase.d * phase.mm, "kg/m3");
+    outln!("{:<30} {:10.4} {:10}", "Molar Mass: ", phase.mm, "g/mol");
+    outln!("{:<30} {:10.4} {:10}", "Compressibility Z: ", phase.z, "[]");
+    outln!("{:<30} {:10.4} {:10}", "Enthalpy: ", phase.h, "J/mol");
+    outln!("{:<30} {:10.4} {:10}", "Entropy: ", phase.s, "J/(mol-K)");
+    outln!("{:<30} {:10.4} {:10}", "Cp: ", phase.cp, "J/(mol-K)");
+    outln!("{:<30} {:10.4} {:10}", "Cv: ", phase.cv, "J/(mol-K)");
+    outln!("{:<30} {:10.4} {:10}", "Speed of Sound w: ", phase.w, "m/s");
+    outln!("{:<30} {:10.4} {:10}", "Isentropic Exponent k: ", phase.kappa, "[]");
+    outln!("{:<30} {:10.4} {:10}", "Joule-Thompson Coefficient: ", phase.jt, "K/kPa");
+}
+
+// Peng-Robinson state at the current pressure and temperature, with the phase split when
+// the gas is inside the phase envelope
+pub fn peng_robinson_report(program_state: &mut ProgramState) {
+    let state = &program_state.gas_state;
+    let units = program_state.units;
+    outln!();
+    outln!("{}", "Peng-Robinson Estimate (cubic EOS, not AGA8)".cyan().bold());
+    outln!("{:<30} {:10.4} {:10}", "Pressure: ", get_pressure(state.p, units.pressure), units.pressure.label());
+    outln!("{:<30} {:10.4} {:10}", "Temperature: ", get_temperature(state.t, units.temp), units.temp.label());
+    match PengRobinson::new(&program_state.gas_comp).flash(state.p, state.t) {
+        Some(PrState::SinglePhase(phase)) => {
+            outln!("Single phase");
+            print_phase(&phase);
+        },
+        Some(PrState::TwoPhase { vapor_fraction, vapor, liquid }) => {
+            outln!("{}", "** Two-phase state - AGA8 results do not apply; values are Peng-Robinson estimates **".red().bold());
+            let v = vapor_fraction / vapor.d + (1.0 - vapor_fraction) / liquid.d;
+            outln!("{:<30} {:10.4} {:10}", "Vapor Fraction (molar): ", vapor_fraction, "[]");
+            outln!("{:<30} {:10.4} {:10}", "Overall Density: ", 1.0 / v, "mol/l");
+            outln!("{:<30} {:10.4} {:10}", "Overall Enthalpy: ", vapor_fraction * vapor.h + (1.0 - vapor_fraction) * liquid.h, "J/mol");
+            outln!("{:<30} {:10.4} {:10}", "Overall Entropy: ", vapor_fraction * vapor.s + (1.0 - vapor_fraction) * liquid.s, "J/(mol-K)");
+            outln!();
+            outln!("{}", "Vapor".bold());
+            print_phase(&vapor);
+            outln!();
+            outln!("{}", "Liquid".bold());
+            print_phase(&liquid);
+            outln!();
+            outln!("{:<20} {:>12} {:>12} {:>12}", "Component", "Feed mol%", "Vapor mol%", "Liquid mol%");
+            let feed = comp_to_array(&program_state.gas_comp);
+            let (y, x) = (comp_to_array(&vapor.composition), comp_to_array(&liquid.composition));
+            for i in (0..NUM_COMPONENTS).filter(|i| feed[*i] > 0.0) {
+                outln!("{:<20} {:12.4} {:12.4} {:12.4}", COMPONENT_LABELS[i], feed[i] * 100.0, y[i] * 100.0, x[i] * 100.0);
+            }
+        },
+        None => outln!("{}", "** Unable to solve the Peng-Robinson state **".red().bold()),
+    }
+    outln!("{}", "* Cubic equation estimate; use AGA8 for custody transfer and single phase gas".yellow());
+    print_gas_state(program_state);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Propane saturates at 997.6 kPa at 300 K (NIST).  The stable root of the equation
+    // changes from vapor to liquid within 5% of it.
+    #[test]
+    fn propane_vapor_pressure() {
+        let model = PengRobinson::new(&Composition { propane: 1.0, ..Default::default() });
+        let vapor = model.phase_properties(&[1.0], 950.0, 300.0).unwrap();
+        let liquid = model.phase_properties(&[1.0], 1050.0, 300.0).unwrap();
+        assert!(vapor.z > 0.7);
+        assert!(liquid.z < 0.1);
+    }
+
+    // Gas without hydrocarbons heavier than methane has no hydrocarbon dew point model
+    #[test]
+    fn hydrocarbon_model_needs_heavier_hydrocarbons() {
+        assert!(hydrocarbon_model(&Composition { methane: 0.9, nitrogen: 0.1, ..Default::default() }).is_none());
+        assert!(hydrocarbon_model(&Composition { methane: 0.9, propane: 0.1, ..Default::default() }).is_some());
+    }
+}
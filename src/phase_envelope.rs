@@ -0,0 +1,131 @@
+use colored::Colorize;
+
+use crate::csv::write_csv;
+use crate::peng_robinson::{water_free_model, PengRobinson};
+use crate::{get_pressure, get_temperature, print_gas_state, read_input, ProgramState};
+
+// The trace starts at P_START (kPa) and the pressure rises by P_RATIO until there is no
+// phase boundary, then the cricondenbar is bracketed by bisection
+const P_START: f64 = 100.0;
+const P_RATIO: f64 = 1.2;
+const P_MAX: f64 = 50000.0;
+const P_BISECTIONS: usize = 8;
+// Offset (K) into the two-phase region at which a boundary point is classified
+const T_INSIDE: f64 = 0.01;
+
+// Point on the phase envelope: pressure (kPa), temperature (K) and vapor fraction, 1 on
+// the dew curve and 0 on the bubble curve
+pub struct EnvelopePoint {
+    pub p: f64,
+    pub t: f64,
+    pub vapor_fraction: f64,
+}
+
+// Upper and lower phase boundaries at p (kPa).  None when the feed is single phase at all
+// temperatures.
+fn boundaries(model: &PengRobinson, p: f64) -> Option<(EnvelopePoint, Option<EnvelopePoint>)> {
+    let t_upper = model.dew_point(p)?;
+    let upper = EnvelopePoint { p, t: t_upper, vapor_fraction: model.boundary_vapor_fraction(p, t_upper - T_INSIDE).unwrap_or(1.0) };
+    let lower = model.next_boundary(p, t_upper - T_INSIDE).map(|t| {
+        EnvelopePoint { p, t, vapor_fraction: model.boundary_vapor_fraction(p, t + T_INSIDE).unwrap_or(0.0) }
+    });
+    Some((upper, lower))
+}
+
+// Phase envelope up to the cricondenbar as one line, from the low pressure end of the
+// lower boundary (the bubble curve) through the critical region and back down the upper
+// boundary (the dew curve)
+pub fn phase_envelope(model: &PengRobinson) -> Vec<EnvelopePoint> {
+    let mut upper = Vec::new();
+    let mut lower = Vec::new();
+    let mut p = P_START;
+    let mut last = None;
+    while p < P_MAX {
+        let Some((upper_point, lower_point)) = boundaries(model, p) else {
+            break;
+        };
+        upper.push(upper_point);
+        lower.extend(lower_point);
+        last = Some(p);
+        p *= P_RATIO;
+    }
+    if let Some(mut low) = last && p < P_MAX {
+        let mut high = p;
+        let mut top = None;
+        for _ in 0..P_BISECTIONS {
+            let mid = (low * high).sqrt();
+            match boundaries(model, mid) {
+                Some(points) => {
+                    low = mid;
+                    top = Some(points);
+                },
+                None => high = mid,
+            }
+        }
+        if let Some((upper_point, lower_point)) = top {
+            upper.push(upper_point);
+            lower.extend(lower_point);
+        }
+    }
+    lower.extend(upper.into_iter().rev());
+    lower
+}
+
+pub fn phase_envelope_report(program_state: &mut ProgramState) {
+    outln!();
+    outln!("{}", "Phase Envelope (Peng-Robinson estimate)".cyan().bold());
+    if program_state.gas_comp.water > 0.0 {
+        outln!("Water-free basis; water condensation is covered by the water dew point.");
+    }
+    let model = water_free_model(&program_state.gas_comp);
+    let envelope = phase_envelope(&model);
+    if envelope.is_empty() {
+        outln!("{}", "** No phase boundary found - single phase at all pressures and temperatures searched **".red().bold());
+        print_gas_state(program_state);
+        return;
+    }
+
+    let units = program_state.units;
+    let p_label = format!("P ({})", units.pressure.label());
+    let t_label = format!("T ({})", units.temp.label());
+    let headers = [p_label.as_str(), t_label.as_str(), "Vapor Fraction"];
+    let mut rows = Vec::new();
+    outln!();
+    outln!("{:>12} {:>12} {:>8}", headers[0], headers[1], "Curve");
+    for point in &envelope {
+        let p_display = get_pressure(point.p, units.pressure);
+        let t_display = get_temperature(point.t, units.temp);
+        outln!("{:12.4} {:12.4} {:>8}", p_display, t_display, if point.vapor_fraction == 1.0 { "Dew" } else { "Bubble" });
+        rows.push(vec![p_display, t_display, point.vapor_fraction]);
+    }
+
+    outln!();
+    let cricondenbar = envelope.iter().max_by(|a, b| a.p.total_cmp(&b.p)).unwrap();
+    let cricondentherm = envelope.iter().max_by(|a, b| a.t.total_cmp(&b.t)).unwrap();
+    outln!("{:<30} {:10.4} {:10}", "Cricondenbar: ", get_pressure(cricondenbar.p, units.pressure), units.pressure.label());
+    outln!("{:<30} {:10.4} {:10}", "Cricondentherm: ", get_temperature(cricondentherm.t, units.temp), units.temp.label());
+    // The critical point lies where the curve changes from bubble to dew
+    if let Some(pair) = envelope.windows(2).find(|pair| pair[0].vapor_fraction != pair[1].vapor_fraction) {
+        outln!("{:<30} {:10.4} {:10}", "Critical Pressure (approx.): ", get_pressure(0.5 * (pair[0].p + pair[1].p), units.pressure), units.pressure.label());
+        outln!("{:<30} {:10.4} {:10}", "Critical Temp. (approx.): ", get_temperature(0.5 * (pair[0].t + pair[1].t), units.temp), units.temp.label());
+    }
+    let state = &program_state.gas_state;
+    if model.is_unstable(state.p, state.t) {
+        outln!("{}", "** Current state is inside the phase envelope - AGA8 results assume single phase **".red().bold());
+    } else {
+        outln!("Current state is outside the phase envelope.");
+    }
+    outln!("{}", "* Cubic equation estimate; the envelope is approximate near the critical point".yellow());
+
+    outln!();
+    outln!("Enter CSV file path to export the table (leave blank to skip):");
+    let path = read_input();
+    if !path.is_empty() {
+        match write_csv(&path, &headers, &rows) {
+            Ok(()) => outln!("Table written to {}", path),
+            Err(err) => outln!("{}", format!("** {} **", err).red().bold()),
+        }
+    }
+
+    print_gas_state(program_state);
+}
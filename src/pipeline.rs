@@ -0,0 +1,398 @@
+use aga8::composition::Composition;
+use colored::Colorize;
+
+use crate::csv::{field_f64, write_csv, CsvTable};
+use crate::process::isenthalpic_state;
+use crate::quality::relative_density;
+use crate::{
+    print_gas_state, read_f64, read_input, read_pressure, read_temperature, try_gas_state, Pressure, ProgramState, Temperature,
+};
+
+#[derive(Clone, Copy)]
+pub enum FlowEquation {
+    // General flow equation with the AGA fully turbulent transmission factor
+    AgaTurbulent { roughness: f64 },
+    Weymouth,
+    PanhandleA,
+    PanhandleB,
+}
+
+pub struct Pipeline {
+    // Internal diameter in mm, length in km and pipeline efficiency
+    pub diameter: f64,
+    pub length: f64,
+    pub efficiency: f64,
+    pub equation: FlowEquation,
+}
+
+// Average pressure along the pipe
+fn average_pressure(p1: Pressure, p2: Pressure) -> Pressure {
+    let (p1, p2) = (p1.kpa(), p2.kpa());
+    Pressure::from_kpa(2.0 / 3.0 * (p1 + p2 - p1 * p2 / (p1 + p2)))
+}
+
+// Flow at base conditions in m3/day between p1 and p2 at the flowing temperature t, SI
+// forms of the equations (kPa and K) without elevation change.
+pub fn pipeline_flow(gas_comp: &Composition, pipe: &Pipeline, p1: Pressure, p2: Pressure, t: Temperature, p_base: Pressure, t_base: Temperature) -> Option<f64> {
+    let z = try_gas_state(gas_comp, average_pressure(p1, p2).kpa(), t.kelvin())?.z;
+    let g = relative_density(gas_comp, p_base, t_base);
+    let (p1, p2, t) = (p1.kpa(), p2.kpa(), t.kelvin());
+    let (p_base, t_base) = (p_base.kpa(), t_base.kelvin());
+    let dp2 = p1 * p1 - p2 * p2;
+    let d = pipe.diameter;
+    let e = pipe.efficiency;
+    let flow = match pipe.equation {
+        FlowEquation::AgaTurbulent { roughness } => {
+            let transmission_factor = 4.0 * (3.7 * d / roughness).log10();
+            5.747e-4 * e * transmission_factor * (t_base / p_base) * (dp2 / (g * t * pipe.length * z)).sqrt() * d.powf(2.5)
+        },
+        FlowEquation::Weymouth => {
+            3.7435e-3 * e * (t_base / p_base) * (dp2 / (g * t * pipe.length * z)).sqrt() * d.powf(2.667)
+        },
+        FlowEquation::PanhandleA => {
+            4.5965e-3 * e * (t_base / p_base).powf(1.0788) * (dp2 / (g.powf(0.8539) * t * pipe.length * z)).powf(0.5394) * d.powf(2.6182)
+        },
+        FlowEquation::PanhandleB => {
+            1.002e-2 * e * (t_base / p_base).powf(1.02) * (dp2 / (g.powf(0.961) * t * pipe.length * z)).powf(0.51) * d.powf(2.53)
+        },
+    };
+    Some(flow)
+}
+
+// Outlet pressure for the flow at base conditions in m3/day, by bisection.  None when
+// the flow exceeds the capacity with zero outlet pressure.
+pub fn outlet_pressure(gas_comp: &Composition, pipe: &Pipeline, p1: Pressure, flow: f64, t: Temperature, p_base: Pressure, t_base: Temperature) -> Option<Pressure> {
+    let mut low = p1 * 1.0e-3;
+    let mut high = p1;
+    if pipeline_flow(gas_comp, pipe, p1, low, t, p_base, t_base)? < flow {
+        return None;
+    }
+    for _ in 0..100 {
+        let mid = (low + high) * 0.5;
+        if pipeline_flow(gas_comp, pipe, p1, mid, t, p_base, t_base)? > flow {
+            low = mid;
+        } else {
+            high = mid;
+        }
+        if (high - low).kpa() < 1.0e-9 * p1.kpa() {
+            break;
+        }
+    }
+    Some((low + high) * 0.5)
+}
+
+fn read_flow_equation() -> FlowEquation {
+    loop {
+        outln!("Select flow equation:");
+        outln!("1 - General flow equation, AGA fully turbulent");
+        outln!("2 - Weymouth");
+        outln!("3 - Panhandle A");
+        outln!("4 - Panhandle B");
+        return match read_input().as_str() {
+            "1" => FlowEquation::AgaTurbulent { roughness: read_f64("Enter pipe absolute roughness (mm, e.g. 0.0457 new steel):") },
+            "2" => FlowEquation::Weymouth,
+            "3" => FlowEquation::PanhandleA,
+            "4" => FlowEquation::PanhandleB,
+            _ => continue,
+        };
+    }
+}
+
+pub fn pipeline_calculation(program_state: &mut ProgramState) {
+    outln!();
+    outln!("{}", "Pipeline Pressure Drop / Capacity".green().bold());
+    outln!("Inlet pressure and flowing temperature are the current state.");
+    let equation = read_flow_equation();
+    let pipe = Pipeline {
+        diameter: read_f64("Enter pipe internal diameter (mm):"),
+        length: read_f64("Enter pipe length (km):"),
+        efficiency: read_f64("Enter pipeline efficiency (e.g. 0.95, 1.0 for the AGA equation):"),
+        equation,
+    };
+    outln!("1 - Flow for a given outlet pressure");
+    outln!("2 - Outlet pressure for a given flow");
+    let gas_comp = &program_state.gas_comp;
+    let p1 = Pressure::from_kpa(program_state.gas_state.p);
+    let t = Temperature::from_kelvin(program_state.gas_state.t);
+    let p_base = program_state.base_pressure;
+    let t_base = program_state.base_temperature;
+    let (p2, flow) = match read_input().as_str() {
+        "2" => {
+            let flow = read_f64("Enter flow at base conditions (m3/day):");
+            (outlet_pressure(gas_comp, &pipe, p1, flow, t, p_base, t_base), Some(flow))
+        },
+        _ => {
+            let p2 = read_pressure(program_state, "outlet pressure");
+            if p2 >= p1 || p2.kpa() <= 0.0 {
+                outln!("{}", "** Outlet pressure must be between zero and the inlet pressure **".red().bold());
+                print_gas_state(program_state);
+                return;
+            }
+            (Some(p2), pipeline_flow(gas_comp, &pipe, p1, p2, t, p_base, t_base))
+        },
+    };
+    let (Some(p2), Some(flow)) = (p2, flow) else {
+        outln!("{}", "** Flow exceeds the pipeline capacity or the state could not be calculated **".red().bold());
+        print_gas_state(program_state);
+        return;
+    };
+
+    let p_unit = program_state.units.pressure.label();
+    let p_avg = average_pressure(p1, p2);
+    outln!();
+    outln!("{:<30} {:10.4} {:10}", "Inlet Pressure: ", p1.value(program_state.units.pressure), p_unit);
+    outln!("{:<30} {:10.4} {:10}", "Outlet Pressure: ", p2.value(program_state.units.pressure), p_unit);
+    outln!("{:<30} {:10.4} {:10}", "Pressure Drop: ", (p1 - p2).value(program_state.units.pressure), p_unit);
+    outln!("{:<30} {:10.4} {:10}", "Average Pressure: ", p_avg.value(program_state.units.pressure), p_unit);
+    if let Some(average) = try_gas_state(gas_comp, p_avg.kpa(), t.kelvin()) {
+        outln!("{:<30} {:10.4} {:10}", "Average Compressibility Z: ", average.z, "[]");
+    }
+    outln!("{:<30} {:10.4} {:10}", "Gas Gravity: ", relative_density(gas_comp, p_base, t_base), "[]");
+    outln!("{:<30} {:10.1} {:10}", "Flow at Base Conditions: ", flow, "m3/day");
+    if let Some(base) = try_gas_state(gas_comp, p_base.kpa(), t_base.kelvin()) {
+        outln!("{:<30} {:10.4} {:10}", "Mass Flow: ", flow * base.d * base.mm / 86_400.0, "kg/s");
+    }
+    if let Some(outlet) = try_gas_state(gas_comp, p2.kpa(), t.kelvin()) {
+        // Outlet velocity from the actual volumetric flow
+        let base_moles = try_gas_state(gas_comp, p_base.kpa(), t_base.kelvin()).map_or(f64::NAN, |base| flow * base.d / 86_400.0);
+        let area = std::f64::consts::PI / 4.0 * (pipe.diameter / 1000.0).powi(2);
+        let velocity = base_moles / outlet.d / area;
+        outln!("{:<30} {:10.4} {:10}", "Outlet Velocity: ", velocity, "m/s");
+        if velocity > 20.0 {
+            outln!("{}", "* Outlet velocity is above 20 m/s - check erosion and noise limits".yellow());
+        }
+    }
+
+    print_gas_state(program_state);
+}
+
+const GRAVITY: f64 = 9.80665;
+
+// Route point: distance in km, elevation in m and estimated pressure
+pub struct RoutePoint {
+    pub distance: f64,
+    pub elevation: f64,
+    pub p: Pressure,
+}
+
+#[derive(Clone, Copy)]
+pub enum TemperatureModel {
+    Isothermal,
+    // Joule-Thomson cooling and elevation change, no heat exchange
+    Adiabatic,
+    // Adiabatic step relaxed toward the ground temperature with the overall heat transfer
+    // coefficient (W/m2-K) on the pipe internal surface
+    HeatTransfer { u: f64, t_ground: Temperature },
+}
+
+pub struct ProfilePoint {
+    pub distance: f64,
+    pub elevation: f64,
+    pub p: Pressure,
+    pub t: Temperature,
+    // kg/m3 and m/s
+    pub density: f64,
+    pub velocity: f64,
+    pub z: f64,
+}
+
+fn read_route(path: &str) -> Result<Vec<RoutePoint>, String> {
+    let table = CsvTable::read(path)?;
+    let columns = [table.require_column("distance")?, table.require_column("elevation")?, table.require_column("pressure")?];
+    let mut points = Vec::new();
+    for (line, row) in table.rows.iter().enumerate() {
+        let row_error = |err: String| format!("Row {}: {}", line + 2, err);
+        let mut values = [0.0; 3];
+        for (value, column) in values.iter_mut().zip(columns) {
+            *value = field_f64(row, Some(column)).map_err(row_error)?.ok_or(row_error("missing value".to_string()))?;
+        }
+        let [distance, elevation, p] = values;
+        if points.last().is_some_and(|last: &RoutePoint| distance <= last.distance) || p <= 0.0 {
+            return Err(row_error("distances must increase and pressures be positive".to_string()));
+        }
+        points.push(RoutePoint { distance, elevation, p: Pressure::from_kpa(p) });
+    }
+    if points.len() < 2 {
+        return Err(format!("{} needs at least 2 route points", path));
+    }
+    Ok(points)
+}
+
+// Marches along the route from the inlet temperature t_in with the mass flow (kg/s)
+// in a pipe of internal diameter (mm).  Pressures are the route estimates; each segment
+// keeps the enthalpy less the potential energy gained, then exchanges heat with the ground
+// for the heat transfer model.  Stops at the first point that cannot be solved.
+pub fn elevation_profile(gas_comp: &Composition, route: &[RoutePoint], t_in: Temperature, mass_flow: f64, diameter: f64, model: TemperatureModel) -> Vec<ProfilePoint> {
+    let t_in = t_in.kelvin();
+    let d = diameter / 1000.0;
+    let area = std::f64::consts::PI / 4.0 * d * d;
+    let point = |route_point: &RoutePoint, state: &aga8::detail::Detail| {
+        let density = state.d * state.mm;
+        ProfilePoint {
+            distance: route_point.distance,
+            elevation: route_point.elevation,
+            p: route_point.p,
+            t: Temperature::from_kelvin(state.t),
+            density,
+            velocity: mass_flow / (density * area),
+            z: state.z,
+        }
+    };
+    let Some(mut state) = try_gas_state(gas_comp, route[0].p.kpa(), t_in) else {
+        return Vec::new();
+    };
+    let mut profile = vec![point(&route[0], &state)];
+    for segment in route.windows(2) {
+        let (from, to) = (&segment[0], &segment[1]);
+        let next = match model {
+            TemperatureModel::Isothermal => try_gas_state(gas_comp, to.p.kpa(), t_in),
+            TemperatureModel::Adiabatic | TemperatureModel::HeatTransfer { .. } => {
+                // g dz in J/kg to J/mol
+                let h = state.h - GRAVITY * (to.elevation - from.elevation) * state.mm / 1000.0;
+                let adiabatic = isenthalpic_state(gas_comp, to.p.kpa(), h, state.t);
+                match (model, adiabatic) {
+                    (TemperatureModel::HeatTransfer { u, t_ground }, Some(adiabatic)) => {
+                        let length = (to.distance - from.distance) * 1000.0;
+                        // cp in J/(mol-K) to J/(kg-K)
+                        let ntu = u * std::f64::consts::PI * d * length / (mass_flow * adiabatic.cp / adiabatic.mm * 1000.0);
+                        let t_ground = t_ground.kelvin();
+                        try_gas_state(gas_comp, to.p.kpa(), t_ground + (adiabatic.t - t_ground) * (-ntu).exp())
+                    },
+                    (_, adiabatic) => adiabatic,
+                }
+            },
+        };
+        let Some(next) = next else {
+            break;
+        };
+        profile.push(point(to, &next));
+        state = next;
+    }
+    profile
+}
+
+fn read_temperature_model(program_state: &ProgramState) -> TemperatureModel {
+    loop {
+        outln!("Select temperature model:");
+        outln!("1 - Isothermal at the inlet temperature");
+        outln!("2 - Adiabatic (Joule-Thomson and elevation)");
+        outln!("3 - Heat transfer to ground");
+        return match read_input().as_str() {
+            "1" => TemperatureModel::Isothermal,
+            "2" => TemperatureModel::Adiabatic,
+            "3" => TemperatureModel::HeatTransfer {
+                u: read_f64("Enter overall heat transfer coefficient (W/m2-K, e.g. 2 buried):"),
+                t_ground: read_temperature(program_state, "ground temperature"),
+            },
+            _ => continue,
+        };
+    }
+}
+
+pub fn pipeline_elevation_profile(program_state: &mut ProgramState) {
+    outln!();
+    outln!("{}", "Pipeline Elevation Profile".green().bold());
+    outln!("Inlet temperature is the current state.");
+    outln!("Enter CSV file path of the route (columns: distance (km), elevation (m), pressure (kPa)):");
+    let route = match read_route(&read_input()) {
+        Ok(route) => route,
+        Err(err) => {
+            outln!("{}", format!("** {} **", err).red().bold());
+            print_gas_state(program_state);
+            return;
+        }
+    };
+    let diameter = read_f64("Enter pipe internal diameter (mm):");
+    let mass_flow = read_f64("Enter mass flow (kg/s):");
+    let model = read_temperature_model(program_state);
+    if diameter <= 0.0 || mass_flow <= 0.0 {
+        outln!("{}", "** Diameter and mass flow must be positive **".red().bold());
+        print_gas_state(program_state);
+        return;
+    }
+
+    let profile = elevation_profile(&program_state.gas_comp, &route, Temperature::from_kelvin(program_state.gas_state.t), mass_flow, diameter, model);
+    let p_unit = program_state.units.pressure.label();
+    let t_unit = program_state.units.temp.label();
+    outln!();
+    outln!("{:>10} {:>10} {:>12} {:>12} {:>10} {:>10} {:>8}", "km", "m", p_unit, t_unit, "kg/m3", "m/s", "Z");
+    for point in &profile {
+        outln!("{:10.3} {:10.1} {:12.4} {:12.4} {:10.4} {:10.4} {:8.4}", point.distance, point.elevation,
+            point.p.value(program_state.units.pressure), point.t.value(program_state.units.temp),
+            point.density, point.velocity, point.z);
+    }
+    if profile.len() < route.len() {
+        outln!("{}", format!("** Stopped at point {} of {}: the state could not be solved **", profile.len() + 1, route.len()).red().bold());
+    }
+    if profile.iter().any(|point| point.velocity > 20.0) {
+        outln!("{}", "* Velocity above 20 m/s along the route - check erosion and noise limits".yellow());
+    }
+
+    outln!();
+    outln!("Enter CSV file path to export the profile (leave blank to skip):");
+    let path = read_input();
+    if !path.is_empty() {
+        let headers = ["distance_km", "elevation_m", "pressure_kpa", "temperature_k", "density_kg_m3", "velocity_m_s", "z"];
+        let rows: Vec<Vec<f64>> = profile.iter()
+            .map(|point| vec![point.distance, point.elevation, point.p.kpa(), point.t.kelvin(), point.density, point.velocity, point.z])
+            .collect();
+        match write_csv(&path, &headers, &rows) {
+            Ok(()) => outln!("Profile written to {}", path),
+            Err(err) => outln!("{}", format!("** {} **", err).red().bold()),
+        }
+    }
+    print_gas_state(program_state);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{get_gas_comp, GasComp};
+
+    // m3 per standard cubic foot
+    const M3_PER_SCF: f64 = 0.028_316_846_6;
+
+    // The SI constants against the published US customary forms (scf/day, psia, R,
+    // miles, inches): general flow 38.77 F, Weymouth 433.5, Panhandle A 435.87 and
+    // Panhandle B 737.
+    #[test]
+    fn si_constants_match_us_customary_forms() {
+        let gas_comp = get_gas_comp(GasComp::NaturalGas);
+        let (p1, p2, t) = (Pressure::from_kpa(7000.0), Pressure::from_kpa(5000.0), Temperature::from_kelvin(288.15));
+        let (p_base, t_base) = (Pressure::from_kpa(101.325), Temperature::from_kelvin(288.15));
+        let z = try_gas_state(&gas_comp, average_pressure(p1, p2).kpa(), t.kelvin()).unwrap().z;
+        let g = relative_density(&gas_comp, p_base, t_base);
+
+        let (d, length, e, roughness): (f64, f64, f64, f64) = (500.0, 100.0, 0.95, 0.02);
+        let inches = d / 25.4;
+        let miles = length / 1.609_344;
+        let psia = |p: Pressure| p.kpa() * 0.145_037_738;
+        let rankine = |t: Temperature| t.kelvin() * 1.8;
+        let dp2 = psia(p1).powi(2) - psia(p2).powi(2);
+        let base = rankine(t_base) / psia(p_base);
+        let transmission_factor = 4.0 * (3.7 * d / roughness).log10();
+        let cases = [
+            (FlowEquation::AgaTurbulent { roughness }, 38.77 * transmission_factor * base * (dp2 / (g * rankine(t) * miles * z)).sqrt() * inches.powf(2.5)),
+            (FlowEquation::Weymouth, 433.5 * base * (dp2 / (g * rankine(t) * miles * z)).sqrt() * inches.powf(2.667)),
+            (FlowEquation::PanhandleA, 435.87 * base.powf(1.0788) * (dp2 / (g.powf(0.8539) * rankine(t) * miles * z)).powf(0.5394) * inches.powf(2.6182)),
+            (FlowEquation::PanhandleB, 737.0 * base.powf(1.02) * (dp2 / (g.powf(0.961) * rankine(t) * miles * z)).powf(0.51) * inches.powf(2.53)),
+        ];
+        for (equation, scf_per_day) in cases {
+            let pipe = Pipeline { diameter: d, length, efficiency: e, equation };
+            let flow = pipeline_flow(&gas_comp, &pipe, p1, p2, t, p_base, t_base).unwrap();
+            assert!((flow / (e * scf_per_day * M3_PER_SCF) - 1.0).abs() < 2.0e-4);
+        }
+    }
+
+    // Outlet pressure inverts the flow
+    #[test]
+    fn outlet_pressure_round_trip() {
+        let gas_comp = get_gas_comp(GasComp::NaturalGas);
+        let pipe = Pipeline { diameter: 300.0, length: 50.0, efficiency: 0.92, equation: FlowEquation::PanhandleA };
+        let (p1, t) = (Pressure::from_kpa(6000.0), Temperature::from_kelvin(283.15));
+        let (p_base, t_base) = (Pressure::from_kpa(101.325), Temperature::from_kelvin(288.15));
+        let flow = pipeline_flow(&gas_comp, &pipe, p1, Pressure::from_kpa(4500.0), t, p_base, t_base).unwrap();
+        let p2 = outlet_pressure(&gas_comp, &pipe, p1, flow, t, p_base, t_base).unwrap();
+        assert!((p2.kpa() - 4500.0).abs() < 1.0e-3);
+    }
+}
@@ -0,0 +1,242 @@
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+
+use crate::{output, print_gas_state, read_input, ProgramState};
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Quantity {
+    Pressure,
+    Temperature,
+    Density,
+    MolarMass,
+    // Internal energy, enthalpy and Gibbs energy
+    Energy,
+    // Entropy and heat capacities
+    Entropy,
+    // Z, k, Cp/Cv and ratios
+    Dimensionless,
+    SpeedOfSound,
+    // Density times speed of sound, of the order of 100 to 10^5 kg/(m2-s)
+    AcousticImpedance,
+    JouleThomson,
+    // Partial derivatives of pressure
+    Derivative,
+    // Isothermal and isentropic compressibility and volume expansivity
+    Coefficient,
+}
+
+pub const QUANTITIES: [(Quantity, &str); 12] = [
+    (Quantity::Pressure, "Pressure"),
+    (Quantity::Temperature, "Temperature"),
+    (Quantity::Density, "Density"),
+    (Quantity::MolarMass, "Molar mass"),
+    (Quantity::Energy, "Energy (u, h, g)"),
+    (Quantity::Entropy, "Entropy and heat capacity"),
+    (Quantity::Dimensionless, "Dimensionless (Z, k, ratios)"),
+    (Quantity::SpeedOfSound, "Speed of sound"),
+    (Quantity::AcousticImpedance, "Acoustic impedance"),
+    (Quantity::JouleThomson, "Joule-Thomson coefficient"),
+    (Quantity::Derivative, "Pressure derivatives"),
+    (Quantity::Coefficient, "Compressibility and expansivity"),
+];
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Digits {
+    Decimals(usize),
+    Significant(usize),
+}
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct NumberFormat {
+    pub digits: Digits,
+    pub scientific: bool,
+}
+
+impl NumberFormat {
+    fn describe(&self) -> String {
+        let digits = match self.digits {
+            Digits::Decimals(n) => format!("{} decimal places", n),
+            Digits::Significant(n) => format!("{} significant figures", n),
+        };
+        if self.scientific { format!("{}, scientific", digits) } else { digits }
+    }
+}
+
+// Number formats of the state printout and exports, one per quantity type
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct Precision {
+    formats: [NumberFormat; QUANTITIES.len()],
+    pub thousands: bool,
+}
+
+impl Default for Precision {
+    fn default() -> Precision {
+        let mut precision = Precision {
+            formats: [NumberFormat { digits: Digits::Decimals(4), scientific: false }; QUANTITIES.len()],
+            thousands: false,
+        };
+        // Coefficients are of the order of 1/p and 1/T
+        precision.set(Quantity::Coefficient, NumberFormat { digits: Digits::Decimals(4), scientific: true });
+        precision.set(Quantity::AcousticImpedance, NumberFormat { digits: Digits::Decimals(1), scientific: false });
+        precision
+    }
+}
+
+fn index(quantity: Quantity) -> usize {
+    QUANTITIES.iter().position(|(q, _)| *q == quantity).unwrap()
+}
+
+// Inserts a comma every three digits of the integer part
+fn group_thousands(text: &str) -> String {
+    let (sign, rest) = text.strip_prefix('-').map_or(("", text), |rest| ("-", rest));
+    let (integer, fraction) = rest.split_once('.').map_or((rest, None), |(integer, fraction)| (integer, Some(fraction)));
+    if !integer.bytes().all(|b| b.is_ascii_digit()) {
+        return text.to_string();
+    }
+    let mut grouped = String::new();
+    for (i, digit) in integer.chars().enumerate() {
+        if i > 0 && (integer.len() - i).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    match fraction {
+        Some(fraction) => format!("{}{}.{}", sign, grouped, fraction),
+        None => format!("{}{}", sign, grouped),
+    }
+}
+
+impl Precision {
+    pub fn get(&self, quantity: Quantity) -> NumberFormat {
+        self.formats[index(quantity)]
+    }
+
+    pub fn set(&mut self, quantity: Quantity, format: NumberFormat) {
+        self.formats[index(quantity)] = format;
+    }
+
+    pub fn format(&self, quantity: Quantity, value: f64) -> String {
+        let format = self.get(quantity);
+        if format.scientific {
+            let decimals = match format.digits {
+                Digits::Decimals(n) => n,
+                Digits::Significant(n) => n.saturating_sub(1),
+            };
+            return format!("{:.*e}", decimals, value);
+        }
+        let decimals = match format.digits {
+            Digits::Decimals(n) => n,
+            // The exponent of the rounded value, so 9.996 to three figures is 10.0
+            Digits::Significant(n) if value.is_finite() && value != 0.0 => {
+                let rounded = format!("{:.*e}", n.saturating_sub(1), value);
+                let exponent = rounded.split_once('e').and_then(|(_, exponent)| exponent.parse::<i32>().ok()).unwrap_or(0);
+                (n as i32 - 1 - exponent).max(0) as usize
+            },
+            Digits::Significant(n) => n.saturating_sub(1),
+        };
+        let text = format!("{:.*}", decimals, value);
+        if self.thousands { group_thousands(&text) } else { text }
+    }
+}
+
+fn read_number_format() -> Option<NumberFormat> {
+    outln!("Digits: d - Decimal places, s - Significant figures");
+    let significant = match read_input().as_str() {
+        "d" => false,
+        "s" => true,
+        _ => return None,
+    };
+    outln!("Enter number of {}:", if significant { "significant figures" } else { "decimal places" });
+    let n = read_input().parse::<usize>().ok().filter(|n| *n <= 15 && (*n > 0 || !significant))?;
+    outln!("Scientific notation? (y/n)");
+    let scientific = read_input() == "y";
+    let digits = if significant { Digits::Significant(n) } else { Digits::Decimals(n) };
+    Some(NumberFormat { digits, scientific })
+}
+
+pub fn precision_menu(program_state: &mut ProgramState) {
+    loop {
+        if !output::quiet() {
+            outln!();
+            outln!("Number Format");
+            outln!("-------------");
+            for (i, (quantity, name)) in QUANTITIES.iter().enumerate() {
+                outln!("{} - {} ({})", i + 1, name, program_state.precision.get(*quantity).describe());
+            }
+            outln!("a - All quantities");
+            outln!("t - Thousands separators ({})", if program_state.precision.thousands { "on" } else { "off" });
+            outln!("b - Back");
+        }
+        let input = read_input();
+        let quantities: Vec<Quantity> = match input.as_str() {
+            "b" => {
+                print_gas_state(program_state);
+                return;
+            },
+            "t" => {
+                program_state.precision.thousands = !program_state.precision.thousands;
+                continue;
+            },
+            "a" => QUANTITIES.iter().map(|(quantity, _)| *quantity).collect(),
+            _ => match input.parse::<usize>().ok().and_then(|i| QUANTITIES.get(i.wrapping_sub(1))) {
+                Some((quantity, _)) => vec![*quantity],
+                None => continue,
+            },
+        };
+        match read_number_format() {
+            Some(format) => {
+                for quantity in quantities {
+                    program_state.precision.set(quantity, format);
+                }
+            },
+            None => outln!("{}", "**Invalid number format!**".bold().red()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn significant(n: usize) -> Precision {
+        let mut precision = Precision::default();
+        precision.set(Quantity::Density, NumberFormat { digits: Digits::Significant(n), scientific: false });
+        precision
+    }
+
+    #[test]
+    fn significant_figures() {
+        let precision = significant(3);
+        assert_eq!(precision.format(Quantity::Density, 0.001_234_5), "0.00123");
+        assert_eq!(precision.format(Quantity::Density, -12.345), "-12.3");
+        // Rounding up to the next power of ten keeps the number of figures
+        assert_eq!(precision.format(Quantity::Density, 9.996), "10.0");
+        assert_eq!(precision.format(Quantity::Density, 0.099_96), "0.100");
+        // Integer digits are never dropped
+        assert_eq!(precision.format(Quantity::Density, 123_456.0), "123456");
+        assert_eq!(precision.format(Quantity::Density, 0.0), "0.00");
+        assert_eq!(precision.format(Quantity::Density, f64::NAN), "NaN");
+        assert_eq!(significant(1).format(Quantity::Density, 0.96), "1");
+    }
+
+    #[test]
+    fn scientific_notation() {
+        let mut precision = Precision::default();
+        precision.set(Quantity::Pressure, NumberFormat { digits: Digits::Significant(3), scientific: true });
+        assert_eq!(precision.format(Quantity::Pressure, 101.325), "1.01e2");
+        assert_eq!(precision.format(Quantity::Coefficient, -0.000_123_45), "-1.2345e-4");
+        precision.thousands = true;
+        assert_eq!(precision.format(Quantity::Pressure, 123_456.0), "1.23e5");
+    }
+
+    #[test]
+    fn thousands_separators() {
+        let mut precision = Precision { thousands: true, ..Precision::default() };
+        assert_eq!(precision.format(Quantity::Pressure, 1_234_567.891), "1,234,567.8910");
+        assert_eq!(precision.format(Quantity::Pressure, -123_456.0), "-123,456.0000");
+        assert_eq!(precision.format(Quantity::Pressure, 999.0), "999.0000");
+        assert_eq!(precision.format(Quantity::Pressure, f64::INFINITY), "inf");
+        precision.set(Quantity::Pressure, NumberFormat { digits: Digits::Decimals(0), scientific: false });
+        assert_eq!(precision.format(Quantity::Pressure, 1000.0), "1,000");
+    }
+}
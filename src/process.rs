@@ -0,0 +1,220 @@
+use aga8::composition::Composition;
+use aga8::detail::Detail;
+use colored::Colorize;
+
+pub use comp_perf::thermo::{isenthalpic_state, isentropic_state};
+use comp_perf::thermo::solve_state;
+
+use crate::peng_robinson::{hydrocarbon_dew_point, print_hydrocarbon_dew_point_margin, DEW_POINT_MARGIN};
+use crate::water::{print_dew_point_margin, read_water_content};
+use crate::{
+    energy_in_units, entropy_in_units, entropy_label, get_power, get_pressure, get_temperature, get_temperature_difference, jt_in_units, jt_label,
+    print_gas_state, read_f64, read_input, read_pressure, read_temperature, set_gas_state, temperature_to_kelvin, try_gas_state, ProgramState, Temperature,
+};
+
+// Solves for the state at temperature t (K) with molar density d_target (mol/l),
+// starting the Newton iteration from p_guess (kPa).
+pub fn isochoric_state(gas_comp: &Composition, d_target: f64, t: f64, p_guess: f64) -> Option<Detail> {
+    let mut state = Detail::new();
+    state.set_composition(gas_comp).ok()?;
+    solve_isochoric_state(&mut state, d_target, t, p_guess)?;
+    Some(state)
+}
+
+// isochoric_state on an instance that already has its composition set, as solve_state
+pub fn solve_isochoric_state(state: &mut Detail, d_target: f64, t: f64, p_guess: f64) -> Option<()> {
+    let mut p = p_guess;
+    for _ in 0..50 {
+        solve_state(state, p, t)?;
+        let dp = state.dp_dd * (d_target - state.d);
+        p = (p + dp).max(0.5 * p);
+        if dp.abs() < 1.0e-9 * p {
+            return solve_state(state, p, t);
+        }
+    }
+    None
+}
+
+// Throttling across a valve from the current state to a downstream pressure at constant enthalpy.
+pub fn throttling(program_state: &mut ProgramState) {
+    outln!();
+    outln!("{}", "Isenthalpic Throttling (JT Valve)".yellow().bold());
+    outln!("Upstream state is the current state.");
+    let upstream = &program_state.gas_state;
+    let p_down = loop {
+        let p_down = read_pressure(program_state, "downstream pressure").kpa();
+        if p_down < upstream.p {
+            break p_down;
+        }
+        outln!("{}", "** Downstream pressure must be below the upstream pressure **".red().bold());
+    };
+
+    let downstream = match isenthalpic_state(&program_state.gas_comp, p_down, upstream.h, upstream.t) {
+        Some(downstream) => downstream,
+        None => {
+            outln!("{}", "** Unable to solve the downstream state **".red().bold());
+            print_gas_state(program_state);
+            return;
+        }
+    };
+
+    let p_unit = program_state.units.pressure.label();
+    let t_unit = program_state.units.temp.label();
+    let dt = Temperature::from_kelvin(downstream.t) - Temperature::from_kelvin(upstream.t);
+    outln!();
+    outln!("{:<30} {:10.4} {:10}", "Upstream Pressure: ", get_pressure(upstream.p, program_state.units.pressure), p_unit);
+    outln!("{:<30} {:10.4} {:10}", "Upstream Temperature: ", get_temperature(upstream.t, program_state.units.temp), t_unit);
+    outln!("{:<30} {:10.4} {:10}", "Downstream Pressure: ", get_pressure(downstream.p, program_state.units.pressure), p_unit);
+    outln!("{:<30} {:10.4} {:10}", "Downstream Temperature: ", get_temperature(downstream.t, program_state.units.temp), t_unit);
+    outln!("{:<30} {:10.4} {:10}", "Temperature Change: ", dt.value(program_state.units.temp), t_unit);
+    outln!("{:<30} {:10.4} {:10}", "Enthalpy: ", energy_in_units(upstream.h, upstream.mm, program_state.units.internal_energy), program_state.units.internal_energy.label());
+    outln!("{:<30} {:10.4} {:10}", "Downstream Density: ", downstream.d, "mol/l");
+    outln!("{:<30} {:10.4} {:10}", "Downstream Compressibility Z: ", downstream.z, "[]");
+    let units = program_state.units;
+    outln!("{:<30} {:10.6} {:10}", "Average JT Coefficient: ",
+        jt_in_units(dt.kelvin() / (downstream.p - upstream.p), units.temp, units.pressure), jt_label(units.temp, units.pressure));
+    print_hydrocarbon_dew_point_margin(program_state, downstream.p, downstream.t);
+    outln!();
+    outln!("Check the downstream water dew point? (y/n)");
+    if read_input() == "y" {
+        match read_water_content() {
+            Some(w) => print_dew_point_margin(program_state, downstream.p, downstream.t, w),
+            None => outln!("{}", "**Invalid water content!**".bold().red()),
+        }
+    }
+    if dt.kelvin() > 0.0 {
+        outln!("{}", "* Gas warms on expansion - upstream state is above the JT inversion temperature".yellow());
+    }
+
+    outln!();
+    outln!("Use the downstream state as the current state? (y/n)");
+    if read_input() == "y" {
+        set_gas_state(program_state, downstream);
+    }
+
+    print_gas_state(program_state);
+}
+
+// Expansion from the current state to an outlet pressure with an isentropic efficiency.
+pub fn turboexpander(program_state: &mut ProgramState) {
+    outln!();
+    outln!("{}", "Turboexpander".yellow().bold());
+    outln!("Inlet state is the current state.");
+    let inlet = &program_state.gas_state;
+    let p_out = read_pressure(program_state, "outlet pressure").kpa();
+    let efficiency = read_f64("Enter isentropic efficiency (e.g. 0.85):");
+    let mass_flow = read_f64("Enter mass flow (kg/s):");
+    outln!("Enter hydrocarbon dew point at the outlet pressure ({}, leave blank for the Peng-Robinson estimate):", program_state.units.temp.label());
+    let dew_point = read_input().parse::<f64>().ok().map(|t| temperature_to_kelvin(t, program_state.units.temp));
+
+    let gas_comp = &program_state.gas_comp;
+    let dew_point = dew_point.or_else(|| hydrocarbon_dew_point(gas_comp, p_out));
+    // Ideal gas estimate of the isentropic outlet temperature as the starting point
+    let t_guess = inlet.t * (p_out / inlet.p).powf((inlet.kappa - 1.0) / inlet.kappa);
+    let Some(isentropic) = isentropic_state(gas_comp, p_out, inlet.s, t_guess) else {
+        outln!("{}", "** Unable to solve the isentropic outlet state **".red().bold());
+        print_gas_state(program_state);
+        return;
+    };
+    let dh = efficiency * (inlet.h - isentropic.h);
+    let Some(outlet) = isenthalpic_state(gas_comp, p_out, inlet.h - dh, isentropic.t) else {
+        outln!("{}", "** Unable to solve the actual outlet state **".red().bold());
+        print_gas_state(program_state);
+        return;
+    };
+
+    let p_unit = program_state.units.pressure.label();
+    let t_unit = program_state.units.temp.label();
+    outln!();
+    outln!("{:<30} {:10.4} {:10}", "Pressure Ratio: ", inlet.p / p_out, "[]");
+    outln!("{:<30} {:10.4} {:10}", "Outlet Pressure: ", get_pressure(p_out, program_state.units.pressure), p_unit);
+    outln!("{:<30} {:10.4} {:10}", "Isentropic Outlet Temp.: ", get_temperature(isentropic.t, program_state.units.temp), t_unit);
+    outln!("{:<30} {:10.4} {:10}", "Outlet Temperature: ", get_temperature(outlet.t, program_state.units.temp), t_unit);
+    outln!("{:<30} {:10.4} {:10}", "Temperature Drop: ", get_temperature_difference(inlet.t - outlet.t, program_state.units.temp), t_unit);
+    outln!("{:<30} {:10.4} {:10}", "Specific Work: ", energy_in_units(dh, inlet.mm, program_state.units.internal_energy), program_state.units.internal_energy.label());
+    outln!("{:<30} {:10.4} {:10}", "Recoverable Power: ", get_power(dh / inlet.mm * mass_flow, program_state.units.power), program_state.units.power.label());
+    outln!("{:<30} {:10.4} {:10}", "Outlet Compressibility Z: ", outlet.z, "[]");
+    if let Some(dew_point) = dew_point {
+        let margin = outlet.t - dew_point;
+        outln!("{:<30} {:10.4} {:10}", "Outlet Dew Point: ", get_temperature(dew_point, program_state.units.temp), t_unit);
+        outln!("{:<30} {:10.4} {:10}", "Margin Above Dew Point: ", get_temperature_difference(margin, program_state.units.temp), t_unit);
+        if margin < 0.0 {
+            outln!("{}", "** Outlet is below the dew point - liquid dropout expected, results assume single phase **".red().bold());
+        } else if margin < DEW_POINT_MARGIN {
+            outln!("{}", "* Outlet is approaching the dew point".yellow());
+        }
+    }
+
+    print_gas_state(program_state);
+}
+
+// Heating or cooling of a trapped inventory at the density of the current state.
+pub fn isochoric_process(program_state: &mut ProgramState) {
+    outln!();
+    outln!("{}", "Isochoric (Constant Volume) Heating / Cooling".yellow().bold());
+    outln!("Initial state is the current state.");
+    let initial = &program_state.gas_state;
+    let t_final = read_temperature(program_state, "final temperature").kelvin();
+    let p_ideal = initial.p * t_final / initial.t;
+
+    let Some(end_state) = isochoric_state(&program_state.gas_comp, initial.d, t_final, p_ideal) else {
+        outln!("{}", "** Unable to solve the final state **".red().bold());
+        print_gas_state(program_state);
+        return;
+    };
+
+    let p_unit = program_state.units.pressure.label();
+    outln!();
+    outln!("{:<30} {:10.4} {:10}", "Density: ", initial.d, "mol/l");
+    outln!("{:<30} {:10.4} {:10}", "Initial Pressure: ", get_pressure(initial.p, program_state.units.pressure), p_unit);
+    outln!("{:<30} {:10.4} {:10}", "Final Pressure: ", get_pressure(end_state.p, program_state.units.pressure), p_unit);
+    outln!("{:<30} {:10.4} {:10}", "Pressure Change: ", get_pressure(end_state.p - initial.p, program_state.units.pressure), p_unit);
+    outln!("{:<30} {:10.4} {:10}", "Ideal Gas Final Pressure: ", get_pressure(p_ideal, program_state.units.pressure), p_unit);
+    outln!("{:<30} {:10.4} {:10}", "Deviation from Ideal Gas: ", (end_state.p - p_ideal) / p_ideal * 100.0, "%");
+    outln!("{:<30} {:10.4} {:10}", "Final Compressibility Z: ", end_state.z, "[]");
+    // Heat per mole at constant volume is the internal energy change
+    outln!("{:<30} {:10.4} {:10}", "Heat Added: ", energy_in_units(end_state.u - initial.u, initial.mm, program_state.units.internal_energy), program_state.units.internal_energy.label());
+
+    outln!();
+    outln!("Use the final state as the current state? (y/n)");
+    if read_input() == "y" {
+        set_gas_state(program_state, end_state);
+    }
+
+    print_gas_state(program_state);
+}
+
+// Heating or cooling of a flowing stream at the pressure of the current state.
+pub fn isobaric_process(program_state: &mut ProgramState) {
+    outln!();
+    outln!("{}", "Constant Pressure Heating / Cooling".yellow().bold());
+    outln!("Initial state is the current state.");
+    let initial = &program_state.gas_state;
+    let t_final = read_temperature(program_state, "final temperature").kelvin();
+    let mass_flow = read_f64("Enter mass flow (kg/s):");
+
+    let Some(end_state) = try_gas_state(&program_state.gas_comp, initial.p, t_final) else {
+        outln!("{}", "** Unable to solve the final state **".red().bold());
+        print_gas_state(program_state);
+        return;
+    };
+
+    let dh = end_state.h - initial.h;
+    let t_unit = program_state.units.temp.label();
+    outln!();
+    outln!("{:<30} {:10.4} {:10}", "Pressure: ", get_pressure(initial.p, program_state.units.pressure), program_state.units.pressure.label());
+    outln!("{:<30} {:10.4} {:10}", "Temperature Change: ", get_temperature_difference(t_final - initial.t, program_state.units.temp), t_unit);
+    outln!("{:<30} {:10.4} {:10}", "Specific Duty: ", energy_in_units(dh, initial.mm, program_state.units.internal_energy), program_state.units.internal_energy.label());
+    outln!("{:<30} {:10.4} {:10}", "Average Cp: ",
+        entropy_in_units(dh / (t_final - initial.t), program_state.units.temp), entropy_label(program_state.units.temp));
+    outln!("{:<30} {:10.4} {:10}", "Duty: ", get_power(dh / initial.mm * mass_flow, program_state.units.power), program_state.units.power.label());
+    outln!("{:<30} {:>10}", "Service: ", if dh >= 0.0 { "Heating" } else { "Cooling" });
+
+    outln!();
+    outln!("Use the final state as the current state? (y/n)");
+    if read_input() == "y" {
+        set_gas_state(program_state, end_state);
+    }
+
+    print_gas_state(program_state);
+}
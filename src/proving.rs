@@ -0,0 +1,59 @@
+use colored::Colorize;
+
+use crate::{new_gas_state, print_gas_state, read_f64, read_pressure, read_temperature, Pressure, ProgramState, Temperature};
+
+struct ProvingCorrection {
+    pressure_ratio: f64,
+    temperature_ratio: f64,
+    z_ratio: f64,
+    combined: f64,
+}
+
+// Correction that converts a volume measured at the prover (master meter) condition
+// to the line meter condition.  Mass balance between the two meters gives
+// V_meter = V_prover * (P_prover / P_meter) * (T_meter / T_prover) * (Z_meter / Z_prover).
+fn proving_correction(p_prover: Pressure, t_prover: Temperature, z_prover: f64, p_meter: Pressure, t_meter: Temperature, z_meter: f64) -> ProvingCorrection {
+    let pressure_ratio = p_prover.kpa() / p_meter.kpa();
+    let temperature_ratio = t_meter.kelvin() / t_prover.kelvin();
+    let z_ratio = z_meter / z_prover;
+    ProvingCorrection {
+        pressure_ratio,
+        temperature_ratio,
+        z_ratio,
+        combined: pressure_ratio * temperature_ratio * z_ratio,
+    }
+}
+
+pub fn meter_proving(program_state: &mut ProgramState) {
+    outln!();
+    outln!("{}", "Meter Proving Correction".magenta().bold());
+    let p_prover = read_pressure(program_state, "prover (master meter) pressure");
+    let t_prover = read_temperature(program_state, "prover (master meter) temperature");
+    let p_meter = read_pressure(program_state, "line meter pressure");
+    let t_meter = read_temperature(program_state, "line meter temperature");
+    let prover_volume = read_f64("Enter prover indicated volume:");
+    let prover_factor = read_f64("Enter prover meter factor (1.0 if none):");
+    let meter_volume = read_f64("Enter line meter indicated volume:");
+
+    let prover_state = new_gas_state(&program_state.gas_comp, p_prover.kpa(), t_prover.kelvin());
+    let meter_state = new_gas_state(&program_state.gas_comp, p_meter.kpa(), t_meter.kelvin());
+    let correction = proving_correction(p_prover, t_prover, prover_state.z, p_meter, t_meter, meter_state.z);
+
+    let reference_volume = prover_volume * prover_factor * correction.combined;
+    let meter_factor = reference_volume / meter_volume;
+    let meter_error = (meter_volume - reference_volume) / reference_volume * 100.0;
+
+    outln!();
+    outln!("{:<30} {:10.4} {:10}", "Prover Z: ", prover_state.z, "[]");
+    outln!("{:<30} {:10.4} {:10}", "Line Meter Z: ", meter_state.z, "[]");
+    outln!("{:<30} {:10.6} {:10}", "Pressure Ratio Pp/Pm: ", correction.pressure_ratio, "[]");
+    outln!("{:<30} {:10.6} {:10}", "Temperature Ratio Tm/Tp: ", correction.temperature_ratio, "[]");
+    outln!("{:<30} {:10.6} {:10}", "Z Ratio Zm/Zp: ", correction.z_ratio, "[]");
+    outln!("{:<30} {:10.6} {:10}", "Combined Correction: ", correction.combined, "[]");
+    outln!("{:<30} {:10.4} {:10}", "Prover Volume at Meter: ", reference_volume, "[vol]");
+    outln!("{:<30} {:10.4} {:10}", "Line Meter Volume: ", meter_volume, "[vol]");
+    outln!("{:<30} {:10.6} {:10}", "Meter Factor: ", meter_factor, "[]");
+    outln!("{:<30} {:10.4} {:10}", "Meter Error: ", meter_error, "%");
+
+    print_gas_state(program_state);
+}
@@ -0,0 +1,93 @@
+use aga8::composition::Composition;
+use colored::Colorize;
+
+use crate::components::{comp_to_array, CRITICAL_PRESSURE, CRITICAL_TEMPERATURE};
+use crate::{get_pressure, get_temperature, get_temperature_difference, print_gas_state, ProgramState};
+
+// Mixture pseudo-critical temperature (K) and pressure (kPa)
+#[derive(Clone, Copy)]
+pub struct PseudoCritical {
+    pub t: f64,
+    pub p: f64,
+}
+
+// Kay's rule: mole fraction weighted critical temperatures and pressures
+pub fn kay_rule(gas_comp: &Composition) -> PseudoCritical {
+    let x = comp_to_array(gas_comp);
+    let t = x.iter().zip(CRITICAL_TEMPERATURE).map(|(xi, tc)| xi * tc).sum();
+    let p = x.iter().zip(CRITICAL_PRESSURE).map(|(xi, pc)| xi * pc).sum();
+    PseudoCritical { t, p }
+}
+
+// Wichert and Aziz (1972) temperature adjustment (K) for CO2 and H2S:
+// e (R) = 120 (A^0.9 - A^1.6) + 15 (B^0.5 - B^4) with A = yCO2 + yH2S and B = yH2S
+pub fn wichert_aziz_epsilon(gas_comp: &Composition) -> f64 {
+    let a = gas_comp.carbon_dioxide + gas_comp.hydrogen_sulfide;
+    let b = gas_comp.hydrogen_sulfide;
+    (120.0 * (a.powf(0.9) - a.powf(1.6)) + 15.0 * (b.sqrt() - b.powi(4))) / 1.8
+}
+
+// Sour gas pseudo-critical properties: Tpc' = Tpc - e, Ppc' = Ppc Tpc' / (Tpc + B (1 - B) e)
+pub fn wichert_aziz(kay: PseudoCritical, gas_comp: &Composition) -> PseudoCritical {
+    let epsilon = wichert_aziz_epsilon(gas_comp);
+    let b = gas_comp.hydrogen_sulfide;
+    let t = kay.t - epsilon;
+    PseudoCritical { t, p: kay.p * t / (kay.t + b * (1.0 - b) * epsilon) }
+}
+
+// Pseudo-critical properties of the current composition, with the Wichert-Aziz correction
+// when it is switched on in the settings
+pub fn pseudo_critical(program_state: &ProgramState) -> PseudoCritical {
+    let kay = kay_rule(&program_state.gas_comp);
+    if program_state.wichert_aziz {
+        wichert_aziz(kay, &program_state.gas_comp)
+    } else {
+        kay
+    }
+}
+
+pub fn pseudo_critical_report(program_state: &mut ProgramState) {
+    let units = program_state.units;
+    let state = &program_state.gas_state;
+    let kay = kay_rule(&program_state.gas_comp);
+    let corrected = wichert_aziz(kay, &program_state.gas_comp);
+    outln!();
+    outln!("{}", "Pseudo-Critical Properties (Kay's rule)".cyan().bold());
+    outln!("{:<30} {:10.4} {:10}", "Pseudo-Critical Temperature: ", get_temperature(kay.t, units.temp), units.temp.label());
+    outln!("{:<30} {:10.4} {:10}", "Pseudo-Critical Pressure: ", get_pressure(kay.p, units.pressure), units.pressure.label());
+    outln!("{:<30} {:10.4} {:10}", "Reduced Temperature Tr: ", state.t / kay.t, "[]");
+    outln!("{:<30} {:10.4} {:10}", "Reduced Pressure Pr: ", state.p / kay.p, "[]");
+    outln!();
+    outln!("{}", "Wichert-Aziz Sour Gas Correction".bold());
+    outln!("{:<30} {:10.4} {:10}", "Adjustment e: ", get_temperature_difference(wichert_aziz_epsilon(&program_state.gas_comp), units.temp), units.temp.label());
+    outln!("{:<30} {:10.4} {:10}", "Corrected Temperature Tpc': ", get_temperature(corrected.t, units.temp), units.temp.label());
+    outln!("{:<30} {:10.4} {:10}", "Corrected Pressure Ppc': ", get_pressure(corrected.p, units.pressure), units.pressure.label());
+    outln!("{:<30} {:10.4} {:10}", "Reduced Temperature Tr: ", state.t / corrected.t, "[]");
+    outln!("{:<30} {:10.4} {:10}", "Reduced Pressure Pr: ", state.p / corrected.p, "[]");
+    outln!();
+    outln!("The state display uses the {} values (see settings).", if program_state.wichert_aziz { "corrected" } else { "Kay's rule" });
+    print_gas_state(program_state);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Wichert and Aziz (1972) example as worked by Ahmed: 10% CO2 and 20% H2S give an
+    // adjustment of 29.8 R
+    #[test]
+    fn wichert_aziz_adjustment() {
+        let gas_comp = Composition { methane: 0.7, carbon_dioxide: 0.1, hydrogen_sulfide: 0.2, ..Default::default() };
+        assert!((wichert_aziz_epsilon(&gas_comp) * 1.8 - 29.8).abs() < 0.05);
+        let kay = kay_rule(&gas_comp);
+        let sour = wichert_aziz(kay, &gas_comp);
+        assert!(sour.t < kay.t && sour.p < kay.p);
+    }
+
+    // Kay's rule of a pure component is its critical point
+    #[test]
+    fn kay_rule_pure_methane() {
+        let kay = kay_rule(&Composition { methane: 1.0, ..Default::default() });
+        assert_eq!((kay.t, kay.p), (CRITICAL_TEMPERATURE[0], CRITICAL_PRESSURE[0]));
+    }
+}
@@ -0,0 +1,149 @@
+// Python extension module gas_thermodynamics._native, built with maturin from
+// python/pyproject.toml:
+//   cd python && maturin develop --release
+// The package in python/gas_thermodynamics wraps it with the Composition class.
+// Compositions are sequences of mole fractions in the order of COMPONENTS, pressures
+// are in kPa and temperatures in K, as in the C API.
+
+use aga8::composition::Composition;
+use aga8::detail::Detail;
+use pyo3::create_exception;
+use pyo3::exceptions::{PyRuntimeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+use crate::components::{array_to_comp, COMPONENT_NAMES, NUM_COMPONENTS};
+use crate::ffi::CompPerfState;
+use crate::thermo::{compressed_state, throttled_state, try_gas_state};
+use crate::units::{get_pressure, get_temperature, pressure_to_kpa, temperature_to_kelvin, UnitPressure, UnitTemp, PRESSURE_UNITS, TEMP_UNITS};
+
+create_exception!(_native, CalculationError, PyRuntimeError);
+
+fn fields(state: &CompPerfState) -> [(&'static str, f64); 14] {
+    [
+        ("p", state.p),
+        ("t", state.t),
+        ("d", state.d),
+        ("z", state.z),
+        ("mm", state.mm),
+        ("u", state.u),
+        ("h", state.h),
+        ("s", state.s),
+        ("cp", state.cp),
+        ("cv", state.cv),
+        ("w", state.w),
+        ("kappa", state.kappa),
+        ("g", state.g),
+        ("jt", state.jt),
+    ]
+}
+
+#[pymethods]
+impl CompPerfState {
+    fn as_dict<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let dict = PyDict::new(py);
+        for (name, value) in fields(self) {
+            dict.set_item(name, value)?;
+        }
+        Ok(dict)
+    }
+
+    fn __repr__(&self) -> String {
+        let fields: Vec<String> = fields(self).iter().map(|(name, value)| format!("{}={:.6}", name, value)).collect();
+        format!("State({})", fields.join(", "))
+    }
+}
+
+fn read_composition(x: Vec<f64>) -> PyResult<Composition> {
+    let x: [f64; NUM_COMPONENTS] = x.try_into()
+        .map_err(|_| PyValueError::new_err(format!("Composition needs {} mole fractions", NUM_COMPONENTS)))?;
+    let gas_comp = array_to_comp(&x);
+    Detail::new().set_composition(&gas_comp)
+        .map_err(|_| CalculationError::new_err("invalid composition (mole fractions must sum to 1)"))?;
+    Ok(gas_comp)
+}
+
+fn solve(x: Vec<f64>, calculate: impl FnOnce(&Composition) -> Option<Detail>) -> PyResult<CompPerfState> {
+    let gas_comp = read_composition(x)?;
+    calculate(&gas_comp).map(|state| CompPerfState::of(&state))
+        .ok_or_else(|| CalculationError::new_err("no solution (the state or process could not be solved)"))
+}
+
+fn pressure_unit(unit: &str) -> PyResult<UnitPressure> {
+    UnitPressure::from_label(unit).ok_or_else(|| PyValueError::new_err(format!("Unknown pressure unit '{}'", unit)))
+}
+
+fn temperature_unit(unit: &str) -> PyResult<UnitTemp> {
+    UnitTemp::from_label(unit).ok_or_else(|| PyValueError::new_err(format!("Unknown temperature unit '{}'", unit)))
+}
+
+/// Properties at pressure p (kPa) and temperature t (K).
+#[pyfunction]
+fn state(x: Vec<f64>, p: f64, t: f64) -> PyResult<CompPerfState> {
+    solve(x, |gas_comp| try_gas_state(gas_comp, p, t))
+}
+
+/// Discharge state of a compression from p1 (kPa) and t1 (K) to a higher p2 (kPa) with
+/// an isentropic efficiency between 0 and 1.
+#[pyfunction]
+fn compress(x: Vec<f64>, p1: f64, t1: f64, p2: f64, efficiency: f64) -> PyResult<CompPerfState> {
+    if !(efficiency > 0.0 && efficiency <= 1.0) {
+        return Err(PyValueError::new_err("Efficiency must be between 0 and 1"));
+    }
+    if p2 <= p1 {
+        return Err(PyValueError::new_err("Discharge pressure must be above the inlet pressure"));
+    }
+    solve(x, |gas_comp| compressed_state(gas_comp, p1, t1, p2, efficiency))
+}
+
+/// Downstream state of isenthalpic throttling from p1 (kPa) and t1 (K) to a lower p2 (kPa).
+#[pyfunction]
+fn throttle(x: Vec<f64>, p1: f64, t1: f64, p2: f64) -> PyResult<CompPerfState> {
+    if p2 >= p1 {
+        return Err(PyValueError::new_err("Downstream pressure must be below the upstream pressure"));
+    }
+    solve(x, |gas_comp| throttled_state(gas_comp, p1, t1, p2))
+}
+
+/// Converts a pressure in one of PRESSURE_UNITS to kPa.
+#[pyfunction(name = "pressure_to_kpa")]
+fn pressure_to_kpa_py(value: f64, unit: &str) -> PyResult<f64> {
+    Ok(pressure_to_kpa(value, pressure_unit(unit)?))
+}
+
+/// Converts a pressure in kPa to one of PRESSURE_UNITS.
+#[pyfunction]
+fn pressure_from_kpa(value: f64, unit: &str) -> PyResult<f64> {
+    Ok(get_pressure(value, pressure_unit(unit)?))
+}
+
+/// Converts a temperature in one of TEMPERATURE_UNITS to K.
+#[pyfunction(name = "temperature_to_kelvin")]
+fn temperature_to_kelvin_py(value: f64, unit: &str) -> PyResult<f64> {
+    Ok(temperature_to_kelvin(value, temperature_unit(unit)?))
+}
+
+/// Converts a temperature in K to one of TEMPERATURE_UNITS.
+#[pyfunction]
+fn temperature_from_kelvin(value: f64, unit: &str) -> PyResult<f64> {
+    Ok(get_temperature(value, temperature_unit(unit)?))
+}
+
+#[pymodule]
+#[pyo3(name = "_native")]
+fn native(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add("COMPONENTS", COMPONENT_NAMES)?;
+    // Unit labels, matched case-insensitively
+    m.add("PRESSURE_UNITS", PRESSURE_UNITS.map(UnitPressure::label))?;
+    m.add("TEMPERATURE_UNITS", TEMP_UNITS.map(UnitTemp::label))?;
+    m.add("CalculationError", m.py().get_type::<CalculationError>())?;
+    m.add_class::<CompPerfState>()?;
+    m.add_function(wrap_pyfunction!(state, m)?)?;
+    m.add_function(wrap_pyfunction!(compress, m)?)?;
+    m.add_function(wrap_pyfunction!(throttle, m)?)?;
+    m.add_function(wrap_pyfunction!(pressure_to_kpa_py, m)?)?;
+    m.add_function(wrap_pyfunction!(pressure_from_kpa, m)?)?;
+    m.add_function(wrap_pyfunction!(temperature_to_kelvin_py, m)?)?;
+    m.add_function(wrap_pyfunction!(temperature_from_kelvin, m)?)?;
+    Ok(())
+}
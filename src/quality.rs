@@ -0,0 +1,50 @@
+use aga8::composition::Composition;
+
+use crate::components::{comp_to_array, GROSS_HV};
+use crate::{get_gas_comp, new_gas_state, GasComp, Pressure, Temperature};
+
+// Molar gas constant in J/(mol-K)
+pub const R: f64 = 8.314462618;
+
+// Ideal gross heating value in kJ/mol
+pub fn gross_hv_molar(gas_comp: &Composition) -> f64 {
+    comp_to_array(gas_comp).iter().zip(GROSS_HV.iter()).map(|(x, hv)| x * hv).sum()
+}
+
+// Real-gas gross heating value in MJ/m3 at the base pressure and temperature
+pub fn gross_hv_volumetric(gas_comp: &Composition, p_base: Pressure, t_base: Temperature) -> f64 {
+    let (p_base, t_base) = (p_base.kpa(), t_base.kelvin());
+    let z = new_gas_state(gas_comp, p_base, t_base).z;
+    gross_hv_molar(gas_comp) * p_base / (z * R * t_base)
+}
+
+// Real-gas relative density (air = 1) at the base pressure and temperature
+pub fn relative_density(gas_comp: &Composition, p_base: Pressure, t_base: Temperature) -> f64 {
+    let (p_base, t_base) = (p_base.kpa(), t_base.kelvin());
+    let gas = new_gas_state(gas_comp, p_base, t_base);
+    let air = new_gas_state(&get_gas_comp(GasComp::Air), p_base, t_base);
+    (gas.mm / gas.z) / (air.mm / air.z)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // ISO 6976 methane at 25 C combustion and 15 C metering: ideal superior heating value
+    // 37.706 MJ/m3 and compression factor 0.9981 give 37.778 MJ/m3 real
+    #[test]
+    fn methane_superior_heating_value() {
+        let methane = Composition { methane: 1.0, ..Default::default() };
+        let (p_base, t_base) = (Pressure::from_kpa(101.325), Temperature::from_kelvin(288.15));
+        assert!((gross_hv_volumetric(&methane, p_base, t_base) - 37.778).abs() < 0.01);
+    }
+
+    // ISO 6976 methane at 15 C metering: the ideal relative density 0.55386 with the
+    // compression factors 0.99958 of air and 0.9981 of methane gives 0.5547 real
+    #[test]
+    fn methane_relative_density() {
+        let methane = Composition { methane: 1.0, ..Default::default() };
+        let (p_base, t_base) = (Pressure::from_kpa(101.325), Temperature::from_kelvin(288.15));
+        assert!((relative_density(&methane, p_base, t_base) - 0.5547).abs() < 2.0e-4);
+    }
+}
@@ -0,0 +1,289 @@
+use aga8::composition::Composition;
+use aga8::detail::Detail;
+use rayon::prelude::*;
+
+use comp_perf::frontend::Frontend;
+use comp_perf::thermo::solve_state;
+
+use crate::components::{array_to_comp, comp_to_array, composition_from_fractions, NUM_COMPONENTS};
+use crate::{get_gas_comp, GasComp};
+
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, RwLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// Interpolated properties, in this order in each table node
+pub const TABLE_PROPERTIES: [&str; 10] = ["d", "z", "mm", "h", "s", "cp", "cv", "w", "kappa", "jt"];
+const NUM_PROPERTIES: usize = TABLE_PROPERTIES.len();
+// Units of the interpolated properties, as in CompPerfState
+const TABLE_UNITS: [&str; NUM_PROPERTIES] = ["mol/l", "", "g/mol", "J/mol", "J/(mol-K)", "J/(mol-K)", "J/(mol-K)", "m/s", "", "K/kPa"];
+
+// The table properties of a solved state
+pub fn table_values(state: &Detail) -> [f64; NUM_PROPERTIES] {
+    [state.d, state.z, state.mm, state.h, state.s, state.cp, state.cv, state.w, state.kappa, state.jt]
+}
+
+// Pressure range in kPa and temperature range in K with the number of nodes in each
+#[derive(Clone, Copy)]
+pub struct TableSpec {
+    pub p_min: f64,
+    pub p_max: f64,
+    pub np: usize,
+    pub t_min: f64,
+    pub t_max: f64,
+    pub nt: usize,
+}
+
+impl Default for TableSpec {
+    fn default() -> TableSpec {
+        TableSpec { p_min: 100.0, p_max: 15_000.0, np: 80, t_min: 250.0, t_max: 400.0, nt: 61 }
+    }
+}
+
+// Properties on a pressure-temperature grid for one composition.  Nodes where DETAIL
+// fails hold NaN and the cells around them are outside the table.
+pub struct PropertyTable {
+    spec: TableSpec,
+    x: [f64; NUM_COMPONENTS],
+    nodes: Vec<[f64; NUM_PROPERTIES]>,
+    pub built_at: Instant,
+    pub build_time: Duration,
+}
+
+impl PropertyTable {
+    pub fn build(gas_comp: &Composition, spec: TableSpec) -> PropertyTable {
+        let start = Instant::now();
+        // Nodes in row-major order, evaluated in parallel with one DETAIL instance per worker
+        let nodes = (0..spec.np * spec.nt).into_par_iter().map_init(
+            || {
+                let mut state = Detail::new();
+                state.set_composition(gas_comp).ok().map(|_| state)
+            },
+            |state, index| {
+                let p = spec.p_min + (spec.p_max - spec.p_min) * (index / spec.nt) as f64 / (spec.np - 1) as f64;
+                let t = spec.t_min + (spec.t_max - spec.t_min) * (index % spec.nt) as f64 / (spec.nt - 1) as f64;
+                match state.as_mut().and_then(|s| solve_state(s, p, t).map(|_| s)) {
+                    Some(s) => table_values(s),
+                    None => [f64::NAN; NUM_PROPERTIES],
+                }
+            },
+        ).collect();
+        PropertyTable { spec, x: comp_to_array(gas_comp), nodes, built_at: Instant::now(), build_time: start.elapsed() }
+    }
+
+    // Bilinear interpolation at p (kPa) and t (K); None outside the table
+    pub fn lookup(&self, p: f64, t: f64) -> Option<[f64; NUM_PROPERTIES]> {
+        let spec = &self.spec;
+        let fi = (p - spec.p_min) / (spec.p_max - spec.p_min) * (spec.np - 1) as f64;
+        let fj = (t - spec.t_min) / (spec.t_max - spec.t_min) * (spec.nt - 1) as f64;
+        if !(0.0..=(spec.np - 1) as f64).contains(&fi) || !(0.0..=(spec.nt - 1) as f64).contains(&fj) {
+            return None;
+        }
+        let (i, j) = ((fi as usize).min(spec.np - 2), (fj as usize).min(spec.nt - 2));
+        let (u, v) = (fi - i as f64, fj - j as f64);
+        let node = |i: usize, j: usize| &self.nodes[i * spec.nt + j];
+        let mut values = [0.0; NUM_PROPERTIES];
+        for (k, value) in values.iter_mut().enumerate() {
+            *value = (1.0 - u) * (1.0 - v) * node(i, j)[k] + u * (1.0 - v) * node(i + 1, j)[k]
+                + (1.0 - u) * v * node(i, j + 1)[k] + u * v * node(i + 1, j + 1)[k];
+        }
+        values.iter().all(|value| value.is_finite()).then_some(values)
+    }
+}
+
+pub struct Evaluation {
+    // None outside the table or before the first table is built
+    pub values: Option<[f64; NUM_PROPERTIES]>,
+    // Time since the table in use was built
+    pub age: Duration,
+    // The table is for an earlier composition; a refresh is in progress
+    pub stale: bool,
+}
+
+// Answers lookups from the latest table without waiting for DETAIL.  Composition changes
+// rebuild the table on a background thread; until it is swapped in, results come from
+// the previous table and are flagged stale.
+pub struct RealtimeEvaluator {
+    table: Arc<RwLock<Option<Arc<PropertyTable>>>>,
+    requested: [f64; NUM_COMPONENTS],
+    refresh: Sender<[f64; NUM_COMPONENTS]>,
+}
+
+fn refresh_worker(receiver: Receiver<[f64; NUM_COMPONENTS]>, table: Arc<RwLock<Option<Arc<PropertyTable>>>>, spec: TableSpec) {
+    while let Ok(mut x) = receiver.recv() {
+        // Only the newest of several queued compositions is built
+        while let Ok(newer) = receiver.try_recv() {
+            x = newer;
+        }
+        let built = Arc::new(PropertyTable::build(&array_to_comp(&x), spec));
+        *table.write().unwrap() = Some(built);
+    }
+}
+
+impl RealtimeEvaluator {
+    pub fn new(gas_comp: &Composition, spec: TableSpec) -> RealtimeEvaluator {
+        let table = Arc::new(RwLock::new(None));
+        let (refresh, receiver) = mpsc::channel();
+        let worker_table = Arc::clone(&table);
+        thread::spawn(move || refresh_worker(receiver, worker_table, spec));
+        let evaluator = RealtimeEvaluator { table, requested: comp_to_array(gas_comp), refresh };
+        evaluator.refresh.send(evaluator.requested).ok();
+        evaluator
+    }
+
+    pub fn set_composition(&mut self, gas_comp: &Composition) {
+        let x = comp_to_array(gas_comp);
+        if x != self.requested {
+            self.requested = x;
+            self.refresh.send(x).ok();
+        }
+    }
+
+    fn current_table(&self) -> Option<Arc<PropertyTable>> {
+        self.table.read().unwrap().clone()
+    }
+
+    pub fn evaluate(&self, p: f64, t: f64) -> Evaluation {
+        match self.current_table() {
+            Some(table) => Evaluation { values: table.lookup(p, t), age: table.built_at.elapsed(), stale: table.x != self.requested },
+            None => Evaluation { values: None, age: Duration::ZERO, stale: true },
+        }
+    }
+
+    // Seconds since the table was built and its build time, None before the first table
+    pub fn table_status(&self) -> Option<(Duration, Duration)> {
+        self.current_table().map(|table| (table.built_at.elapsed(), table.build_time))
+    }
+}
+
+// Mole fractions from "name=value" pairs, e.g. "methane=0.9 ethane=0.1"
+fn parse_composition(pairs: &[&str]) -> Result<Composition, String> {
+    let fractions = pairs.iter()
+        .map(|pair| {
+            let (name, value) = pair.split_once('=').ok_or(format!("Expected name=value, got '{}'", pair))?;
+            Ok((name, value.parse::<f64>().map_err(|_| format!("Invalid number '{}'", value))?))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    composition_from_fractions(fractions)
+}
+
+pub fn format_evaluation(evaluation: &Evaluation) -> String {
+    let status = format!("age_ms={} stale={}", evaluation.age.as_millis(), u8::from(evaluation.stale));
+    match evaluation.values {
+        Some(values) => {
+            let fields: Vec<String> = TABLE_PROPERTIES.iter().zip(values).map(|(name, value)| format!("{}={}", name, value)).collect();
+            format!("ok {} {}", fields.join(" "), status)
+        },
+        None => format!("error outside_table {}", status),
+    }
+}
+
+// Interpolated properties at p (kPa) and t (K) for a display, with the age of the table
+// and whether it is still being rebuilt for a new composition
+pub fn format_table_state(evaluator: &RealtimeEvaluator, p: f64, t: f64) -> String {
+    if evaluator.table_status().is_none() {
+        return "Property table is being built\n".to_string();
+    }
+    let evaluation = evaluator.evaluate(p, t);
+    let mut out = String::new();
+    match evaluation.values {
+        Some(values) => {
+            for ((name, value), unit) in TABLE_PROPERTIES.iter().zip(values).zip(TABLE_UNITS) {
+                out.push_str(&format!("{:<30} {:10.4} {:10}\n", name, value, unit));
+            }
+        },
+        None => out.push_str("Outside the property table\n"),
+    }
+    let stale = if evaluation.stale { " (stale, rebuilding for the new composition)" } else { "" };
+    out.push_str(&format!("Property table built {:.1} s ago{}\n", evaluation.age.as_secs_f64(), stale));
+    out
+}
+
+// Line protocol over the front end with a fixed, table-bounded cost per request:
+//   <p kPa> <t K>              properties by interpolation
+//   composition name=x ...     new composition, table rebuilt in the background
+//   status                     age and build time of the table in use
+// Starts with natural gas over the default table range.
+pub fn run_realtime(frontend: &mut impl Frontend) -> i32 {
+    let mut evaluator = RealtimeEvaluator::new(&get_gas_comp(GasComp::NaturalGas), TableSpec::default());
+    while let Some(line) = frontend.read_line() {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let response = match words.as_slice() {
+            [] => continue,
+            ["quit"] => return 0,
+            ["status"] => match evaluator.table_status() {
+                Some((age, build_time)) => format!("ok age_ms={} build_ms={}", age.as_millis(), build_time.as_millis()),
+                None => "ok building".to_string(),
+            },
+            ["composition", pairs @ ..] => match parse_composition(pairs) {
+                Ok(gas_comp) => {
+                    evaluator.set_composition(&gas_comp);
+                    "ok refreshing".to_string()
+                },
+                Err(err) => format!("error {}", err),
+            },
+            [p, t] => match (p.parse::<f64>(), t.parse::<f64>()) {
+                (Ok(p), Ok(t)) => format_evaluation(&evaluator.evaluate(p, t)),
+                _ => "error invalid_number".to_string(),
+            },
+            _ => "error unknown_command".to_string(),
+        };
+        frontend.write_line(&response);
+    }
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SPEC: TableSpec = TableSpec { p_min: 100.0, p_max: 300.0, np: 3, t_min: 250.0, t_max: 400.0, nt: 4 };
+
+    // Nodes linear in p and t, which bilinear interpolation reproduces exactly
+    fn linear_table() -> PropertyTable {
+        let nodes = (0..SPEC.np * SPEC.nt)
+            .map(|index| {
+                let (p, t) = (100.0 + 100.0 * (index / SPEC.nt) as f64, 250.0 + 50.0 * (index % SPEC.nt) as f64);
+                std::array::from_fn(|k| p + 10.0 * t + k as f64)
+            })
+            .collect();
+        PropertyTable { spec: SPEC, x: [0.0; NUM_COMPONENTS], nodes, built_at: Instant::now(), build_time: Duration::ZERO }
+    }
+
+    // The table edges are inside, anything past them or not a number is outside
+    #[test]
+    fn lookup_at_the_bounds() {
+        let table = linear_table();
+        for (p, t) in [(100.0, 250.0), (300.0, 400.0), (100.0, 400.0), (300.0, 250.0), (175.0, 333.0)] {
+            assert!((table.lookup(p, t).unwrap()[3] - (p + 10.0 * t + 3.0)).abs() < 1.0e-9, "{} {}", p, t);
+        }
+        for (p, t) in [(99.9, 300.0), (300.1, 300.0), (200.0, 249.9), (200.0, 400.1), (f64::NAN, 300.0), (200.0, f64::INFINITY)] {
+            assert!(table.lookup(p, t).is_none(), "{} {}", p, t);
+        }
+    }
+
+    // A failed node takes out the cells around it and no others
+    #[test]
+    fn lookup_around_a_nan_node() {
+        let mut table = linear_table();
+        // p 200 kPa, t 300 K
+        table.nodes[SPEC.nt + 1] = [f64::NAN; NUM_PROPERTIES];
+        assert!(table.lookup(200.0, 300.0).is_none());
+        assert!(table.lookup(150.0, 275.0).is_none());
+        assert!(table.lookup(250.0, 325.0).is_none());
+        assert!(table.lookup(150.0, 375.0).is_some());
+        assert!(table.lookup(250.0, 375.0).is_some());
+    }
+
+    // Table nodes are the DETAIL states
+    #[test]
+    fn built_nodes_match_detail() {
+        let gas_comp = get_gas_comp(GasComp::NaturalGas);
+        let table = PropertyTable::build(&gas_comp, SPEC);
+        let mut state = Detail::new();
+        state.set_composition(&gas_comp).unwrap();
+        solve_state(&mut state, 200.0, 300.0).unwrap();
+        assert_eq!(table.lookup(200.0, 300.0).unwrap(), table_values(&state));
+    }
+}
@@ -0,0 +1,152 @@
+use colored::Colorize;
+
+use crate::csv::{field_f64, CsvTable};
+use crate::quality::gross_hv_volumetric;
+use crate::{print_gas_state, read_input, ProgramState};
+
+struct EnergyRecord {
+    point: String,
+    receipt: bool,
+    // Standard volume at base conditions in m3
+    volume: f64,
+    // Heating value used for the recomputation in MJ/m3
+    heating_value: f64,
+    // Energy in GJ
+    energy: f64,
+    reported_energy: Option<f64>,
+}
+
+// Reads a CSV with the columns point, type (receipt/delivery), volume and the optional
+// columns heating_value (MJ/m3) and reported_energy (GJ).  Rows without a heating value
+// use the current composition's gross heating value at the configured base conditions.
+fn read_energy_records(path: &str, default_hv: f64) -> Result<Vec<EnergyRecord>, String> {
+    let table = CsvTable::read(path)?;
+    let point_col = table.require_column("point")?;
+    let type_col = table.require_column("type")?;
+    let volume_col = table.require_column("volume")?;
+    let hv_col = table.column("heating_value");
+    let reported_col = table.column("reported_energy");
+
+    let mut records = Vec::new();
+    for (line, row) in table.rows.iter().enumerate() {
+        let row_error = |err: String| format!("Row {}: {}", line + 2, err);
+        let receipt = match row.get(type_col).map(|field| field.to_lowercase()).as_deref() {
+            Some("receipt") => true,
+            Some("delivery") => false,
+            _ => return Err(row_error("type must be 'receipt' or 'delivery'".to_string())),
+        };
+        let volume = field_f64(row, Some(volume_col)).map_err(row_error)?.ok_or(row_error("missing volume".to_string()))?;
+        let heating_value = field_f64(row, hv_col).map_err(row_error)?.unwrap_or(default_hv);
+        let reported_energy = field_f64(row, reported_col).map_err(row_error)?;
+        records.push(EnergyRecord {
+            point: row.get(point_col).cloned().unwrap_or_default(),
+            receipt,
+            volume,
+            heating_value,
+            energy: volume * heating_value / 1000.0,
+            reported_energy,
+        });
+    }
+    Ok(records)
+}
+
+// Receipt and delivery energy in GJ and the imbalance in percent of the receipts, None
+// without receipt energy
+fn balance(records: &[EnergyRecord]) -> (f64, f64, Option<f64>) {
+    let receipts: f64 = records.iter().filter(|record| record.receipt).map(|record| record.energy).sum();
+    let deliveries: f64 = records.iter().filter(|record| !record.receipt).map(|record| record.energy).sum();
+    let percent = (receipts != 0.0).then(|| (receipts - deliveries) / receipts * 100.0);
+    (receipts, deliveries, percent)
+}
+
+pub fn energy_reconciliation(program_state: &mut ProgramState) {
+    outln!();
+    outln!("{}", "Energy Imbalance Reconciliation".magenta().bold());
+    outln!("Enter CSV file path (columns: point,type,volume[,heating_value,reported_energy]):");
+    let path = read_input();
+
+    let default_hv = gross_hv_volumetric(&program_state.gas_comp, program_state.base_pressure, program_state.base_temperature);
+    let records = match read_energy_records(&path, default_hv) {
+        Ok(records) => records,
+        Err(err) => {
+            outln!("{}", format!("** {} **", err).red().bold());
+            print_gas_state(program_state);
+            return;
+        }
+    };
+
+    outln!();
+    outln!("{:<16} {:>9} {:>14} {:>10} {:>12} {:>12} {:>10}",
+        "Point", "Type", "Volume (m3)", "HV MJ/m3", "Energy GJ", "Reported GJ", "Diff GJ");
+    for record in &records {
+        let (reported, diff) = match record.reported_energy {
+            Some(reported) => (format!("{:12.3}", reported), format!("{:10.3}", record.energy - reported)),
+            None => (format!("{:>12}", "-"), format!("{:>10}", "-")),
+        };
+        outln!("{:<16} {:>9} {:14.2} {:10.4} {:12.3} {} {}",
+            record.point, if record.receipt { "Receipt" } else { "Delivery" }, record.volume, record.heating_value, record.energy, reported, diff);
+    }
+
+    let (receipts, deliveries, percent) = balance(&records);
+    let imbalance = receipts - deliveries;
+    outln!();
+    outln!("{:<30} {:10.4} {:10}", "Default Heating Value: ", default_hv, "MJ/m3");
+    outln!("{:<30} {:10.3} {:10}", "Total Receipts: ", receipts, "GJ");
+    outln!("{:<30} {:10.3} {:10}", "Total Deliveries: ", deliveries, "GJ");
+    outln!("{:<30} {:10.3} {:10}", "Imbalance: ", imbalance, "GJ");
+    match percent {
+        Some(percent) => outln!("{:<30} {:10.4} {:10}", "Imbalance of Receipts: ", percent, "%"),
+        None => outln!("{:<30} {:>10} {:10}", "Imbalance of Receipts: ", "-", "(no receipt energy)"),
+    }
+
+    print_gas_state(program_state);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs;
+
+    // Reads records written to a temporary file with a default heating value of 38 MJ/m3
+    fn read(name: &str, text: &str) -> Result<Vec<EnergyRecord>, String> {
+        let path = std::env::temp_dir().join(format!("comp_perf_{}_{}.csv", name, std::process::id()));
+        fs::write(&path, text).unwrap();
+        let records = read_energy_records(path.to_str().unwrap(), 38.0);
+        fs::remove_file(&path).ok();
+        records
+    }
+
+    #[test]
+    fn records_and_balance() {
+        let text = "point,type,volume,heating_value,reported_energy\n\
+                    Inlet A,Receipt,100000,39.0,3895\n\
+                    Inlet B,RECEIPT,50000,,\n\
+                    Town,delivery,140000,38.5,\n";
+        let records = read("reconciliation_records", text).unwrap();
+        assert_eq!((records[0].energy, records[0].reported_energy), (3900.0, Some(3895.0)));
+        // A blank heating value is the default
+        assert_eq!((records[1].heating_value, records[1].energy), (38.0, 1900.0));
+        let (receipts, deliveries, percent) = balance(&records);
+        assert_eq!((receipts, deliveries), (5800.0, 5390.0));
+        assert!((percent.unwrap() - 410.0 / 58.0).abs() < 1.0e-12);
+    }
+
+    // Deliveries only give no imbalance percentage rather than an infinite one
+    #[test]
+    fn no_receipts() {
+        let records = read("reconciliation_deliveries", "point,type,volume\nTown,delivery,1000\n").unwrap();
+        assert_eq!(balance(&records), (0.0, 38.0, None));
+        assert_eq!(balance(&[]), (0.0, 0.0, None));
+    }
+
+    // Errors name the file row, counting the header as row 1
+    #[test]
+    fn row_errors() {
+        let error = |name: &str, text: &str| read(name, text).err().unwrap();
+        assert_eq!(error("reconciliation_type", "point,type,volume\nA,receipt,1\nB,transfer,1\n"), "Row 3: type must be 'receipt' or 'delivery'");
+        assert_eq!(error("reconciliation_volume", "point,type,volume\nA,receipt,\n"), "Row 2: missing volume");
+        assert_eq!(error("reconciliation_number", "point,type,volume\nA,receipt,1e3x\n"), "Row 2: Invalid number '1e3x'");
+        assert_eq!(error("reconciliation_column", "point,volume\nA,1\n"), "Missing column 'type'");
+    }
+}
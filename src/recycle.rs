@@ -0,0 +1,158 @@
+use aga8::composition::Composition;
+use colored::Colorize;
+
+use crate::blending::{mix_streams, MixStream};
+use crate::components::{array_to_comp, comp_to_array};
+use crate::process::{isenthalpic_state, isentropic_state};
+use crate::{
+    change_conditions, get_power, print_gas_state, print_state_errors, read_f64, read_input, read_pressure, read_temperature, try_gas_state, update_gas_state,
+    Pressure, ProgramState, Temperature,
+};
+
+const MAX_ITERATIONS: usize = 200;
+// Convergence on the recycle temperature (K) and mole fractions
+const TEMPERATURE_TOLERANCE: f64 = 1.0e-6;
+const COMPOSITION_TOLERANCE: f64 = 1.0e-10;
+
+pub struct RecycleInputs {
+    // Fresh feed mass flow in kg/s and temperature, at the suction pressure
+    pub feed_flow: f64,
+    pub t_feed: Temperature,
+    pub p_suction: Pressure,
+    pub p_discharge: Pressure,
+    pub efficiency: f64,
+    // Recycle mass flow in kg/s, taken after the aftercooler at t_cooler or, when None,
+    // from the hot compressor discharge
+    pub recycle_flow: f64,
+    pub t_cooler: Option<Temperature>,
+}
+
+pub struct RecycleLoop {
+    pub iterations: usize,
+    pub suction_comp: Composition,
+    pub t_suction: Temperature,
+    pub t_discharge: Temperature,
+    pub t_recycle_upstream: Temperature,
+    pub t_recycle: Temperature,
+    // Compressor power and aftercooler duty in kW
+    pub power: f64,
+    pub cooler_duty: f64,
+}
+
+// One pass around the loop: mix the feed with the recycle, compress, cool and throttle
+// the recycle back to suction.  Returns the loop states and the new recycle stream.
+fn loop_pass(feed_comp: &Composition, recycle_comp: &Composition, t_recycle: Temperature, inputs: &RecycleInputs) -> Option<RecycleLoop> {
+    let streams = [
+        MixStream { gas_comp: array_to_comp(&comp_to_array(feed_comp)), mass_flow: inputs.feed_flow, p: inputs.p_suction, t: inputs.t_feed },
+        MixStream { gas_comp: array_to_comp(&comp_to_array(recycle_comp)), mass_flow: inputs.recycle_flow, p: inputs.p_suction, t: t_recycle },
+    ];
+    let (suction_comp, t_suction) = mix_streams(&streams, inputs.p_suction)?;
+    let (p_suction, p_discharge) = (inputs.p_suction.kpa(), inputs.p_discharge.kpa());
+    let suction = try_gas_state(&suction_comp, p_suction, t_suction.kelvin())?;
+    let t_guess = t_suction.kelvin() * (p_discharge / p_suction).powf((suction.kappa - 1.0) / suction.kappa);
+    let isentropic = isentropic_state(&suction_comp, p_discharge, suction.s, t_guess)?;
+    let h_discharge = suction.h + (isentropic.h - suction.h) / inputs.efficiency;
+    let discharge = isenthalpic_state(&suction_comp, p_discharge, h_discharge, isentropic.t)?;
+
+    let total_flow = inputs.feed_flow + inputs.recycle_flow;
+    // J/mol to kJ/kg is 1/mm, times kg/s gives kW
+    let power = (discharge.h - suction.h) / suction.mm * total_flow;
+    let t_discharge = Temperature::from_kelvin(discharge.t);
+    let (upstream, cooler_duty) = match inputs.t_cooler {
+        Some(t_cooler) => {
+            let cooled = try_gas_state(&suction_comp, p_discharge, t_cooler.kelvin())?;
+            let duty = (discharge.h - cooled.h) / suction.mm * total_flow;
+            (cooled, duty)
+        },
+        None => (discharge, 0.0),
+    };
+    let recycle = isenthalpic_state(&suction_comp, p_suction, upstream.h, upstream.t)?;
+    Some(RecycleLoop {
+        iterations: 0,
+        t_suction,
+        t_discharge,
+        t_recycle_upstream: Temperature::from_kelvin(upstream.t),
+        t_recycle: Temperature::from_kelvin(recycle.t),
+        power,
+        cooler_duty,
+        suction_comp,
+    })
+}
+
+// Converges the recycle stream by direct substitution, starting from a recycle at the
+// feed composition and temperature.
+pub fn converge_recycle(feed_comp: &Composition, inputs: &RecycleInputs) -> Result<RecycleLoop, String> {
+    let mut recycle_comp = array_to_comp(&comp_to_array(feed_comp));
+    let mut t_recycle = inputs.t_feed;
+    for iteration in 1..=MAX_ITERATIONS {
+        let mut pass = loop_pass(feed_comp, &recycle_comp, t_recycle, inputs)
+            .ok_or_else(|| format!("Unable to solve the loop states at iteration {}", iteration))?;
+        let dx = comp_to_array(&pass.suction_comp).iter().zip(comp_to_array(&recycle_comp))
+            .map(|(new, old)| (new - old).abs())
+            .fold(0.0, f64::max);
+        let dt = (pass.t_recycle - t_recycle).kelvin().abs();
+        if dt < TEMPERATURE_TOLERANCE && dx < COMPOSITION_TOLERANCE {
+            pass.iterations = iteration;
+            return Ok(pass);
+        }
+        recycle_comp = array_to_comp(&comp_to_array(&pass.suction_comp));
+        t_recycle = pass.t_recycle;
+    }
+    Err(format!("Recycle loop did not converge in {} iterations", MAX_ITERATIONS))
+}
+
+// Compressor with a recycle (anti-surge) valve from the discharge back to suction.  The
+// fresh feed is the current gas at the current temperature.
+pub fn recycle_loop(program_state: &mut ProgramState) {
+    outln!();
+    outln!("{}", "Compressor Recycle Loop".yellow().bold());
+    outln!("Fresh feed is the current gas at the current temperature.");
+    let feed_flow = read_f64("Enter fresh feed mass flow (kg/s):");
+    let p_suction = read_pressure(program_state, "suction pressure");
+    let p_discharge = read_pressure(program_state, "discharge pressure");
+    let efficiency = read_f64("Enter compressor isentropic efficiency (e.g. 0.78):");
+    let recycle_flow = read_f64("Enter recycle mass flow (kg/s):");
+    outln!("Recycle source: 1 - Aftercooler outlet (cold recycle), 2 - Compressor discharge (hot recycle)");
+    let t_cooler = match read_input().as_str() {
+        "2" => None,
+        _ => Some(read_temperature(program_state, "aftercooler outlet temperature")),
+    };
+    let inputs = RecycleInputs { feed_flow, t_feed: Temperature::from_kelvin(program_state.gas_state.t), p_suction, p_discharge, efficiency, recycle_flow, t_cooler };
+
+    let result = match converge_recycle(&program_state.gas_comp, &inputs) {
+        Ok(result) => result,
+        Err(err) => {
+            outln!("{}", format!("** {} **", err).red().bold());
+            print_gas_state(program_state);
+            return;
+        }
+    };
+
+    let t_unit = program_state.units.temp.label();
+    let temp = |t: Temperature| t.value(program_state.units.temp);
+    outln!();
+    outln!("{:<30} {:10}", "Iterations: ", result.iterations);
+    outln!("{:<30} {:10.4} {:10}", "Suction Pressure: ", p_suction.value(program_state.units.pressure), program_state.units.pressure.label());
+    outln!("{:<30} {:10.4} {:10}", "Feed Temperature: ", temp(inputs.t_feed), t_unit);
+    outln!("{:<30} {:10.4} {:10}", "Mixed Suction Temperature: ", temp(result.t_suction), t_unit);
+    outln!("{:<30} {:10.4} {:10}", "Discharge Pressure: ", p_discharge.value(program_state.units.pressure), program_state.units.pressure.label());
+    outln!("{:<30} {:10.4} {:10}", "Discharge Temperature: ", temp(result.t_discharge), t_unit);
+    outln!("{:<30} {:10.4} {:10}", "Recycle Valve Inlet Temp.: ", temp(result.t_recycle_upstream), t_unit);
+    outln!("{:<30} {:10.4} {:10}", "Recycle Valve Outlet Temp.: ", temp(result.t_recycle), t_unit);
+    outln!("{:<30} {:10.4} {:10}", "Compressor Flow: ", feed_flow + recycle_flow, "kg/s");
+    let power = |kw| get_power(kw, program_state.units.power);
+    outln!("{:<30} {:10.4} {:10}", "Compressor Power: ", power(result.power), program_state.units.power.label());
+    if t_cooler.is_some() {
+        outln!("{:<30} {:10.4} {:10}", "Aftercooler Duty: ", power(result.cooler_duty), program_state.units.power.label());
+    } else {
+        outln!("{}", "* Hot recycle raises the suction temperature each pass; check the discharge temperature limit".yellow());
+    }
+
+    outln!();
+    outln!("Load the converged suction state as the current state? (y/n)");
+    if read_input() == "y" {
+        change_conditions(program_state, p_suction, result.t_suction);
+        print_state_errors(&update_gas_state(program_state));
+    }
+    print_gas_state(program_state);
+}
@@ -0,0 +1,186 @@
+use aga8::composition::Composition;
+use colored::Colorize;
+
+use crate::components::{comp_to_array, COMPONENT_NAMES};
+use crate::nozzle::critical_flow;
+use crate::process::{isenthalpic_state, isentropic_state};
+use crate::{print_gas_state, read_f64, read_input, read_pressure, try_gas_state, Pressure, ProgramState, Temperature};
+
+use std::f64::consts::PI;
+use std::fs;
+
+pub struct SourceTerm {
+    pub choked: bool,
+    // Release rate in kg/s
+    pub mass_rate: f64,
+    // Conditions in the orifice plane: kPa, K, kg/m3, m/s, m
+    pub exit_pressure: f64,
+    pub exit_temperature: f64,
+    pub exit_density: f64,
+    pub exit_velocity: f64,
+    pub orifice_diameter: f64,
+    // Conditions after expansion to ambient pressure (notional nozzle): K, kg/m3, m/s, m
+    pub expanded_temperature: f64,
+    pub expanded_density: f64,
+    pub expanded_velocity: f64,
+    pub expanded_diameter: f64,
+}
+
+// Conditions in the orifice plane: choked flag, pressure (kPa), temperature (K),
+// density (kg/m3) and velocity (m/s).
+pub struct OrificeExit {
+    pub choked: bool,
+    pub p: f64,
+    pub t: f64,
+    pub density: f64,
+    pub velocity: f64,
+}
+
+// Isentropic flow from stagnation conditions p0 (kPa), t0 (K) to the orifice plane,
+// choked or expanded to the downstream pressure.
+pub fn orifice_exit(gas_comp: &Composition, p0: f64, t0: f64, p_downstream: f64) -> Option<OrificeExit> {
+    let critical = critical_flow(gas_comp, Pressure::from_kpa(p0), Temperature::from_kelvin(t0))?;
+    if p0 * critical.pressure_ratio > p_downstream {
+        return Some(OrificeExit {
+            choked: true,
+            p: p0 * critical.pressure_ratio,
+            t: t0 * critical.temperature_ratio,
+            density: critical.mass_flux / critical.velocity,
+            velocity: critical.velocity,
+        });
+    }
+    let stagnation = try_gas_state(gas_comp, p0, t0)?;
+    let exit = isentropic_state(gas_comp, p_downstream, stagnation.s, t0)?;
+    let velocity = (2.0 * (stagnation.h - exit.h) * 1000.0 / stagnation.mm).max(0.0).sqrt();
+    Some(OrificeExit { choked: false, p: p_downstream, t: exit.t, density: exit.d * stagnation.mm, velocity })
+}
+
+// Release of the gas at stagnation conditions p0, t0 through an orifice to ambient
+// pressure.  The expanded state conserves mass, momentum and energy between the orifice
+// plane and the point where the jet reaches ambient pressure.
+pub fn release_source_term(gas_comp: &Composition, p0: Pressure, t0: Temperature, p_ambient: Pressure, diameter: f64, cd: f64) -> Option<SourceTerm> {
+    let (p0, t0, p_ambient) = (p0.kpa(), t0.kelvin(), p_ambient.kpa());
+    let stagnation = try_gas_state(gas_comp, p0, t0)?;
+    let mm = stagnation.mm;
+    let area = PI * diameter * diameter / 4.0;
+
+    let exit = orifice_exit(gas_comp, p0, t0, p_ambient)?;
+    let (choked, exit_pressure, exit_temperature, exit_density, exit_velocity) = (exit.choked, exit.p, exit.t, exit.density, exit.velocity);
+    let mass_rate = cd * exit_density * exit_velocity * area;
+
+    let expanded_velocity = exit_velocity + (exit_pressure - p_ambient) * 1000.0 / (exit_density * exit_velocity);
+    let h_expanded = stagnation.h - expanded_velocity * expanded_velocity / 2.0 * mm / 1000.0;
+    let expanded = isenthalpic_state(gas_comp, p_ambient, h_expanded, exit_temperature)?;
+    let expanded_density = expanded.d * mm;
+    let expanded_diameter = (4.0 * mass_rate / (PI * expanded_density * expanded_velocity)).sqrt();
+
+    Some(SourceTerm {
+        choked,
+        mass_rate,
+        exit_pressure,
+        exit_temperature,
+        exit_density,
+        exit_velocity,
+        orifice_diameter: diameter,
+        expanded_temperature: expanded.t,
+        expanded_density,
+        expanded_velocity,
+        expanded_diameter,
+    })
+}
+
+fn source_term_fields(term: &SourceTerm) -> [(&'static str, f64); 11] {
+    [
+        ("mass_rate_kg_s", term.mass_rate),
+        ("exit_pressure_kpa", term.exit_pressure),
+        ("exit_temperature_k", term.exit_temperature),
+        ("exit_density_kg_m3", term.exit_density),
+        ("exit_velocity_m_s", term.exit_velocity),
+        ("orifice_diameter_m", term.orifice_diameter),
+        ("expanded_temperature_k", term.expanded_temperature),
+        ("expanded_density_kg_m3", term.expanded_density),
+        ("expanded_velocity_m_s", term.expanded_velocity),
+        ("expanded_diameter_m", term.expanded_diameter),
+        ("choked", if term.choked { 1.0 } else { 0.0 }),
+    ]
+}
+
+pub fn source_term_csv(gas: &str, term: &SourceTerm) -> String {
+    let fields = source_term_fields(term);
+    let mut headers = vec!["material".to_string()];
+    let mut values = vec![gas.replace(',', " ")];
+    for (name, value) in fields {
+        headers.push(name.to_string());
+        values.push(value.to_string());
+    }
+    format!("{}\n{}\n", headers.join(","), values.join(","))
+}
+
+pub fn source_term_json(gas: &str, gas_comp: &Composition, term: &SourceTerm) -> String {
+    let mut json = String::from("{\n");
+    json.push_str(&format!("  \"material\": \"{}\",\n", gas.replace('"', "'")));
+    json.push_str("  \"composition_mole_fraction\": {");
+    let components: Vec<String> = comp_to_array(gas_comp).iter().zip(COMPONENT_NAMES.iter())
+        .filter(|(fraction, _)| **fraction > 0.0)
+        .map(|(fraction, name)| format!("\"{}\": {}", name, fraction))
+        .collect();
+    json.push_str(&components.join(", "));
+    json.push_str("},\n");
+    for (name, value) in source_term_fields(term) {
+        if name == "choked" {
+            json.push_str(&format!("  \"choked\": {}\n", term.choked));
+        } else {
+            json.push_str(&format!("  \"{}\": {},\n", name, value));
+        }
+    }
+    json.push('}');
+    json.push('\n');
+    json
+}
+
+pub fn release_export(program_state: &mut ProgramState) {
+    outln!();
+    outln!("{}", "Vent / Leak Release Source Term".yellow().bold());
+    outln!("Release source is the current state.");
+    let p_ambient = read_pressure(program_state, "ambient pressure");
+    let diameter = read_f64("Enter orifice diameter (mm):") / 1000.0;
+    let cd = read_f64("Enter discharge coefficient (e.g. 0.62 sharp edged, 1.0 ideal):");
+
+    let term = match release_source_term(&program_state.gas_comp, Pressure::from_kpa(program_state.gas_state.p), Temperature::from_kelvin(program_state.gas_state.t), p_ambient, diameter, cd) {
+        Some(term) => term,
+        None => {
+            outln!("{}", "** Unable to calculate the release **".red().bold());
+            print_gas_state(program_state);
+            return;
+        }
+    };
+
+    outln!();
+    outln!("{:<30} {:>10}", "Flow: ", if term.choked { "Choked" } else { "Subsonic" });
+    outln!("{:<30} {:10.4} {:10}", "Release Rate: ", term.mass_rate, "kg/s");
+    outln!("{:<30} {:10.4} {:10}", "Exit Pressure: ", term.exit_pressure, "kPa");
+    outln!("{:<30} {:10.4} {:10}", "Exit Temperature: ", term.exit_temperature, "K");
+    outln!("{:<30} {:10.4} {:10}", "Exit Density: ", term.exit_density, "kg/m3");
+    outln!("{:<30} {:10.4} {:10}", "Exit Velocity: ", term.exit_velocity, "m/s");
+    outln!("{:<30} {:10.4} {:10}", "Expanded Temperature: ", term.expanded_temperature, "K");
+    outln!("{:<30} {:10.4} {:10}", "Expanded Density: ", term.expanded_density, "kg/m3");
+    outln!("{:<30} {:10.4} {:10}", "Expanded Velocity: ", term.expanded_velocity, "m/s");
+    outln!("{:<30} {:10.4} {:10}", "Expanded Diameter: ", term.expanded_diameter * 1000.0, "mm");
+
+    outln!();
+    outln!("Enter export file path (.csv or .json, leave blank to skip):");
+    let path = read_input();
+    if !path.is_empty() {
+        let text = if path.ends_with(".json") {
+            source_term_json(&program_state.gas, &program_state.gas_comp, &term)
+        } else {
+            source_term_csv(&program_state.gas, &term)
+        };
+        match fs::write(&path, text) {
+            Ok(()) => outln!("Source term written to {}", path),
+            Err(err) => outln!("{}", format!("** Unable to write {}: {} **", path, err).red().bold()),
+        }
+    }
+
+    print_gas_state(program_state);
+}
@@ -0,0 +1,160 @@
+use colored::Colorize;
+
+use crate::{print_gas_state, read_f64, read_pressure, try_gas_state, Pressure, ProgramState, Temperature};
+
+// Standard effective orifice areas in mm2 (API 526)
+const ORIFICES: [(&str, f64); 14] = [
+    ("D", 71.0),
+    ("E", 126.5),
+    ("F", 198.1),
+    ("G", 324.5),
+    ("H", 506.5),
+    ("J", 830.3),
+    ("K", 1186.0),
+    ("L", 1841.0),
+    ("M", 2323.0),
+    ("N", 2800.0),
+    ("P", 4116.0),
+    ("Q", 7129.0),
+    ("R", 10323.0),
+    ("T", 16774.0),
+];
+
+pub struct ReliefInputs {
+    // Required relief rate in kg/h
+    pub mass_flow: f64,
+    // Relieving and back pressure (absolute) and relieving temperature
+    pub p1: Pressure,
+    pub p2: Pressure,
+    pub t: Temperature,
+    pub z: f64,
+    pub mm: f64,
+    pub kappa: f64,
+    // Discharge, back pressure and rupture disk combination coefficients
+    pub kd: f64,
+    pub kb: f64,
+    pub kc: f64,
+}
+
+pub struct ReliefResult {
+    pub critical: bool,
+    pub critical_pressure: Pressure,
+    // Required effective area in mm2
+    pub area: f64,
+}
+
+// API 520 Part I effective area for gas relief, SI units.  Critical flow uses the
+// coefficient C, subcritical flow the coefficient F2 (kPa and K).
+pub fn relief_area(inputs: &ReliefInputs) -> ReliefResult {
+    let k = inputs.kappa;
+    let (p1, p2, t) = (inputs.p1.kpa(), inputs.p2.kpa(), inputs.t.kelvin());
+    let critical_pressure = p1 * (2.0 / (k + 1.0)).powf(k / (k - 1.0));
+    let critical = p2 <= critical_pressure;
+    let area = if critical {
+        let c = 0.03948 * (k * (2.0 / (k + 1.0)).powf((k + 1.0) / (k - 1.0))).sqrt();
+        inputs.mass_flow / (c * inputs.kd * p1 * inputs.kb * inputs.kc) * (t * inputs.z / inputs.mm).sqrt()
+    } else {
+        let r = p2 / p1;
+        let f2 = (k / (k - 1.0) * r.powf(2.0 / k) * (1.0 - r.powf((k - 1.0) / k)) / (1.0 - r)).sqrt();
+        17.9 * inputs.mass_flow / (f2 * inputs.kd * inputs.kc)
+            * (t * inputs.z / (inputs.mm * p1 * (p1 - p2))).sqrt()
+    };
+    ReliefResult { critical, critical_pressure: Pressure::from_kpa(critical_pressure), area }
+}
+
+pub fn relief_valve_sizing(program_state: &mut ProgramState) {
+    outln!();
+    outln!("{}", "Relief Valve Sizing (API 520, gas)".yellow().bold());
+    outln!("Relieving temperature is the current temperature.");
+    let p_set = read_pressure(program_state, "set pressure (absolute)");
+    let overpressure = read_f64("Enter allowable overpressure (%, e.g. 10, 21 fire case):");
+    let p_back = read_pressure(program_state, "total back pressure (absolute)");
+    let mass_flow = read_f64("Enter required relief rate (kg/h):");
+    let kd = read_f64("Enter effective discharge coefficient Kd (e.g. 0.975):");
+    let kb = read_f64("Enter back pressure correction Kb (1.0 conventional valve):");
+    let kc = read_f64("Enter rupture disk combination factor Kc (1.0 none, 0.9 with disk):");
+
+    // Overpressure is a percentage of the gauge set pressure
+    let atmosphere = Pressure::from_kpa(101.325);
+    let p1 = p_set + (p_set - atmosphere) * (overpressure / 100.0);
+    let t = Temperature::from_kelvin(program_state.gas_state.t);
+    let relieving = match try_gas_state(&program_state.gas_comp, p1.kpa(), t.kelvin()) {
+        Some(state) => state,
+        None => {
+            outln!("{}", "** Unable to calculate the relieving state **".red().bold());
+            print_gas_state(program_state);
+            return;
+        }
+    };
+
+    let result = relief_area(&ReliefInputs {
+        mass_flow,
+        p1,
+        p2: p_back,
+        t,
+        z: relieving.z,
+        mm: relieving.mm,
+        kappa: relieving.kappa,
+        kd,
+        kb,
+        kc,
+    });
+
+    let p_unit = program_state.units.pressure.label();
+    outln!();
+    outln!("{:<30} {:10.4} {:10}", "Relieving Pressure: ", p1.value(program_state.units.pressure), p_unit);
+    outln!("{:<30} {:10.4} {:10}", "Compressibility Z: ", relieving.z, "[]");
+    outln!("{:<30} {:10.4} {:10}", "Molar Mass: ", relieving.mm, "g/mol");
+    outln!("{:<30} {:10.4} {:10}", "Isentropic Exponent k: ", relieving.kappa, "[]");
+    outln!("{:<30} {:10.4} {:10}", "Critical Flow Pressure: ", result.critical_pressure.value(program_state.units.pressure), p_unit);
+    outln!("{:<30} {:>10}", "Flow: ", if result.critical { "Critical" } else { "Subcritical" });
+    outln!("{:<30} {:10.2} {:10}", "Required Effective Area: ", result.area, "mm2");
+    match ORIFICES.iter().find(|(_, area)| *area >= result.area) {
+        Some((letter, area)) => outln!("{:<30} {:>10} {:10}", "Selected Orifice: ", letter, format!("({:.1} mm2)", area)),
+        None => outln!("{}", "** Required area exceeds the largest standard orifice (T) - use multiple valves **".red().bold()),
+    }
+    if !result.critical && kb != 1.0 {
+        outln!("{}", "* Kb is not applied to subcritical flow of conventional valves".yellow());
+    }
+
+    print_gas_state(program_state);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn example(p2: f64) -> ReliefInputs {
+        ReliefInputs {
+            mass_flow: 24_270.0,
+            p1: Pressure::from_kpa(670.0),
+            p2: Pressure::from_kpa(p2),
+            t: Temperature::from_kelvin(348.0),
+            z: 0.90,
+            mm: 51.0,
+            kappa: 1.11,
+            kd: 0.975,
+            kb: 1.0,
+            kc: 1.0,
+        }
+    }
+
+    // API 520 Part I Annex B critical flow example (SI): 24270 kg/h of hydrocarbon vapor,
+    // M 51, k 1.11, Z 0.90 at 670 kPa and 348 K, Kd 0.975.  Published area 3699 mm2.
+    #[test]
+    fn api_520_critical_example() {
+        let result = relief_area(&example(101.325));
+        assert!(result.critical);
+        assert!((result.area - 3699.0).abs() < 1.0);
+    }
+
+    // The subcritical form meets the critical form at the critical pressure ratio
+    #[test]
+    fn subcritical_meets_critical() {
+        let critical = relief_area(&example(101.325));
+        let p2 = critical.critical_pressure.kpa() * (1.0 + 1.0e-9);
+        let subcritical = relief_area(&example(p2));
+        assert!(!subcritical.critical);
+        assert!((subcritical.area / critical.area - 1.0).abs() < 2.0e-3);
+    }
+}
@@ -0,0 +1,35 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::fs;
+
+thread_local! {
+    // Commands waiting to be fed to the menus in place of keyboard input
+    static SCRIPT_INPUT: RefCell<VecDeque<String>> = const { RefCell::new(VecDeque::new()) };
+}
+
+pub fn queue_lines<S: AsRef<str>>(lines: &[S]) {
+    SCRIPT_INPUT.with(|script| {
+        let mut script = script.borrow_mut();
+        for line in lines {
+            script.push_back(line.as_ref().trim_end().to_string());
+        }
+    });
+}
+
+pub fn next_line() -> Option<String> {
+    SCRIPT_INPUT.with(|script| script.borrow_mut().pop_front())
+}
+
+// Queues the lines of a script file ahead of any queued commands, so a script can run
+// another script.  Returns the number of lines queued.
+pub fn run_file(path: &str) -> Result<usize, String> {
+    let text = fs::read_to_string(path).map_err(|err| format!("Unable to read {}: {}", path, err))?;
+    let lines: Vec<&str> = text.lines().collect();
+    SCRIPT_INPUT.with(|script| {
+        let mut script = script.borrow_mut();
+        for line in lines.iter().rev() {
+            script.push_front(line.trim_end().to_string());
+        }
+    });
+    Ok(lines.len())
+}
@@ -0,0 +1,431 @@
+use aga8::composition::Composition;
+use aga8::detail::Detail;
+use serde::{Deserialize, Serialize};
+
+use comp_perf::ffi::CompPerfState;
+use comp_perf::thermo::{compressed_state, throttled_state};
+
+use crate::components::{comp_to_array, composition_serde, NUM_COMPONENTS};
+use crate::realtime::{table_values, RealtimeEvaluator, TableSpec, TABLE_PROPERTIES};
+use crate::try_gas_state;
+
+use std::collections::{HashMap, VecDeque};
+
+use std::io::{BufRead, BufReader, ErrorKind, Read, Take, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+const DEFAULT_ADDRESS: &str = "127.0.0.1:8080";
+// Request bodies are small JSON objects
+const MAX_BODY: usize = 64 * 1024;
+// Request line and all headers together
+const MAX_HEAD: u64 = 8 * 1024;
+// Longest wait for the next bytes of a request, so idle clients cannot hold a worker
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+// Connections handled at once; further connections wait in the queue, then in the
+// listen backlog
+const WORKERS: usize = 16;
+const QUEUED: usize = 64;
+// Compositions with a property table at once, the oldest dropped first
+const MAX_TABLES: usize = 8;
+
+// Mole fractions by component name (e.g. "methane": 0.9), pressures in kPa and
+// temperatures in K
+#[derive(Deserialize)]
+struct PropertiesRequest {
+    #[serde(with = "composition_serde")]
+    composition: Composition,
+    p: f64,
+    t: f64,
+}
+
+#[derive(Deserialize)]
+struct CompressRequest {
+    #[serde(with = "composition_serde")]
+    composition: Composition,
+    p1: f64,
+    t1: f64,
+    p2: f64,
+    efficiency: f64,
+}
+
+#[derive(Deserialize)]
+struct ThrottleRequest {
+    #[serde(with = "composition_serde")]
+    composition: Composition,
+    p1: f64,
+    t1: f64,
+    p2: f64,
+}
+
+// Specific work in kJ/kg is the enthalpy rise over the molar mass
+#[derive(Serialize)]
+struct CompressResponse {
+    inlet: CompPerfState,
+    discharge: CompPerfState,
+    work: f64,
+}
+
+#[derive(Serialize)]
+struct ThrottleResponse {
+    upstream: CompPerfState,
+    downstream: CompPerfState,
+}
+
+struct Response {
+    status: u16,
+    body: String,
+}
+
+impl Response {
+    fn json(value: &impl Serialize) -> Response {
+        Response { status: 200, body: serde_json::to_string(value).unwrap() }
+    }
+
+    fn error(status: u16, message: &str) -> Response {
+        Response { status, body: serde_json::json!({ "error": message }).to_string() }
+    }
+}
+
+fn status_text(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        408 => "Request Timeout",
+        413 => "Payload Too Large",
+        422 => "Unprocessable Entity",
+        431 => "Request Header Fields Too Large",
+        503 => "Service Unavailable",
+        _ => "Internal Server Error",
+    }
+}
+
+fn check_composition(gas_comp: &Composition) -> Result<(), Response> {
+    Detail::new().set_composition(gas_comp).map_err(|_| Response::error(400, "Invalid composition"))
+}
+
+fn parse<'a, T: Deserialize<'a>>(body: &'a str) -> Result<T, Response> {
+    serde_json::from_str(body).map_err(|err| Response::error(400, &format!("Invalid request: {}", err)))
+}
+
+fn no_solution() -> Response {
+    Response::error(422, "No solution for the requested state")
+}
+
+fn properties(body: &str) -> Result<Response, Response> {
+    let request: PropertiesRequest = parse(body)?;
+    check_composition(&request.composition)?;
+    let gas_comp = &request.composition;
+    let state = try_gas_state(gas_comp, request.p, request.t).ok_or_else(no_solution)?;
+    Ok(Response::json(&CompPerfState::of(&state)))
+}
+
+// Property tables kept for the most recently requested compositions, each built once in
+// the background and never switched to another composition
+struct RealtimeTables {
+    tables: HashMap<[u64; NUM_COMPONENTS], RealtimeEvaluator>,
+    // Keys oldest first, for eviction
+    order: VecDeque<[u64; NUM_COMPONENTS]>,
+}
+
+impl RealtimeTables {
+    fn new() -> RealtimeTables {
+        RealtimeTables { tables: HashMap::new(), order: VecDeque::new() }
+    }
+
+    // The evaluator of the composition, started when it is new
+    fn evaluator(&mut self, gas_comp: &Composition) -> &RealtimeEvaluator {
+        let key = comp_to_array(gas_comp).map(f64::to_bits);
+        if !self.tables.contains_key(&key) {
+            if self.order.len() == MAX_TABLES && let Some(oldest) = self.order.pop_front() {
+                self.tables.remove(&oldest);
+            }
+            self.order.push_back(key);
+            self.tables.insert(key, RealtimeEvaluator::new(gas_comp, TableSpec::default()));
+        }
+        &self.tables[&key]
+    }
+}
+
+// Properties interpolated from the property table of the request composition, with the
+// age of the table.  Until that table is built the state is solved directly.
+fn realtime_properties(tables: &Mutex<RealtimeTables>, body: &str) -> Result<Response, Response> {
+    let request: PropertiesRequest = parse(body)?;
+    check_composition(&request.composition)?;
+    let evaluation = tables.lock().unwrap().evaluator(&request.composition).evaluate(request.p, request.t);
+    let mut fields = serde_json::Map::new();
+    let values = match evaluation.values {
+        Some(values) if !evaluation.stale => {
+            fields.insert("source".to_string(), "table".into());
+            fields.insert("age_ms".to_string(), (evaluation.age.as_millis() as u64).into());
+            values
+        },
+        _ if evaluation.stale => {
+            let state = try_gas_state(&request.composition, request.p, request.t).ok_or_else(no_solution)?;
+            fields.insert("source".to_string(), "detail".into());
+            table_values(&state)
+        },
+        _ => return Err(Response::error(422, "Outside the property table")),
+    };
+    for (name, value) in TABLE_PROPERTIES.iter().zip(values) {
+        fields.insert(name.to_string(), value.into());
+    }
+    Ok(Response { status: 200, body: serde_json::Value::Object(fields).to_string() })
+}
+
+fn health(realtime: Option<&Mutex<RealtimeTables>>) -> Response {
+    let tables = realtime.map(|tables| {
+        let tables = tables.lock().unwrap();
+        let status: Vec<serde_json::Value> = tables.order.iter().map(|key| match tables.tables[key].table_status() {
+            Some((age, build_time)) => serde_json::json!({ "age_ms": age.as_millis() as u64, "build_ms": build_time.as_millis() as u64 }),
+            None => serde_json::json!("building"),
+        }).collect();
+        status
+    });
+    match tables {
+        Some(tables) => Response::json(&serde_json::json!({ "status": "ok", "tables": tables })),
+        None => Response::json(&serde_json::json!({ "status": "ok" })),
+    }
+}
+
+fn compress(body: &str) -> Result<Response, Response> {
+    let request: CompressRequest = parse(body)?;
+    if !(request.efficiency > 0.0 && request.efficiency <= 1.0) {
+        return Err(Response::error(400, "Efficiency must be between 0 and 1"));
+    }
+    if request.p2 <= request.p1 {
+        return Err(Response::error(422, "Discharge pressure must be above the inlet pressure"));
+    }
+    check_composition(&request.composition)?;
+    let gas_comp = &request.composition;
+    let inlet = try_gas_state(gas_comp, request.p1, request.t1).ok_or_else(no_solution)?;
+    let discharge = compressed_state(gas_comp, request.p1, request.t1, request.p2, request.efficiency).ok_or_else(no_solution)?;
+    let work = (discharge.h - inlet.h) / inlet.mm;
+    Ok(Response::json(&CompressResponse { inlet: CompPerfState::of(&inlet), discharge: CompPerfState::of(&discharge), work }))
+}
+
+fn throttle(body: &str) -> Result<Response, Response> {
+    let request: ThrottleRequest = parse(body)?;
+    if request.p2 >= request.p1 {
+        return Err(Response::error(422, "Downstream pressure must be below the upstream pressure"));
+    }
+    check_composition(&request.composition)?;
+    let gas_comp = &request.composition;
+    let upstream = try_gas_state(gas_comp, request.p1, request.t1).ok_or_else(no_solution)?;
+    let downstream = throttled_state(gas_comp, request.p1, request.t1, request.p2).ok_or_else(no_solution)?;
+    Ok(Response::json(&ThrottleResponse { upstream: CompPerfState::of(&upstream), downstream: CompPerfState::of(&downstream) }))
+}
+
+fn route(method: &str, path: &str, body: &str, realtime: Option<&Mutex<RealtimeTables>>) -> Response {
+    let handler = match path {
+        "/health" => return match method {
+            "GET" => health(realtime),
+            _ => Response::error(405, "Use GET"),
+        },
+        "/properties" => properties,
+        "/compress" => compress,
+        "/throttle" => throttle,
+        _ => return Response::error(404, "Unknown endpoint"),
+    };
+    if method != "POST" {
+        return Response::error(405, "Use POST with a JSON body");
+    }
+    match (path, realtime) {
+        ("/properties", Some(evaluator)) => realtime_properties(evaluator, body),
+        _ => handler(body),
+    }.unwrap_or_else(|err| err)
+}
+
+fn read_error(err: std::io::Error) -> Response {
+    match err.kind() {
+        ErrorKind::WouldBlock | ErrorKind::TimedOut => Response::error(408, "Request timed out"),
+        _ => Response::error(400, "Unreadable request"),
+    }
+}
+
+// Reads one line of the request head.  The reader is limited to MAX_HEAD bytes for the
+// whole head, so a line cut short by the limit means the head is too large.
+fn read_head_line(reader: &mut BufReader<Take<&TcpStream>>) -> Result<String, Response> {
+    let mut line = String::new();
+    reader.read_line(&mut line).map_err(read_error)?;
+    if !line.ends_with('\n') {
+        return Err(match reader.get_ref().limit() {
+            0 => Response::error(431, "Request line and headers too large"),
+            _ => Response::error(400, "Incomplete request"),
+        });
+    }
+    Ok(line)
+}
+
+// Reads one request and returns the method, path and body
+fn read_request(stream: &TcpStream) -> Result<(String, String, String), Response> {
+    stream.set_read_timeout(Some(READ_TIMEOUT)).map_err(|_| Response::error(400, "Unreadable request"))?;
+    let mut reader = BufReader::new(stream.take(MAX_HEAD));
+    let request_line = read_head_line(&mut reader)?;
+    let mut parts = request_line.split_whitespace();
+    let (Some(method), Some(path)) = (parts.next(), parts.next()) else {
+        return Err(Response::error(400, "Malformed request line"));
+    };
+    let mut content_length = 0;
+    loop {
+        let header = read_head_line(&mut reader)?;
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':')
+            && name.eq_ignore_ascii_case("content-length") {
+            content_length = value.trim().parse().map_err(|_| Response::error(400, "Invalid Content-Length"))?;
+        }
+    }
+    if content_length > MAX_BODY {
+        return Err(Response::error(413, "Request body too large"));
+    }
+    // The body may already be partly in the buffer; the limit covers the rest
+    reader.get_mut().set_limit(content_length as u64);
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body).map_err(|err| match err.kind() {
+        ErrorKind::UnexpectedEof => Response::error(400, "Incomplete body"),
+        _ => read_error(err),
+    })?;
+    let body = String::from_utf8(body).map_err(|_| Response::error(400, "Body is not UTF-8"))?;
+    // Query strings are not used by any endpoint
+    let path = path.split('?').next().unwrap_or(path);
+    Ok((method.to_string(), path.to_string(), body))
+}
+
+fn handle_connection(mut stream: TcpStream, realtime: Option<&Mutex<RealtimeTables>>) {
+    let response = match read_request(&stream) {
+        Ok((method, path, body)) => route(&method, &path, &body, realtime),
+        Err(response) => response,
+    };
+    let head = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        response.status, status_text(response.status), response.body.len(),
+    );
+    stream.write_all(head.as_bytes()).and_then(|_| stream.write_all(response.body.as_bytes())).ok();
+}
+
+// Worker thread: handles connections from the queue until the listener stops
+fn worker(queue: Arc<Mutex<Receiver<TcpStream>>>, realtime: Option<Arc<Mutex<RealtimeTables>>>) {
+    loop {
+        let received = queue.lock().unwrap().recv();
+        match received {
+            Ok(stream) => handle_connection(stream, realtime.as_deref()),
+            Err(_) => return,
+        }
+    }
+}
+
+// JSON API over HTTP, on a fixed number of worker threads:
+//   GET  /health
+//   POST /properties  {"composition": {"methane": 0.9, ...}, "p": kPa, "t": K}
+//   POST /compress    {"composition": ..., "p1", "t1", "p2", "efficiency"}
+//   POST /throttle    {"composition": ..., "p1", "t1", "p2"}
+// With --realtime, /properties answers from a property table of the requested
+// composition, built in the background the first time the composition is seen and solved
+// directly until then; the response gives the source and the age of the table, /health
+// the status of every table.
+// Usage: comp_perf serve [address] [--realtime], default 127.0.0.1:8080
+pub fn run_server(args: &[String]) -> i32 {
+    let realtime = args.iter().any(|arg| arg == "--realtime")
+        .then(|| Arc::new(Mutex::new(RealtimeTables::new())));
+    let address = args.iter().find(|arg| *arg != "--realtime").map_or(DEFAULT_ADDRESS, String::as_str);
+    let listener = match TcpListener::bind(address) {
+        Ok(listener) => listener,
+        Err(err) => {
+            eprintln!("Unable to listen on {}: {}", address, err);
+            return 1;
+        }
+    };
+    eprintln!("Serving on http://{}", address);
+    let (sender, receiver) = mpsc::sync_channel(QUEUED);
+    let queue = Arc::new(Mutex::new(receiver));
+    for _ in 0..WORKERS {
+        let (queue, realtime) = (Arc::clone(&queue), realtime.clone());
+        thread::spawn(move || worker(queue, realtime));
+    }
+    // Blocks while the queue is full, leaving new connections in the listen backlog
+    for stream in listener.incoming().flatten() {
+        if sender.send(stream).is_err() {
+            break;
+        }
+    }
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Shutdown;
+
+    // Sends raw request bytes and reads them back as the server does; the status of the
+    // error response otherwise
+    fn request(raw: &[u8]) -> Result<(String, String, String), u16> {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (server, _) = listener.accept().unwrap();
+        let raw = raw.to_vec();
+        // Written from another thread, as a large request does not fit the socket buffers
+        let sender = thread::spawn(move || {
+            client.write_all(&raw).ok();
+            client.shutdown(Shutdown::Write).ok();
+            client
+        });
+        let result = read_request(&server).map_err(|response| response.status);
+        drop(server);
+        sender.join().unwrap();
+        result
+    }
+
+    #[test]
+    fn request_head_and_body() {
+        let raw = b"POST /properties?unit=si HTTP/1.1\r\nHost: x\r\ncontent-LENGTH: 4\r\n\r\n{}\r\n";
+        let (method, path, body) = request(raw).unwrap();
+        assert_eq!((method.as_str(), path.as_str(), body.as_str()), ("POST", "/properties", "{}\r\n"));
+        assert_eq!(request(b"GET /health HTTP/1.1\r\n\r\n").unwrap().2, "");
+    }
+
+    #[test]
+    fn malformed_requests() {
+        assert_eq!(request(b"\r\n\r\n"), Err(400));
+        assert_eq!(request(b"GET /health HTTP/1.1\r\nHost: x"), Err(400));
+        assert_eq!(request(b"POST /compress HTTP/1.1\r\nContent-Length: -1\r\n\r\n"), Err(400));
+        assert_eq!(request(b"POST /compress HTTP/1.1\r\nContent-Length: 10\r\n\r\n{}"), Err(400));
+        assert_eq!(request(b"POST /compress HTTP/1.1\r\nContent-Length: 2\r\n\r\n\xff\xfe"), Err(400));
+    }
+
+    // The head may take MAX_HEAD bytes and the body MAX_BODY
+    #[test]
+    fn size_limits() {
+        let head = |filler: usize| format!("GET /health HTTP/1.1\r\nX-Filler: {}\r\n\r\n", "a".repeat(filler));
+        let fixed = head(0).len();
+        assert!(request(head(MAX_HEAD as usize - fixed).as_bytes()).is_ok());
+        assert_eq!(request(head(MAX_HEAD as usize - fixed + 1).as_bytes()), Err(431));
+
+        let body = |length: usize| format!("POST /properties HTTP/1.1\r\nContent-Length: {}\r\n\r\n{}", length, " ".repeat(length));
+        assert_eq!(request(body(MAX_BODY).as_bytes()).unwrap().2.len(), MAX_BODY);
+        assert_eq!(request(body(MAX_BODY + 1).as_bytes()), Err(413));
+    }
+
+    #[test]
+    fn routes_and_request_errors() {
+        let status = |method: &str, path: &str, body: &str| route(method, path, body, None).status;
+        assert_eq!(status("GET", "/health", ""), 200);
+        assert_eq!(status("POST", "/health", ""), 405);
+        assert_eq!(status("GET", "/properties", ""), 405);
+        assert_eq!(status("POST", "/nothing", ""), 404);
+        assert_eq!(status("POST", "/properties", "{\"p\": 100}"), 400);
+        assert_eq!(status("POST", "/properties", "{\"composition\": {\"methane\": 1.0}, \"p\": 100, \"t\": 300}"), 200);
+        assert_eq!(status("POST", "/properties", "{\"composition\": {\"methane\": 0.5}, \"p\": 100, \"t\": 300}"), 400);
+        let compress = "{\"composition\": {\"methane\": 1.0}, \"p1\": 1000, \"t1\": 300, \"p2\": 3000, \"efficiency\": 0}";
+        assert_eq!(status("POST", "/compress", compress), 400);
+        let throttle = "{\"composition\": {\"methane\": 1.0}, \"p1\": 1000, \"t1\": 300, \"p2\": 3000}";
+        assert_eq!(status("POST", "/throttle", throttle), 422);
+    }
+}
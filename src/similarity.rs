@@ -0,0 +1,114 @@
+use aga8::detail::Detail;
+use colored::Colorize;
+
+use crate::{print_gas_state, read_f64, read_input, read_pressure, read_temperature, try_gas_state, ProgramState};
+
+use std::f64::consts::PI;
+
+pub struct FlowCondition {
+    // Mass flow in kg/s, dynamic viscosity in Pa-s and pressure drop in kPa
+    pub mass_flow: f64,
+    pub viscosity: f64,
+    pub dp: f64,
+    // Impeller tip speed in m/s, None when not a rotating machine
+    pub tip_speed: Option<f64>,
+}
+
+pub struct DimensionlessGroups {
+    // Mean velocity in the flow passage in m/s
+    pub velocity: f64,
+    pub reynolds: f64,
+    pub mach: f64,
+    pub euler: f64,
+    pub machine_mach: Option<f64>,
+}
+
+// Groups for a passage of diameter (m) at the given state
+pub fn dimensionless_groups(state: &Detail, diameter: f64, condition: &FlowCondition) -> DimensionlessGroups {
+    let density = state.d * state.mm;
+    let velocity = condition.mass_flow / (density * PI * diameter * diameter / 4.0);
+    DimensionlessGroups {
+        velocity,
+        reynolds: density * velocity * diameter / condition.viscosity,
+        mach: velocity / state.w,
+        euler: condition.dp * 1000.0 / (density * velocity * velocity),
+        machine_mach: condition.tip_speed.map(|tip_speed| tip_speed / state.w),
+    }
+}
+
+fn read_condition() -> Option<FlowCondition> {
+    let mass_flow = read_f64("Enter mass flow (kg/s):");
+    let viscosity = read_f64("Enter gas dynamic viscosity (cP, e.g. 0.0108 natural gas):") / 1000.0;
+    let dp = read_f64("Enter pressure drop or rise across the component (kPa):");
+    let tip_speed = read_f64("Enter impeller tip speed (m/s, 0 if not a rotating machine):");
+    if mass_flow <= 0.0 || viscosity <= 0.0 || tip_speed < 0.0 {
+        return None;
+    }
+    Some(FlowCondition { mass_flow, viscosity, dp, tip_speed: (tip_speed > 0.0).then_some(tip_speed) })
+}
+
+// Reynolds, Mach and Euler numbers at the current state, optionally side by side with a
+// second condition (e.g. shop test against field) for similarity checks.
+pub fn dimensionless_groups_report(program_state: &mut ProgramState) {
+    outln!();
+    outln!("{}", "Dimensionless Groups".cyan().bold());
+    outln!("Flowing conditions are the current state.");
+    let diameter = read_f64("Enter flow passage diameter (mm):") / 1000.0;
+    let current = read_condition();
+    let (true, Some(current)) = (diameter > 0.0, current) else {
+        outln!("{}", "** Invalid geometry or flow inputs **".red().bold());
+        print_gas_state(program_state);
+        return;
+    };
+
+    outln!("Compare with a second condition, e.g. field against test? (y/n)");
+    let second = if read_input() == "y" {
+        let p = read_pressure(program_state, "second condition pressure");
+        let t = read_temperature(program_state, "second condition temperature");
+        match (try_gas_state(&program_state.gas_comp, p.kpa(), t.kelvin()), read_condition()) {
+            (Some(state), Some(condition)) => Some(dimensionless_groups(&state, diameter, &condition)),
+            _ => {
+                outln!("{}", "** Invalid second condition - showing the current state only **".red().bold());
+                None
+            },
+        }
+    } else {
+        None
+    };
+
+    let groups = dimensionless_groups(&program_state.gas_state, diameter, &current);
+    let rows = |groups: &DimensionlessGroups| [
+        ("Velocity: ", Some(groups.velocity), "m/s"),
+        ("Reynolds Number: ", Some(groups.reynolds), "[]"),
+        ("Mach Number: ", Some(groups.mach), "[]"),
+        ("Euler Number: ", Some(groups.euler), "[]"),
+        ("Machine Mach Number: ", groups.machine_mach, "[]"),
+    ];
+    outln!();
+    match &second {
+        None => {
+            for (label, value, unit) in rows(&groups) {
+                if let Some(value) = value {
+                    outln!("{:<30} {:>12} {:10}", label, format_group(value), unit);
+                }
+            }
+        },
+        Some(second) => {
+            outln!("{:<30} {:>12} {:>12} {:>10} {:10}", "", "Current", "Second", "Ratio", "");
+            for ((label, value, unit), (_, other, _)) in rows(&groups).into_iter().zip(rows(second)) {
+                if let (Some(value), Some(other)) = (value, other) {
+                    outln!("{:<30} {:>12} {:>12} {:10.4} {:10}", label, format_group(value), format_group(other), other / value, unit);
+                }
+            }
+        },
+    }
+    if groups.mach > 0.3 {
+        outln!("{}", "* Mach number above 0.3 - compressibility effects are significant".yellow());
+    }
+    print_gas_state(program_state);
+}
+
+// Reynolds numbers span many decades
+fn format_group(value: f64) -> String {
+    if value.abs() >= 1.0e5 { format!("{:.4e}", value) } else { format!("{:.4}", value) }
+}
@@ -0,0 +1,92 @@
+// Golden-file snapshots of rendered output.  Run with UPDATE_SNAPSHOTS=1 to accept changes.
+use aga8::detail::Detail;
+
+use crate::release::{release_source_term, source_term_csv, source_term_json};
+use crate::{
+    calculate_state, change_conditions, change_gas_comp, format_gas_state, get_gas_comp, initial_program_state, update_gas_state, GasComp, Pressure,
+    ProgramState, Temperature, UnitInternalEnergy, UnitPressure, UnitTemp,
+};
+
+use std::fs;
+use std::path::PathBuf;
+
+fn snapshot_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join("snapshots").join(name)
+}
+
+fn assert_snapshot(name: &str, actual: &str) {
+    let path = snapshot_path(name);
+    if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, actual).unwrap();
+        return;
+    }
+    let expected = fs::read_to_string(&path)
+        .unwrap_or_else(|err| panic!("missing snapshot {} ({}), run with UPDATE_SNAPSHOTS=1 to create it", path.display(), err));
+    assert!(expected == actual, "snapshot {} changed, run with UPDATE_SNAPSHOTS=1 to accept\n--- expected\n{}\n--- actual\n{}", name, expected, actual);
+}
+
+fn plain_state() -> ProgramState {
+    colored::control::set_override(false);
+    initial_program_state()
+}
+
+fn set_state(state: &mut Detail, gas_comp: &aga8::composition::Composition, p: f64, t: f64) {
+    *state = Detail::new();
+    state.set_composition(gas_comp).unwrap();
+    state.p = p;
+    state.t = t;
+    calculate_state(state);
+}
+
+// Sets the current state as the menus do, with the properties kept alongside it
+fn set_current_state(program_state: &mut ProgramState, p: f64, t: f64) {
+    change_conditions(program_state, Pressure::from_kpa(p), Temperature::from_kelvin(t));
+    update_gas_state(program_state);
+}
+
+#[test]
+fn air_initial_state() {
+    let program_state = plain_state();
+    assert_snapshot("air_initial_state.txt", &format_gas_state(&program_state));
+}
+
+#[test]
+fn natural_gas_us_units() {
+    let mut program_state = plain_state();
+    program_state.gas = String::from("Natural Gas");
+    change_gas_comp(&mut program_state, get_gas_comp(GasComp::NaturalGas));
+    set_current_state(&mut program_state, 5000.0, 300.0);
+    program_state.units.pressure = UnitPressure::PSI;
+    program_state.units.temp = UnitTemp::F;
+    program_state.units.internal_energy = UnitInternalEnergy::BTU_lbm;
+    assert_snapshot("natural_gas_us_units.txt", &format_gas_state(&program_state));
+}
+
+#[test]
+fn out_of_range_state() {
+    let mut program_state = plain_state();
+    set_current_state(&mut program_state, 15000.0, 250.0);
+    assert_snapshot("out_of_range_state.txt", &format_gas_state(&program_state));
+}
+
+#[test]
+fn compression_comparison() {
+    let mut program_state = plain_state();
+    program_state.gas = String::from("Methane");
+    change_gas_comp(&mut program_state, get_gas_comp(GasComp::Methane));
+    set_state(&mut program_state.inlet_state, &program_state.gas_comp, 2000.0, 300.0);
+    set_state(&mut program_state.discharge_state, &program_state.gas_comp, 6000.0, 400.0);
+    set_current_state(&mut program_state, 6000.0, 400.0);
+    program_state.show_inlet_state = true;
+    program_state.show_discharge_state = true;
+    assert_snapshot("compression_comparison.txt", &format_gas_state(&program_state));
+}
+
+#[test]
+fn release_source_term_exports() {
+    let gas_comp = get_gas_comp(GasComp::NaturalGas);
+    let term = release_source_term(&gas_comp, Pressure::from_kpa(5000.0), Temperature::from_kelvin(300.0), Pressure::from_kpa(101.325), 0.01, 0.62).unwrap();
+    assert_snapshot("release_source_term.csv", &source_term_csv("Natural Gas", &term));
+    assert_snapshot("release_source_term.json", &source_term_json("Natural Gas", &gas_comp, &term));
+}
@@ -0,0 +1,142 @@
+use aga8::composition::Composition;
+use colored::Colorize;
+
+use crate::quality::{gross_hv_volumetric, relative_density};
+use crate::{
+    change_gas_comp, get_pressure, get_temperature, print_gas_state, print_state_errors, read_f64, read_input, read_pressure, read_temperature, try_gas_state,
+    update_gas_state, ProgramState,
+};
+
+struct SoundSpeedPoint {
+    p: f64,
+    t: f64,
+    w: f64,
+}
+
+fn equivalent_composition(ethane: f64, propane: f64, carbon_dioxide: f64, nitrogen: f64) -> Composition {
+    Composition {
+        methane: 1.0 - ethane - propane - carbon_dioxide - nitrogen,
+        ethane,
+        propane,
+        carbon_dioxide,
+        nitrogen,
+        ..Default::default()
+    }
+}
+
+fn residuals(points: &[SoundSpeedPoint], params: [f64; 2], carbon_dioxide: f64, nitrogen: f64) -> Option<Vec<f64>> {
+    let gas_comp = equivalent_composition(params[0], params[1], carbon_dioxide, nitrogen);
+    points.iter().map(|point| try_gas_state(&gas_comp, point.p, point.t).map(|state| state.w - point.w)).collect()
+}
+
+// Fits the ethane and propane fractions of an equivalent CH4/C2H6/C3H8 gas (with the
+// measured CO2 and N2) to the measured speeds of sound by damped Gauss-Newton iteration.
+fn fit_composition(points: &[SoundSpeedPoint], carbon_dioxide: f64, nitrogen: f64) -> Option<Composition> {
+    let hydrocarbons = 1.0 - carbon_dioxide - nitrogen;
+    let mut params = [0.05 * hydrocarbons, 0.01 * hydrocarbons];
+    let clamp = |params: [f64; 2]| -> [f64; 2] {
+        let ethane = params[0].clamp(0.0, hydrocarbons);
+        let propane = params[1].clamp(0.0, hydrocarbons - ethane);
+        [ethane, propane]
+    };
+
+    for _ in 0..100 {
+        let r = residuals(points, params, carbon_dioxide, nitrogen)?;
+        let step = 1.0e-6;
+        let mut jacobian = vec![[0.0; 2]; points.len()];
+        for k in 0..2 {
+            let mut shifted = params;
+            shifted[k] += step;
+            let r_shifted = residuals(points, shifted, carbon_dioxide, nitrogen)?;
+            for i in 0..points.len() {
+                jacobian[i][k] = (r_shifted[i] - r[i]) / step;
+            }
+        }
+
+        // Normal equations with a small Levenberg damping term
+        let mut a = [[0.0; 2]; 2];
+        let mut g = [0.0; 2];
+        for i in 0..points.len() {
+            for j in 0..2 {
+                g[j] += jacobian[i][j] * r[i];
+                for k in 0..2 {
+                    a[j][k] += jacobian[i][j] * jacobian[i][k];
+                }
+            }
+        }
+        let damping = 1.0e-6 * (a[0][0] + a[1][1]);
+        a[0][0] += damping;
+        a[1][1] += damping;
+        let det = a[0][0] * a[1][1] - a[0][1] * a[1][0];
+        if det.abs() < 1.0e-30 {
+            return None;
+        }
+        let delta = [
+            -(a[1][1] * g[0] - a[0][1] * g[1]) / det,
+            -(a[0][0] * g[1] - a[1][0] * g[0]) / det,
+        ];
+        let next = clamp([params[0] + delta[0], params[1] + delta[1]]);
+        let converged = (next[0] - params[0]).abs() < 1.0e-10 && (next[1] - params[1]).abs() < 1.0e-10;
+        params = next;
+        if converged {
+            break;
+        }
+    }
+    Some(equivalent_composition(params[0], params[1], carbon_dioxide, nitrogen))
+}
+
+pub fn sound_speed_inference(program_state: &mut ProgramState) {
+    outln!();
+    outln!("{}", "Gas Quality from Speed of Sound".cyan().bold());
+    let count = read_f64("Enter number of measured points (2 or more):").max(2.0) as usize;
+    let mut points = Vec::new();
+    for i in 0..count {
+        outln!("Point {}:", i + 1);
+        let p = read_pressure(program_state, "pressure");
+        let t = read_temperature(program_state, "temperature");
+        let w = read_f64("Enter measured speed of sound (m/s):");
+        points.push(SoundSpeedPoint { p: p.kpa(), t: t.kelvin(), w });
+    }
+    let carbon_dioxide = read_f64("Enter CO2 mole fraction:");
+    let nitrogen = read_f64("Enter N2 mole fraction:");
+
+    let gas_comp = match fit_composition(&points, carbon_dioxide, nitrogen) {
+        Some(gas_comp) => gas_comp,
+        None => {
+            outln!("{}", "** Unable to fit an equivalent composition to the measured speeds of sound **".red().bold());
+            print_gas_state(program_state);
+            return;
+        }
+    };
+
+    outln!();
+    outln!("{:>12} {:>12} {:>12} {:>12} {:>10}",
+        format!("P ({})", program_state.units.pressure.label()), format!("T ({})", program_state.units.temp.label()),
+        "w meas m/s", "w calc m/s", "Dev %");
+    for point in &points {
+        let w_calc = try_gas_state(&gas_comp, point.p, point.t).map_or(f64::NAN, |state| state.w);
+        outln!("{:12.4} {:12.4} {:12.3} {:12.3} {:10.4}",
+            get_pressure(point.p, program_state.units.pressure), get_temperature(point.t, program_state.units.temp),
+            point.w, w_calc, (w_calc - point.w) / point.w * 100.0);
+    }
+
+    let heating_value = gross_hv_volumetric(&gas_comp, program_state.base_pressure, program_state.base_temperature);
+    let rd = relative_density(&gas_comp, program_state.base_pressure, program_state.base_temperature);
+    outln!();
+    outln!("{:<30} {:10.6}", "Equivalent Methane: ", gas_comp.methane);
+    outln!("{:<30} {:10.6}", "Equivalent Ethane: ", gas_comp.ethane);
+    outln!("{:<30} {:10.6}", "Equivalent Propane: ", gas_comp.propane);
+    outln!("{:<30} {:10.4} {:10}", "Gross Heating Value: ", heating_value, "MJ/m3");
+    outln!("{:<30} {:10.4} {:10}", "Relative Density: ", rd, "[]");
+
+    outln!("Use the inferred composition as the current gas? (y/n)");
+    if read_input() == "y" {
+        program_state.gas = format!("SOS inferred (HV {:.2}, RD {:.4})", heating_value, rd);
+        change_gas_comp(program_state, gas_comp);
+        program_state.show_inlet_state = false;
+        program_state.show_discharge_state = false;
+        print_state_errors(&update_gas_state(program_state));
+    }
+
+    print_gas_state(program_state);
+}
@@ -0,0 +1,263 @@
+use aga8::composition::Composition;
+use colored::Colorize;
+
+use crate::blending::select_stream_gas;
+use crate::components::{array_to_comp, comp_to_array, NUM_COMPONENTS};
+use crate::csv::{field_f64, write_csv, CsvTable};
+use crate::process::isochoric_state;
+use crate::quality::{gross_hv_volumetric, relative_density};
+use crate::{
+    get_pressure, print_gas_state, read_f64, read_input, read_pressure, read_temperature, try_gas_state, Pressure, ProgramState, Temperature,
+};
+
+use std::f64::consts::PI;
+
+// Well mixed gas inventory held in a fixed volume at constant temperature
+pub struct Inventory {
+    // Component amounts in mol
+    pub moles: [f64; NUM_COMPONENTS],
+    // Storage volume in m3 and temperature in K
+    pub volume: f64,
+    pub temperature: f64,
+}
+
+impl Inventory {
+    pub fn total(&self) -> f64 {
+        self.moles.iter().sum()
+    }
+
+    pub fn composition(&self) -> Composition {
+        let total = self.total();
+        array_to_comp(&self.moles.map(|n| n / total))
+    }
+
+    pub fn inject(&mut self, gas_comp: &Composition, moles: f64) {
+        for (n, x) in self.moles.iter_mut().zip(comp_to_array(gas_comp)) {
+            *n += moles * x;
+        }
+    }
+
+    // Withdrawn gas has the inventory composition.  Returns the moles actually removed.
+    pub fn withdraw(&mut self, moles: f64) -> f64 {
+        let removed = moles.min(self.total());
+        let fraction = removed / self.total();
+        for n in self.moles.iter_mut() {
+            *n *= 1.0 - fraction;
+        }
+        removed
+    }
+
+    // Pressure in kPa of the inventory
+    pub fn pressure(&self, p_guess: f64) -> Option<f64> {
+        // mol/m3 to mol/l
+        let d = self.total() / self.volume / 1000.0;
+        isochoric_state(&self.composition(), d, self.temperature, p_guess).map(|state| state.p)
+    }
+}
+
+// Moles in a volume (m3) at the base pressure and temperature
+fn base_volume_to_moles(gas_comp: &Composition, volume: f64, p_base: Pressure, t_base: Temperature) -> Option<f64> {
+    try_gas_state(gas_comp, p_base.kpa(), t_base.kelvin()).map(|state| volume * state.d * 1000.0)
+}
+
+struct ScheduleStep {
+    step: String,
+    // Volumes at base conditions in m3
+    injection: f64,
+    withdrawal: f64,
+    // Index into the injection sources
+    source: usize,
+}
+
+// Reads a CSV with the columns step, injection and withdrawal (m3 at base conditions) and
+// the optional column source (injection source number, default 1).
+fn read_schedule(path: &str, source_count: usize) -> Result<Vec<ScheduleStep>, String> {
+    let table = CsvTable::read(path)?;
+    let step_col = table.require_column("step")?;
+    let injection_col = table.require_column("injection")?;
+    let withdrawal_col = table.require_column("withdrawal")?;
+    let source_col = table.column("source");
+
+    let mut steps = Vec::new();
+    for (line, row) in table.rows.iter().enumerate() {
+        let row_error = |err: String| format!("Row {}: {}", line + 2, err);
+        let injection = field_f64(row, Some(injection_col)).map_err(row_error)?.unwrap_or(0.0);
+        let withdrawal = field_f64(row, Some(withdrawal_col)).map_err(row_error)?.unwrap_or(0.0);
+        let source = field_f64(row, source_col).map_err(row_error)?.unwrap_or(1.0) as usize;
+        if injection < 0.0 || withdrawal < 0.0 {
+            return Err(row_error("volumes cannot be negative".to_string()));
+        }
+        if source < 1 || source > source_count {
+            return Err(row_error(format!("source must be between 1 and {}", source_count)));
+        }
+        steps.push(ScheduleStep {
+            step: row.get(step_col).cloned().unwrap_or_default(),
+            injection,
+            withdrawal,
+            source: source - 1,
+        });
+    }
+    Ok(steps)
+}
+
+pub fn storage_simulation(program_state: &mut ProgramState) {
+    outln!();
+    outln!("{}", "Storage Inventory Simulation".green().bold());
+    outln!("Initial inventory is the current gas at the current state, held at the current temperature.");
+    let volume = read_f64("Enter storage volume (m3):");
+    let count = read_f64("Enter number of injection sources:").max(1.0) as usize;
+    let mut sources = Vec::new();
+    for i in 0..count {
+        outln!("Source {}:", i + 1);
+        match select_stream_gas(program_state) {
+            Some((_, gas_comp)) => sources.push(gas_comp),
+            None => {
+                outln!("{}", "**Invalid selection!**".bold().red());
+                print_gas_state(program_state);
+                return;
+            }
+        }
+    }
+    outln!("Enter schedule CSV file path (columns: step,injection,withdrawal[,source], volumes in m3 at base conditions):");
+    let path = read_input();
+    let schedule = match read_schedule(&path, sources.len()) {
+        Ok(schedule) => schedule,
+        Err(err) => {
+            outln!("{}", format!("** {} **", err).red().bold());
+            print_gas_state(program_state);
+            return;
+        }
+    };
+
+    let p_base = program_state.base_pressure;
+    let t_base = program_state.base_temperature;
+    let mut inventory = Inventory {
+        moles: [0.0; NUM_COMPONENTS],
+        volume,
+        temperature: program_state.gas_state.t,
+    };
+    inventory.inject(&program_state.gas_comp, program_state.gas_state.d * 1000.0 * volume);
+    let mut pressure = program_state.gas_state.p;
+
+    let p_label = format!("P ({})", program_state.units.pressure.label());
+    let headers = ["Step", "Inventory (m3)", p_label.as_str(), "HV MJ/m3", "RD", "CH4 mol%"];
+    let mut rows = Vec::new();
+    outln!();
+    outln!("{:<10} {:>16} {:>12} {:>10} {:>8} {:>10}", headers[0], headers[1], headers[2], headers[3], headers[4], headers[5]);
+    for (i, step) in schedule.iter().enumerate() {
+        let source = &sources[step.source];
+        let injected = base_volume_to_moles(source, step.injection, p_base, t_base);
+        let withdrawn = base_volume_to_moles(&inventory.composition(), step.withdrawal, p_base, t_base);
+        let (Some(injected), Some(withdrawn)) = (injected, withdrawn) else {
+            outln!("{}", format!("** Step {}: unable to convert base volumes **", step.step).red().bold());
+            break;
+        };
+        inventory.inject(source, injected);
+        if inventory.withdraw(withdrawn) < withdrawn {
+            outln!("{}", format!("** Step {}: withdrawal exceeds the inventory, storage emptied **", step.step).red().bold());
+            break;
+        }
+
+        let gas_comp = inventory.composition();
+        pressure = match inventory.pressure(pressure) {
+            Some(pressure) => pressure,
+            None => {
+                outln!("{}", format!("** Step {}: unable to solve the storage pressure **", step.step).red().bold());
+                break;
+            }
+        };
+        let stored = base_volume_to_moles(&gas_comp, 1.0, p_base, t_base).map_or(f64::NAN, |per_m3| inventory.total() / per_m3);
+        let hv = gross_hv_volumetric(&gas_comp, p_base, t_base);
+        let rd = relative_density(&gas_comp, p_base, t_base);
+        let p_display = get_pressure(pressure, program_state.units.pressure);
+        outln!("{:<10} {:16.1} {:12.4} {:10.4} {:8.4} {:10.4}", step.step, stored, p_display, hv, rd, gas_comp.methane * 100.0);
+        rows.push(vec![(i + 1) as f64, stored, p_display, hv, rd, gas_comp.methane * 100.0]);
+    }
+
+    outln!();
+    outln!("Enter CSV file path to export the history (leave blank to skip):");
+    let path = read_input();
+    if !path.is_empty() {
+        match write_csv(&path, &headers, &rows) {
+            Ok(()) => outln!("History written to {}", path),
+            Err(err) => outln!("{}", format!("** {} **", err).red().bold()),
+        }
+    }
+
+    print_gas_state(program_state);
+}
+
+pub struct ContainedGas {
+    // Amount in kmol, mass in kg and volume at base conditions in m3
+    pub moles: f64,
+    pub mass: f64,
+    pub base_volume: f64,
+    pub z: f64,
+}
+
+// Gas contained in a volume (m3) at p and t
+pub fn contained_gas(gas_comp: &Composition, volume: f64, p: Pressure, t: Temperature, p_base: Pressure, t_base: Temperature) -> Option<ContainedGas> {
+    let state = try_gas_state(gas_comp, p.kpa(), t.kelvin())?;
+    let base = try_gas_state(gas_comp, p_base.kpa(), t_base.kelvin())?;
+    // mol/l times m3 gives kmol
+    let moles = state.d * volume;
+    Some(ContainedGas { moles, mass: moles * state.mm, base_volume: moles / base.d, z: state.z })
+}
+
+fn print_contained_gas(label: &str, gas: &ContainedGas) {
+    outln!("{}", label.bold());
+    outln!("{:<30} {:10.4} {:10}", "Compressibility Z: ", gas.z, "[]");
+    outln!("{:<30} {:10.4} {:10}", "Contained Amount: ", gas.moles, "kmol");
+    outln!("{:<30} {:10.4} {:10}", "Contained Mass: ", gas.mass, "kg");
+    outln!("{:<30} {:10.4} {:10}", "Standard Volume: ", gas.base_volume, "m3");
+}
+
+// Contained gas in a vessel, cavern or pipeline segment at the current state and the
+// change in inventory to a second state (line pack).
+pub fn gas_inventory(program_state: &mut ProgramState) {
+    outln!();
+    outln!("{}", "Gas Inventory / Line Pack".green().bold());
+    outln!("Volume: 1 - Enter volume, 2 - Pipeline segment (length and inside diameter)");
+    let volume = match read_input().as_str() {
+        "2" => {
+            let length = read_f64("Enter segment length (km):") * 1000.0;
+            let diameter = read_f64("Enter inside diameter (mm):") / 1000.0;
+            PI * diameter * diameter / 4.0 * length
+        }
+        _ => read_f64("Enter volume (m3):"),
+    };
+    let (p_base, t_base) = (program_state.base_pressure, program_state.base_temperature);
+    let p1 = Pressure::from_kpa(program_state.gas_state.p);
+    let t1 = Temperature::from_kelvin(program_state.gas_state.t);
+
+    let Some(first) = contained_gas(&program_state.gas_comp, volume, p1, t1, p_base, t_base) else {
+        outln!("{}", "** Unable to solve the contained gas **".red().bold());
+        print_gas_state(program_state);
+        return;
+    };
+    outln!();
+    outln!("{:<30} {:10.4} {:10}", "Volume: ", volume, "m3");
+    outln!("{:<30} {:10.4} {:10}", "Base Pressure: ", p_base.value(program_state.units.pressure), program_state.units.pressure.label());
+    outln!("{:<30} {:10.4} {:10}", "Base Temperature: ", t_base.value(program_state.units.temp), program_state.units.temp.label());
+    print_contained_gas("Current State", &first);
+
+    outln!();
+    outln!("Calculate the change to a second state (line pack)? (y/n)");
+    if read_input() == "y" {
+        let p2 = read_pressure(program_state, "second state pressure (e.g. average pressure after packing)");
+        let t2 = read_temperature(program_state, "second state temperature");
+        match contained_gas(&program_state.gas_comp, volume, p2, t2, p_base, t_base) {
+            Some(second) => {
+                outln!();
+                print_contained_gas("Second State", &second);
+                outln!("{}", "Change (second - current)".bold());
+                outln!("{:<30} {:10.4} {:10}", "Amount: ", second.moles - first.moles, "kmol");
+                outln!("{:<30} {:10.4} {:10}", "Mass: ", second.mass - first.mass, "kg");
+                outln!("{:<30} {:10.4} {:10}", "Standard Volume: ", second.base_volume - first.base_volume, "m3");
+            }
+            None => outln!("{}", "** Unable to solve the second state **".red().bold()),
+        }
+    }
+
+    print_gas_state(program_state);
+}
@@ -0,0 +1,91 @@
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+
+use crate::{output, print_gas_state, read_input, ProgramState};
+
+// Deliverable metadata for a stream, e.g. P&ID tag "PT-1001", service "Export gas",
+// case "Summer max"
+#[derive(Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct StreamTag {
+    pub tag: String,
+    pub service: String,
+    pub case: String,
+}
+
+impl StreamTag {
+    pub fn is_empty(&self) -> bool {
+        self.tag.is_empty() && self.service.is_empty() && self.case.is_empty()
+    }
+
+    // Non-empty fields joined for one-line display
+    pub fn label(&self) -> String {
+        [&self.tag, &self.service, &self.case].into_iter()
+            .filter(|field| !field.is_empty())
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+            .join(" / ")
+    }
+}
+
+// Tags of the current state and of the saved inlet and discharge.  Saving a state as
+// inlet or discharge copies the current tag with it.
+#[derive(Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct StreamTags {
+    pub current: StreamTag,
+    pub inlet: StreamTag,
+    pub discharge: StreamTag,
+}
+
+impl StreamTags {
+    pub fn is_empty(&self) -> bool {
+        self.current.is_empty() && self.inlet.is_empty() && self.discharge.is_empty()
+    }
+}
+
+// Blank keeps the existing value, "-" clears it
+fn read_field(label: &str, value: &mut String) {
+    if !output::quiet() {
+        let current = if value.is_empty() { String::from("none") } else { value.clone() };
+        outln!("Enter {} (current: {}, blank to keep, - to clear):", label, current);
+    }
+    match read_input().as_str() {
+        "" => (),
+        "-" => value.clear(),
+        input => *value = input.to_string(),
+    }
+}
+
+pub fn edit_tags(program_state: &mut ProgramState) {
+    let tag = loop {
+        if !output::quiet() {
+            outln!();
+            outln!("{}", "Stream Tags".cyan().bold());
+            outln!("1 - Current state ({})", describe(&program_state.tags.current));
+            if program_state.show_inlet_state {
+                outln!("2 - Inlet ({})", describe(&program_state.tags.inlet));
+            }
+            if program_state.show_discharge_state {
+                outln!("3 - Discharge ({})", describe(&program_state.tags.discharge));
+            }
+            outln!("b - Back");
+        }
+        match read_input().as_str() {
+            "1" => break &mut program_state.tags.current,
+            "2" if program_state.show_inlet_state => break &mut program_state.tags.inlet,
+            "3" if program_state.show_discharge_state => break &mut program_state.tags.discharge,
+            "b" => {
+                print_gas_state(program_state);
+                return;
+            },
+            _ => outln!("{}", "**Invalid selection!**".bold().red()),
+        }
+    };
+    read_field("tag number", &mut tag.tag);
+    read_field("service description", &mut tag.service);
+    read_field("case name", &mut tag.case);
+    print_gas_state(program_state);
+}
+
+fn describe(tag: &StreamTag) -> String {
+    if tag.is_empty() { String::from("untagged") } else { tag.label() }
+}
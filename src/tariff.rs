@@ -0,0 +1,169 @@
+use aga8::composition::Composition;
+use colored::Colorize;
+use serde::Deserialize;
+
+use crate::quality::{gross_hv_volumetric, relative_density};
+use crate::water::ppmv_to_content;
+use crate::{print_gas_state, read_f64, read_input, Pressure, ProgramState, Temperature};
+
+use std::fs;
+
+// Gas quality specification read from TOML.  Every limit is optional; heating value and
+// Wobbe index in MJ/m3 at the base conditions, components in mol% except H2S in ppm,
+// water content in mg/Sm3 and dew points in C.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Tariff {
+    pub name: Option<String>,
+    pub hv_min: Option<f64>,
+    pub hv_max: Option<f64>,
+    pub wobbe_min: Option<f64>,
+    pub wobbe_max: Option<f64>,
+    pub relative_density_min: Option<f64>,
+    pub relative_density_max: Option<f64>,
+    pub co2_max: Option<f64>,
+    pub h2s_max_ppm: Option<f64>,
+    pub oxygen_max: Option<f64>,
+    pub hydrogen_max: Option<f64>,
+    pub inerts_max: Option<f64>,
+    pub water_max: Option<f64>,
+    pub water_dew_point_max: Option<f64>,
+    pub hc_dew_point_max: Option<f64>,
+}
+
+pub fn read_tariff(path: &str) -> Result<Tariff, String> {
+    let text = fs::read_to_string(path).map_err(|err| format!("Unable to read {}: {}", path, err))?;
+    toml::from_str(&text).map_err(|err| format!("Invalid tariff {}: {}", path, err))
+}
+
+pub struct ComplianceCheck {
+    pub parameter: &'static str,
+    pub unit: &'static str,
+    pub value: f64,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+}
+
+impl ComplianceCheck {
+    pub fn passes(&self) -> bool {
+        self.min.is_none_or(|min| self.value >= min) && self.max.is_none_or(|max| self.value <= max)
+    }
+}
+
+// Checks the composition against every limit in the tariff.  Dew points cannot be
+// derived from the composition, so measured values are passed in (C).  The water content
+// (mg/Sm3) is that of the composition unless a measured value is passed in.
+pub fn check_compliance(tariff: &Tariff, gas_comp: &Composition, p_base: Pressure, t_base: Temperature,
+    water: Option<f64>, water_dew_point: Option<f64>, hc_dew_point: Option<f64>) -> Vec<ComplianceCheck> {
+    let hv = gross_hv_volumetric(gas_comp, p_base, t_base);
+    let rd = relative_density(gas_comp, p_base, t_base);
+    let inerts = gas_comp.nitrogen + gas_comp.carbon_dioxide + gas_comp.helium + gas_comp.argon;
+    let water = water.unwrap_or_else(|| ppmv_to_content(gas_comp.water * 1.0e6));
+    let candidates = [
+        ("Gross Heating Value", "MJ/m3", Some(hv), tariff.hv_min, tariff.hv_max),
+        ("Wobbe Index", "MJ/m3", Some(hv / rd.sqrt()), tariff.wobbe_min, tariff.wobbe_max),
+        ("Relative Density", "[]", Some(rd), tariff.relative_density_min, tariff.relative_density_max),
+        ("Carbon Dioxide", "mol%", Some(gas_comp.carbon_dioxide * 100.0), None, tariff.co2_max),
+        ("Hydrogen Sulfide", "ppm", Some(gas_comp.hydrogen_sulfide * 1.0e6), None, tariff.h2s_max_ppm),
+        ("Oxygen", "mol%", Some(gas_comp.oxygen * 100.0), None, tariff.oxygen_max),
+        ("Hydrogen", "mol%", Some(gas_comp.hydrogen * 100.0), None, tariff.hydrogen_max),
+        ("Total Inerts", "mol%", Some(inerts * 100.0), None, tariff.inerts_max),
+        ("Water Content", "mg/Sm3", Some(water), None, tariff.water_max),
+        ("Water Dew Point", "C", water_dew_point, None, tariff.water_dew_point_max),
+        ("HC Dew Point", "C", hc_dew_point, None, tariff.hc_dew_point_max),
+    ];
+    candidates.into_iter()
+        .filter(|(_, _, _, min, max)| min.is_some() || max.is_some())
+        .filter_map(|(parameter, unit, value, min, max)| value.map(|value| ComplianceCheck { parameter, unit, value, min, max }))
+        .collect()
+}
+
+pub fn format_limit(limit: Option<f64>) -> String {
+    limit.map_or(format!("{:>10}", "-"), |limit| format!("{:10.4}", limit))
+}
+
+pub fn tariff_compliance(program_state: &mut ProgramState) {
+    outln!();
+    outln!("{}", "Gas Quality Tariff Compliance".cyan().bold());
+    outln!("Enter tariff TOML file path:");
+    let tariff = match read_tariff(&read_input()) {
+        Ok(tariff) => tariff,
+        Err(err) => {
+            outln!("{}", format!("** {} **", err).red().bold());
+            print_gas_state(program_state);
+            return;
+        }
+    };
+    // A composition without water is a dry basis analysis
+    let water = tariff.water_max.filter(|_| program_state.gas_comp.water == 0.0).map(|_| read_f64("Enter measured water content (mg/Sm3):"));
+    let water_dew_point = tariff.water_dew_point_max.map(|_| read_f64("Enter measured water dew point (C):"));
+    let hc_dew_point = tariff.hc_dew_point_max.map(|_| read_f64("Enter measured hydrocarbon dew point (C):"));
+
+    let checks = check_compliance(&tariff, &program_state.gas_comp, program_state.base_pressure, program_state.base_temperature,
+        water, water_dew_point, hc_dew_point);
+
+    outln!();
+    outln!("{} - {}", tariff.name.as_deref().unwrap_or("Tariff"), program_state.gas);
+    outln!("{:<22} {:>12} {:>10} {:>10} {:<8} {:>6}", "Parameter", "Value", "Min", "Max", "Unit", "Result");
+    for check in &checks {
+        let result = if check.passes() { "PASS".green().bold() } else { "FAIL".red().bold() };
+        outln!("{:<22} {:12.4} {} {} {:<8} {:>6}", check.parameter, check.value, format_limit(check.min), format_limit(check.max), check.unit, result);
+    }
+    let failures = checks.iter().filter(|check| !check.passes()).count();
+    outln!();
+    if failures == 0 {
+        outln!("{}", format!("Gas complies with all {} tariff limits", checks.len()).green().bold());
+    } else {
+        outln!("{}", format!("** Gas fails {} of {} tariff limits **", failures, checks.len()).red().bold());
+    }
+
+    print_gas_state(program_state);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tariff(text: &str) -> Tariff {
+        toml::from_str(text).unwrap()
+    }
+
+    fn check(value: f64, min: Option<f64>, max: Option<f64>) -> ComplianceCheck {
+        ComplianceCheck { parameter: "", unit: "", value, min, max }
+    }
+
+    // Limits are inclusive and a value that is not a number fails
+    #[test]
+    fn limits() {
+        assert!(check(5.0, Some(5.0), Some(5.0)).passes());
+        assert!(check(1.0e9, None, None).passes());
+        assert!(!check(4.999, Some(5.0), None).passes());
+        assert!(!check(5.001, None, Some(5.0)).passes());
+        assert!(!check(f64::NAN, None, Some(5.0)).passes());
+    }
+
+    // Only parameters with a limit are checked, and dew points only when measured
+    #[test]
+    fn checked_parameters() {
+        let gas_comp = Composition { methane: 0.97, carbon_dioxide: 0.02, nitrogen: 0.01, ..Default::default() };
+        let (p_base, t_base) = (Pressure::from_kpa(101.325), Temperature::from_kelvin(288.15));
+        let limits = tariff("co2_max = 2.0\ninerts_max = 2.5\nwater_max = 65.0\nhc_dew_point_max = -2.0\n");
+        let checks = check_compliance(&limits, &gas_comp, p_base, t_base, Some(80.0), None, None);
+        let names: Vec<&str> = checks.iter().map(|check| check.parameter).collect();
+        assert_eq!(names, ["Carbon Dioxide", "Total Inerts", "Water Content"]);
+        assert!(checks[0].passes());
+        assert!(!checks[1].passes());
+        // The measured water content replaces that of the dry composition
+        assert_eq!(checks[2].value, 80.0);
+
+        let checks = check_compliance(&limits, &gas_comp, p_base, t_base, None, Some(-10.0), Some(0.0));
+        assert_eq!(checks[2].value, 0.0);
+        assert_eq!(checks.last().map(|check| (check.parameter, check.passes())), Some(("HC Dew Point", false)));
+    }
+
+    #[test]
+    fn unknown_limits_are_rejected() {
+        assert!(toml::from_str::<Tariff>("co2_maximum = 2.0\n").is_err());
+        assert!(tariff("").hv_min.is_none());
+    }
+}
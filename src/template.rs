@@ -0,0 +1,310 @@
+use aga8::detail::Detail;
+use colored::Colorize;
+use serde::Serialize;
+use serde_json::Value;
+use tinytemplate::TinyTemplate;
+
+use comp_perf::thermo::{ideal_gas_state, residual_properties, Residual};
+
+use crate::components::{comp_to_array, COMPONENT_LABELS};
+use crate::precision::{Precision, Quantity};
+use crate::tags::StreamTag;
+use crate::{
+    d2p_dd2_in_units, d2p_dd2_label, dp_dd_in_units, dp_dd_label, dp_dt_in_units, dp_dt_label, energy_in_units, entropy_in_units, entropy_label,
+    get_density, get_pressure, get_temperature, internal_energy_in_units, isothermal_sound_speed, jt_in_units, jt_label, print_gas_state, read_input, ProgramState,
+};
+
+use std::fs;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// State properties in the display units of the program
+#[derive(Serialize)]
+pub struct StateValues {
+    pub pressure: f64,
+    pub temperature: f64,
+    pub density: f64,
+    pub molar_mass: f64,
+    pub internal_energy: f64,
+    pub enthalpy: f64,
+    pub entropy: f64,
+    pub cp: f64,
+    // Ideal gas Cp at the same temperature
+    pub ideal_cp: f64,
+    pub cv: f64,
+    pub z: f64,
+    pub kappa: f64,
+    pub speed_of_sound: f64,
+    pub isothermal_speed_of_sound: f64,
+    pub gibbs_energy: f64,
+    pub jt: f64,
+    // At constant density, and the first and second density derivatives at constant
+    // temperature
+    pub dp_dt: f64,
+    pub dp_dd: f64,
+    pub d2p_dd2: f64,
+    // Real minus ideal gas at the same temperature and pressure
+    pub residual_enthalpy: f64,
+    pub residual_entropy: f64,
+    pub residual_internal_energy: f64,
+}
+
+impl StateValues {
+    fn of(state: &Detail, program_state: &ProgramState) -> StateValues {
+        let residual = residual_properties(&program_state.gas_comp, state);
+        let residual = |value: fn(&Residual) -> f64| residual.as_ref().map_or(f64::NAN, value);
+        let units = program_state.units;
+        let entropy = |value| entropy_in_units(value, units.temp);
+        let energy = |value| energy_in_units(value, state.mm, units.internal_energy);
+        StateValues {
+            pressure: get_pressure(state.p, program_state.units.pressure),
+            temperature: get_temperature(state.t, program_state.units.temp),
+            density: get_density(state, program_state.units.density),
+            molar_mass: state.mm,
+            internal_energy: internal_energy_in_units(state, program_state.units.internal_energy),
+            enthalpy: energy(state.h),
+            entropy: entropy(state.s),
+            cp: entropy(state.cp),
+            ideal_cp: entropy(ideal_gas_state(&program_state.gas_comp, state.t).map_or(f64::NAN, |ideal| ideal.cp)),
+            cv: entropy(state.cv),
+            z: state.z,
+            kappa: state.kappa,
+            speed_of_sound: state.w,
+            isothermal_speed_of_sound: isothermal_sound_speed(state),
+            gibbs_energy: energy(state.g),
+            jt: jt_in_units(state.jt, units.temp, units.pressure),
+            dp_dt: dp_dt_in_units(state.dp_dt, units.pressure, units.temp),
+            dp_dd: dp_dd_in_units(state.dp_dd, state.mm, units.pressure, units.density),
+            d2p_dd2: d2p_dd2_in_units(state.d2p_dd2, state.mm, units.pressure, units.density),
+            residual_enthalpy: energy(residual(|r| r.h)),
+            residual_entropy: entropy(residual(|r| r.s)),
+            residual_internal_energy: energy(residual(|r| r.u)),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct UnitLabels {
+    pub pressure: &'static str,
+    pub temperature: &'static str,
+    pub internal_energy: &'static str,
+    pub density: &'static str,
+    pub entropy: String,
+    pub jt: String,
+    pub dp_dt: String,
+    pub dp_dd: String,
+    pub d2p_dd2: String,
+}
+
+// One row of the property table, with a value for each of the report columns formatted
+// as set in the number format settings
+#[derive(Serialize)]
+pub struct PropertyRow {
+    pub name: &'static str,
+    pub unit: String,
+    pub values: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct StreamColumn {
+    pub column: &'static str,
+    pub tag: StreamTag,
+}
+
+#[derive(Serialize)]
+pub struct ComponentValue {
+    pub name: &'static str,
+    pub mole_percent: f64,
+}
+
+// Everything a report template can refer to
+#[derive(Serialize)]
+pub struct ReportContext {
+    // UTC time the report was rendered
+    pub timestamp: String,
+    pub gas: String,
+    pub units: UnitLabels,
+    pub state: StateValues,
+    pub inlet: Option<StateValues>,
+    pub discharge: Option<StateValues>,
+    pub pressure_ratio: Option<f64>,
+    // Property table: Current, then Inlet and Discharge when set
+    pub columns: Vec<&'static str>,
+    // Tag of each column and whether any is set
+    pub streams: Vec<StreamColumn>,
+    pub tagged: bool,
+    pub properties: Vec<PropertyRow>,
+    // Components present in the gas, in the usual component order
+    pub composition: Vec<ComponentValue>,
+}
+
+// Current UTC time as "YYYY-MM-DD HH:MM:SS UTC"
+pub fn utc_timestamp() -> String {
+    let seconds = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_secs()) as i64;
+    let days = seconds.div_euclid(86_400);
+    let time = seconds.rem_euclid(86_400);
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC", year, month, day, time / 3600, time / 60 % 60, time % 60)
+}
+
+type StateField = fn(&StateValues) -> f64;
+
+fn property_rows(states: &[&StateValues], units: &UnitLabels, precision: &Precision) -> Vec<PropertyRow> {
+    let fields: [(&'static str, &str, Quantity, StateField); 23] = [
+        ("Absolute Pressure", units.pressure, Quantity::Pressure, |s| s.pressure),
+        ("Absolute Temperature", units.temperature, Quantity::Temperature, |s| s.temperature),
+        ("Density", units.density, Quantity::Density, |s| s.density),
+        ("Molar Mass", "g/mol", Quantity::MolarMass, |s| s.molar_mass),
+        ("Internal Energy u", units.internal_energy, Quantity::Energy, |s| s.internal_energy),
+        ("Enthalpy", units.internal_energy, Quantity::Energy, |s| s.enthalpy),
+        ("Entropy", &units.entropy, Quantity::Entropy, |s| s.entropy),
+        ("Cp", &units.entropy, Quantity::Entropy, |s| s.cp),
+        ("Ideal Gas Cp°", &units.entropy, Quantity::Entropy, |s| s.ideal_cp),
+        ("Real Gas Correction Cp-Cp°", &units.entropy, Quantity::Entropy, |s| s.cp - s.ideal_cp),
+        ("Cv", &units.entropy, Quantity::Entropy, |s| s.cv),
+        ("Compressibility Z", "", Quantity::Dimensionless, |s| s.z),
+        ("Isentropic Exponent k", "", Quantity::Dimensionless, |s| s.kappa),
+        ("Speed of Sound w", "m/s", Quantity::SpeedOfSound, |s| s.speed_of_sound),
+        ("Isothermal Speed of Sound", "m/s", Quantity::SpeedOfSound, |s| s.isothermal_speed_of_sound),
+        ("Gibbs Energy", units.internal_energy, Quantity::Energy, |s| s.gibbs_energy),
+        ("Joule-Thompson Coefficient", &units.jt, Quantity::JouleThomson, |s| s.jt),
+        ("dP/dT (Constant Density)", &units.dp_dt, Quantity::Derivative, |s| s.dp_dt),
+        ("dP/dρ (Constant T)", &units.dp_dd, Quantity::Derivative, |s| s.dp_dd),
+        ("d²P/dρ² (Constant T)", &units.d2p_dd2, Quantity::Derivative, |s| s.d2p_dd2),
+        ("Residual Enthalpy", units.internal_energy, Quantity::Energy, |s| s.residual_enthalpy),
+        ("Residual Entropy", &units.entropy, Quantity::Entropy, |s| s.residual_entropy),
+        ("Residual Internal Energy", units.internal_energy, Quantity::Energy, |s| s.residual_internal_energy),
+    ];
+    fields.into_iter()
+        .map(|(name, unit, quantity, get)| PropertyRow {
+            name,
+            unit: unit.to_string(),
+            values: states.iter().map(|state| precision.format(quantity, get(state))).collect(),
+        })
+        .collect()
+}
+
+pub fn report_context(program_state: &ProgramState) -> ReportContext {
+    let composition = COMPONENT_LABELS.iter().zip(comp_to_array(&program_state.gas_comp))
+        .filter(|(_, fraction)| *fraction > 0.0)
+        .map(|(name, fraction)| ComponentValue { name, mole_percent: fraction * 100.0 })
+        .collect();
+    let both = program_state.show_inlet_state && program_state.show_discharge_state;
+    let units = UnitLabels {
+        pressure: program_state.units.pressure.label(),
+        temperature: program_state.units.temp.label(),
+        internal_energy: program_state.units.internal_energy.label(),
+        density: program_state.units.density.label(),
+        entropy: entropy_label(program_state.units.temp),
+        jt: jt_label(program_state.units.temp, program_state.units.pressure),
+        dp_dt: dp_dt_label(program_state.units.pressure, program_state.units.temp),
+        dp_dd: dp_dd_label(program_state.units.pressure, program_state.units.density),
+        d2p_dd2: d2p_dd2_label(program_state.units.pressure, program_state.units.density),
+    };
+    let state = StateValues::of(&program_state.gas_state, program_state);
+    let inlet = program_state.show_inlet_state.then(|| StateValues::of(&program_state.inlet_state, program_state));
+    let discharge = program_state.show_discharge_state.then(|| StateValues::of(&program_state.discharge_state, program_state));
+    let mut columns = vec!["Current"];
+    let mut states = vec![&state];
+    let mut tags = vec![&program_state.tags.current];
+    if let Some(inlet) = &inlet {
+        columns.push("Inlet");
+        states.push(inlet);
+        tags.push(&program_state.tags.inlet);
+    }
+    if let Some(discharge) = &discharge {
+        columns.push("Discharge");
+        states.push(discharge);
+        tags.push(&program_state.tags.discharge);
+    }
+    let tagged = tags.iter().any(|tag| !tag.is_empty());
+    let streams = columns.iter().zip(tags).map(|(column, tag)| StreamColumn { column, tag: tag.clone() }).collect();
+    let properties = property_rows(&states, &units, &program_state.precision);
+    ReportContext {
+        timestamp: utc_timestamp(),
+        gas: program_state.gas.clone(),
+        units,
+        state,
+        inlet,
+        discharge,
+        pressure_ratio: both.then(|| program_state.discharge_state.p / program_state.inlet_state.p),
+        columns,
+        streams,
+        tagged,
+        properties,
+        composition,
+    }
+}
+
+fn fixed(value: &Value, output: &mut String, decimals: usize) -> tinytemplate::error::Result<()> {
+    match value.as_f64() {
+        Some(number) => output.push_str(&format!("{:.*}", decimals, number)),
+        None => tinytemplate::format_unescaped(value, output)?,
+    }
+    Ok(())
+}
+
+// Renders a template with the {value | fixed4} style formatters fixed0 to fixed6 and sci.
+// HTML templates escape the substituted text.
+pub fn render_template(text: &str, context: &ReportContext, escape_html: bool) -> Result<String, String> {
+    let mut template = TinyTemplate::new();
+    template.add_template("report", text).map_err(|err| format!("Template error: {}", err))?;
+    if !escape_html {
+        template.set_default_formatter(&tinytemplate::format_unescaped);
+    }
+    for (name, decimals) in [("fixed0", 0), ("fixed1", 1), ("fixed2", 2), ("fixed3", 3), ("fixed4", 4), ("fixed5", 5), ("fixed6", 6)] {
+        template.add_formatter(name, move |value, output| fixed(value, output, decimals));
+    }
+    template.add_formatter("sci", |value, output| {
+        match value.as_f64() {
+            Some(number) => output.push_str(&format!("{:.4e}", number)),
+            None => tinytemplate::format_unescaped(value, output)?,
+        }
+        Ok(())
+    });
+    template.render("report", context).map_err(|err| format!("Template error: {}", err))
+}
+
+pub fn render_user_template(program_state: &mut ProgramState) {
+    outln!();
+    outln!("{}", "Render Output Template".cyan().bold());
+    outln!("Enter template file path:");
+    let template_path = read_input();
+    let text = match fs::read_to_string(&template_path) {
+        Ok(text) => text,
+        Err(err) => {
+            outln!("{}", format!("** Unable to read {}: {} **", template_path, err).red().bold());
+            print_gas_state(program_state);
+            return;
+        }
+    };
+    let escape_html = template_path.ends_with(".html") || template_path.ends_with(".htm");
+    let rendered = match render_template(&text, &report_context(program_state), escape_html) {
+        Ok(rendered) => rendered,
+        Err(err) => {
+            outln!("{}", format!("** {} **", err).red().bold());
+            print_gas_state(program_state);
+            return;
+        }
+    };
+    outln!("Enter output file path (leave blank to print):");
+    let path = read_input();
+    if path.is_empty() {
+        outln!();
+        out!("{}", rendered);
+    } else {
+        match fs::write(&path, rendered) {
+            Ok(()) => outln!("Report written to {}", path),
+            Err(err) => outln!("{}", format!("** Unable to write {}: {} **", path, err).red().bold()),
+        }
+    }
+    print_gas_state(program_state);
+}
@@ -0,0 +1,186 @@
+use aga8::composition::Composition;
+use aga8::detail::Detail;
+
+use crate::components::{array_to_comp, comp_to_array};
+
+// Fully calculated state at pressure p (kPa) and temperature t (K), or None when the
+// density iteration fails.
+pub fn try_gas_state(gas_comp: &Composition, p: f64, t: f64) -> Option<Detail> {
+    let mut state = Detail::new();
+    state.set_composition(gas_comp).ok()?;
+    solve_state(&mut state, p, t)?;
+    Some(state)
+}
+
+// Solves an instance that already has its composition set at p (kPa) and t (K).  Reusing
+// one instance over many points skips the composition setup that try_gas_state repeats.
+pub fn solve_state(state: &mut Detail, p: f64, t: f64) -> Option<()> {
+    state.p = p;
+    state.t = t;
+    state.density().ok()?;
+    state.properties();
+    Some(())
+}
+
+// Solves for the state at pressure p (kPa) with molar entropy s_target (J/(mol-K)),
+// starting the Newton iteration from t_guess (K).
+pub fn isentropic_state(gas_comp: &Composition, p: f64, s_target: f64, t_guess: f64) -> Option<Detail> {
+    let mut state = Detail::new();
+    state.set_composition(gas_comp).ok()?;
+    solve_isentropic_state(&mut state, p, s_target, t_guess)?;
+    Some(state)
+}
+
+// isentropic_state on an instance that already has its composition set, as solve_state
+pub fn solve_isentropic_state(state: &mut Detail, p: f64, s_target: f64, t_guess: f64) -> Option<()> {
+    let mut t = t_guess;
+    for _ in 0..50 {
+        solve_state(state, p, t)?;
+        // (ds/dT) at constant pressure is Cp/T
+        let dt = (s_target - state.s) * t / state.cp;
+        t += dt;
+        if dt.abs() < 1.0e-7 {
+            return solve_state(state, p, t);
+        }
+    }
+    None
+}
+
+// Solves for the state at pressure p (kPa) with molar enthalpy h_target (J/mol),
+// starting the Newton iteration from t_guess (K).
+pub fn isenthalpic_state(gas_comp: &Composition, p: f64, h_target: f64, t_guess: f64) -> Option<Detail> {
+    let mut t = t_guess;
+    for _ in 0..50 {
+        let state = try_gas_state(gas_comp, p, t)?;
+        // (dh/dT) at constant pressure is Cp
+        let dt = (h_target - state.h) / state.cp;
+        t += dt;
+        if dt.abs() < 1.0e-7 {
+            return try_gas_state(gas_comp, p, t);
+        }
+    }
+    None
+}
+
+// Discharge state of a compression from p1 (kPa) and t1 (K) to p2 (kPa) with an
+// isentropic efficiency between 0 and 1.
+pub fn compressed_state(gas_comp: &Composition, p1: f64, t1: f64, p2: f64, efficiency: f64) -> Option<Detail> {
+    let suction = try_gas_state(gas_comp, p1, t1)?;
+    let t_guess = t1 * (p2 / p1).powf((suction.kappa - 1.0) / suction.kappa);
+    let isentropic = isentropic_state(gas_comp, p2, suction.s, t_guess)?;
+    let h_discharge = suction.h + (isentropic.h - suction.h) / efficiency;
+    isenthalpic_state(gas_comp, p2, h_discharge, isentropic.t)
+}
+
+// Downstream state of isenthalpic throttling from p1 (kPa) and t1 (K) to p2 (kPa).  None
+// unless p2 is below p1.
+pub fn throttled_state(gas_comp: &Composition, p1: f64, t1: f64, p2: f64) -> Option<Detail> {
+    if p2 >= p1 {
+        return None;
+    }
+    let upstream = try_gas_state(gas_comp, p1, t1)?;
+    isenthalpic_state(gas_comp, p2, upstream.h, t1)
+}
+
+// Gas constant of the DETAIL equation in J/(mol-K)
+pub const R_DETAIL: f64 = 8.31451;
+// Molar density (mol/l) low enough that the residual Helmholtz energy is negligible
+const IDEAL_GAS_DENSITY: f64 = 1.0e-9;
+
+// Ideal gas state at t (K) from the DETAIL ideal gas part, evaluated in the zero density
+// limit.  h, u, cp and cv do not depend on pressure; s is at the pressure of the state.
+pub fn ideal_gas_state(gas_comp: &Composition, t: f64) -> Option<Detail> {
+    let mut state = Detail::new();
+    state.set_composition(gas_comp).ok()?;
+    state.t = t;
+    state.d = IDEAL_GAS_DENSITY;
+    state.properties();
+    Some(state)
+}
+
+// Residual (departure) properties, real gas minus ideal gas at the same temperature and
+// pressure.  Enthalpy and internal energy in J/mol, entropy in J/(mol-K).
+pub struct Residual {
+    pub h: f64,
+    pub s: f64,
+    pub u: f64,
+}
+
+pub fn residual_properties(gas_comp: &Composition, state: &Detail) -> Option<Residual> {
+    let ideal = ideal_gas_state(gas_comp, state.t)?;
+    let s_ideal = ideal.s - R_DETAIL * (state.p / ideal.p).ln();
+    Some(Residual { h: state.h - ideal.h, s: state.s - s_ideal, u: state.u - ideal.u })
+}
+
+// Relative step in the amount of a component for the chemical potentials
+const MOLE_STEP: f64 = 1.0e-4;
+
+// Chemical potentials (J/mol) of the components present at p (kPa) and t (K), as
+// (component index, potential) in composition order.  mu_i = (dG/dn_i) at constant T, P
+// and the other amounts, by central differences of G = n g with the DETAIL Gibbs energy.
+pub fn chemical_potentials(gas_comp: &Composition, p: f64, t: f64) -> Option<Vec<(usize, f64)>> {
+    let x = comp_to_array(gas_comp);
+    let gibbs = |i: usize, dn: f64| -> Option<f64> {
+        let mut n = x;
+        n[i] += dn;
+        let total = 1.0 + dn;
+        n.iter_mut().for_each(|ni| *ni /= total);
+        Some(total * try_gas_state(&array_to_comp(&n), p, t)?.g)
+    };
+    let mut potentials = Vec::new();
+    for (i, xi) in x.iter().enumerate().filter(|(_, xi)| **xi > 0.0) {
+        let dn = MOLE_STEP * xi;
+        potentials.push((i, (gibbs(i, dn)? - gibbs(i, -dn)?) / (2.0 * dn)));
+    }
+    Some(potentials)
+}
+
+// Specific flow exergy (J/mol) of a state relative to the dead state at p0 (kPa) and t0 (K)
+// with the same composition: ex = (h - h0) - T0 (s - s0).  Only the thermomechanical part;
+// the chemical exergy against the environment is not included.
+pub fn flow_exergy(gas_comp: &Composition, state: &Detail, p0: f64, t0: f64) -> Option<f64> {
+    let dead = try_gas_state(gas_comp, p0, t0)?;
+    Some(state.h - dead.h - t0 * (state.s - dead.s))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Nitrogen at 1000 kPa and the dead state temperature 298.15 K holds close to the ideal
+    // isothermal compression work R T0 ln(p/p0) = 5708 J/mol above a 100 kPa dead state
+    #[test]
+    fn isothermal_flow_exergy() {
+        let nitrogen = Composition { nitrogen: 1.0, ..Default::default() };
+        let state = try_gas_state(&nitrogen, 1000.0, 298.15).unwrap();
+        let ex = flow_exergy(&nitrogen, &state, 100.0, 298.15).unwrap();
+        assert!((ex - 5708.0).abs() < 20.0);
+    }
+
+    // Throttling only lowers the pressure
+    #[test]
+    fn throttle_to_a_higher_pressure_is_rejected() {
+        let nitrogen = Composition { nitrogen: 1.0, ..Default::default() };
+        assert!(throttled_state(&nitrogen, 1000.0, 300.0, 3000.0).is_none());
+        assert!(throttled_state(&nitrogen, 1000.0, 300.0, 1000.0).is_none());
+        let downstream = throttled_state(&nitrogen, 3000.0, 300.0, 1000.0).unwrap();
+        assert!(downstream.t < 300.0);
+    }
+
+    // Ideal mixing near atmospheric pressure: mu_i = g_i(pure) + R T ln x_i, and
+    // G = sum x_i mu_i
+    #[test]
+    fn chemical_potentials_of_ideal_mixing() {
+        let mixture = Composition { methane: 0.5, nitrogen: 0.5, ..Default::default() };
+        let (p, t) = (100.0, 300.0);
+        let potentials = chemical_potentials(&mixture, p, t).unwrap();
+        let methane = try_gas_state(&Composition { methane: 1.0, ..Default::default() }, p, t).unwrap();
+        let nitrogen = try_gas_state(&Composition { nitrogen: 1.0, ..Default::default() }, p, t).unwrap();
+        let mixing = R_DETAIL * t * 0.5_f64.ln();
+        assert_eq!(potentials.iter().map(|(i, _)| *i).collect::<Vec<_>>(), [0, 1]);
+        assert!((potentials[0].1 - (methane.g + mixing)).abs() < 1.0);
+        assert!((potentials[1].1 - (nitrogen.g + mixing)).abs() < 1.0);
+        let g = try_gas_state(&mixture, p, t).unwrap().g;
+        assert!((0.5 * (potentials[0].1 + potentials[1].1) - g).abs() < 1.0e-3);
+    }
+}
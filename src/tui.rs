@@ -0,0 +1,306 @@
+use aga8::composition::Composition;
+use colored::Colorize;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, List, ListState, Paragraph};
+use ratatui::Frame;
+
+use crate::components::{array_to_comp, comp_to_array, COMPONENT_LABELS, NUM_COMPONENTS};
+use crate::{
+    change_conditions, change_gas_comp, clear_stored_states, format_comparison, format_gas_state, format_state, get_gas_comp,
+    get_pressure, get_temperature, history, main_menu, output, read_stdin_line, record_state, store_discharge, store_inlet,
+    update_gas_state, GasComp, Pressure, ProgramState, Temperature,
+};
+
+use std::io::{self, IsTerminal};
+
+const PRESETS: [&str; 7] = ["Air", "Argon", "Nitrogen", "Oxygen", "Natural Gas", "Methane", "Hydrogen"];
+// Lines moved by Page Up and Page Down in the state panel
+const PAGE: u16 = 10;
+
+fn preset_comp(index: usize) -> GasComp {
+    match index {
+        0 => GasComp::Air,
+        1 => GasComp::Argon,
+        2 => GasComp::Nitrogen,
+        3 => GasComp::Oxygen,
+        4 => GasComp::NaturalGas,
+        5 => GasComp::Methane,
+        _ => GasComp::Hydrogen,
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Focus {
+    // Pressure, temperature and gas preset rows
+    Fields,
+    Composition,
+}
+
+// Value typed on the status line and what it is for
+#[derive(Clone, Copy)]
+enum Target {
+    Pressure,
+    Temperature,
+    Component(usize),
+}
+
+struct Input {
+    target: Target,
+    text: String,
+}
+
+struct View {
+    focus: Focus,
+    field: usize,
+    component: usize,
+    // First line shown in the state panel
+    scroll: u16,
+    input: Option<Input>,
+    message: String,
+}
+
+// The panels are styled by ratatui, so the text is formatted without ANSI escapes
+fn plain(format: fn(&ProgramState) -> String, program_state: &ProgramState) -> String {
+    colored::control::set_override(false);
+    let text = format(program_state);
+    colored::control::set_override(output::color());
+    text.trim_matches('\n').to_string()
+}
+
+fn selected(on: bool) -> Style {
+    if on { Style::new().add_modifier(Modifier::REVERSED) } else { Style::new() }
+}
+
+fn input_label(program_state: &ProgramState, target: Target) -> String {
+    match target {
+        Target::Pressure => format!("Pressure ({})", program_state.units.pressure.label()),
+        Target::Temperature => format!("Temperature ({})", program_state.units.temp.label()),
+        Target::Component(i) => format!("{} (mol%)", COMPONENT_LABELS[i]),
+    }
+}
+
+fn draw(frame: &mut Frame, program_state: &ProgramState, view: &View) {
+    let [title, body, help, status] = Layout::vertical([
+        Constraint::Length(1), Constraint::Fill(1), Constraint::Length(1), Constraint::Length(1),
+    ]).areas(frame.area());
+    frame.render_widget(Paragraph::new("Thermodynamic Properties Calculator")
+        .style(Style::new().fg(Color::Blue).add_modifier(Modifier::BOLD)), title);
+
+    let [left, right] = Layout::horizontal([Constraint::Fill(3), Constraint::Fill(2)]).areas(body);
+    let comparison = plain(format_comparison, program_state);
+    let comparison_height = if comparison.is_empty() { 0 } else { comparison.lines().count() as u16 + 2 };
+    let [state_area, comparison_area] = Layout::vertical([Constraint::Fill(1), Constraint::Length(comparison_height)]).areas(left);
+    frame.render_widget(Paragraph::new(plain(format_state, program_state))
+        .block(Block::bordered().title("State"))
+        .scroll((view.scroll, 0)), state_area);
+    if !comparison.is_empty() {
+        frame.render_widget(Paragraph::new(comparison).block(Block::bordered().title("Inlet to Discharge")), comparison_area);
+    }
+
+    let [fields_area, composition_area] = Layout::vertical([Constraint::Length(5), Constraint::Fill(1)]).areas(right);
+    let on_field = |i: usize| selected(view.focus == Focus::Fields && view.field == i);
+    let fields = vec![
+        Line::styled(format!("{:<14} {:14.4} {}", "Pressure",
+            get_pressure(program_state.gas_state.p, program_state.units.pressure), program_state.units.pressure.label()), on_field(0)),
+        Line::styled(format!("{:<14} {:14.4} {}", "Temperature",
+            get_temperature(program_state.gas_state.t, program_state.units.temp), program_state.units.temp.label()), on_field(1)),
+        Line::styled(format!("{:<14} {:>14}", "Gas", format!("< {} >", program_state.gas)), on_field(2)),
+    ];
+    frame.render_widget(Paragraph::new(fields).block(Block::bordered().title("Edit")), fields_area);
+
+    let x = comp_to_array(&program_state.gas_comp);
+    let components: Vec<String> = (0..NUM_COMPONENTS).map(|i| format!("{:<18} {:9.4}", COMPONENT_LABELS[i], x[i] * 100.0)).collect();
+    let mut list_state = ListState::default().with_selected((view.focus == Focus::Composition).then_some(view.component));
+    frame.render_stateful_widget(List::new(components)
+        .block(Block::bordered().title("Composition (mol%)"))
+        .highlight_style(selected(true)), composition_area, &mut list_state);
+
+    frame.render_widget(Paragraph::new("Up/Down select  Enter edit  Left/Right gas  Tab panel  PgUp/PgDn scroll  \
+        1 inlet  2 discharge  c clear  z undo  y redo  m menu  q quit").style(Style::new().add_modifier(Modifier::DIM)), help);
+    let status_line = match &view.input {
+        Some(input) => Line::from(format!("{}: {}_   (Enter accept, Esc cancel)", input_label(program_state, input.target), input.text)),
+        None => Line::styled(view.message.as_str(), Style::new().fg(Color::Yellow)),
+    };
+    frame.render_widget(Paragraph::new(status_line), status);
+}
+
+// The errors of the calculation, for the status line
+fn status(errors: Vec<String>) -> Result<(), String> {
+    if errors.is_empty() { Ok(()) } else { Err(errors.join("  ")) }
+}
+
+fn recalculate(program_state: &mut ProgramState) -> Result<(), String> {
+    let errors = update_gas_state(program_state);
+    record_state(program_state);
+    status(errors)
+}
+
+fn select_preset(program_state: &mut ProgramState, step: isize) -> Result<(), String> {
+    let current = PRESETS.iter().position(|name| *name == program_state.gas).unwrap_or(0) as isize;
+    let index = (current + step).rem_euclid(PRESETS.len() as isize) as usize;
+    set_composition(program_state, PRESETS[index], get_gas_comp(preset_comp(index)))
+}
+
+fn set_composition(program_state: &mut ProgramState, name: &str, gas_comp: Composition) -> Result<(), String> {
+    program_state.gas = name.to_string();
+    change_gas_comp(program_state, gas_comp);
+    program_state.show_inlet_state = false;
+    program_state.show_discharge_state = false;
+    recalculate(program_state)
+}
+
+// Sets one component to the entered mol% and scales the others to keep the sum at 100%.
+fn edit_component(program_state: &mut ProgramState, component: usize, percent: f64) -> Result<(), String> {
+    let value = percent / 100.0;
+    if !(0.0..=1.0).contains(&value) {
+        return Err(String::from("Component must be between 0 and 100 mol%"));
+    }
+    let mut x = comp_to_array(&program_state.gas_comp);
+    let others: f64 = x.iter().enumerate().filter(|(i, _)| *i != component).map(|(_, xi)| xi).sum();
+    if others <= 0.0 && value < 1.0 {
+        return Err(String::from("No other components to balance the composition"));
+    }
+    for (i, xi) in x.iter_mut().enumerate() {
+        *xi = if i == component { value } else if others > 0.0 { *xi * (1.0 - value) / others } else { 0.0 };
+    }
+    set_composition(program_state, "Custom", array_to_comp(&x))
+}
+
+fn apply_input(program_state: &mut ProgramState, input: Input) -> Result<(), String> {
+    match input.target {
+        Target::Pressure => {
+            let p = Pressure::parse(&input.text, program_state.units.pressure)?;
+            let t = Temperature::from_kelvin(program_state.gas_state.t);
+            change_conditions(program_state, p, t);
+            recalculate(program_state)?;
+        },
+        Target::Temperature => {
+            let t = Temperature::parse(&input.text, program_state.units.temp)?;
+            let p = Pressure::from_kpa(program_state.gas_state.p);
+            change_conditions(program_state, p, t);
+            recalculate(program_state)?;
+        },
+        Target::Component(i) => {
+            let percent = input.text.trim().parse::<f64>().map_err(|_| String::from("Invalid number"))?;
+            edit_component(program_state, i, percent)?;
+        },
+    }
+    Ok(())
+}
+
+// Runs one command of the line menus on the normal screen, for the calculations that
+// have no panel in the full screen view
+fn menu_command(program_state: &mut ProgramState) {
+    out!("{}", format_gas_state(program_state));
+    main_menu(program_state);
+    outln!("{}", "(press Enter to return to the full screen view)".dimmed());
+    read_stdin_line();
+}
+
+// Full screen front end with the state, the inlet and discharge comparison and the
+// composition side by side, edited in place from the keyboard.  Commands without a panel
+// run through the line menus.  Returns false when the terminal cannot be used.
+pub fn run_tui(program_state: &mut ProgramState) -> bool {
+    if !io::stdin().is_terminal() || !io::stdout().is_terminal() {
+        return false;
+    }
+    let Ok(mut terminal) = ratatui::try_init() else {
+        ratatui::restore();
+        return false;
+    };
+    record_state(program_state);
+    let mut view = View { focus: Focus::Fields, field: 0, component: 0, scroll: 0, input: None, message: String::new() };
+    loop {
+        if terminal.draw(|frame| draw(frame, program_state, &view)).is_err() {
+            break;
+        }
+        let key = match event::read() {
+            Ok(Event::Key(key)) if key.kind == KeyEventKind::Press => key,
+            Ok(_) => continue,
+            Err(_) => break,
+        };
+
+        if let Some(input) = view.input.as_mut() {
+            match key.code {
+                KeyCode::Char(c) => input.text.push(c),
+                KeyCode::Backspace => {
+                    input.text.pop();
+                },
+                KeyCode::Esc => view.input = None,
+                KeyCode::Enter => {
+                    let input = view.input.take().unwrap();
+                    if let Err(err) = apply_input(program_state, input) {
+                        view.message = err;
+                    }
+                },
+                _ => (),
+            }
+            continue;
+        }
+
+        view.message.clear();
+        match (key.code, view.focus) {
+            (KeyCode::Char('q'), _) => break,
+            (KeyCode::Tab, Focus::Fields) => view.focus = Focus::Composition,
+            (KeyCode::Tab, Focus::Composition) => view.focus = Focus::Fields,
+            (KeyCode::Up, Focus::Fields) => view.field = view.field.saturating_sub(1),
+            (KeyCode::Down, Focus::Fields) => view.field = (view.field + 1).min(2),
+            (KeyCode::Up, Focus::Composition) => view.component = view.component.saturating_sub(1),
+            (KeyCode::Down, Focus::Composition) => view.component = (view.component + 1).min(NUM_COMPONENTS - 1),
+            (KeyCode::PageUp, _) => view.scroll = view.scroll.saturating_sub(PAGE),
+            (KeyCode::PageDown, _) => view.scroll = view.scroll.saturating_add(PAGE),
+            (KeyCode::Left | KeyCode::Right, Focus::Fields) if view.field == 2 => {
+                let step = if key.code == KeyCode::Left { -1 } else { 1 };
+                if let Err(err) = select_preset(program_state, step) {
+                    view.message = err;
+                }
+            },
+            (KeyCode::Enter, Focus::Fields) if view.field == 0 => view.input = Some(Input { target: Target::Pressure, text: String::new() }),
+            (KeyCode::Enter, Focus::Fields) if view.field == 1 => view.input = Some(Input { target: Target::Temperature, text: String::new() }),
+            (KeyCode::Enter, Focus::Composition) => view.input = Some(Input { target: Target::Component(view.component), text: String::new() }),
+            (KeyCode::Char('1'), _) => {
+                let errors = store_inlet(program_state);
+                record_state(program_state);
+                view.message = errors.join("  ");
+            },
+            (KeyCode::Char('2'), _) => {
+                let errors = store_discharge(program_state);
+                record_state(program_state);
+                view.message = errors.join("  ");
+            },
+            (KeyCode::Char('c'), _) => {
+                clear_stored_states(program_state);
+                record_state(program_state);
+            },
+            (KeyCode::Char('z'), _) => {
+                let changed = history::undo(program_state);
+                if !changed {
+                    view.message = String::from("Nothing to undo");
+                }
+            },
+            (KeyCode::Char('y'), _) => {
+                let changed = history::redo(program_state);
+                if !changed {
+                    view.message = String::from("Nothing to redo");
+                }
+            },
+            (KeyCode::Char('m'), _) => {
+                ratatui::restore();
+                menu_command(program_state);
+                match ratatui::try_init() {
+                    Ok(new_terminal) => terminal = new_terminal,
+                    Err(_) => {
+                        ratatui::restore();
+                        return true;
+                    },
+                }
+            },
+            _ => (),
+        }
+    }
+    ratatui::restore();
+    true
+}
@@ -0,0 +1,58 @@
+use colored::Colorize;
+
+use crate::script::queue_lines;
+use crate::{print_gas_state, ProgramState};
+
+// Worked example: natural gas compressed from 3000 kPa / 20 C to 7500 kPa / 117 C.
+// Lines starting with '#' are printed as narration once the preceding command has run.
+const TUTORIAL: [&str; 42] = [
+    "# Every step below is typed for you; watch the command after each '>' prompt.",
+    "# First the units are set to kPa and Kelvin so the numbers match this walkthrough.",
+    "!pause",
+    "u",
+    "1",
+    "1",
+    "u",
+    "2",
+    "2",
+    "# Next select a typical pipeline natural gas from the gas menu (g, then 7).",
+    "!pause",
+    "g",
+    "7",
+    "# The table shows the gas at the current pressure and temperature.  Compressibility Z",
+    "# below 1 means the gas is denser than an ideal gas; k is the isentropic exponent used",
+    "# for compressor calculations.  Now set the suction condition: 3000 kPa and 293.15 K.",
+    "!pause",
+    "p",
+    "3000",
+    "t",
+    "293.15",
+    "# Press 1 to store this state as the compressor inlet.",
+    "!pause",
+    "1",
+    "# The Inlet column now holds the suction state.  Next the measured discharge:",
+    "# 7500 kPa and 390 K, stored with 2 as the discharge condition.",
+    "!pause",
+    "p",
+    "7500",
+    "t",
+    "390",
+    "2",
+    "# With both states set the comparison appears below the table:",
+    "# - Pressure Ratio is discharge over suction pressure.",
+    "# - Isentropic Efficiency compares the ideal temperature rise with the measured one,",
+    "#   using the average isentropic exponent of the two states.",
+    "# - Specific Compression Work is the real-gas enthalpy rise in kJ/kg, which is also the",
+    "#   compression power in kW for every kg/s of gas.  For 10 kg/s multiply it by 10.",
+    "!pause",
+    "# Use c to clear the inlet and discharge states and explore on your own.",
+    "# Type tutorial at the main menu to run this example again.",
+    "!pause",
+];
+
+pub fn start_tutorial(program_state: &mut ProgramState) {
+    outln!();
+    outln!("{}", "Tutorial: Compressor Performance from Suction and Discharge".green().bold());
+    queue_lines(&TUTORIAL);
+    print_gas_state(program_state);
+}
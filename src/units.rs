@@ -0,0 +1,591 @@
+use aga8::detail::Detail;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use std::ops::{Add, Mul, Sub};
+
+#[allow(non_camel_case_types, clippy::upper_case_acronyms)]
+#[derive(Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum UnitPressure {
+    kPa,
+    PSI,
+    Bar,
+    MPa,
+    atm,
+    mbar,
+    kgf_cm2,
+    // Conventional inch of water (4 C) and millimetre of mercury (0 C)
+    inH2O,
+    mmHg,
+}
+
+// Pressure units in menu order
+pub const PRESSURE_UNITS: [UnitPressure; 9] = [
+    UnitPressure::kPa,
+    UnitPressure::Bar,
+    UnitPressure::PSI,
+    UnitPressure::MPa,
+    UnitPressure::atm,
+    UnitPressure::mbar,
+    UnitPressure::kgf_cm2,
+    UnitPressure::inH2O,
+    UnitPressure::mmHg,
+];
+
+#[derive(Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum UnitTemp {
+    C,
+    K,
+    F,
+    R,
+}
+
+pub const TEMP_UNITS: [UnitTemp; 4] = [UnitTemp::C, UnitTemp::K, UnitTemp::F, UnitTemp::R];
+
+#[allow(non_camel_case_types, clippy::upper_case_acronyms)]
+#[derive(Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum UnitInternalEnergy {
+    J_mol,
+    kJ_kg,
+    BTU_lbm,
+    kJ_mol,
+    kcal_kg,
+    BTU_lbmol,
+}
+
+// Energy units in menu order
+pub const ENERGY_UNITS: [UnitInternalEnergy; 6] = [
+    UnitInternalEnergy::J_mol,
+    UnitInternalEnergy::kJ_kg,
+    UnitInternalEnergy::BTU_lbm,
+    UnitInternalEnergy::kJ_mol,
+    UnitInternalEnergy::kcal_kg,
+    UnitInternalEnergy::BTU_lbmol,
+];
+
+// Units of power, duty and other energy flow results
+#[allow(non_camel_case_types, clippy::upper_case_acronyms)]
+#[derive(Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum UnitPower {
+    kW,
+    hp,
+    BTU_h,
+}
+
+pub const POWER_UNITS: [UnitPower; 3] = [UnitPower::kW, UnitPower::hp, UnitPower::BTU_h];
+
+// Molar density, or mass density from the molar density and molar mass
+#[allow(non_camel_case_types)]
+#[derive(Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum UnitDensity {
+    mol_l,
+    kg_m3,
+    lb_ft3,
+    g_cm3,
+}
+
+pub const DENSITY_UNITS: [UnitDensity; 4] = [UnitDensity::mol_l, UnitDensity::kg_m3, UnitDensity::lb_ft3, UnitDensity::g_cm3];
+
+// Unit families for the consistency check between pressure, temperature and energy
+#[derive(Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum UnitSystem {
+    SI,
+    USCustomary,
+}
+
+impl UnitSystem {
+    pub fn name(self) -> &'static str {
+        match self {
+            UnitSystem::SI => "SI",
+            UnitSystem::USCustomary => "US customary",
+        }
+    }
+}
+
+impl UnitPressure {
+    // Case-insensitive match of the unit label, e.g. "psi" or "KPA"
+    pub fn from_label(label: &str) -> Option<UnitPressure> {
+        PRESSURE_UNITS.into_iter().find(|unit| unit.label().eq_ignore_ascii_case(label))
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            UnitPressure::kPa => "kPa",
+            UnitPressure::PSI => "PSI",
+            UnitPressure::Bar => "Bar",
+            UnitPressure::MPa => "MPa",
+            UnitPressure::atm => "atm",
+            UnitPressure::mbar => "mbar",
+            UnitPressure::kgf_cm2 => "kgf/cm2",
+            UnitPressure::inH2O => "inH2O",
+            UnitPressure::mmHg => "mmHg",
+        }
+    }
+
+    pub fn system(self) -> UnitSystem {
+        match self {
+            UnitPressure::kPa | UnitPressure::Bar | UnitPressure::MPa | UnitPressure::atm | UnitPressure::mbar
+                | UnitPressure::kgf_cm2 | UnitPressure::mmHg => UnitSystem::SI,
+            UnitPressure::PSI | UnitPressure::inH2O => UnitSystem::USCustomary,
+        }
+    }
+
+    // kPa in one of the unit
+    fn kpa(self) -> f64 {
+        match self {
+            UnitPressure::kPa => 1.0,
+            UnitPressure::Bar => 100.0,
+            UnitPressure::PSI => 6.894757293,
+            UnitPressure::MPa => 1000.0,
+            UnitPressure::atm => 101.325,
+            UnitPressure::mbar => 0.1,
+            UnitPressure::kgf_cm2 => 98.0665,
+            UnitPressure::inH2O => 0.24908891,
+            UnitPressure::mmHg => 0.133322387,
+        }
+    }
+}
+
+impl UnitTemp {
+    pub fn from_label(label: &str) -> Option<UnitTemp> {
+        TEMP_UNITS.into_iter().find(|unit| unit.label().eq_ignore_ascii_case(label))
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            UnitTemp::C => "C",
+            UnitTemp::K => "K",
+            UnitTemp::F => "F",
+            UnitTemp::R => "R",
+        }
+    }
+
+    pub fn system(self) -> UnitSystem {
+        match self {
+            UnitTemp::C | UnitTemp::K => UnitSystem::SI,
+            UnitTemp::F | UnitTemp::R => UnitSystem::USCustomary,
+        }
+    }
+}
+
+impl UnitInternalEnergy {
+    pub fn label(self) -> &'static str {
+        match self {
+            UnitInternalEnergy::J_mol => "J/mol",
+            UnitInternalEnergy::kJ_kg => "kJ/kg",
+            UnitInternalEnergy::BTU_lbm => "BTU/lbm",
+            UnitInternalEnergy::kJ_mol => "kJ/mol",
+            UnitInternalEnergy::kcal_kg => "kcal/kg",
+            UnitInternalEnergy::BTU_lbmol => "BTU/lbmol",
+        }
+    }
+
+    pub fn system(self) -> UnitSystem {
+        match self {
+            UnitInternalEnergy::J_mol | UnitInternalEnergy::kJ_kg | UnitInternalEnergy::kJ_mol | UnitInternalEnergy::kcal_kg => UnitSystem::SI,
+            UnitInternalEnergy::BTU_lbm | UnitInternalEnergy::BTU_lbmol => UnitSystem::USCustomary,
+        }
+    }
+}
+
+impl UnitPower {
+    pub fn label(self) -> &'static str {
+        match self {
+            UnitPower::kW => "kW",
+            UnitPower::hp => "hp",
+            UnitPower::BTU_h => "BTU/h",
+        }
+    }
+
+    pub fn system(self) -> UnitSystem {
+        match self {
+            UnitPower::kW => UnitSystem::SI,
+            UnitPower::hp | UnitPower::BTU_h => UnitSystem::USCustomary,
+        }
+    }
+
+    // kW in one of the unit; hp is the mechanical horsepower
+    fn kw(self) -> f64 {
+        match self {
+            UnitPower::kW => 1.0,
+            UnitPower::hp => 0.745699872,
+            UnitPower::BTU_h => 1.0 / 3412.14163,
+        }
+    }
+}
+
+impl UnitDensity {
+    pub fn label(self) -> &'static str {
+        match self {
+            UnitDensity::mol_l => "mol/l",
+            UnitDensity::kg_m3 => "kg/m3",
+            UnitDensity::lb_ft3 => "lb/ft3",
+            UnitDensity::g_cm3 => "g/cm3",
+        }
+    }
+
+    pub fn system(self) -> UnitSystem {
+        match self {
+            UnitDensity::mol_l | UnitDensity::kg_m3 | UnitDensity::g_cm3 => UnitSystem::SI,
+            UnitDensity::lb_ft3 => UnitSystem::USCustomary,
+        }
+    }
+}
+
+// Describes a selection that mixes SI and US customary units, e.g. "BTU/lbm energy with
+// kPa pressure", with the system most of the selected units belong to.
+pub fn unit_mix(
+    pressure: UnitPressure, temp: UnitTemp, energy: UnitInternalEnergy, power: UnitPower, density: UnitDensity,
+) -> Option<(String, UnitSystem)> {
+    let units = [
+        (pressure.system(), format!("{} pressure", pressure.label())),
+        (temp.system(), format!("{} temperature", temp.label())),
+        (energy.system(), format!("{} energy", energy.label())),
+        (power.system(), format!("{} power", power.label())),
+        (density.system(), format!("{} density", density.label())),
+    ];
+    let si = units.iter().filter(|(system, _)| *system == UnitSystem::SI).count();
+    if si == 0 || si == units.len() {
+        return None;
+    }
+    let majority = if si * 2 > units.len() { UnitSystem::SI } else { UnitSystem::USCustomary };
+    let (odd, rest): (Vec<_>, Vec<_>) = units.into_iter().partition(|(system, _)| *system != majority);
+    let names = |units: Vec<(UnitSystem, String)>| units.into_iter().map(|(_, name)| name).collect::<Vec<_>>().join(" and ");
+    Some((format!("{} with {}", names(odd), names(rest)), majority))
+}
+
+// Units of entropy, the heat capacities and the JT coefficient.  Their degrees are
+// temperature differences in the selected scale: a degree C is a kelvin and a degree F a
+// rankine, with no offset.
+pub fn entropy_label(temp: UnitTemp) -> String {
+    format!("J/(mol-{})", temp.label())
+}
+
+pub fn jt_label(temp: UnitTemp, pressure: UnitPressure) -> String {
+    format!("{}/{}", temp.label(), pressure.label())
+}
+
+pub fn dp_dt_label(pressure: UnitPressure, temp: UnitTemp) -> String {
+    format!("{}/{}", pressure.label(), temp.label())
+}
+
+pub fn dp_dd_label(pressure: UnitPressure, density: UnitDensity) -> String {
+    format!("{}/({})", pressure.label(), density.label())
+}
+
+pub fn d2p_dd2_label(pressure: UnitPressure, density: UnitDensity) -> String {
+    format!("{}/({})2", pressure.label(), density.label())
+}
+
+// Converts an entropy or heat capacity in J/(mol-K)
+pub fn entropy_in_units(value: f64, temp: UnitTemp) -> f64 {
+    value / TemperatureDifference::from_kelvin(1.0).value(temp)
+}
+
+// Converts a JT coefficient in K/kPa
+pub fn jt_in_units(jt: f64, temp: UnitTemp, pressure: UnitPressure) -> f64 {
+    TemperatureDifference::from_kelvin(jt).value(temp) * pressure.kpa()
+}
+
+// Converts a dP/dT at constant density in kPa/K
+pub fn dp_dt_in_units(dp_dt: f64, pressure: UnitPressure, temp: UnitTemp) -> f64 {
+    dp_dt / pressure.kpa() / TemperatureDifference::from_kelvin(1.0).value(temp)
+}
+
+// Converts a dP/dρ at constant temperature in kPa/(mol/l) of a gas with molar mass mm (g/mol)
+pub fn dp_dd_in_units(dp_dd: f64, mm: f64, pressure: UnitPressure, density: UnitDensity) -> f64 {
+    dp_dd / pressure.kpa() / density_in_units(1.0, mm, density)
+}
+
+// Converts a d²P/dρ² at constant temperature in kPa/(mol/l)2
+pub fn d2p_dd2_in_units(d2p_dd2: f64, mm: f64, pressure: UnitPressure, density: UnitDensity) -> f64 {
+    d2p_dd2 / pressure.kpa() / density_in_units(1.0, mm, density).powi(2)
+}
+
+// Converts an isothermal compressibility in 1/kPa
+pub fn compressibility_in_units(kappa: f64, pressure: UnitPressure) -> f64 {
+    kappa * pressure.kpa()
+}
+
+// Converts a volume expansivity in 1/K
+pub fn expansivity_in_units(beta: f64, temp: UnitTemp) -> f64 {
+    beta / TemperatureDifference::from_kelvin(1.0).value(temp)
+}
+
+// Quantities carried in the units of the equation of state, kPa and K, and converted to
+// a display unit only when read or entered.  Serialized as the bare number in kPa or K.
+#[derive(Clone, Copy, PartialEq, PartialOrd, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(transparent))]
+pub struct Pressure {
+    kpa: f64,
+}
+
+impl Pressure {
+    pub fn from_kpa(kpa: f64) -> Pressure {
+        Pressure { kpa }
+    }
+
+    pub fn new(value: f64, unit: UnitPressure) -> Pressure {
+        Pressure { kpa: value * unit.kpa() }
+    }
+
+    pub fn kpa(self) -> f64 {
+        self.kpa
+    }
+
+    pub fn value(self, unit: UnitPressure) -> f64 {
+        self.kpa / unit.kpa()
+    }
+
+    // Parses a number with an optional unit, e.g. "150 psig", "3.5 MPa" or "12bara".  A
+    // trailing g marks a gauge pressure above the standard atmosphere; a bare number is in
+    // the default unit.
+    pub fn parse(input: &str, default: UnitPressure) -> Result<Pressure, String> {
+        let (value, label) = split_quantity(input)?;
+        if label.is_empty() {
+            return Ok(Pressure::new(value, default));
+        }
+        if let Some(unit) = UnitPressure::from_label(label) {
+            return Ok(Pressure::new(value, unit));
+        }
+        let (base, gauge) = match label.char_indices().last() {
+            Some((i, 'g' | 'G')) => (&label[..i], true),
+            Some((i, 'a' | 'A')) => (&label[..i], false),
+            _ => (label, false),
+        };
+        let unit = UnitPressure::from_label(base).ok_or(format!("Unknown pressure unit '{}'", label))?;
+        let p = Pressure::new(value, unit);
+        Ok(if gauge { Pressure::from_kpa(p.kpa + ATMOSPHERE) } else { p })
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, PartialOrd, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(transparent))]
+pub struct Temperature {
+    kelvin: f64,
+}
+
+impl Temperature {
+    pub fn from_kelvin(kelvin: f64) -> Temperature {
+        Temperature { kelvin }
+    }
+
+    pub fn new(value: f64, unit: UnitTemp) -> Temperature {
+        let kelvin = match unit {
+            UnitTemp::K => value,
+            UnitTemp::C => value + 273.15,
+            UnitTemp::F => (value - 32.0) * 5.0 / 9.0 + 273.15,
+            UnitTemp::R => value * 5.0 / 9.0,
+        };
+        Temperature { kelvin }
+    }
+
+    pub fn kelvin(self) -> f64 {
+        self.kelvin
+    }
+
+    pub fn value(self, unit: UnitTemp) -> f64 {
+        match unit {
+            UnitTemp::K => self.kelvin,
+            UnitTemp::C => self.kelvin - 273.15,
+            UnitTemp::F => (self.kelvin - 273.15) * 9.0 / 5.0 + 32.0,
+            UnitTemp::R => self.kelvin * 9.0 / 5.0,
+        }
+    }
+
+    // Parses a number with an optional unit, e.g. "25 C", "77F" or "300 °K"
+    pub fn parse(input: &str, default: UnitTemp) -> Result<Temperature, String> {
+        let (value, label) = split_quantity(input)?;
+        if label.is_empty() {
+            return Ok(Temperature::new(value, default));
+        }
+        let base = label.strip_prefix('°').or_else(|| label.strip_prefix("deg")).unwrap_or(label).trim_start();
+        let unit = UnitTemp::from_label(base).ok_or(format!("Unknown temperature unit '{}'", label))?;
+        Ok(Temperature::new(value, unit))
+    }
+}
+
+// Temperature differences carry no offset: a degree C is a kelvin and a degree F a rankine
+#[derive(Clone, Copy, PartialEq, PartialOrd, Debug)]
+pub struct TemperatureDifference {
+    kelvin: f64,
+}
+
+impl TemperatureDifference {
+    pub fn from_kelvin(kelvin: f64) -> TemperatureDifference {
+        TemperatureDifference { kelvin }
+    }
+
+    pub fn new(value: f64, unit: UnitTemp) -> TemperatureDifference {
+        match unit {
+            UnitTemp::K | UnitTemp::C => TemperatureDifference { kelvin: value },
+            UnitTemp::F | UnitTemp::R => TemperatureDifference { kelvin: value * 5.0 / 9.0 },
+        }
+    }
+
+    pub fn kelvin(self) -> f64 {
+        self.kelvin
+    }
+
+    pub fn value(self, unit: UnitTemp) -> f64 {
+        match unit {
+            UnitTemp::K | UnitTemp::C => self.kelvin,
+            UnitTemp::F | UnitTemp::R => self.kelvin * 9.0 / 5.0,
+        }
+    }
+}
+
+impl Sub for Temperature {
+    type Output = TemperatureDifference;
+
+    fn sub(self, other: Temperature) -> TemperatureDifference {
+        TemperatureDifference { kelvin: self.kelvin - other.kelvin }
+    }
+}
+
+impl Mul<f64> for TemperatureDifference {
+    type Output = TemperatureDifference;
+
+    fn mul(self, factor: f64) -> TemperatureDifference {
+        TemperatureDifference { kelvin: self.kelvin * factor }
+    }
+}
+
+// Pressure differences share the type: there is no offset between pressure units
+impl Add for Pressure {
+    type Output = Pressure;
+
+    fn add(self, other: Pressure) -> Pressure {
+        Pressure { kpa: self.kpa + other.kpa }
+    }
+}
+
+impl Sub for Pressure {
+    type Output = Pressure;
+
+    fn sub(self, other: Pressure) -> Pressure {
+        Pressure { kpa: self.kpa - other.kpa }
+    }
+}
+
+impl Mul<f64> for Pressure {
+    type Output = Pressure;
+
+    fn mul(self, factor: f64) -> Pressure {
+        Pressure { kpa: self.kpa * factor }
+    }
+}
+
+impl Add<TemperatureDifference> for Temperature {
+    type Output = Temperature;
+
+    fn add(self, difference: TemperatureDifference) -> Temperature {
+        Temperature { kelvin: self.kelvin + difference.kelvin }
+    }
+}
+
+// Standard atmosphere in kPa, the reference of gauge pressures
+const ATMOSPHERE: f64 = 101.325;
+
+// Splits input into the number and the unit text following it
+fn split_quantity(input: &str) -> Result<(f64, &str), String> {
+    let input = input.trim();
+    let end = input.find(|c: char| !(c.is_ascii_digit() || matches!(c, '.' | '+' | '-' | 'e' | 'E'))).unwrap_or(input.len());
+    let value = input[..end].parse::<f64>().map_err(|_| format!("Invalid number '{}'", input))?;
+    Ok((value, input[end..].trim()))
+}
+
+pub fn pressure_to_kpa(pressure: f64, unit: UnitPressure) -> f64 {
+    Pressure::new(pressure, unit).kpa()
+}
+
+pub fn temperature_to_kelvin(temperature: f64, unit: UnitTemp) -> f64 {
+    Temperature::new(temperature, unit).kelvin()
+}
+
+pub fn get_pressure(pressure: f64, unit: UnitPressure) -> f64 {
+    Pressure::from_kpa(pressure).value(unit)
+}
+
+pub fn get_temperature(temperature: f64, unit: UnitTemp) -> f64 {
+    Temperature::from_kelvin(temperature).value(unit)
+}
+
+pub fn get_temperature_difference(temperature: f64, unit: UnitTemp) -> f64 {
+    TemperatureDifference::from_kelvin(temperature).value(unit)
+}
+
+// Converts a molar energy in J/mol of a gas with molar mass mm (g/mol)
+pub fn energy_in_units(energy: f64, mm: f64, unit: UnitInternalEnergy) -> f64 {
+    match unit {
+        UnitInternalEnergy::J_mol => energy,
+        UnitInternalEnergy::kJ_mol => energy / 1000.0,
+        UnitInternalEnergy::kJ_kg => energy / mm,
+        UnitInternalEnergy::kcal_kg => energy / mm / 4.1868,
+        UnitInternalEnergy::BTU_lbm => energy / mm * 0.429923,
+        // J/mol is kJ/kmol, and kJ/kmol to BTU/lbmol is the same factor as kJ/kg to BTU/lbm
+        UnitInternalEnergy::BTU_lbmol => energy * 0.429923,
+    }
+}
+
+pub fn internal_energy_in_units(state: &Detail, unit: UnitInternalEnergy) -> f64 {
+    energy_in_units(state.u, state.mm, unit)
+}
+
+// Converts a molar density in mol/l of a gas with molar mass mm (g/mol); g/l is kg/m3
+pub fn density_in_units(density: f64, mm: f64, unit: UnitDensity) -> f64 {
+    match unit {
+        UnitDensity::mol_l => density,
+        UnitDensity::kg_m3 => density * mm,
+        UnitDensity::lb_ft3 => density * mm * 0.0624279606,
+        UnitDensity::g_cm3 => density * mm / 1000.0,
+    }
+}
+
+pub fn get_power(power: f64, unit: UnitPower) -> f64 {
+    power / unit.kw()
+}
+
+pub fn power_to_kw(power: f64, unit: UnitPower) -> f64 {
+    power * unit.kw()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // NIST SP 811: 1 psi = 6.894757 kPa, 1 kgf/cm2 = 98.0665 kPa, 1 mmHg = 0.1333224 kPa
+    #[test]
+    fn pressure_conversions() {
+        assert!((Pressure::new(1.0, UnitPressure::PSI).kpa() - 6.894757).abs() < 1.0e-6);
+        assert!((Pressure::new(1.0, UnitPressure::kgf_cm2).kpa() - 98.0665).abs() < 1.0e-9);
+        assert!((Pressure::new(760.0, UnitPressure::mmHg).value(UnitPressure::atm) - 1.0).abs() < 1.0e-6);
+    }
+
+    // Temperatures convert with the offset, differences without it
+    #[test]
+    fn temperature_and_difference_conversions() {
+        let t = Temperature::new(60.0, UnitTemp::F);
+        assert!((t.kelvin() - 288.705_556).abs() < 1.0e-6);
+        assert!((t.value(UnitTemp::R) - 519.67).abs() < 1.0e-9);
+        let dt = Temperature::new(30.0, UnitTemp::C) - Temperature::new(10.0, UnitTemp::C);
+        assert!((dt.value(UnitTemp::F) - 36.0).abs() < 1.0e-9);
+        assert!(((t + dt).value(UnitTemp::F) - 96.0).abs() < 1.0e-9);
+    }
+
+    // Derivatives convert by the pressure unit over the temperature difference or density unit
+    #[test]
+    fn derivative_conversions() {
+        assert!((dp_dt_in_units(100.0, UnitPressure::PSI, UnitTemp::F) - 100.0 / 6.894757 / 1.8).abs() < 1.0e-6);
+        assert!((dp_dt_in_units(100.0, UnitPressure::kPa, UnitTemp::C) - 100.0).abs() < 1.0e-12);
+        assert!((dp_dd_in_units(100.0, 16.043, UnitPressure::kPa, UnitDensity::kg_m3) - 100.0 / 16.043).abs() < 1.0e-12);
+        assert!((d2p_dd2_in_units(100.0, 16.043, UnitPressure::MPa, UnitDensity::kg_m3) - 0.1 / 16.043 / 16.043).abs() < 1.0e-12);
+        assert_eq!(dp_dd_label(UnitPressure::PSI, UnitDensity::lb_ft3), "PSI/(lb/ft3)");
+    }
+}
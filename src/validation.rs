@@ -0,0 +1,174 @@
+use aga8::composition::Composition;
+use aga8::DensityError;
+use colored::Colorize;
+
+// AGA8 DETAIL ranges of applicability (AGA Report No. 8, 1994, Table 1 "Ranges of gas
+// mixture characteristics"): normal range 0 - 1750 psia and 17 - 143 F, extended range
+// up to 40000 psia and -200 - 400 F.  Temperatures converted from F to K.
+const NORMAL_PRESSURE_MAX: f64 = 12_000.0;
+const EXTENDED_PRESSURE_MAX: f64 = 280_000.0;
+const NORMAL_TEMPERATURE: (f64, f64) = ((17.0 + 459.67) / 1.8, (143.0 + 459.67) / 1.8);
+const EXTENDED_TEMPERATURE: (f64, f64) = ((-200.0 + 459.67) / 1.8, (400.0 + 459.67) / 1.8);
+// DETAIL characterization limits of CO2 and H2S in mol% (normal range).  Beyond them
+// AGA8 Part 2 (GERG-2008) is the recommended method.
+const CO2_RICH: f64 = 30.0;
+const SOUR: f64 = 0.02;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Range {
+    Normal,
+    Extended,
+}
+
+pub struct RangeViolation {
+    // Range that was exceeded
+    pub range: Range,
+    pub quantity: String,
+    pub value: f64,
+    pub limit: f64,
+    pub unit: &'static str,
+}
+
+impl RangeViolation {
+    pub fn message(&self) -> String {
+        let range = match self.range {
+            Range::Normal => "normal",
+            Range::Extended => "extended",
+        };
+        let side = if self.value > self.limit { "above" } else { "below" };
+        format!("{} {:.4} {} is {:.4} {} {} the {} range limit of {:.4} {}",
+            self.quantity, self.value, self.unit, (self.value - self.limit).abs(), self.unit, side, range, self.limit, self.unit)
+    }
+}
+
+// Component groups in mol%: (name, value, normal max, extended max)
+fn component_groups(gas_comp: &Composition) -> [(&'static str, f64, f64, f64); 14] {
+    [
+        ("Nitrogen", gas_comp.nitrogen, 50.0, 100.0),
+        ("Carbon Dioxide", gas_comp.carbon_dioxide, 30.0, 100.0),
+        ("Ethane", gas_comp.ethane, 10.0, 100.0),
+        ("Propane", gas_comp.propane, 4.0, 12.0),
+        ("Butanes", gas_comp.isobutane + gas_comp.n_butane, 1.0, 6.0),
+        ("Pentanes", gas_comp.isopentane + gas_comp.n_pentane, 0.3, 4.0),
+        ("Hexanes Plus", gas_comp.hexane + gas_comp.heptane + gas_comp.octane + gas_comp.nonane + gas_comp.decane, 0.2, 100.0),
+        ("Helium", gas_comp.helium, 0.2, 3.0),
+        ("Hydrogen", gas_comp.hydrogen, 10.0, 100.0),
+        ("Carbon Monoxide", gas_comp.carbon_monoxide, 3.0, 3.0),
+        ("Argon", gas_comp.argon, 0.0, 1.0),
+        ("Oxygen", gas_comp.oxygen, 0.0, 21.0),
+        ("Water", gas_comp.water, 0.05, 100.0),
+        ("Hydrogen Sulfide", gas_comp.hydrogen_sulfide, 0.02, 100.0),
+    ]
+}
+
+// Returns the most severe violation for a value with upper limits only.
+fn check_max(quantity: &str, value: f64, normal_max: f64, extended_max: f64, unit: &'static str) -> Option<RangeViolation> {
+    let tolerance = 1.0e-9;
+    if value > extended_max + tolerance {
+        Some(RangeViolation { range: Range::Extended, quantity: quantity.to_string(), value, limit: extended_max, unit })
+    } else if value > normal_max + tolerance {
+        Some(RangeViolation { range: Range::Normal, quantity: quantity.to_string(), value, limit: normal_max, unit })
+    } else {
+        None
+    }
+}
+
+pub fn check_state(gas_comp: &Composition, p: f64, t: f64) -> Vec<RangeViolation> {
+    let mut violations = Vec::new();
+
+    violations.extend(check_max("Pressure", p, NORMAL_PRESSURE_MAX, EXTENDED_PRESSURE_MAX, "kPa"));
+    if t < EXTENDED_TEMPERATURE.0 {
+        violations.push(RangeViolation { range: Range::Extended, quantity: "Temperature".to_string(), value: t, limit: EXTENDED_TEMPERATURE.0, unit: "K" });
+    } else if t > EXTENDED_TEMPERATURE.1 {
+        violations.push(RangeViolation { range: Range::Extended, quantity: "Temperature".to_string(), value: t, limit: EXTENDED_TEMPERATURE.1, unit: "K" });
+    } else if t < NORMAL_TEMPERATURE.0 {
+        violations.push(RangeViolation { range: Range::Normal, quantity: "Temperature".to_string(), value: t, limit: NORMAL_TEMPERATURE.0, unit: "K" });
+    } else if t > NORMAL_TEMPERATURE.1 {
+        violations.push(RangeViolation { range: Range::Normal, quantity: "Temperature".to_string(), value: t, limit: NORMAL_TEMPERATURE.1, unit: "K" });
+    }
+
+    let methane = gas_comp.methane * 100.0;
+    if methane < 45.0 {
+        violations.push(RangeViolation { range: Range::Normal, quantity: "Methane".to_string(), value: methane, limit: 45.0, unit: "mol%" });
+    }
+    for (name, fraction, normal_max, extended_max) in component_groups(gas_comp) {
+        violations.extend(check_max(name, fraction * 100.0, normal_max, extended_max, "mol%"));
+    }
+
+    violations
+}
+
+// Explicit warnings for CO2-rich (CCS) and sour gases, beyond the DETAIL characterization
+pub fn method_warnings(gas_comp: &Composition) -> Vec<String> {
+    let mut warnings = Vec::new();
+    let recommendation = "use GERG-2008 (Analysis Tools - Compare DETAIL, GERG-2008 and Gross)";
+    let co2 = gas_comp.carbon_dioxide * 100.0;
+    if co2 > CO2_RICH {
+        warnings.push(format!("CO2-rich gas: {:.4} mol% CO2 is beyond the {} mol% DETAIL characterization limit; {}", co2, CO2_RICH, recommendation));
+    }
+    let h2s = gas_comp.hydrogen_sulfide * 100.0;
+    if h2s > SOUR {
+        warnings.push(format!("Sour gas: {:.4} mol% H2S is beyond the {} mol% DETAIL characterization limit; {}", h2s, SOUR, recommendation));
+    }
+    warnings
+}
+
+pub fn format_method_warnings(warnings: &[String]) -> String {
+    warnings.iter().map(|warning| format!("{}\n", format!("* {}", warning).yellow())).collect()
+}
+
+pub fn density_error_message(err: &DensityError) -> &'static str {
+    match err {
+        DensityError::Ok => "No error",
+        DensityError::IterationFail => "Density iteration failed to converge, ideal gas density returned",
+        DensityError::PressureTooLow => "Pressure is too low (zero) to calculate a density",
+    }
+}
+
+pub fn violation_text(violation: &RangeViolation) -> String {
+    match violation.range {
+        Range::Normal => format!("* AGA8 normal range: {}", violation.message()),
+        Range::Extended => format!("** AGA8 extended range: {} **", violation.message()),
+    }
+}
+
+pub fn format_violations(violations: &[RangeViolation]) -> String {
+    let mut out = String::new();
+    for violation in violations {
+        let line = match violation.range {
+            Range::Normal => violation_text(violation).yellow(),
+            Range::Extended => violation_text(violation).red().bold(),
+        };
+        out.push_str(&format!("{}\n", line));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn methane() -> Composition {
+        Composition { methane: 1.0, ..Default::default() }
+    }
+
+    fn temperature_violation(t: f64) -> Option<RangeViolation> {
+        check_state(&methane(), 5000.0, t).into_iter().find(|violation| violation.quantity == "Temperature")
+    }
+
+    // AGA Report No. 8 Table 1: normal 17 - 143 F, extended -200 - 400 F
+    #[test]
+    fn temperature_limits() {
+        assert!(temperature_violation(300.0).is_none());
+        let normal = temperature_violation(450.0).unwrap();
+        assert!(normal.range == Range::Normal);
+        assert!((normal.limit - 334.8167).abs() < 1.0e-3);
+        let extended = temperature_violation(500.0).unwrap();
+        assert!(extended.range == Range::Extended);
+        assert!((extended.limit - 477.5944).abs() < 1.0e-3);
+        let cold = temperature_violation(260.0).unwrap();
+        assert!(cold.range == Range::Normal);
+        assert!((cold.limit - 264.8167).abs() < 1.0e-3);
+        assert!(temperature_violation(140.0).unwrap().range == Range::Extended);
+    }
+}
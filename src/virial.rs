@@ -0,0 +1,112 @@
+use aga8::composition::Composition;
+use aga8::detail::Detail;
+use colored::Colorize;
+
+use crate::csv::write_csv;
+use crate::quality::R;
+use crate::{get_pressure, get_temperature, print_gas_state, read_f64, read_input, read_temperature, ProgramState};
+
+// Density step in mol/l for the zero-density limit
+const DENSITY_STEP: f64 = 1.0e-3;
+
+// Mixture second (l/mol) and third ((l/mol)^2) virial coefficients at t (K) from the
+// DETAIL equation.  (Z - 1)/d = B + C d + D d^2 is fitted through three low densities and
+// extrapolated to zero density.
+pub fn virial_coefficients(gas_comp: &Composition, t: f64) -> Option<(f64, f64)> {
+    let mut state = Detail::new();
+    state.set_composition(gas_comp).ok()?;
+    state.t = t;
+    let mut y = [0.0; 3];
+    for (i, yi) in y.iter_mut().enumerate() {
+        state.d = DENSITY_STEP * (i + 1) as f64;
+        state.pressure();
+        *yi = (state.z - 1.0) / state.d;
+    }
+    let b = 3.0 * y[0] - 3.0 * y[1] + y[2];
+    let c = (-5.0 * y[0] + 8.0 * y[1] - 3.0 * y[2]) / (2.0 * DENSITY_STEP);
+    Some((b, c))
+}
+
+pub fn virial_report(program_state: &mut ProgramState) {
+    let (p, t) = (program_state.gas_state.p, program_state.gas_state.t);
+    let Some((b, c)) = virial_coefficients(&program_state.gas_comp, t) else {
+        outln!("{}", "** Unable to evaluate the virial coefficients **".red().bold());
+        print_gas_state(program_state);
+        return;
+    };
+    // p / RT in mol/l with p in kPa
+    let reduced = p / (R * t);
+    let z_second = 1.0 + b * reduced;
+    let z_third = z_second + (c - b * b) * reduced * reduced;
+    let z = program_state.gas_state.z;
+
+    outln!();
+    outln!("{}", "Mixture Virial Coefficients (DETAIL)".cyan().bold());
+    outln!("{:<30} {:10.4} {:10}", "Temperature: ", get_temperature(t, program_state.units.temp), program_state.units.temp.label());
+    outln!("{:<30} {:10.4} {:10}", "Second Virial B: ", b * 1.0e3, "cm3/mol");
+    outln!("{:<30} {:10.4} {:10}", "Third Virial C: ", c * 1.0e6, "cm6/mol2");
+    outln!();
+    outln!("{:<30} {:10.4} {:10}", "Pressure: ", get_pressure(p, program_state.units.pressure), program_state.units.pressure.label());
+    outln!("{:<30} {:10.6} {:10}", "Z (DETAIL): ", z, "[]");
+    outln!("{:<30} {:10.6} {:10}", "Z = 1 + Bp/RT: ", z_second, "[]");
+    outln!("{:<30} {:10.6} {:10}", "Z with (C - B^2)(p/RT)^2: ", z_third, "[]");
+    outln!("{:<30} {:10.4} {:10}", "Error of B Truncation: ", (z_second - z) / z * 100.0, "%");
+    outln!("{:<30} {:10.4} {:10}", "Error of B, C Truncation: ", (z_third - z) / z * 100.0, "%");
+    outln!();
+    outln!("Tabulate B(T) over a temperature range? (y/n)");
+    if read_input() == "y" {
+        virial_table(program_state);
+    }
+    print_gas_state(program_state);
+}
+
+// Second and third virial coefficients over a temperature range, with optional CSV export
+fn virial_table(program_state: &ProgramState) {
+    let t_start = read_temperature(program_state, "start temperature");
+    let t_end = read_temperature(program_state, "end temperature");
+    let steps = read_f64("Enter number of temperature steps:").max(1.0) as usize;
+
+    let t_label = format!("T ({})", program_state.units.temp.label());
+    let headers = [t_label.as_str(), "B (cm3/mol)", "C (cm6/mol2)"];
+    let mut rows = Vec::new();
+    outln!();
+    outln!("{:>12} {:>14} {:>14}", headers[0], headers[1], headers[2]);
+    for i in 0..=steps {
+        let t = t_start + (t_end - t_start) * (i as f64 / steps as f64);
+        let t_display = t.value(program_state.units.temp);
+        match virial_coefficients(&program_state.gas_comp, t.kelvin()) {
+            Some((b, c)) => {
+                outln!("{:12.4} {:14.4} {:14.4}", t_display, b * 1.0e3, c * 1.0e6);
+                rows.push(vec![t_display, b * 1.0e3, c * 1.0e6]);
+            },
+            None => outln!("{:12.4} {:>14}", t_display, "failed"),
+        }
+    }
+
+    outln!();
+    outln!("Enter CSV file path to export the table (leave blank to skip):");
+    let path = read_input();
+    if !path.is_empty() {
+        match write_csv(&path, &headers, &rows) {
+            Ok(()) => outln!("Table written to {}", path),
+            Err(err) => outln!("{}", format!("** {} **", err).red().bold()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Second virial coefficients at 300 K (Dymond and Smith): methane -42 cm3/mol,
+    // nitrogen -4 cm3/mol
+    #[test]
+    fn second_virial_coefficients() {
+        let methane = Composition { methane: 1.0, ..Default::default() };
+        let nitrogen = Composition { nitrogen: 1.0, ..Default::default() };
+        let (b_methane, _) = virial_coefficients(&methane, 300.0).unwrap();
+        let (b_nitrogen, _) = virial_coefficients(&nitrogen, 300.0).unwrap();
+        assert!((b_methane * 1000.0 + 42.0).abs() < 1.5);
+        assert!((b_nitrogen * 1000.0 + 4.0).abs() < 1.5);
+    }
+}
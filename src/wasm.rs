@@ -0,0 +1,105 @@
+// JavaScript bindings over the core calculations for the browser build:
+//   cargo build --lib --release --features wasm --target wasm32-unknown-unknown
+//   wasm-bindgen --target web target/wasm32-unknown-unknown/release/comp_perf.wasm --out-dir pkg
+// Compositions are arrays of mole fractions in the order of componentNames(), pressures
+// are in kPa and temperatures in K, as in the C API.
+
+use aga8::composition::Composition;
+use aga8::detail::Detail;
+use wasm_bindgen::prelude::*;
+
+use crate::components::{array_to_comp, COMPONENT_NAMES, NUM_COMPONENTS};
+use crate::ffi::CompPerfState;
+use crate::thermo::{compressed_state, throttled_state, try_gas_state};
+use crate::units::{get_pressure, get_temperature, pressure_to_kpa, temperature_to_kelvin, UnitPressure, UnitTemp, PRESSURE_UNITS, TEMP_UNITS};
+
+fn read_composition(x: &[f64]) -> Result<Composition, JsError> {
+    let x: &[f64; NUM_COMPONENTS] = x.try_into()
+        .map_err(|_| JsError::new(&format!("Composition needs {} mole fractions", NUM_COMPONENTS)))?;
+    let gas_comp = array_to_comp(x);
+    Detail::new().set_composition(&gas_comp).map_err(|_| JsError::new("Invalid composition"))?;
+    Ok(gas_comp)
+}
+
+fn solve(x: &[f64], calculate: impl FnOnce(&Composition) -> Option<Detail>) -> Result<CompPerfState, JsError> {
+    let gas_comp = read_composition(x)?;
+    calculate(&gas_comp).map(|state| CompPerfState::of(&state)).ok_or_else(|| JsError::new("No solution"))
+}
+
+fn pressure_unit(unit: &str) -> Result<UnitPressure, JsError> {
+    UnitPressure::from_label(unit).ok_or_else(|| JsError::new(&format!("Unknown pressure unit '{}'", unit)))
+}
+
+fn temperature_unit(unit: &str) -> Result<UnitTemp, JsError> {
+    UnitTemp::from_label(unit).ok_or_else(|| JsError::new(&format!("Unknown temperature unit '{}'", unit)))
+}
+
+/// Component names in composition array order.
+#[wasm_bindgen(js_name = componentNames)]
+pub fn component_names() -> Vec<String> {
+    COMPONENT_NAMES.iter().map(|name| name.to_string()).collect()
+}
+
+/// Pressure unit labels, matched case-insensitively.
+#[wasm_bindgen(js_name = pressureUnits)]
+pub fn pressure_units() -> Vec<String> {
+    PRESSURE_UNITS.iter().map(|unit| unit.label().to_string()).collect()
+}
+
+/// Temperature unit labels, matched case-insensitively.
+#[wasm_bindgen(js_name = temperatureUnits)]
+pub fn temperature_units() -> Vec<String> {
+    TEMP_UNITS.iter().map(|unit| unit.label().to_string()).collect()
+}
+
+/// Properties at pressure p (kPa) and temperature t (K).
+#[wasm_bindgen]
+pub fn state(x: &[f64], p: f64, t: f64) -> Result<CompPerfState, JsError> {
+    solve(x, |gas_comp| try_gas_state(gas_comp, p, t))
+}
+
+/// Discharge state of a compression from p1 (kPa) and t1 (K) to a higher p2 (kPa) with
+/// an isentropic efficiency between 0 and 1.
+#[wasm_bindgen]
+pub fn compress(x: &[f64], p1: f64, t1: f64, p2: f64, efficiency: f64) -> Result<CompPerfState, JsError> {
+    if !(efficiency > 0.0 && efficiency <= 1.0) {
+        return Err(JsError::new("Efficiency must be between 0 and 1"));
+    }
+    if p2 <= p1 {
+        return Err(JsError::new("Discharge pressure must be above the inlet pressure"));
+    }
+    solve(x, |gas_comp| compressed_state(gas_comp, p1, t1, p2, efficiency))
+}
+
+/// Downstream state of isenthalpic throttling from p1 (kPa) and t1 (K) to a lower p2 (kPa).
+#[wasm_bindgen]
+pub fn throttle(x: &[f64], p1: f64, t1: f64, p2: f64) -> Result<CompPerfState, JsError> {
+    if p2 >= p1 {
+        return Err(JsError::new("Downstream pressure must be below the upstream pressure"));
+    }
+    solve(x, |gas_comp| throttled_state(gas_comp, p1, t1, p2))
+}
+
+/// Converts a pressure in one of pressureUnits() to kPa.
+#[wasm_bindgen(js_name = pressureToKpa)]
+pub fn pressure_to_kpa_js(value: f64, unit: &str) -> Result<f64, JsError> {
+    Ok(pressure_to_kpa(value, pressure_unit(unit)?))
+}
+
+/// Converts a pressure in kPa to one of pressureUnits().
+#[wasm_bindgen(js_name = pressureFromKpa)]
+pub fn pressure_from_kpa_js(value: f64, unit: &str) -> Result<f64, JsError> {
+    Ok(get_pressure(value, pressure_unit(unit)?))
+}
+
+/// Converts a temperature in one of temperatureUnits() to K.
+#[wasm_bindgen(js_name = temperatureToKelvin)]
+pub fn temperature_to_kelvin_js(value: f64, unit: &str) -> Result<f64, JsError> {
+    Ok(temperature_to_kelvin(value, temperature_unit(unit)?))
+}
+
+/// Converts a temperature in K to one of temperatureUnits().
+#[wasm_bindgen(js_name = temperatureFromKelvin)]
+pub fn temperature_from_kelvin_js(value: f64, unit: &str) -> Result<f64, JsError> {
+    Ok(get_temperature(value, temperature_unit(unit)?))
+}
@@ -0,0 +1,469 @@
+use aga8::composition::Composition;
+use aga8::detail::Detail;
+use colored::Colorize;
+
+use crate::components::{array_to_comp, comp_to_array};
+use crate::{
+    get_power, get_pressure, get_temperature, get_temperature_difference, print_gas_state, read_f64, read_input, read_pressure, read_temperature, try_gas_state,
+    Pressure, ProgramState, Temperature,
+};
+
+// Water content is per standard m3 of gas at 15 C and 101.325 kPa (ideal gas, m3/kmol)
+const STANDARD_MOLAR_VOLUME: f64 = 23.645;
+// Normal m3 are at 0 C and 101.325 kPa
+const NORMAL_MOLAR_VOLUME: f64 = 22.414;
+// mg/Sm3 per lb/MMscf
+const MG_SM3_PER_LB_MMSCF: f64 = 16.0185;
+
+// Saturation pressure of water in kPa, over liquid above the triple point (Wagner and
+// Pruss, IAPWS 1992) and over ice below it (Wagner, Saul and Pruss, 1994).
+pub fn vapor_pressure(t: f64) -> f64 {
+    if t >= 273.16 {
+        liquid_vapor_pressure(t)
+    } else {
+        let theta = t / 273.16;
+        0.611657 * (-13.928169 * (1.0 - theta.powf(-1.5)) + 34.7078238 * (1.0 - theta.powf(-1.25))).exp()
+    }
+}
+
+// Saturation pressure of liquid water in kPa, extrapolated below the triple point for
+// subcooled liquid.
+fn liquid_vapor_pressure(t: f64) -> f64 {
+    let tc = 647.096;
+    let tau = 1.0 - t / tc;
+    let sum = -7.85951783 * tau + 1.84408259 * tau.powf(1.5) - 11.7866497 * tau.powi(3)
+        + 22.6807411 * tau.powf(3.5) - 15.9618719 * tau.powi(4) + 1.80122502 * tau.powf(7.5);
+    22064.0 * (tc / t * sum).exp()
+}
+
+// Saturated water content of sweet natural gas in mg/Sm3 at p (kPa) and t (K) by the
+// Bukacek correlation, W = 47484 pv / p + B in lb/MMscf with log10 B = 6.69449 - 3083.87 / T(R).
+pub fn water_content(p: f64, t: f64) -> f64 {
+    let t_rankine = t * 1.8;
+    let b = 10f64.powf(6.69449 - 3083.87 / t_rankine);
+    let lb_mmscf = 47484.0 * vapor_pressure(t) / p + b;
+    lb_mmscf * MG_SM3_PER_LB_MMSCF
+}
+
+// Water dew point in K of gas with water content w (mg/Sm3) at p (kPa), by bisection.
+pub fn water_dew_point(p: f64, w: f64) -> Option<f64> {
+    let (mut low, mut high) = (200.0, 450.0);
+    if w <= water_content(p, low) || w >= water_content(p, high) {
+        return None;
+    }
+    for _ in 0..100 {
+        let mid = 0.5 * (low + high);
+        if water_content(p, mid) > w {
+            high = mid;
+        } else {
+            low = mid;
+        }
+    }
+    Some(0.5 * (low + high))
+}
+
+// Prompts for a water specification at pressure p (kPa), returning the content in mg/Sm3.
+pub fn read_water_spec(program_state: &ProgramState, label: &str, p: f64) -> Option<f64> {
+    outln!("{} water specification:", label);
+    outln!("1 - Water dew point at {:.4} {}", get_pressure(p, program_state.units.pressure), program_state.units.pressure.label());
+    outln!("2 - Water content (mg/Sm3)");
+    outln!("3 - Water content (lb/MMscf)");
+    outln!("4 - Water content (ppmv)");
+    match read_input().as_str() {
+        "1" => Some(water_content(p, read_temperature(program_state, "water dew point").kelvin())),
+        "2" => Some(read_f64("Enter water content (mg/Sm3):")),
+        "3" => Some(read_f64("Enter water content (lb/MMscf):") * MG_SM3_PER_LB_MMSCF),
+        "4" => Some(ppmv_to_content(read_f64("Enter water content (ppmv):"))),
+        _ => None,
+    }
+}
+
+// Dry gas diluted by water to mole fraction x_water
+pub fn humid_composition(dry: &Composition, x_water: f64) -> Composition {
+    let mut x = comp_to_array(dry).map(|xi| xi * (1.0 - x_water));
+    x[17] += x_water;
+    array_to_comp(&x)
+}
+
+// Prompts for the humidity and barometric pressure and returns the name and composition
+// of the dry air with its water vapor, as ideal mixing of the partial pressures.
+// Relative humidity is over liquid water, also below freezing; dew points below the
+// triple point are frost points.
+pub fn humid_air(program_state: &ProgramState, dry_air: &Composition) -> Option<(String, Composition)> {
+    outln!("Humidity:");
+    outln!("1 - Relative humidity and air temperature");
+    outln!("2 - Dew point");
+    let units = program_state.units;
+    let (p_water, name) = match read_input().as_str() {
+        "1" => {
+            let rh = read_f64("Enter relative humidity (%):");
+            if !(0.0..=100.0).contains(&rh) {
+                outln!("{}", "** Relative humidity must be between 0 and 100% **".red().bold());
+                return None;
+            }
+            let t = read_temperature(program_state, "air temperature").kelvin();
+            (rh / 100.0 * liquid_vapor_pressure(t), format!("Humid air (RH {}%)", rh))
+        },
+        "2" => {
+            let t_dew = read_temperature(program_state, "dew point").kelvin();
+            (vapor_pressure(t_dew), format!("Humid air (dew point {:.1} {})", get_temperature(t_dew, units.temp), units.temp.label()))
+        },
+        _ => return None,
+    };
+    let p_baro = read_pressure(program_state, "barometric pressure").kpa();
+    if p_water >= p_baro {
+        outln!("{}", "** Water vapor pressure exceeds the barometric pressure **".red().bold());
+        return None;
+    }
+    let x_water = p_water / p_baro;
+    let mut dry = Detail::new();
+    dry.set_composition(dry_air).ok()?;
+    let mm_dry = dry.molar_mass();
+    outln!("{:<30} {:10.4} {:10}", "Water Partial Pressure: ", get_pressure(p_water, units.pressure), units.pressure.label());
+    outln!("{:<30} {:10.4} {:10}", "Water Mole Fraction: ", x_water * 100.0, "mol%");
+    outln!("{:<30} {:10.4} {:10}", "Humidity Ratio: ", x_water / (1.0 - x_water) * WATER_MOLAR_MASS / mm_dry * 1000.0, "g/kg dry");
+    Some((name, humid_composition(dry_air, x_water)))
+}
+
+// Prompts for a water content in lb/MMscf, mg/Sm3, mg/Nm3 or ppmv, returning mg/Sm3.
+pub fn read_water_content() -> Option<f64> {
+    outln!("Water content unit:");
+    outln!("1 - lb/MMscf");
+    outln!("2 - mg/Sm3 (15 C)");
+    outln!("3 - mg/Nm3 (0 C)");
+    outln!("4 - ppmv");
+    let to_content: fn(f64) -> f64 = match read_input().as_str() {
+        "1" => |w| w * MG_SM3_PER_LB_MMSCF,
+        "2" => |w| w,
+        "3" => |w| w * NORMAL_MOLAR_VOLUME / STANDARD_MOLAR_VOLUME,
+        "4" => ppmv_to_content,
+        _ => return None,
+    };
+    let w = read_f64("Enter water content:");
+    (w > 0.0).then(|| to_content(w))
+}
+
+// Water dew point of gas with water content w (mg/Sm3) at p (kPa) and its margin to the
+// gas temperature t (K)
+pub fn print_dew_point_margin(program_state: &ProgramState, p: f64, t: f64, w: f64) {
+    let unit = program_state.units.temp;
+    let Some(t_dew) = water_dew_point(p, w) else {
+        outln!("{}", "* Water dew point is outside the correlation range".yellow());
+        return;
+    };
+    let margin = t - t_dew;
+    outln!("{:<30} {:10.4} {:10}", "Water Dew Point: ", get_temperature(t_dew, unit), unit.label());
+    outln!("{:<30} {:10.4} {:10}", "Margin Above Water Dew Point: ", get_temperature_difference(margin, unit), unit.label());
+    if margin < 0.0 {
+        outln!("{}", "** Gas is below its water dew point - free water condenses **".red().bold());
+    }
+}
+
+// Water dew point at the current (line) pressure from a measured water content, with the
+// margin to the current temperature
+pub fn water_dew_point_report(program_state: &mut ProgramState) {
+    outln!();
+    outln!("{}", "Water Dew Point from Water Content".cyan().bold());
+    let Some(w) = read_water_content() else {
+        outln!("{}", "**Invalid water content!**".bold().red());
+        print_gas_state(program_state);
+        return;
+    };
+    let state = &program_state.gas_state;
+    outln!();
+    outln!("{:<30} {:10.4} {:10}", "Line Pressure: ", get_pressure(state.p, program_state.units.pressure), program_state.units.pressure.label());
+    outln!("{:<30} {:10.4} {:10}", "Water Content: ", w, "mg/Sm3");
+    outln!("{:<30} {:10.4} {:10}", "Water Content: ", w / MG_SM3_PER_LB_MMSCF, "lb/MMscf");
+    print_dew_point_margin(program_state, state.p, state.t, w);
+    print_gas_state(program_state);
+}
+
+// mg/Sm3 from parts per million by volume (mole basis)
+pub fn ppmv_to_content(ppmv: f64) -> f64 {
+    ppmv * 18.015 / STANDARD_MOLAR_VOLUME
+}
+
+pub fn content_to_ppmv(w: f64) -> f64 {
+    w * STANDARD_MOLAR_VOLUME / 18.015
+}
+
+// Standard gas flow in Sm3/h from a mass flow in kg/s and molar mass in g/mol
+pub fn standard_flow(mass_flow: f64, mm: f64) -> f64 {
+    mass_flow / mm * STANDARD_MOLAR_VOLUME * 3600.0
+}
+
+pub fn print_water_spec(program_state: &ProgramState, label: &str, p: f64, w: f64) {
+    outln!("{}", label.bold());
+    outln!("{:<30} {:10.4} {:10}", "Water Content: ", w, "mg/Sm3");
+    outln!("{:<30} {:10.4} {:10}", "Water Content: ", w / MG_SM3_PER_LB_MMSCF, "lb/MMscf");
+    outln!("{:<30} {:10.4} {:10}", "Water Content: ", content_to_ppmv(w), "ppmv");
+    match water_dew_point(p, w) {
+        Some(t_dew) => outln!("{:<30} {:10.4} {:10}", "Water Dew Point: ", get_temperature(t_dew, program_state.units.temp), program_state.units.temp.label()),
+        None => outln!("{}", "* Water dew point is outside the correlation range".yellow()),
+    }
+}
+
+// Prompts for the inlet and outlet water contents (mg/Sm3) at pressure p (kPa).
+fn read_inlet_outlet(program_state: &ProgramState, p: f64) -> Option<(f64, f64)> {
+    outln!("Inlet gas: 1 - Saturated at the current temperature, 2 - Enter specification");
+    let w_in = match read_input().as_str() {
+        "2" => read_water_spec(program_state, "Inlet", p)?,
+        _ => water_content(p, program_state.gas_state.t),
+    };
+    let w_out = read_water_spec(program_state, "Outlet", p)?;
+    Some((w_in, w_out))
+}
+
+// Water to remove (or add) to bring the gas at the current pressure from an inlet to an
+// outlet water specification.
+pub fn dehydration_load(program_state: &mut ProgramState) {
+    outln!();
+    outln!("{}", "Dehydration / Humidification Load".yellow().bold());
+    outln!("Specifications are at the current pressure; the correlation applies to sweet natural gas.");
+    let p = program_state.gas_state.p;
+    let Some((w_in, w_out)) = read_inlet_outlet(program_state, p) else {
+        outln!("{}", "**Invalid selection!**".bold().red());
+        print_gas_state(program_state);
+        return;
+    };
+    let mass_flow = read_f64("Enter dry gas mass flow (kg/s):");
+    let flow = standard_flow(mass_flow, program_state.gas_state.mm);
+
+    // mg/Sm3 times Sm3/h to kg/h
+    let load = (w_in - w_out) * flow / 1.0e6;
+    outln!();
+    print_water_spec(program_state, "Inlet", p, w_in);
+    print_water_spec(program_state, "Outlet", p, w_out);
+    outln!("{:<30} {:10.4} {:10}", "Standard Gas Flow: ", flow, "Sm3/h");
+    outln!("{:<30} {:10.4} {:10}", "Water Load: ", load.abs(), "kg/h");
+    outln!("{:<30} {:10.4} {:10}", "Water Load: ", load.abs() * 24.0, "kg/day");
+    outln!("{:<30} {:>10}", "Service: ", if load >= 0.0 { "Dehydration" } else { "Humidification" });
+    let saturated = water_content(p, program_state.gas_state.t);
+    if w_in > saturated * 1.0001 || w_out > saturated * 1.0001 {
+        outln!("{}", "* A specification exceeds saturation at the current temperature, free water is present".yellow());
+    }
+
+    print_gas_state(program_state);
+}
+
+// Activity coefficient of water in lean TEG solutions at contactor temperatures
+const TEG_WATER_ACTIVITY_COEFFICIENT: f64 = 0.7;
+const TEG_MOLAR_MASS: f64 = 150.17;
+const WATER_MOLAR_MASS: f64 = 18.015;
+// Lean TEG concentrations (wt%) reached by an atmospheric reboiler at 204 C, and with
+// stripping gas, beyond which enhanced regeneration processes are needed
+const ATMOSPHERIC_REBOILER_LIMIT: f64 = 98.7;
+const STRIPPING_GAS_LIMIT: f64 = 99.9;
+
+// Lean TEG concentration (wt%) in equilibrium with a water dew point t_dew (K) at the
+// contactor temperature t_contact (K).  Dew points follow the liquid water (metastable)
+// convention used for glycol equilibrium charts.
+pub fn lean_teg_concentration(t_dew: f64, t_contact: f64) -> Option<f64> {
+    let x_water = liquid_vapor_pressure(t_dew) / (TEG_WATER_ACTIVITY_COEFFICIENT * liquid_vapor_pressure(t_contact));
+    if x_water >= 1.0 {
+        return None;
+    }
+    let water_mass = x_water * WATER_MOLAR_MASS;
+    let teg_mass = (1.0 - x_water) * TEG_MOLAR_MASS;
+    Some(teg_mass / (teg_mass + water_mass) * 100.0)
+}
+
+// TEG contactor quick screen at the current pressure and temperature (contactor conditions).
+pub fn teg_screen(program_state: &mut ProgramState) {
+    outln!();
+    outln!("{}", "TEG Dehydration Quick Screen".yellow().bold());
+    outln!("Contactor pressure and temperature are the current state.");
+    let p = program_state.gas_state.p;
+    let t_contact = program_state.gas_state.t;
+    outln!("Wet gas: 1 - Saturated at the contactor temperature, 2 - Enter specification");
+    let w_in = match read_input().as_str() {
+        "2" => read_water_spec(program_state, "Wet gas", p),
+        _ => Some(water_content(p, t_contact)),
+    };
+    let Some(w_in) = w_in else {
+        outln!("{}", "**Invalid selection!**".bold().red());
+        print_gas_state(program_state);
+        return;
+    };
+    let t_target = read_temperature(program_state, "target water dew point at the contactor pressure").kelvin();
+    let approach = read_f64("Enter approach to equilibrium dew point (K, typically 5 to 10):");
+    let ratio = read_f64("Enter circulation ratio (L TEG per kg water removed, typically 15 to 40):");
+    let mass_flow = read_f64("Enter dry gas mass flow (kg/s):");
+
+    let w_out = water_content(p, t_target);
+    let flow = standard_flow(mass_flow, program_state.gas_state.mm);
+    // kg/h
+    let water_removed = (w_in - w_out) * flow / 1.0e6;
+    let t_equilibrium = t_target - approach;
+
+    outln!();
+    print_water_spec(program_state, "Wet Gas", p, w_in);
+    print_water_spec(program_state, "Dry Gas", p, w_out);
+    outln!("{:<30} {:10.4} {:10}", "Water Removed: ", water_removed, "kg/h");
+    outln!("{:<30} {:10.4} {:10}", "Equilibrium Dew Point: ", get_temperature(t_equilibrium, program_state.units.temp), program_state.units.temp.label());
+    if water_removed <= 0.0 {
+        outln!("{}", "* Wet gas already meets the target dew point, no dehydration required".yellow());
+        print_gas_state(program_state);
+        return;
+    }
+    match lean_teg_concentration(t_equilibrium, t_contact) {
+        Some(lean) => {
+            outln!("{:<30} {:10.4} {:10}", "Required Lean TEG: ", lean, "wt%");
+            outln!("{:<30} {:10.4} {:10}", "TEG Circulation: ", water_removed * ratio, "L/h");
+            outln!("{:<30} {:10.4} {:10}", "TEG Circulation: ", water_removed * ratio / 3.78541, "US gal/h");
+            if lean > STRIPPING_GAS_LIMIT {
+                outln!("{}", "** Lean TEG is beyond stripping gas regeneration, enhanced regeneration required **".red().bold());
+            } else if lean > ATMOSPHERIC_REBOILER_LIMIT {
+                outln!("{}", "* Lean TEG is beyond an atmospheric reboiler, stripping gas required".yellow());
+            }
+        }
+        None => outln!("{}", "* Target is met by any TEG concentration at the contactor temperature".yellow()),
+    }
+
+    print_gas_state(program_state);
+}
+
+// Heat of desorption of water from 4A molecular sieve, kJ/kg water
+const SIEVE_DESORPTION_HEAT: f64 = 4190.0;
+// Heat capacities of the sieve and the vessel steel in kJ/(kg-K)
+const SIEVE_HEAT_CAPACITY: f64 = 0.96;
+const STEEL_HEAT_CAPACITY: f64 = 0.50;
+// Allowance for heat losses to the surroundings
+const REGENERATION_HEAT_LOSS: f64 = 0.10;
+
+pub struct RegenerationInputs {
+    // Water adsorbed per cycle in kg and sieve and vessel steel masses in kg
+    pub water: f64,
+    pub sieve_mass: f64,
+    pub steel_mass: f64,
+    // Bed temperature at the start of heating and regeneration (bed) temperature
+    pub t_bed: Temperature,
+    pub t_regen: Temperature,
+    // Regeneration gas temperature leaving the heater and heating time in h
+    pub t_heater: Temperature,
+    pub heating_time: f64,
+}
+
+pub struct RegenerationResult {
+    // Heat absorbed by the bed over the heating step in kJ
+    pub desorption: f64,
+    pub sieve: f64,
+    pub steel: f64,
+    pub total: f64,
+    // Regeneration gas flow in kg/h and heater duty in kW
+    pub gas_flow: f64,
+    pub heater_duty: f64,
+}
+
+// Molecular sieve regeneration heat balance.  The regeneration gas enters the bed at the
+// heater outlet temperature and leaves at the mean of the initial and final bed
+// temperatures on average over the heating step; enthalpies are real gas at the
+// regeneration pressure p, with the gas fed to the heater at t_feed.
+pub fn regeneration_requirement(gas_comp: &Composition, p: Pressure, t_feed: Temperature, inputs: &RegenerationInputs) -> Option<RegenerationResult> {
+    let (p, t_bed, t_regen) = (p.kpa(), inputs.t_bed.kelvin(), inputs.t_regen.kelvin());
+    let dt_bed = t_regen - t_bed;
+    let desorption = inputs.water * SIEVE_DESORPTION_HEAT;
+    let sieve = inputs.sieve_mass * SIEVE_HEAT_CAPACITY * dt_bed;
+    let steel = inputs.steel_mass * STEEL_HEAT_CAPACITY * dt_bed;
+    let total = (desorption + sieve + steel) * (1.0 + REGENERATION_HEAT_LOSS);
+
+    let feed = try_gas_state(gas_comp, p, t_feed.kelvin())?;
+    let hot = try_gas_state(gas_comp, p, inputs.t_heater.kelvin())?;
+    let outlet = try_gas_state(gas_comp, p, 0.5 * (t_bed + t_regen))?;
+    // J/mol to kJ/kg is 1/mm
+    let dh_bed = (hot.h - outlet.h) / hot.mm;
+    if dh_bed <= 0.0 {
+        return None;
+    }
+    let gas_flow = total / dh_bed / inputs.heating_time;
+    let heater_duty = gas_flow * (hot.h - feed.h) / hot.mm / 3600.0;
+    Some(RegenerationResult { desorption, sieve, steel, total, gas_flow, heater_duty })
+}
+
+// Regeneration gas and heater duty for a molecular sieve dryer, with the current gas at
+// the current pressure and temperature as the regeneration gas fed to the heater.
+pub fn mol_sieve_regeneration(program_state: &mut ProgramState) {
+    outln!();
+    outln!("{}", "Molecular Sieve Regeneration".yellow().bold());
+    outln!("Regeneration gas is the current gas, fed to the heater at the current state.");
+    outln!("Water load: 1 - Enter load (kg/h), 2 - Calculate from water specifications at the current pressure");
+    let load = match read_input().as_str() {
+        "2" => {
+            let Some((w_in, w_out)) = read_inlet_outlet(program_state, program_state.gas_state.p) else {
+                outln!("{}", "**Invalid selection!**".bold().red());
+                print_gas_state(program_state);
+                return;
+            };
+            let mass_flow = read_f64("Enter dried gas mass flow (kg/s):");
+            (w_in - w_out).max(0.0) * standard_flow(mass_flow, program_state.gas_state.mm) / 1.0e6
+        }
+        _ => read_f64("Enter water load (kg/h):"),
+    };
+    let adsorption_time = read_f64("Enter adsorption time per bed (h):");
+    let capacity = read_f64("Enter sieve design capacity (kg water per 100 kg sieve, typically 8 to 12):");
+    let steel_mass = read_f64("Enter vessel steel mass per bed (kg):");
+    let t_bed = read_temperature(program_state, "bed temperature at the start of heating");
+    let t_regen = read_temperature(program_state, "regeneration bed temperature (e.g. 260 to 290 C)");
+    let t_heater = read_temperature(program_state, "regeneration heater outlet temperature");
+    let heating_time = read_f64("Enter heating time (h):");
+
+    let water = load * adsorption_time;
+    let sieve_mass = water / (capacity / 100.0);
+    let inputs = RegenerationInputs { water, sieve_mass, steel_mass, t_bed, t_regen, t_heater, heating_time };
+    let Some(result) = regeneration_requirement(&program_state.gas_comp, Pressure::from_kpa(program_state.gas_state.p), Temperature::from_kelvin(program_state.gas_state.t), &inputs) else {
+        outln!("{}", "** Unable to solve the regeneration, check that the heater outlet is above the bed temperatures **".red().bold());
+        print_gas_state(program_state);
+        return;
+    };
+
+    outln!();
+    outln!("{:<30} {:10.4} {:10}", "Water Load: ", load, "kg/h");
+    outln!("{:<30} {:10.4} {:10}", "Water per Cycle: ", water, "kg");
+    outln!("{:<30} {:10.4} {:10}", "Sieve Mass per Bed: ", sieve_mass, "kg");
+    outln!("{:<30} {:10.1} {:10}", "Desorption Heat: ", result.desorption, "kJ");
+    outln!("{:<30} {:10.1} {:10}", "Sieve Heat: ", result.sieve, "kJ");
+    outln!("{:<30} {:10.1} {:10}", "Vessel Steel Heat: ", result.steel, "kJ");
+    outln!("{:<30} {:10.1} {:10}", "Total with Losses: ", result.total, "kJ");
+    outln!("{:<30} {:10.4} {:10}", "Regeneration Gas Flow: ", result.gas_flow, "kg/h");
+    outln!("{:<30} {:10.4} {:10}", "Regeneration Gas Flow: ", standard_flow(result.gas_flow / 3600.0, program_state.gas_state.mm), "Sm3/h");
+    outln!("{:<30} {:10.4} {:10}", "Heater Duty: ", get_power(result.heater_duty, program_state.units.power), program_state.units.power.label());
+    if heating_time >= adsorption_time {
+        outln!("{}", "* Heating time should be shorter than the adsorption time to leave time for cooling".yellow());
+    }
+
+    print_gas_state(program_state);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // IAPWS saturation pressures: 101.418 kPa at 100 C and 3.1699 kPa at 25 C over
+    // liquid, 0.10326 kPa at -20 C over ice
+    #[test]
+    fn saturation_pressures() {
+        assert!((vapor_pressure(373.15) - 101.418).abs() < 1.0e-3);
+        assert!((vapor_pressure(298.15) - 3.1699).abs() < 1.0e-4);
+        assert!((vapor_pressure(253.15) - 0.10326).abs() < 1.0e-5);
+    }
+
+    // McKetta-Wehe chart (GPSA), sweet gas at 1000 psia and 100 F: about 60 lb/MMscf.
+    // The dew point of that content at the same pressure returns 100 F.
+    #[test]
+    fn bukacek_water_content() {
+        let (p, t) = (1000.0 / 0.145_037_738, (100.0 + 459.67) / 1.8);
+        let w = water_content(p, t);
+        assert!((w / MG_SM3_PER_LB_MMSCF - 60.0).abs() < 1.0);
+        assert!((water_dew_point(p, w).unwrap() - t).abs() < 1.0e-6);
+    }
+
+    // Lower dew points need leaner water in the TEG, and a dew point at the contactor
+    // temperature is met without glycol
+    #[test]
+    fn lean_teg_equilibrium() {
+        let moderate = lean_teg_concentration(263.15, 303.15).unwrap();
+        let deep = lean_teg_concentration(243.15, 303.15).unwrap();
+        assert!(moderate > 95.0 && deep > moderate && deep < 100.0);
+        assert!(lean_teg_concentration(303.15, 303.15).is_none());
+    }
+}